@@ -18,14 +18,25 @@ Architecture:
 """
 
 import argparse
+import array
 import asyncio
 import contextlib
+import importlib.util
 import json
 import logging
+import math
 import os
+import queue
+import random
+import shlex
+import shutil
 import signal
+import struct
 import sys
+import threading
 import time
+import wave
+from collections import deque
 from collections.abc import Callable
 from enum import Enum
 from typing import Any
@@ -66,6 +77,238 @@ logging.basicConfig(
 )
 logger = logging.getLogger(__name__)
 
+# Version of the stdio command protocol, checked by the host's handshake.
+# Bump when commands or envelopes change incompatibly.
+PROTOCOL_VERSION = 1
+
+# Answered by the stdio server as soon as they're read, even while another
+# command (a long scan) is still running, instead of waiting their turn.
+IMMEDIATE_ACTIONS = frozenset({"cancel_scan", "cancel"})
+
+# Loudness, in LUFS, that ReplayGain track gains bring a song to.
+REPLAYGAIN_REFERENCE_LUFS = -18.0
+
+# Microphone chunks (512 frames each, about 12 ms at 44.1 kHz) held back
+# while mic monitoring's channel is busy.
+MIC_BACKLOG_CHUNKS = 8
+
+# System fonts with CJK glyphs, tried in order; the bundled DejaVu Sans
+# has none.
+_CJK_FONTS = "notosanscjkjp,notosanscjksc,notosanscjk,droidsansfallback,arialunicodems"
+
+# Languages lyrics can be rendered in: display name, .kar text encoding and
+# the font to draw them with (None keeps the default font).
+LYRICS_LANGUAGES = {
+    "en": ("Western European", "cp1252", None),
+    "pl": ("Central European", "iso-8859-2", None),
+    "ru": ("Cyrillic", "cp1251", None),
+    "el": ("Greek", "iso-8859-7", None),
+    "ja": ("Japanese", "shift_jis", _CJK_FONTS),
+    "zh": ("Chinese", "gbk", _CJK_FONTS),
+    "ko": ("Korean", "euc-kr", _CJK_FONTS),
+}
+
+# Colour schemes for drawn lyrics: display label and the background,
+# unsung, sung, info and title colours.  CDG graphics keep their own.
+LYRICS_THEMES = {
+    "classic": ("Classic", (0, 0, 0), (255, 50, 50), (255, 255, 255), (0, 0, 200), (100, 100, 255)),
+    "high_contrast": ("High contrast", (0, 0, 0), (255, 255, 0), (255, 255, 255), (255, 255, 255), (255, 255, 0)),
+    "stage": ("Stage", (16, 0, 32), (255, 120, 200), (120, 255, 255), (180, 140, 255), (255, 200, 90)),
+    "daylight": ("Daylight", (245, 245, 235), (40, 40, 160), (200, 30, 30), (90, 90, 90), (20, 20, 20)),
+}
+
+# Audio and lyrics formats the bundled players decode themselves.
+NATIVE_CODECS = ("cdg", "kar", "mid", "mp3", "ogg", "wav")
+
+# Optional features reported by get_backend_capabilities, by the action
+# that provides each; only those this backend handles are listed.
+FEATURE_ACTIONS = {
+    "waveform": "waveform",
+    "fade_out": "fade_out",
+    "preload": "preload",
+    "capture_frame": "capture_frame",
+    "vocal_removal": "set_vocal_removal",
+    "normalization": "set_normalization",
+    "crossfade": "set_crossfade",
+    "test_tone": "play_test_tone",
+    "themes": "set_theme",
+    "audio_reinit": "reinit_audio",
+    "mic_monitoring": "set_mic_monitoring",
+    "transpose": "set_transpose",
+    "tempo": "set_tempo",
+}
+
+# Optional modules that add to what the backend can do when installed:
+# tags and lengths, the C CDG decoder, and audio device listing.
+OPTIONAL_PLUGINS = ("mutagen", "_pycdgAux", "pygame._sdl2")
+
+
+def _module_available(name: str) -> bool:
+    try:
+        return importlib.util.find_spec(name) is not None
+    except (ImportError, ValueError):
+        return False
+
+
+def _audio_device_names(capture: bool = False) -> list[str]:
+    """Names of the SDL audio output devices, or input devices with
+    ``capture`` (needs pygame 2)."""
+    from pygame._sdl2 import audio as sdl2_audio
+
+    return list(sdl2_audio.get_audio_device_names(capture))
+
+
+def _open_capture_device(name: str, rate: int, channels: int, callback: Callable[[Any, Any], None]) -> Any:
+    """Start recording signed 16-bit audio from input device ``name``,
+    handing each chunk to ``callback`` on SDL's audio thread."""
+    from pygame._sdl2 import audio as sdl2_audio
+
+    device = sdl2_audio.AudioDevice(
+        devicename=name,
+        iscapture=True,
+        frequency=rate,
+        audioformat=sdl2_audio.AUDIO_S16,
+        numchannels=channels,
+        chunksize=512,
+        allowed_changes=0,
+        callback=callback,
+    )
+    device.pause(0)
+    return device
+
+
+def _scaled_pcm16(data: bytes, gain: float) -> bytes:
+    """Signed 16-bit samples ``data`` scaled by ``gain``, clipped to full
+    scale.  numpy scales a whole chunk at once when it is installed."""
+    try:
+        import numpy
+    except ImportError:
+        samples = array.array("h", data)
+        return array.array("h", (max(-0x8000, min(0x7FFF, int(s * gain))) for s in samples)).tobytes()
+    scaled = numpy.frombuffer(data, dtype=numpy.int16).astype(numpy.float64) * gain
+    return numpy.clip(numpy.trunc(scaled), -0x8000, 0x7FFF).astype(numpy.int16).tobytes()
+
+
+def _audio_length_ms(path: str) -> int | None:
+    """Length of the audio file at ``path`` from its headers, if Mutagen
+    is installed and recognises it."""
+    try:
+        import mutagen
+    except ImportError:
+        return None
+    try:
+        audio = mutagen.File(path)
+    except Exception:
+        return None
+    if audio is None or not getattr(audio.info, "length", None):
+        return None
+    return int(audio.info.length * 1000)
+
+
+def _replaygain_db(path: str) -> float | None:
+    """ReplayGain track gain tagged on the audio file at ``path``, in dB
+    against the -18 LUFS reference, if Mutagen is installed and finds one."""
+    try:
+        import mutagen
+    except ImportError:
+        return None
+    try:
+        audio = mutagen.File(path, easy=True)
+        values = audio.tags.get("replaygain_track_gain") if audio is not None and audio.tags else None
+        return float(values[0].split()[0]) if values else None
+    except Exception:
+        return None
+
+
+def _song_replaygain_db(song: Any) -> float | None:
+    """``_replaygain_db`` of the audio ``song`` plays: its own file, or a
+    CDG's companion audio file.  None for audio stored in a zip."""
+    if song.type == song.T_CDG:
+        audio = [d for d in song.get_song_datas() if d.ext in (".wav", ".ogg", ".mp3")]
+        return _replaygain_db(audio[0].filename) if audio and audio[0].true_file else None
+    return None if song.zip_stored_name else _replaygain_db(song.filepath)
+
+
+def _audio_samples(path: str) -> tuple[array.array, float]:
+    """Every sample of the audio file at ``path``, channels interleaved,
+    and the value a full-scale sample has.  WAV files are read directly;
+    anything else is decoded by an initialised pygame mixer."""
+    if path.lower().endswith(".wav"):
+        with wave.open(path, "rb") as wav:
+            width = wav.getsampwidth()
+            frames = wav.readframes(wav.getnframes())
+        if width == 1:
+            # 8-bit WAV samples are unsigned.
+            frames = frames.translate(bytes((b + 128) & 0xFF for b in range(256)))
+        typecodes = {1: "b", 2: "h", 4: "i"}
+        if width not in typecodes:
+            raise ValueError(f"Unsupported sample width: {width * 8} bits")
+        samples = array.array(typecodes[width], frames)
+        if sys.byteorder == "big":
+            samples.byteswap()
+        return samples, float(2 ** (width * 8 - 1))
+
+    import pygame
+
+    mixer = pygame.mixer.get_init()
+    formats = {-8: ("b", 128.0), -16: ("h", 32768.0), 32: ("f", 1.0)}
+    if not mixer or mixer[1] not in formats:
+        raise ValueError("Audio output isn't open")
+    typecode, full_scale = formats[mixer[1]]
+    try:
+        raw = pygame.mixer.Sound(path).get_raw()
+    except pygame.error as e:
+        raise ValueError(str(e)) from e
+    return array.array(typecode, raw), full_scale
+
+
+def _peaks(samples: array.array, full_scale: float, buckets: int) -> list[float]:
+    """The loudest sample in each of ``buckets`` equal slices of
+    ``samples``, scaled to 0..1.  A song holds millions of samples, so they
+    are never visited one by one in Python: numpy reduces each slice when
+    it is installed, otherwise the C ``max``/``min`` of each slice do."""
+    count = len(samples)
+    starts = [count * bucket // buckets for bucket in range(buckets)]
+    try:
+        import numpy
+    except ImportError:
+        numpy = None
+    if numpy is not None and count:
+        levels = numpy.abs(numpy.frombuffer(samples, dtype=samples.typecode).astype(numpy.float64))
+        in_range = [start for start in starts if start < count]
+        # Equal starts (more buckets than samples) give that one sample.
+        loudest = numpy.maximum.reduceat(levels, in_range).tolist()
+        loudest += [0.0] * (buckets - len(loudest))
+    else:
+        loudest = []
+        for bucket, start in enumerate(starts):
+            chunk = samples[start:max(count * (bucket + 1) // buckets, start + 1)]
+            loudest.append(max(max(chunk), -min(chunk)) if chunk else 0)
+    return [min(level / full_scale, 1.0) for level in loudest]
+
+
+class _ScanProgress:
+    """Stands in for ``database.BusyCancelDialog`` during a scan,
+    forwarding its progress to the frontend as ``scan_progress`` events.
+    The scan stops once ``cancel`` is set."""
+
+    def __init__(self, emit: Callable[[str, dict[str, Any]], None], cancel: threading.Event):
+        self._emit = emit
+        self._cancel = cancel
+
+    @property
+    def clicked(self):
+        return self._cancel.is_set()
+
+    def show(self):
+        pass
+
+    def set_progress(self, label, progress):
+        self._emit("scan_progress", {"label": label, "progress": progress})
+
+    def destroy(self):
+        pass
+
 
 class BackendState(Enum):
     """Playback state enumeration"""
@@ -99,9 +342,47 @@ class PyKaraokeBackend:
         self.playlist_index: int = -1
         self.song_db: Any | None = None  # database.SongDatabase when available
         self.volume: float = 0.75
+        # Loudness normalization, re-applied to every song loaded; the
+        # current song's ReplayGain gain is None when it isn't tagged.
+        self.normalization: dict[str, Any] = {"enabled": False, "target_lufs": REPLAYGAIN_REFERENCE_LUFS}
+        self._song_gain_db: float | None = None
         self.position_ms: int = 0
         self.duration_ms: int = 0
         self.error_message: str | None = None
+        # Hosts that advance the playlist themselves turn this off.
+        self.auto_advance: bool = True
+        # "off", "one" (replay the finished song) or "all" (wrap around).
+        self.repeat_mode: str = "off"
+        # Length of the fade at the end of every song and at the start of
+        # the one advanced to after it; 0 is a hard stop and start.
+        self.crossfade_ms: int = 0
+        self._song_finished = False
+        self._fading_in = False
+        # Set by fade_out, so the crossfade doesn't turn the music back up.
+        self._fading_out = False
+        # Queue order from before set_shuffle, restored when it's turned off.
+        self._unshuffled: list[Any] | None = None
+        # Set by cancel_scan, from the stdio reader thread, to stop the
+        # scan in progress.
+        self._scanning = False
+        self._scan_cancel = threading.Event()
+        # request_id of the command the stdio loop is handling, and those
+        # cancelled before their turn came, which are skipped.  Both change
+        # under _cancel_lock, since cancel arrives on the reader thread.
+        self.running_request_id: Any | None = None
+        self._cancelled_requests: set[Any] = set()
+        self._cancel_lock = threading.Lock()
+
+        # (song, song_datas) read ahead by preload for a gapless start.
+        self._preloaded: tuple[Any, list[Any]] | None = None
+        # The test tone playing, kept so it isn't collected mid-tone.
+        self._test_tone: Any | None = None
+        # Microphone chosen with set_input_device (None for the first one
+        # SDL lists) and, while set_mic_monitoring has it on, the open
+        # capture device and the gain its sound is played back at.
+        self.input_device: str | None = None
+        self.mic_gain: float = 1.0
+        self._mic: Any | None = None
 
         # Event callback for notifying frontend of state changes
         self.event_callback: Callable[[dict[str, Any]], None] | None = None
@@ -111,6 +392,9 @@ class PyKaraokeBackend:
             "play": self._handle_play,
             "pause": lambda _: self._handle_pause(),
             "stop": lambda _: self._handle_stop(),
+            "preload": self._handle_preload,
+            "fade_out": self._handle_fade_out,
+            "play_test_tone": self._handle_play_test_tone,
             "next": lambda _: self._handle_next(),
             "previous": lambda _: self._handle_previous(),
             "seek": self._handle_seek,
@@ -118,16 +402,48 @@ class PyKaraokeBackend:
             "rewind": self._handle_rewind,
             "set_volume": self._handle_set_volume,
             "load_song": self._handle_load_song,
+            "probe": self._handle_probe,
+            "waveform": self._handle_waveform,
             "add_to_playlist": self._handle_add_to_playlist,
+            "insert_in_playlist": self._handle_insert_in_playlist,
             "remove_from_playlist": self._handle_remove_from_playlist,
+            "move_in_playlist": self._handle_move_in_playlist,
             "clear_playlist": lambda _: self._handle_clear_playlist(),
             "get_state": lambda _: {"status": "ok", "data": self.get_state()},
             "search_songs": self._handle_search_songs,
             "get_library": self._handle_get_library,
             "scan_library": self._handle_scan_library,
             "add_folder": self._handle_add_folder,
+            "cancel_scan": self._handle_cancel_scan,
+            "cancel": self._handle_cancel,
             "get_settings": lambda _: self._handle_get_settings(),
             "update_settings": self._handle_update_settings,
+            "set_library_roots": self._handle_set_library_roots,
+            "version": lambda _: self._handle_version(),
+            "handshake": self._handle_handshake,
+            "ping": lambda _: {"status": "ok", "data": {"pong": True}},
+            "get_supported_formats": lambda _: self._handle_get_supported_formats(),
+            "get_backend_capabilities": lambda _: self._handle_get_backend_capabilities(),
+            "list_audio_devices": lambda _: self._handle_list_audio_devices(),
+            "set_audio_device": self._handle_set_audio_device,
+            "list_input_devices": lambda _: self._handle_list_input_devices(),
+            "set_input_device": self._handle_set_input_device,
+            "set_mic_monitoring": self._handle_set_mic_monitoring,
+            "get_audio_stats": lambda _: self._handle_get_audio_stats(),
+            "reopen_audio": lambda _: self._handle_reopen_audio(),
+            "reinit_audio": self._handle_reinit_audio,
+            "capture_frame": self._handle_capture_frame,
+            "set_auto_advance": self._handle_set_auto_advance,
+            "set_shuffle": self._handle_set_shuffle,
+            "set_repeat_mode": self._handle_set_repeat_mode,
+            "set_av_sync_offset": self._handle_set_av_sync_offset,
+            "set_normalization": self._handle_set_normalization,
+            "set_crossfade": self._handle_set_crossfade,
+            "get_supported_languages": lambda _: self._handle_get_supported_languages(),
+            "set_lyrics_language": self._handle_set_lyrics_language,
+            "get_available_themes": lambda _: self._handle_get_available_themes(),
+            "set_theme": self._handle_set_theme,
+            "set_log_level": self._handle_set_log_level,
         }
 
         # Initialize the song database
@@ -211,7 +527,15 @@ class PyKaraokeBackend:
 
     def _emit_event(self, event_type: str, data: dict[str, Any] | None = None):
         """Emit an event to the frontend"""
-        event = {"type": event_type, "timestamp": time.time(), "data": data or {}}
+        self._send_event({"type": event_type, "timestamp": time.time(), "data": data or {}})
+
+    def _emit_error(self, code: str, message: str):
+        """Tell the frontend about a failure the user should see.  Unlike
+        other events, ``error`` carries ``code`` and ``message`` in place
+        of ``data``."""
+        self._send_event({"type": "error", "timestamp": time.time(), "code": code, "message": message})
+
+    def _send_event(self, event: dict[str, Any]):
         if self.event_callback:
             try:
                 self.event_callback(event)
@@ -261,14 +585,19 @@ class PyKaraokeBackend:
         }
 
     def _song_to_dict(self, song: Any) -> dict[str, Any]:
-        """Convert a SongStruct to a dictionary"""
-        return {
+        """Convert a SongStruct to a dictionary.  Playlist entries queued
+        for a singer also carry ``singer``."""
+        song_dict = {
             "title": getattr(song, "title", ""),
             "artist": getattr(song, "artist", ""),
             "filename": getattr(song, "display_filename", ""),
             "filepath": getattr(song, "filepath", ""),
             "zip_name": getattr(song, "zip_stored_name", None),
         }
+        singer = getattr(song, "singer", None)
+        if isinstance(singer, str):
+            song_dict["singer"] = singer
+        return song_dict
 
     # Playback control handlers
 
@@ -316,6 +645,7 @@ class PyKaraokeBackend:
 
     def _handle_stop(self) -> dict[str, Any]:
         """Handle stop command"""
+        self._song_finished = self._fading_out = False
         if self.current_player:
             self.current_player.stop()
             self.current_player = None
@@ -329,6 +659,59 @@ class PyKaraokeBackend:
         self.state = BackendState.STOPPED
         return {"status": "ok"}  # Already stopped
 
+    def _handle_fade_out(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Start fading the music out over duration_ms; the frontend sends
+        stop once the fade is over."""
+        duration_ms = params.get("duration_ms")
+        if not isinstance(duration_ms, int) or duration_ms <= 0:
+            return {"status": "error", "message": "duration_ms must be a positive integer"}
+        if not self.current_player or self.state != BackendState.PLAYING:
+            return {"status": "error", "message": "Not playing"}
+        import pygame
+
+        try:
+            pygame.mixer.music.fadeout(duration_ms)
+        except pygame.error as e:
+            return {"status": "error", "message": f"Can't fade out: {e}"}
+        self._fading_out = True
+        return {"status": "ok"}
+
+    def _handle_play_test_tone(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Play a sine tone of frequency_hz (20 to 20000) for duration_ms
+        (100 to 10000) on the left, right or both speakers, beside the
+        music rather than in place of it; the frontend pauses the song
+        first."""
+        frequency = params.get("frequency_hz")
+        duration_ms = params.get("duration_ms")
+        channel = params.get("channel", "both")
+        if isinstance(frequency, bool) or not isinstance(frequency, (int, float)) or not 20 <= frequency <= 20000:
+            return {"status": "error", "message": "frequency_hz must be a number between 20 and 20000"}
+        if isinstance(duration_ms, bool) or not isinstance(duration_ms, int) or not 100 <= duration_ms <= 10000:
+            return {"status": "error", "message": "duration_ms must be an integer between 100 and 10000"}
+        if channel not in ("left", "right", "both"):
+            return {"status": "error", "message": "channel must be one of left, right, both"}
+        import pygame
+
+        mixer = pygame.mixer.get_init()
+        if not mixer:
+            return {"status": "error", "message": "Audio is not open"}
+        rate, size, channels = mixer
+        if size != -16:
+            return {"status": "error", "message": "The test tone needs a signed 16-bit mixer"}
+        # Half scale, with its own volume so it doesn't follow the music's.
+        amplitude = 0x3FFF
+        speakers = [channel in ("left", "both"), channel in ("right", "both")] if channels == 2 else [True] * channels
+        samples = array.array("h")
+        for i in range(rate * duration_ms // 1000):
+            value = int(amplitude * math.sin(2 * math.pi * frequency * i / rate))
+            samples.extend(value if on else 0 for on in speakers)
+        try:
+            self._test_tone = pygame.mixer.Sound(buffer=samples.tobytes())
+            self._test_tone.play()
+        except pygame.error as e:
+            return {"status": "error", "message": f"Can't play the test tone: {e}"}
+        return {"status": "ok", "data": {"duration_ms": duration_ms}}
+
     def _handle_next(self) -> dict[str, Any]:
         """Handle next track command"""
         if self.playlist_index < len(self.playlist) - 1:
@@ -379,7 +762,7 @@ class PyKaraokeBackend:
         self.volume = volume
         if manager.initialized:
             try:
-                manager.set_volume(volume)
+                manager.set_volume(self._output_volume())
             except Exception:
                 logger.exception("Failed to set volume on player")
         self._emit_event("volume_changed", {"volume": volume})
@@ -399,11 +782,7 @@ class PyKaraokeBackend:
                 self.current_player.close()
 
             # Create new player for the song
-            self.current_player = self.current_song.make_player(
-                self.song_db,
-                error_notify_callback=self._on_player_error,
-                done_callback=self._on_song_finished,
-            )
+            self.current_player = self._make_player(self.current_song)
 
             if not self.current_player:
                 raise RuntimeError("Failed to create player")
@@ -411,15 +790,24 @@ class PyKaraokeBackend:
             # Check if the player successfully parsed the song file
             if hasattr(self.current_player, "is_valid") and not self.current_player.is_valid:
                 raise RuntimeError("Song file could not be parsed (corrupt or unsupported format)")
+            # next, previous and auto-advance change songs without load_song.
+            self._song_gain_db = _song_replaygain_db(self.current_song)
 
-            # Start playback
+            # Start playback, fading in if this song follows one that
+            # just finished.
+            self._fading_in, self._song_finished = self._song_finished and self.crossfade_ms > 0, False
+            self._fading_out = False
             self.current_player.play()
             self.state = BackendState.PLAYING
             self.position_ms = 0
             self.duration_ms = int(self.current_player.get_length() * 1000) if hasattr(self.current_player, 'get_length') else 0
-            manager.set_volume(self.volume)
+            manager.set_volume(self._output_volume())
 
             self._emit_state_change()
+            self._emit_event(
+                "song_started",
+                {"song": self._song_to_dict(self.current_song), "playlist_index": self.playlist_index},
+            )
             return {"status": "ok"}
 
         except SystemExit:
@@ -429,26 +817,72 @@ class PyKaraokeBackend:
             self.state = BackendState.ERROR
             self.error_message = str(e)
             self._emit_state_change()
+            self._emit_error("playback_failed", str(e))
             return {"status": "error", "message": str(e)}
 
+    def _make_player(self, song: Any) -> Any:
+        """Create the player for song, handing it the files preload read
+        ahead if this is the preloaded song."""
+        preloaded, self._preloaded = self._preloaded, None
+        if preloaded is not None and preloaded[0] is song:
+            song_datas = preloaded[1]
+            song.get_song_datas = lambda: song_datas
+        try:
+            return song.make_player(
+                self.song_db,
+                error_notify_callback=self._on_player_error,
+                done_callback=self._on_song_finished,
+            )
+        finally:
+            vars(song).pop("get_song_datas", None)
+
+    def _handle_preload(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Read the files of the playlist entry at playlist_index into
+        memory, so starting it doesn't wait on the disk (or on extracting
+        it from a ZIP)."""
+        index = params.get("playlist_index")
+        if not isinstance(index, int) or not 0 <= index < len(self.playlist):
+            return {"status": "error", "message": "Invalid playlist index"}
+        song = self.playlist[index]
+        try:
+            song_datas = song.get_song_datas()
+            size = sum(len(song_data.get_data()) for song_data in song_datas)
+        except (OSError, ValueError) as e:
+            return {"status": "error", "message": str(e)}
+        self._preloaded = (song, song_datas)
+        return {"status": "ok", "data": {"playlist_index": index, "bytes": size}}
+
     def _on_player_error(self, error: str):
         """Callback when player encounters an error"""
         logger.error("Player error: %s", error)
         self.error_message = error
         self.state = BackendState.ERROR
         self._emit_event("playback_error", {"error": error})
+        self._emit_error("player_error", error)
 
     def _on_song_finished(self):
         """Callback when song finishes"""
         logger.info("Song finished")
+        self._song_finished = True
         self._emit_event("song_finished", {})
 
         # Auto-advance to next song if available
-        if self.playlist_index < len(self.playlist) - 1:
+        if self.auto_advance and self.repeat_mode == "one" and self.current_song:
+            self._start_playback()
+        elif self.auto_advance and self.playlist_index < len(self.playlist) - 1:
             self.playlist_index += 1
             self.current_song = self.playlist[self.playlist_index]
             self._start_playback()
+        elif self.auto_advance and self.repeat_mode == "all" and self.playlist:
+            self.playlist_index = 0
+            self.current_song = self.playlist[0]
+            self._start_playback()
         else:
+            # Only a song the host advances to next fades in; a song started
+            # by hand after the end of the playlist doesn't.
+            follows = self.repeat_mode != "off" or self.playlist_index < len(self.playlist) - 1
+            self._song_finished = not self.auto_advance and follows
+            self._fading_out = False
             self.current_player = None
             self.current_song = None
             self.position_ms = 0
@@ -456,6 +890,156 @@ class PyKaraokeBackend:
             self.state = BackendState.IDLE
             self._emit_state_change()
 
+    def _handle_set_auto_advance(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Turn advancing to the next song on song_finished on or off"""
+        self.auto_advance = bool(params.get("enabled", True))
+        return {"status": "ok"}
+
+    def _handle_set_repeat_mode(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Choose what auto-advance does when a song finishes"""
+        mode = params.get("mode")
+        if mode not in ("off", "one", "all"):
+            return {"status": "error", "message": "mode must be one of off, one, all"}
+        self.repeat_mode = mode
+        return {"status": "ok"}
+
+    def _handle_set_log_level(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Change how much is logged to stderr from now on"""
+        level = params.get("level")
+        if level not in ("DEBUG", "INFO", "WARNING", "ERROR"):
+            return {"status": "error", "message": "level must be one of DEBUG, INFO, WARNING, ERROR"}
+        logging.getLogger().setLevel(level)
+        logger.info("Log level set to %s", level)
+        return {"status": "ok", "data": {"level": level}}
+
+    def _handle_set_av_sync_offset(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Shift lyrics against the audio by ``offset_ms`` (-500 to 500);
+        the players add it as ``sync_delay_ms``."""
+        offset_ms = params.get("offset_ms")
+        if isinstance(offset_ms, bool) or not isinstance(offset_ms, int) or not -500 <= offset_ms <= 500:
+            return {"status": "error", "message": "offset_ms must be an integer between -500 and 500"}
+        self.song_db.settings.sync_delay_ms = offset_ms
+        return {"status": "ok"}
+
+    def _handle_set_normalization(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Scale each song's volume by its ReplayGain gain so it plays at
+        ``target_lufs`` (-30 to -5).  Says whether the current song is
+        tagged; untagged songs play unscaled."""
+        enabled = params.get("enabled")
+        target = params.get("target_lufs")
+        if not isinstance(enabled, bool):
+            return {"status": "error", "message": "enabled must be true or false"}
+        if isinstance(target, bool) or not isinstance(target, (int, float)) or not -30 <= target <= -5:
+            return {"status": "error", "message": "target_lufs must be a number between -30 and -5"}
+        self.normalization = {"enabled": enabled, "target_lufs": float(target)}
+        if manager.initialized:
+            manager.set_volume(self._output_volume())
+        return {"status": "ok", "data": self._normalization_report()}
+
+    def _handle_set_crossfade(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Fade the last ``duration_ms`` (0 to 10000; 0 turns it off) of each
+        song out, and the song played after it finishes in."""
+        duration_ms = params.get("duration_ms")
+        if isinstance(duration_ms, bool) or not isinstance(duration_ms, int) or not 0 <= duration_ms <= 10000:
+            return {"status": "error", "message": "duration_ms must be an integer between 0 and 10000"}
+        self.crossfade_ms = duration_ms
+        if manager.initialized:
+            manager.set_volume(self._output_volume())
+        return {"status": "ok", "data": {"duration_ms": duration_ms}}
+
+    def _crossfade_gain(self) -> float:
+        """How far the crossfade has the music turned down at the current
+        position, from 0 (silent) to 1."""
+        if not self.crossfade_ms or self.state != BackendState.PLAYING:
+            return 1.0
+        gain = 1.0
+        if self._fading_in:
+            gain = min(gain, self.position_ms / self.crossfade_ms)
+        if self.duration_ms:
+            gain = min(gain, (self.duration_ms - self.position_ms) / self.crossfade_ms)
+        return max(0.0, gain)
+
+    def _normalization_report(self) -> dict[str, Any]:
+        gain_db = None
+        if self.normalization["enabled"] and self._song_gain_db is not None:
+            gain_db = self._song_gain_db + self.normalization["target_lufs"] - REPLAYGAIN_REFERENCE_LUFS
+        return {**self.normalization, "has_gain_metadata": self._song_gain_db is not None, "gain_db": gain_db}
+
+    def _output_volume(self) -> float:
+        """The volume to open the mixer at: the user's, scaled by the
+        normalization gain and the crossfade.  Gains above full scale are
+        capped, not boosted."""
+        gain_db = self._normalization_report()["gain_db"]
+        volume = self.volume if gain_db is None else min(1.0, self.volume * 10 ** (gain_db / 20))
+        return volume * self._crossfade_gain()
+
+    def _handle_get_supported_languages(self) -> dict[str, Any]:
+        """List the languages lyrics can be rendered in"""
+        languages = [{"id": lang, "name": name} for lang, (name, _, _) in LYRICS_LANGUAGES.items()]
+        return {"status": "ok", "data": {"languages": languages}}
+
+    def _handle_set_lyrics_language(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Decode and draw lyrics for ``lang``; applies from the next song"""
+        lang = params.get("lang")
+        if lang not in LYRICS_LANGUAGES:
+            return {"status": "error", "message": f"Unsupported language: {lang}"}
+        _name, encoding, font = LYRICS_LANGUAGES[lang]
+        settings = self.song_db.settings
+        settings.kar_encoding = encoding
+        # find_pygame_font scales system fonts by size / 10, so 10 keeps
+        # the size the player picks.
+        settings.kar_font = database.FontData(font, 10) if font else database.FontData("DejaVuSans.ttf")
+        return {"status": "ok"}
+
+    def _handle_get_available_themes(self) -> dict[str, Any]:
+        """List the colour schemes lyrics can be drawn in"""
+        themes = [{"name": name, "label": theme[0]} for name, theme in LYRICS_THEMES.items()]
+        return {"status": "ok", "data": {"themes": themes}}
+
+    def _handle_set_theme(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Draw KAR and MIDI lyrics in theme ``name``, from the next frame"""
+        name = params.get("name")
+        if name not in LYRICS_THEMES:
+            return {"status": "error", "message": f"Unknown theme: {name}"}
+        settings = self.song_db.settings
+        (
+            _label,
+            settings.kar_background_colour,
+            settings.kar_ready_colour,
+            settings.kar_sweep_colour,
+            settings.kar_info_colour,
+            settings.kar_title_colour,
+        ) = LYRICS_THEMES[name]
+        return {"status": "ok"}
+
+    def _handle_set_shuffle(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Shuffle the songs after the current one, or restore the order
+        they were queued in.  Songs added while shuffled stay at the end."""
+        enabled = bool(params.get("enabled", True))
+        current = self.playlist[self.playlist_index] if 0 <= self.playlist_index < len(self.playlist) else None
+        if enabled:
+            if self._unshuffled is None:
+                self._unshuffled = list(self.playlist)
+            upcoming = self.playlist[self.playlist_index + 1:]
+            random.shuffle(upcoming)
+            self.playlist[self.playlist_index + 1:] = upcoming
+        elif self._unshuffled is not None:
+            remaining = list(self.playlist)
+            restored = []
+            for song in self._unshuffled:
+                if song in remaining:
+                    remaining.remove(song)
+                    restored.append(song)
+            self.playlist = restored + remaining
+            self._unshuffled = None
+            if current is not None:
+                self.playlist_index = self.playlist.index(current)
+
+        self._emit_event(
+            "playlist_updated", {"playlist": [self._song_to_dict(s) for s in self.playlist]}
+        )
+        return {"status": "ok"}
+
     # Playlist management handlers
 
     def _handle_load_song(self, params: dict[str, Any]) -> dict[str, Any]:
@@ -467,24 +1051,113 @@ class PyKaraokeBackend:
 
         try:
             logger.info("Loading song: %s", filepath)
-            self.current_song = self.song_db.make_song_struct(filepath)
+            song = self.song_db.make_song_struct(filepath)
+            if song.type is None:
+                ext = os.path.splitext(filepath)[1] or "Extensionless"
+                return self._load_failed(filepath, "unsupported_format", f"{ext} files can't be played")
+            if song.type == song.T_CDG:
+                if not any(d.ext in (".wav", ".ogg", ".mp3") for d in song.get_song_datas()):
+                    message = "No .mp3, .ogg or .wav file with the same name next to the CDG"
+                    return self._load_failed(filepath, "missing_companion", message)
+            self.current_song = song
+            self._song_gain_db = _song_replaygain_db(song)
             self._emit_state_change()
             return {"status": "ok"}
+        except PermissionError as e:
+            return self._load_failed(filepath, "permission_denied", str(e))
         except (RuntimeError, ValueError, OSError) as e:
             logger.exception("Failed to load song %s", filepath)
+            return self._load_failed(filepath, "decode_error", str(e))
+
+    def _load_failed(self, filepath: str, reason: str, message: str) -> dict[str, Any]:
+        """Emit ``load_failed`` for ``filepath`` and answer ``load_song``
+        with the same ``reason`` code next to the message."""
+        logger.warning("Cannot load %s (%s): %s", filepath, reason, message)
+        self._emit_event("load_failed", {"filepath": filepath, "reason": reason, "message": message})
+        return {"status": "error", "message": message, "data": {"reason": reason}}
+
+    def _handle_probe(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Describe a song file without loading it.  A CDG's length comes
+        from its matching audio file; ``duration_ms`` is None when unknown."""
+        filepath = params.get("filepath")
+        if not filepath:
+            return {"status": "error", "message": "filepath required"}
+
+        try:
+            song = self.song_db.make_song_struct(filepath)
+            audio_path = None
+            if not song.zip_stored_name:
+                audio_path = filepath
+                if song.type == song.T_CDG:
+                    audio = [d for d in song.get_song_datas() if d.ext in (".wav", ".ogg", ".mp3")]
+                    audio_path = audio[0].filename if audio else None
+        except (RuntimeError, ValueError, OSError) as e:
+            return {"status": "error", "message": str(e)}
+        formats = {song.T_KAR: "kar", song.T_CDG: "cdg", song.T_MPG: "mpg"}
+        return {
+            "status": "ok",
+            "data": {
+                "title": song.title,
+                "artist": song.artist,
+                "duration_ms": _audio_length_ms(audio_path) if audio_path else None,
+                "format": formats.get(song.type, ""),
+            },
+        }
+
+    def _handle_waveform(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Peak levels of a song's audio in ``buckets`` equal slices, for
+        drawing its waveform.  Only CDG songs have audio of their own to
+        read; MIDI and video songs are refused."""
+        filepath = params.get("filepath")
+        buckets = params.get("buckets")
+        if not filepath:
+            return {"status": "error", "message": "filepath required"}
+        if not isinstance(buckets, int) or buckets < 1:
+            return {"status": "error", "message": "buckets must be a positive integer"}
+
+        try:
+            song = self.song_db.make_song_struct(filepath)
+            if song.type != song.T_CDG or song.zip_stored_name:
+                return {"status": "error", "message": "Only CDG songs outside ZIP files have a waveform"}
+            audio = [d for d in song.get_song_datas() if d.ext in (".wav", ".ogg", ".mp3")]
+            if not audio:
+                return {"status": "error", "message": "No audio file found for this song"}
+            samples, full_scale = _audio_samples(audio[0].filename)
+        except (ImportError, RuntimeError, ValueError, OSError, EOFError, wave.Error) as e:
             return {"status": "error", "message": str(e)}
+        return {"status": "ok", "data": {"peaks": _peaks(samples, full_scale, buckets)}}
 
     def _handle_add_to_playlist(self, params: dict[str, Any]) -> dict[str, Any]:
-        """Add song to playlist"""
+        """Add song to playlist, optionally for ``singer``"""
+        return self._enqueue(params, len(self.playlist))
+
+    def _handle_insert_in_playlist(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Queue a song at playlist position ``index``, e.g. right after
+        the current song to play it next."""
+        index = params.get("index")
+        if not isinstance(index, int) or isinstance(index, bool) or not 0 <= index <= len(self.playlist):
+            return {"status": "error", "message": f"index must be between 0 and {len(self.playlist)}"}
+        return self._enqueue(params, index)
+
+    def _enqueue(self, params: dict[str, Any], index: int) -> dict[str, Any]:
+        """Queue the song at ``params["filepath"]`` at ``index``."""
         filepath = params.get("filepath")
         if not filepath:
             logger.warning("add_to_playlist called without filepath")
             return {"status": "error", "message": "filepath required"}
+        singer = params.get("singer")
+        if singer is not None and not isinstance(singer, str):
+            return {"status": "error", "message": "singer must be a string"}
 
         try:
             logger.info("Enqueueing song: %s", filepath)
+            # Each entry is a fresh SongStruct, so the singer moves with it
+            # through reorders, removals and shuffles.
             song = self.song_db.make_song_struct(filepath)
-            self.playlist.append(song)
+            song.singer = singer or None
+            self.playlist.insert(index, song)
+            if index <= self.playlist_index:
+                self.playlist_index += 1
             logger.info(
                 "Song enqueued: title=%s artist=%s (queue length=%d)",
                 song.title, song.artist, len(self.playlist),
@@ -512,6 +1185,26 @@ class PyKaraokeBackend:
         )
         return {"status": "ok"}
 
+    def _handle_move_in_playlist(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Move the playlist entry at ``from`` to position ``to``; the
+        current song stays current"""
+        source, target = params.get("from"), params.get("to")
+        valid = range(len(self.playlist))
+        if source not in valid or target not in valid:
+            return {"status": "error", "message": "Invalid index"}
+
+        self.playlist.insert(target, self.playlist.pop(source))
+        if self.playlist_index == source:
+            self.playlist_index = target
+        elif source < self.playlist_index <= target:
+            self.playlist_index -= 1
+        elif target <= self.playlist_index < source:
+            self.playlist_index += 1
+
+        playlist = [self._song_to_dict(s) for s in self.playlist]
+        self._emit_event("playlist_updated", {"playlist": playlist})
+        return {"status": "ok", "data": {"playlist": playlist}}
+
     def _handle_clear_playlist(self) -> dict[str, Any]:
         """Clear the playlist"""
         self.playlist = []
@@ -522,60 +1215,485 @@ class PyKaraokeBackend:
     # Library management handlers
 
     def _handle_search_songs(self, params: dict[str, Any]) -> dict[str, Any]:
-        """Search the song library"""
+        """Search the song library.
+
+        ``offset`` and ``limit`` select a page of the matches; ``total``
+        is always the number of matches before paging."""
         query = params.get("query", "")
+        offset = params.get("offset", 0)
+        limit = params.get("limit")
         try:
             results = self.song_db.search_database(query, database.AppYielder())
+            page = results[offset:] if limit is None else results[offset:offset + limit]
             return {
                 "status": "ok",
-                "data": {"results": [self._song_to_dict(song) for song in results]},
+                "data": {
+                    "results": [self._song_to_dict(song) for song in page],
+                    "total": len(results),
+                },
             }
         except (AttributeError, ValueError) as e:
             return {"status": "error", "message": str(e)}
 
-    def _handle_get_library(self, _params: dict[str, Any]) -> dict[str, Any]:
-        """Get library contents"""
+    _LIBRARY_SORT_KEYS = {
+        "title": lambda song: (song.title or "").casefold(),
+        "artist": lambda song: (song.artist or "").casefold(),
+    }
+
+    def _handle_get_library(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Get library contents.
+
+        ``filter`` keeps songs whose title, artist or filename contains it
+        (ignoring case).  ``sort_by`` is ``title``, ``artist`` or
+        ``date_added`` (the order songs entered the library), ascending
+        unless ``ascending`` is false.  ``offset`` and ``limit`` select a
+        page; ``total`` is the number of matches before paging."""
+        sort_by = params.get("sort_by")
+        if sort_by not in (None, "date_added") and sort_by not in self._LIBRARY_SORT_KEYS:
+            return {"status": "error", "message": f"Unknown sort_by: {sort_by}"}
         try:
-            songs = self.song_db.song_list if hasattr(self.song_db, "song_list") else []
-            return {"status": "ok", "data": {"songs": [self._song_to_dict(song) for song in songs]}}
+            if sort_by is None:
+                songs = list(getattr(self.song_db, "song_list", []))
+            else:
+                songs = list(getattr(self.song_db, "full_song_list", []))
+            query = (params.get("filter") or "").casefold()
+            if query:
+                songs = [
+                    song for song in songs
+                    if any(
+                        query in (getattr(song, field, "") or "").casefold()
+                        for field in ("title", "artist", "display_filename")
+                    )
+                ]
+            if sort_by in self._LIBRARY_SORT_KEYS:
+                songs.sort(key=self._LIBRARY_SORT_KEYS[sort_by])
+            if not params.get("ascending", True):
+                songs.reverse()
+            offset = params.get("offset", 0)
+            limit = params.get("limit")
+            page = songs[offset:] if limit is None else songs[offset:offset + limit]
+            return {
+                "status": "ok",
+                "data": {"songs": [self._song_to_dict(song) for song in page], "total": len(songs)},
+            }
         except (AttributeError, ValueError) as e:
             return {"status": "error", "message": str(e)}
 
-    def _handle_scan_library(self, _params: dict[str, Any]) -> dict[str, Any]:
-        """Scan library folders"""
+    def _handle_scan_library(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Scan library folders, skipping folders matching ``excludes``"""
         logger.info("Starting library scan")
+        db = self.song_db
+        excludes = self._scan_excludes(params)
+        if excludes is None:
+            return {"status": "error", "message": "excludes must be a list of strings"}
+        db.scan_excludes = excludes
+        previous = (db.full_song_list, getattr(db, "unique_song_list", None), getattr(db, "titles_files", []))
+        before = len(db.full_song_list)
+        started = time.monotonic()
         try:
-            self.song_db.build_search_database(
-                database.AppYielder(), database.BusyCancelDialog()
-            )
+            with self._scan() as progress:
+                db.build_search_database(database.AppYielder(), progress)
+            if progress.clicked:
+                # A rescan starts from scratch, so keep the library as it
+                # was rather than the part scanned so far.
+                db.full_song_list, db.unique_song_list, db.titles_files = previous
+                db.select_sort("filename")
+                summary = self._emit_scan_complete(0, started, cancelled=True)
+                return {"status": "ok", "message": "Library scan cancelled", "data": summary}
             # Populate song_list so get_library / search work immediately
-            self.song_db.select_sort("filename")
-            self.song_db.save_database()
-            count = len(self.song_db.full_song_list)
+            db.select_sort("filename")
+            db.save_database()
+            count = len(db.full_song_list)
             logger.info("Library scan complete: %d songs found", count)
-            self._emit_event("library_scan_complete", {"song_count": count})
-            return {"status": "ok", "message": "Library scan complete", "data": {"song_count": count}}
+            summary = self._emit_scan_complete(max(count - before, 0), started)
+            return {"status": "ok", "message": "Library scan complete", "data": summary}
         except (OSError, RuntimeError, ValueError) as e:
             return {"status": "error", "message": str(e)}
 
     def _handle_add_folder(self, params: dict[str, Any]) -> dict[str, Any]:
-        """Add a folder to the library and scan it for songs."""
+        """Add a folder to the library and scan it for songs.
+
+        With ``recursive`` false only the files directly inside the folder
+        are scanned, and the folder is not remembered for later rescans
+        (which always descend into subfolders)."""
         folder = params.get("folder")
         if not folder:
             return {"status": "error", "message": "folder required"}
+        excludes = self._scan_excludes(params)
+        if excludes is None:
+            return {"status": "error", "message": "excludes must be a list of strings"}
+        self.song_db.scan_excludes = excludes
+        recursive = params.get("recursive", True)
+        before = len(self.song_db.full_song_list)
+        started = time.monotonic()
 
         try:
-            self.song_db.folder_add(folder)
-            self.song_db.save_settings()
-            # Scan the newly added folder so its songs are available immediately
-            self.song_db.add_file(folder)
+            if recursive:
+                self.song_db.folder_add(folder)
+                self.song_db.save_settings()
+                roots = [folder]
+            else:
+                roots = [
+                    path
+                    for path in (os.path.join(folder, name) for name in sorted(os.listdir(folder)))
+                    if not os.path.isdir(path)
+                ]
+            # Scan the newly added folder so its songs are available
+            # immediately.  If cancelled, the songs found so far are kept.
+            with self._scan() as progress:
+                self.song_db.do_search(roots, database.AppYielder(), progress)
             self.song_db.select_sort("filename")
             self.song_db.save_database()
-            self._emit_event("library_scan_complete", {})
-            return {"status": "ok", "message": f"Folder added and scanned: {folder}"}
+            songs_added = max(len(self.song_db.full_song_list) - before, 0)
+            if progress.clicked:
+                summary = self._emit_scan_complete(songs_added, started, cancelled=True)
+                return {"status": "ok", "message": f"Scan of {folder} cancelled", "data": summary}
+            summary = self._emit_scan_complete(songs_added, started)
+            return {"status": "ok", "message": f"Folder added and scanned: {folder}", "data": summary}
         except Exception as e:
             return {"status": "error", "message": str(e)}
 
+    @staticmethod
+    def _scan_excludes(params: dict[str, Any]) -> list[str] | None:
+        """The ``excludes`` folder patterns of a scan request; None if
+        they aren't a list of strings."""
+        excludes = params.get("excludes", [])
+        if not isinstance(excludes, list) or not all(isinstance(p, str) for p in excludes):
+            return None
+        return excludes
+
+    @contextlib.contextmanager
+    def _scan(self):
+        """Mark a scan as running for ``cancel_scan`` and yield the progress
+        dialog it reports through.  A cancel left over from an earlier scan
+        doesn't stop this one."""
+        self._scan_cancel.clear()
+        self._scanning = True
+        try:
+            yield _ScanProgress(self._emit_event, self._scan_cancel)
+        finally:
+            self._scanning = False
+
+    def _handle_cancel_scan(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Stop the running scan; it answers its own request with what it
+        found so far.  Called from the stdio reader thread."""
+        if not self._scanning:
+            return {"status": "error", "message": "No scan in progress"}
+        logger.info("Cancelling scan (request %s)", params.get("request_id"))
+        self._scan_cancel.set()
+        return {"status": "ok"}
+
+    def _handle_cancel(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Abort the command sent with ``request_id``: a scan it is running
+        stops where it is, and a command still queued is skipped.  Other
+        running commands can't be interrupted and finish as usual.  Called
+        from the stdio reader thread."""
+        target = params.get("request_id")
+        if target is None:
+            return {"status": "error", "message": "request_id required"}
+        with self._cancel_lock:
+            if target != self.running_request_id:
+                self._cancelled_requests.add(target)
+                return {"status": "ok", "data": {"cancelled": True}}
+        if self._scanning:
+            logger.info("Cancelling scan (request %s)", target)
+            self._scan_cancel.set()
+            return {"status": "ok", "data": {"cancelled": True}}
+        return {"status": "ok", "data": {"cancelled": False}}
+
+    def begin_request(self, request_id: Any) -> bool:
+        """Make the command with ``request_id`` the running one, unless it
+        was cancelled while it waited its turn; returns whether to run it.
+        A cancel then lands either before, skipping it, or after, seeing it
+        running."""
+        with self._cancel_lock:
+            if request_id is not None and request_id in self._cancelled_requests:
+                self._cancelled_requests.discard(request_id)
+                return False
+            self.running_request_id = request_id
+            return True
+
+    def _emit_scan_complete(self, songs_added: int, started: float, cancelled: bool = False) -> dict[str, Any]:
+        """Emit ``scan_complete`` (or ``scan_cancelled``) summarising the
+        scan that just finished, and return the summary."""
+        count = len(self.song_db.full_song_list)
+        summary = {
+            "song_count": count,
+            "songs_added": songs_added,
+            "errors": list(getattr(self.song_db, "scan_errors", [])),
+            "duration_ms": int((time.monotonic() - started) * 1000),
+            "cancelled": cancelled,
+        }
+        self._emit_event("scan_cancelled" if cancelled else "scan_complete", summary)
+        return summary
+
+    def _handle_version(self) -> dict[str, Any]:
+        """Report the backend's package version"""
+        from pykaraoke import __version__
+
+        return {"status": "ok", "data": {"version": __version__}}
+
+    def _handle_handshake(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Report the protocol version this backend speaks"""
+        logger.info(
+            "Handshake: host protocol %s, backend protocol %d",
+            params.get("protocol"), PROTOCOL_VERSION,
+        )
+        return {"status": "ok", "data": {"protocol": PROTOCOL_VERSION}}
+
+    def _handle_get_supported_formats(self) -> dict[str, Any]:
+        """File extensions (lower case, without the dot) the library and
+        players accept, per the current settings."""
+        settings = self.song_db.settings
+        extensions = [
+            ext.lstrip(".").lower()
+            for ext in settings.cdg_extensions + settings.kar_extensions + settings.mpg_extensions
+            if ext not in settings.ignored_extensions
+        ]
+        if settings.look_inside_zips:
+            extensions.append("zip")
+        return {"status": "ok", "data": {"extensions": extensions}}
+
+    def _handle_get_backend_capabilities(self) -> dict[str, Any]:
+        """What this backend can decode, the optional features it handles
+        and the optional modules it found.  Video (``mpg``) is only listed
+        when the external player configured for it is installed."""
+        codecs = list(NATIVE_CODECS)
+        try:
+            player = shlex.split(self.song_db.settings.mpg_external)[0]
+        except (AttributeError, IndexError, ValueError):
+            player = None
+        if player and shutil.which(player):
+            codecs.append("mpg")
+        features = [feature for feature, action in FEATURE_ACTIONS.items() if action in self._command_handlers]
+        plugins = [name for name in OPTIONAL_PLUGINS if _module_available(name)]
+        return {"status": "ok", "data": {"codecs": codecs, "features": features, "plugins": plugins}}
+
+    # Audio device handlers
+
+    def _handle_list_audio_devices(self) -> dict[str, Any]:
+        """List audio output devices.  The selected device is marked as
+        default, or the first one (SDL's usual default) if none is chosen."""
+        try:
+            names = _audio_device_names()
+        except Exception as e:
+            return {"status": "error", "message": f"Cannot list audio devices: {e}"}
+        selected = manager.audio_device
+        devices = [
+            {
+                "id": name,
+                "name": name,
+                "is_default": name == selected if selected else index == 0,
+            }
+            for index, name in enumerate(names)
+        ]
+        return {"status": "ok", "data": {"devices": devices}}
+
+    def _handle_list_input_devices(self) -> dict[str, Any]:
+        """List audio input devices, marked as for the outputs."""
+        try:
+            names = _audio_device_names(capture=True)
+        except Exception as e:
+            return {"status": "error", "message": f"Cannot list input devices: {e}"}
+        devices = [
+            {
+                "id": name,
+                "name": name,
+                "is_default": name == self.input_device if self.input_device else index == 0,
+            }
+            for index, name in enumerate(names)
+        ]
+        return {"status": "ok", "data": {"devices": devices}}
+
+    def _handle_set_input_device(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Switch the microphone; mic monitoring, if on, moves over to it"""
+        device = params.get("id")
+        if not device:
+            return {"status": "error", "message": "id required"}
+        previous, self.input_device = self.input_device, device
+        if self._mic is not None:
+            self._stop_mic_monitoring()
+            try:
+                self._start_mic_monitoring()
+            except Exception as e:
+                # Keep the microphone that worked, and monitoring on it.
+                self.input_device = previous
+                self._resume_mic_monitoring(True)
+                return {"status": "error", "message": f"Can't monitor {device}: {e}"}
+        return {"status": "ok"}
+
+    def _handle_set_mic_monitoring(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Play the microphone through the audio output while ``enabled``,
+        scaled by ``gain`` (0 to 2), so singers hear themselves over the
+        music.  Needs the audio to be open."""
+        enabled = params.get("enabled")
+        gain = params.get("gain", 1.0)
+        if not isinstance(enabled, bool):
+            return {"status": "error", "message": "enabled must be true or false"}
+        if isinstance(gain, bool) or not isinstance(gain, (int, float)) or not 0 <= gain <= 2:
+            return {"status": "error", "message": "gain must be a number between 0 and 2"}
+        self.mic_gain = float(gain)
+        if enabled and self._mic is None:
+            try:
+                self._start_mic_monitoring()
+            except Exception as e:
+                return {"status": "error", "message": f"Can't monitor the microphone: {e}"}
+        elif not enabled:
+            self._stop_mic_monitoring()
+        data = {"enabled": enabled, "gain": self.mic_gain, "device": self.input_device}
+        return {"status": "ok", "data": data}
+
+    def _start_mic_monitoring(self) -> None:
+        """Open the input device and queue what it records on a channel
+        kept back from the music, the tone and sound effects."""
+        import pygame
+
+        mixer = pygame.mixer.get_init()
+        if not mixer:
+            raise RuntimeError("Audio is not open")
+        rate, size, channels = mixer
+        if size != -16:
+            raise RuntimeError("Mic monitoring needs a signed 16-bit mixer")
+        if self.input_device is None:
+            names = _audio_device_names(capture=True)
+            if not names:
+                raise RuntimeError("No input device found")
+            self.input_device = names[0]
+        pygame.mixer.set_reserved(1)
+        output = pygame.mixer.Channel(0)
+        # A channel queues only one sound behind the playing one, so later
+        # chunks wait here; past the limit the oldest is dropped, keeping
+        # the delay singers hear short.
+        backlog: deque[Any] = deque(maxlen=MIC_BACKLOG_CHUNKS)
+
+        def forward(_device: Any, chunk: Any) -> None:
+            data = bytes(chunk)
+            if self.mic_gain != 1.0:
+                data = _scaled_pcm16(data, self.mic_gain)
+            try:
+                backlog.append(pygame.mixer.Sound(buffer=data))
+                if not output.get_busy():
+                    output.play(backlog.popleft())
+                if backlog and output.get_queue() is None:
+                    output.queue(backlog.popleft())
+            except pygame.error:
+                # The mixer was closed under us, e.g. by reinit_audio.
+                pass
+
+        self._mic = _open_capture_device(self.input_device, rate, channels, forward)
+
+    def _stop_mic_monitoring(self) -> None:
+        if self._mic is None:
+            return
+        self._mic.close()
+        self._mic = None
+
+    def _resume_mic_monitoring(self, was_on: bool) -> None:
+        """Start mic monitoring again if it was on, e.g. once the mixer it
+        played through has been reopened."""
+        if not was_on:
+            return
+        try:
+            self._start_mic_monitoring()
+        except Exception:
+            logger.exception("Can't resume mic monitoring")
+
+    def _handle_get_audio_stats(self) -> dict[str, Any]:
+        """Format of the open audio output.  SDL doesn't report buffer
+        underruns to pygame, so ``underruns`` is always null here; the
+        format fields are null while the audio is closed."""
+        props = manager.audio_props
+        frequency, _, channels, _ = props if props else (None, None, None, None)
+        return {
+            "status": "ok",
+            "data": {
+                "underruns": None,
+                "buffer_ms": manager.get_audio_buffer_ms(),
+                "sample_rate": frequency,
+                "channels": abs(channels) if channels else None,
+            },
+        }
+
+    def _handle_reopen_audio(self) -> dict[str, Any]:
+        """Reinitialise the audio device, e.g. after system sleep tore it
+        down.  If the audio wasn't open it simply opens on the next song."""
+        props = manager.audio_props
+        monitoring = self._mic is not None
+        self._stop_mic_monitoring()
+        try:
+            manager.close_audio()
+            if props:
+                frequency, size, channels, _buffer = props
+                manager.open_audio(frequency, size, channels)
+        except Exception as e:
+            return {"status": "error", "message": f"Cannot reopen audio: {e}"}
+        self._resume_mic_monitoring(monitoring and bool(props))
+        return {"status": "ok"}
+
+    def _handle_reinit_audio(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Close and reopen the audio output, on device ``id`` if given,
+        e.g. after a USB device was unplugged.  The playlist is untouched
+        and the current song carries on from where it was, still paused
+        if it was paused.  Emits ``audio_ready`` once the device is open."""
+        device = params.get("id")
+        was = self.state
+        resume = self.current_player is not None and was in (BackendState.PLAYING, BackendState.PAUSED)
+        props = manager.audio_props
+        # The mic's channel belongs to the mixer about to be closed.
+        monitoring = self._mic is not None
+        self._stop_mic_monitoring()
+        try:
+            if device:
+                manager.set_audio_device(device)
+            manager.close_audio()
+            if props:
+                frequency, size, channels, _buffer = props
+                manager.open_audio(frequency, size, channels)
+            else:
+                manager.open_audio()
+            if resume:
+                # A new player rather than _start_playback, so the song
+                # isn't announced as started again.
+                self.current_player.close()
+                self.current_player = self._make_player(self.current_song)
+                if not self.current_player:
+                    raise RuntimeError("Failed to create player")
+                self.current_player.play()
+                self.current_player.seek(self.position_ms)
+                manager.set_volume(self._output_volume())
+                if was == BackendState.PAUSED:
+                    self.current_player.pause()
+        except Exception as e:
+            logger.exception("Audio re-init failed")
+            return {"status": "error", "message": f"Cannot reinitialise audio: {e}"}
+        self._resume_mic_monitoring(monitoring)
+        data = {"device": manager.audio_device, "position_ms": self.position_ms, "resumed": resume}
+        self._emit_event("audio_ready", data)
+        return {"status": "ok", "data": data}
+
+    def _handle_capture_frame(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Save the current render surface to ``path`` as a PNG"""
+        path = params.get("path")
+        if not path:
+            return {"status": "error", "message": "path required"}
+        if manager.surface is None:
+            return {"status": "error", "message": "Nothing is being displayed"}
+        import pygame
+
+        try:
+            pygame.image.save(manager.surface, path)
+        except (pygame.error, OSError) as e:
+            return {"status": "error", "message": f"Cannot save frame: {e}"}
+        return {"status": "ok", "data": {"path": path}}
+
+    def _handle_set_audio_device(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Switch the audio output device; applies from the next song"""
+        device = params.get("id")
+        if not device:
+            return {"status": "error", "message": "id required"}
+        manager.set_audio_device(device)
+        return {"status": "ok"}
+
     # Settings handlers
 
     def _handle_get_settings(self) -> dict[str, Any]:
@@ -596,6 +1714,15 @@ class PyKaraokeBackend:
             },
         }
 
+    def _handle_set_library_roots(self, params: dict[str, Any]) -> dict[str, Any]:
+        """Replace the folders scan_library scans with ``roots``"""
+        roots = params.get("roots")
+        if not isinstance(roots, list) or not all(isinstance(root, str) for root in roots):
+            return {"status": "error", "message": "roots must be a list of folders"}
+        self.song_db.settings.folder_list = list(dict.fromkeys(roots))
+        self.song_db.save_settings()
+        return {"status": "ok", "data": {"roots": self.song_db.get_folder_list()}}
+
     def _handle_update_settings(self, params: dict[str, Any]) -> dict[str, Any]:
         """Update settings"""
         logger.info("Updating settings: %d key(s)", len(params))
@@ -637,16 +1764,33 @@ class PyKaraokeBackend:
             if self.state == BackendState.PLAYING:
                 with contextlib.suppress(Exception):
                     self.position_ms = self.current_player.get_pos()
+                if self.crossfade_ms and not self._fading_out:
+                    with contextlib.suppress(Exception):
+                        manager.set_volume(self._output_volume())
 
     def shutdown(self):
         """Shutdown the backend"""
         logger.info("Shutting down backend")
+        self._stop_mic_monitoring()
         if self.current_player:
             self.current_player.close()
         manager.quit()
 
 
-def create_stdio_server(backend: PyKaraokeBackend, *, json_out=None):
+def _read_frames(stream):
+    """Yield length-prefixed messages (4-byte big-endian length + UTF-8)."""
+    while True:
+        header = stream.read(4)
+        if len(header) < 4:
+            return
+        (length,) = struct.unpack(">I", header)
+        payload = stream.read(length)
+        if len(payload) < length:
+            return
+        yield payload.decode("utf-8", errors="replace")
+
+
+def create_stdio_server(backend: PyKaraokeBackend, *, json_out=None, framing="ndjson"):
     """
     Create a stdio-based command server.
     Reads JSON commands from stdin and writes responses to stdout.
@@ -664,6 +1808,10 @@ def create_stdio_server(backend: PyKaraokeBackend, *, json_out=None):
         The file object connected to the real stdout (the JSON protocol
         channel).  If *None*, ``sys.stdout`` is used (and then swapped
         to stderr).
+    framing : str
+        ``"ndjson"`` (one JSON object per line, the default) or
+        ``"length-prefixed"`` (4-byte big-endian length + payload, for
+        both stdin and stdout).
     """
 
     # ── guard the JSON channel ──────────────────────────────────────
@@ -671,40 +1819,97 @@ def create_stdio_server(backend: PyKaraokeBackend, *, json_out=None):
         json_out = sys.stdout          # private handle for protocol output
         sys.stdout = sys.stderr        # stray print() → stderr, not the pipe
 
+    length_prefixed = framing == "length-prefixed"
+    if length_prefixed:
+        json_out = getattr(json_out, "buffer", json_out)
+        messages = _read_frames(getattr(sys.stdin, "buffer", sys.stdin))
+    else:
+        messages = sys.stdin
+
+    # Immediate actions are answered from the reader thread.
+    write_lock = threading.Lock()
+
     def _write_json(obj: dict[str, Any]):
         """Write a single JSON object to the protocol channel."""
-        json_out.write(json.dumps(obj))
-        json_out.write("\n")
-        json_out.flush()
+        with write_lock:
+            if length_prefixed:
+                data = json.dumps(obj).encode("utf-8")
+                json_out.write(struct.pack(">I", len(data)) + data)
+            else:
+                json_out.write(json.dumps(obj))
+                json_out.write("\n")
+            json_out.flush()
+
+    # ``request_id`` of the command being handled, echoed on its response
+    # and on any events it emits so the host can correlate them.  Events
+    # from other threads (the update loop, a scan) happen to coincide with
+    # the command and aren't tagged.
+    request_id = None
+    command_thread = threading.current_thread()
+
+    def _envelope(kind: str, payload: dict[str, Any]) -> dict[str, Any]:
+        obj = {"type": kind, kind: payload}
+        if request_id is not None and threading.current_thread() is command_thread:
+            obj["request_id"] = request_id
+        return obj
 
     def event_callback(event: dict[str, Any]):
         """Send events to frontend via the protocol channel."""
-        _write_json({"type": "event", "event": event})
+        _write_json(_envelope("event", event))
 
     backend.set_event_callback(event_callback)
 
     logger.info("Starting stdio server")
 
+    # Read on a separate thread so IMMEDIATE_ACTIONS get through while the
+    # main loop is busy; everything else is queued in order.
+    lines: queue.Queue[str | None] = queue.Queue()
+
+    def _read_messages():
+        try:
+            for line in messages:
+                try:
+                    command = json.loads(line)
+                except json.JSONDecodeError:
+                    command = None
+                if isinstance(command, dict) and command.get("action") in IMMEDIATE_ACTIONS:
+                    # Tagged with its own request_id, not the running command's.
+                    obj = {"type": "response", "response": backend.handle_command(command)}
+                    if command.get("request_id") is not None:
+                        obj["request_id"] = command["request_id"]
+                    _write_json(obj)
+                else:
+                    lines.put(line)
+        finally:
+            lines.put(None)
+
+    threading.Thread(target=_read_messages, name="stdio-reader", daemon=True).start()
+
     try:
-        for line in sys.stdin:
+        for line in iter(lines.get, None):
             line = line.strip()
             if not line:
                 continue
 
             try:
                 command = json.loads(line)
-                response = backend.handle_command(command)
-                _write_json({"type": "response", "response": response})
+                if isinstance(command, dict):
+                    request_id = command.get("request_id")
+                if backend.begin_request(request_id):
+                    response = backend.handle_command(command)
+                else:
+                    response = {"status": "error", "message": "Cancelled"}
+                _write_json(_envelope("response", response))
             except json.JSONDecodeError as e:
                 _write_json({
                     "type": "response",
                     "response": {"status": "error", "message": f"Invalid JSON: {e}"},
                 })
             except (ValueError, TypeError) as e:
-                _write_json({
-                    "type": "response",
-                    "response": {"status": "error", "message": str(e)},
-                })
+                _write_json(_envelope("response", {"status": "error", "message": str(e)}))
+            finally:
+                request_id = None
+                backend.running_request_id = None
 
     except KeyboardInterrupt:
         logger.info("Received interrupt signal")
@@ -965,6 +2170,14 @@ Examples:
         help="Explicitly set the mode (stdio or http)",
     )
 
+    parser.add_argument(
+        "--framing",
+        type=str,
+        choices=["ndjson", "length-prefixed"],
+        default=os.getenv("PYKARAOKE_FRAMING", "ndjson"),
+        help="stdio message framing (default: ndjson, env: PYKARAOKE_FRAMING)",
+    )
+
     # HTTP-specific options
     parser.add_argument(
         "--host",
@@ -1012,7 +2225,7 @@ Examples:
     if mode == "http":
         create_http_server(backend, host=args.host, port=args.port)
     else:
-        create_stdio_server(backend, json_out=json_out)
+        create_stdio_server(backend, json_out=json_out, framing=args.framing)
 
 
 if __name__ == "__main__":
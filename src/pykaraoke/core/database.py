@@ -23,6 +23,7 @@
 """This module provides support for the PyKaraoke song database, as
 well as the user's settings file."""
 
+import fnmatch
 import os
 import sys
 import time
@@ -995,6 +996,10 @@ class SongDB:
         # A cache of zip files.
         self.zip_files = []
 
+        # Glob patterns of folders to skip while scanning, matched
+        # against the full path and the folder name.
+        self.scan_excludes = []
+
         # Set true if there are local changes to the database that
         # need to be saved to disk.
         self.database_dirty = False
@@ -1452,6 +1457,9 @@ class SongDB:
         # cause the same song file to be added multiple times.
         self._scanned_paths: set[str] = set()
 
+        # Paths that couldn't be read, reported in the scan summary.
+        self.scan_errors: list[str] = []
+
         for i in range(len(file_list)):
             root_path = file_list[i]
 
@@ -1511,6 +1519,7 @@ class SongDB:
             filedir_list = os.listdir(folder_to_scan)
         except OSError:
             print("Couldn't scan %s" % (repr(folder_to_scan)))
+            self.scan_errors.append("Couldn't scan %s" % folder_to_scan)
             return False
 
         # Sort the list, using printable strings for the sort key to
@@ -1577,7 +1586,7 @@ class SongDB:
         # Recurse into subdirectories
         if os.path.isdir(full_path):
             basename = os.path.split(full_path)[1]
-            if basename not in ("CVS", ".svn"):
+            if basename not in ("CVS", ".svn") and not self.is_scan_excluded(full_path):
                 self.folder_scan(full_path, progress, yielder)
             if self.busy_dlg.clicked:
                 return
@@ -1592,6 +1601,15 @@ class SongDB:
         elif self.settings.look_inside_zips and ext.lower() == ".zip":
             self._scan_zip_file(full_path, progress, yielder)
 
+    def is_scan_excluded(self, folder):
+        """Returns True if the folder matches one of scan_excludes."""
+        path = os.path.normpath(str(folder))
+        name = os.path.basename(path)
+        return any(
+            fnmatch.fnmatch(path, pattern) or fnmatch.fnmatch(name, pattern)
+            for pattern in self.scan_excludes
+        )
+
     def _update_progress_if_needed(self, full_path, progress, yielder):
         """Update the progress bar if enough time has elapsed."""
         now = time.time()
@@ -1625,6 +1643,7 @@ class SongDB:
         try:
             if not zipfile.is_zipfile(full_path):
                 print("Cannot parse ZIP file: " + repr(full_path))
+                self.scan_errors.append("Cannot parse ZIP file: %s" % full_path)
                 return
             zf = self.get_zip_file(full_path)
             namelist = zf.namelist()
@@ -1633,6 +1652,7 @@ class SongDB:
                 self._process_zip_member(full_path, filename, zf)
         except (zipfile.BadZipFile, OSError):
             print("Error looking inside zip " + repr(full_path))
+            self.scan_errors.append("Error looking inside zip %s" % full_path)
 
     def _update_zip_progress(self, full_path, progress, i, total, yielder):
         """Update progress bar during ZIP scanning."""
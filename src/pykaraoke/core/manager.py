@@ -59,6 +59,8 @@ class PykManager:
         self.display = None
         self.surface = None
         self.audio_props = None
+        # SDL output device name; None for the system default.
+        self.audio_device = None
 
         self.display_size = None
         self.display_flags = 0
@@ -333,9 +335,17 @@ class PykManager:
             # If the audio properties have changed, we have to shut
             # down and re-start the audio subsystem.
             pygame.mixer.quit()
-            pygame.mixer.init(*audio_props)
+            device = {"devicename": self.audio_device} if self.audio_device else {}
+            pygame.mixer.init(*audio_props, **device)
             self.audio_props = audio_props
 
+    def set_audio_device(self, device):
+        """Choose the audio output device by name (None for the system
+        default).  Takes effect the next time the audio is opened."""
+        if device != self.audio_device:
+            self.audio_device = device
+            self.close_audio()
+
     def close_audio(self):
         pygame.mixer.quit()
         self.audio_props = None
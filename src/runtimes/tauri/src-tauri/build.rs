@@ -1,4 +1,6 @@
-use std::path::PathBuf;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 
 fn main() {
     // The "backend/**" resource glob in tauri.conf.json must match at least one
@@ -32,5 +34,69 @@ fn main() {
         std::fs::write(&placeholder, "# placeholder so backend/** glob matches during cargo test\n").ok();
     }
 
+    write_backend_manifest(&backend_dir);
     tauri_build::build()
 }
+
+/// Write `backend_manifest.json` to `OUT_DIR` for the app to check the
+/// installed backend against and report which backend it was built with
+/// (see `integrity.rs`): the app and backend versions, whether the tree
+/// is only the placeholder, and the SHA-256 and size of every file in it,
+/// with `/`-separated paths relative to the backend directory.
+fn write_backend_manifest(backend_dir: &Path) {
+    println!("cargo:rerun-if-changed={}", backend_dir.display());
+    let mut files = Vec::new();
+    collect_files(backend_dir, backend_dir, &mut files);
+    files.sort();
+    // Once created, the placeholder directory counts as a real file above,
+    // so tell from what was actually staged.
+    let placeholder = files == ["pykaraoke/PLACEHOLDER"];
+    let files: Vec<_> = files
+        .into_iter()
+        .map(|relative| {
+            let data = std::fs::read(backend_dir.join(&relative)).expect("backend file is readable");
+            let sha256: String = Sha256::digest(&data).iter().map(|byte| format!("{byte:02x}")).collect();
+            json!({ "path": relative, "sha256": sha256, "size": data.len() })
+        })
+        .collect();
+    let manifest = json!({
+        "app_version": std::env::var("CARGO_PKG_VERSION").unwrap(),
+        "backend_version": backend_version(backend_dir),
+        "placeholder": placeholder,
+        "files": files,
+    });
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let text = serde_json::to_string_pretty(&manifest).expect("manifest serializes");
+    std::fs::write(out_dir.join("backend_manifest.json"), text).expect("OUT_DIR is writable");
+}
+
+/// The `__version__` in the staged `pykaraoke/__init__.py`, if there is
+/// one; a PyInstaller build has only the executable.
+fn backend_version(backend_dir: &Path) -> Option<String> {
+    let init = std::fs::read_to_string(backend_dir.join("pykaraoke").join("__init__.py")).ok()?;
+    init.lines().find_map(|line| {
+        let value = line.strip_prefix("__version__")?.trim_start().strip_prefix('=')?.trim();
+        Some(value.trim_matches(|c| c == '"' || c == '\'').to_string())
+    })
+}
+
+/// Files under `dir`, skipping dotfiles and bytecode caches, which differ
+/// between machines.
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = dir.read_dir() else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || name == "__pycache__" {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(root, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            files.push(parts.join("/"));
+        }
+    }
+}
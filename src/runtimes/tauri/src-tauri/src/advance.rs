@@ -0,0 +1,113 @@
+//! Host-driven playlist auto-advance.
+//!
+//! Once the frontend calls `set_auto_advance`, the backend's own
+//! advancing is turned off and a `song_finished` event makes the app send
+//! `next` itself, announcing it with `playlist_advanced`.  The end of the
+//! playlist stops the chain because the backend rejects `next` there; as
+//! a second guard, advances closer together than
+//! [`MIN_ADVANCE_INTERVAL`] are skipped so a run of songs that fail
+//! instantly can't spin.
+//!
+//! The repeat mode decides what an advance does: replay the same song
+//! (`one`), go to the next one and wrap to the start (`all`), or just go
+//! to the next one (`off`).
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+pub const MIN_ADVANCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What happens when a song finishes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    /// Play the same song again.
+    One,
+    /// Start over from the top after the last song.
+    All,
+}
+
+impl RepeatMode {
+    /// Every accepted `mode` value, for param validation.
+    pub const NAMES: &'static [&'static str] = &["off", "one", "all"];
+}
+
+#[derive(Debug, Default)]
+pub struct AutoAdvance {
+    /// `None` until the frontend takes over advancing.
+    enabled: Option<bool>,
+    last_advance: Option<Instant>,
+    repeat: RepeatMode,
+}
+
+impl AutoAdvance {
+    pub fn set(&mut self, enabled: bool) {
+        self.enabled = Some(enabled);
+    }
+
+    pub fn set_repeat(&mut self, mode: RepeatMode) {
+        self.repeat = mode;
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    /// Whether the host, not the backend, decides about advancing.
+    pub fn host_managed(&self) -> bool {
+        self.enabled.is_some()
+    }
+
+    /// Whether a song finishing at `now` should trigger `next`.  Returning
+    /// true records the advance.
+    pub fn should_advance(&mut self, now: Instant) -> bool {
+        if self.enabled != Some(true) {
+            return false;
+        }
+        if self
+            .last_advance
+            .is_some_and(|last| now.duration_since(last) < MIN_ADVANCE_INTERVAL)
+        {
+            return false;
+        }
+        self.last_advance = Some(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advances_only_when_enabled() {
+        let mut advance = AutoAdvance::default();
+        assert!(!advance.host_managed());
+        assert!(!advance.should_advance(Instant::now()));
+        advance.set(false);
+        assert!(advance.host_managed());
+        assert!(!advance.should_advance(Instant::now()));
+        advance.set(true);
+        assert!(advance.should_advance(Instant::now()));
+    }
+
+    #[test]
+    fn repeat_mode_parses_from_snake_case() {
+        let mode: RepeatMode = serde_json::from_str("\"one\"").unwrap();
+        assert_eq!(mode, RepeatMode::One);
+        assert!(serde_json::from_str::<RepeatMode>("\"shuffle\"").is_err());
+        assert_eq!(AutoAdvance::default().repeat(), RepeatMode::Off);
+    }
+
+    #[test]
+    fn rapid_song_finished_events_do_not_chain() {
+        let mut advance = AutoAdvance::default();
+        advance.set(true);
+        let start = Instant::now();
+        assert!(advance.should_advance(start));
+        assert!(!advance.should_advance(start + Duration::from_millis(10)));
+        assert!(advance.should_advance(start + MIN_ADVANCE_INTERVAL));
+    }
+}
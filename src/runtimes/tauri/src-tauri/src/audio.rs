@@ -0,0 +1,133 @@
+//! Typed views of the backend's audio devices and audio health.
+//!
+//! Karaoke rigs often have several outputs (HDMI to the TV, a USB mixer),
+//! so the frontend lets the user pick one.  `set_audio_device` only sends
+//! ids the backend itself listed.  Microphones are listed and picked the
+//! same way, and `set_mic_monitoring` plays the chosen one through the
+//! output so singers hear themselves over the music.
+//!
+//! For diagnosing stutter, `get_audio_stats` reports the output format
+//! and how often the audio buffer ran dry.  The count only ever grows
+//! while a backend runs, so a rise since the last report means the system
+//! couldn't keep up in between.
+//!
+//! When a device goes away mid-song, `reinit_audio` reopens the output
+//! without restarting the backend, so the playlist and the place in the
+//! song are kept.  The backend confirms with an `audio_ready` event, which
+//! the command waits up to [`AUDIO_READY_TIMEOUT`] for.
+
+use crate::error::BackendError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long `reinit_audio` waits for the backend's `audio_ready`.
+pub const AUDIO_READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Loudest `set_mic_monitoring` gain; 1 plays the mic as recorded.
+pub const MAX_MIC_GAIN: f64 = 2.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioDevice {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+/// The `get_audio_stats` answer.  Fields a backend can't measure are
+/// `None`; the Python backend can't see underruns, for one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioStats {
+    /// Times the output buffer ran dry since the backend started.
+    pub underruns: Option<u64>,
+    /// How long a full output buffer plays for.
+    pub buffer_ms: f64,
+    /// `None` while the audio output is closed.
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+}
+
+impl AudioStats {
+    /// Underruns since `previous`, the last stats from the same backend;
+    /// `None` unless the count rose.
+    pub fn new_underruns(&self, previous: Option<&AudioStats>) -> Option<u64> {
+        let before = previous.and_then(|stats| stats.underruns).unwrap_or(0);
+        self.underruns.filter(|&now| now > before).map(|now| now - before)
+    }
+}
+
+/// The `audio_ready` confirmation, re-emitted as `audio-ready`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioReady {
+    /// The device opened; `None` for the system default.
+    pub device: Option<String>,
+    /// Where the current song carries on from.
+    pub position_ms: u64,
+    /// Whether a playing or paused song was picked up again.
+    pub resumed: bool,
+}
+
+/// What `set_mic_monitoring` applied.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MicMonitoring {
+    pub enabled: bool,
+    pub gain: f64,
+    /// The input device monitored; `None` when the backend didn't say.
+    pub device: Option<String>,
+}
+
+/// Parse the `data` field of a `list_audio_devices` or
+/// `list_input_devices` response.
+pub fn devices_from_backend(data: &serde_json::Value) -> Result<Vec<AudioDevice>, serde_json::Error> {
+    Vec::<AudioDevice>::deserialize(&data["devices"])
+}
+
+/// Reject an `id` that isn't among `devices`, as `field` of `action`.
+pub fn ensure_known_device(action: &str, field: &str, id: &str, devices: &[AudioDevice]) -> Result<(), BackendError> {
+    if devices.iter().any(|d| d.id == id) {
+        return Ok(());
+    }
+    Err(BackendError::InvalidParams {
+        action: action.to_string(),
+        field: field.to_string(),
+        reason: format!("'{id}' is not one of the listed audio devices"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_device_list() {
+        let data = json!({ "devices": [
+            { "id": "hdmi", "name": "HDMI", "is_default": true },
+            { "id": "usb", "name": "USB Mixer" },
+        ]});
+        let devices = devices_from_backend(&data).unwrap();
+        assert_eq!(devices.len(), 2);
+        assert!(devices[0].is_default);
+        assert!(!devices[1].is_default);
+    }
+
+    #[test]
+    fn only_a_rising_count_is_new_underruns() {
+        let stats = |underruns| AudioStats { underruns, ..Default::default() };
+        assert_eq!(stats(Some(3)).new_underruns(None), Some(3));
+        assert_eq!(stats(Some(5)).new_underruns(Some(&stats(Some(3)))), Some(2));
+        assert_eq!(stats(Some(3)).new_underruns(Some(&stats(Some(3)))), None);
+        assert_eq!(stats(None).new_underruns(Some(&stats(Some(3)))), None);
+        assert_eq!(stats(Some(0)).new_underruns(None), None);
+    }
+
+    #[test]
+    fn only_listed_devices_are_accepted() {
+        let devices = devices_from_backend(&json!({ "devices": [{ "id": "hdmi", "name": "HDMI" }] })).unwrap();
+        assert!(ensure_known_device("set_audio_device", "id", "hdmi", &devices).is_ok());
+        let err = ensure_known_device("set_audio_device", "id", "spdif", &devices).unwrap_err();
+        assert!(matches!(err, BackendError::InvalidParams { ref field, .. } if field == "id"));
+    }
+}
@@ -0,0 +1,50 @@
+//! Measuring how many commands the IPC layer can carry.
+//!
+//! `benchmark_ipc` writes a burst of `ping` commands without waiting for
+//! each reply, then times how long the replies take to come back.  A rate
+//! far above what the UI asks for means sluggishness lies elsewhere, e.g.
+//! in rendering.  Pings still unanswered after [`BENCHMARK_TIMEOUT`] are
+//! left out of `received` and their late replies are ignored.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// Most pings one benchmark sends.
+pub const MAX_BENCHMARK_PINGS: usize = 10_000;
+
+/// How long a benchmark waits for its replies.
+pub const BENCHMARK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IpcBenchmark {
+    pub sent: usize,
+    pub received: usize,
+    /// From the first ping written to the last reply received.
+    pub elapsed_ms: f64,
+    /// Replies received per second over `elapsed_ms`.
+    pub rate_per_sec: f64,
+}
+
+impl IpcBenchmark {
+    pub fn new(sent: usize, received: usize, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        IpcBenchmark {
+            sent,
+            received,
+            elapsed_ms: secs * 1000.0,
+            rate_per_sec: if secs > 0.0 { received as f64 / secs } else { 0.0 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_counts_received_replies() {
+        let result = IpcBenchmark::new(100, 50, Duration::from_millis(250));
+        assert_eq!(result, IpcBenchmark { sent: 100, received: 50, elapsed_ms: 250.0, rate_per_sec: 200.0 });
+        assert_eq!(IpcBenchmark::new(1, 0, Duration::ZERO).rate_per_sec, 0.0);
+    }
+}
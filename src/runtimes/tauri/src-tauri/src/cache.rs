@@ -0,0 +1,111 @@
+//! What the app's caches hold, for `get_cache_stats` and `clear_cache`.
+//!
+//! The probe and waveform caches live in memory and are sized from their
+//! entries; `disk` is the app cache directory, where the app and its
+//! webview keep files, and is sized by walking it.  Symlinks are counted
+//! as themselves rather than followed, so a link to a song folder can't
+//! make the cache look huge or be emptied along with it.
+
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheKind {
+    /// `probe_song` results.
+    Probe,
+    /// `get_waveform` peaks.
+    Waveform,
+    /// The app cache directory.
+    Disk,
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 3] = [CacheKind::Probe, CacheKind::Waveform, CacheKind::Disk];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CacheKind::Probe => "probe",
+            CacheKind::Waveform => "waveform",
+            CacheKind::Disk => "disk",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Result<Self, String> {
+        CacheKind::ALL.into_iter().find(|known| known.name() == kind).ok_or_else(|| {
+            let names: Vec<_> = CacheKind::ALL.iter().map(|known| known.name()).collect();
+            format!("must be one of {}", names.join(", "))
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CacheStats {
+    pub kind: CacheKind,
+    /// Cached results, or files for `disk`.
+    pub entries: u64,
+    /// Approximate for the in-memory caches.
+    pub bytes: u64,
+}
+
+/// Files under `dir` and their total size; a missing directory is empty.
+pub fn dir_usage(dir: &Path) -> (u64, u64) {
+    let Ok(children) = std::fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    let (mut files, mut bytes) = (0, 0);
+    for child in children.flatten() {
+        let Ok(metadata) = child.path().symlink_metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            let (sub_files, sub_bytes) = dir_usage(&child.path());
+            files += sub_files;
+            bytes += sub_bytes;
+        } else {
+            files += 1;
+            bytes += metadata.len();
+        }
+    }
+    (files, bytes)
+}
+
+/// Remove everything inside `dir`, keeping `dir` itself.
+pub fn clear_dir(dir: &Path) -> Result<(), String> {
+    let Ok(children) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for child in children.flatten() {
+        let path = child.path();
+        let is_dir = path.symlink_metadata().is_ok_and(|metadata| metadata.is_dir());
+        let removed = if is_dir { std::fs::remove_dir_all(&path) } else { std::fs::remove_file(&path) };
+        removed.map_err(|e| format!("Failed to remove {}: {e}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_are_parsed_by_name() {
+        assert_eq!(CacheKind::parse("waveform"), Ok(CacheKind::Waveform));
+        assert_eq!(CacheKind::parse("thumbnails"), Err("must be one of probe, waveform, disk".to_string()));
+    }
+
+    #[test]
+    fn directories_are_walked_and_emptied() {
+        let dir = std::env::temp_dir().join(format!("pykaraoke-cache-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("a.bin"), [0; 10]).unwrap();
+        std::fs::write(dir.join("nested").join("b.bin"), [0; 5]).unwrap();
+        assert_eq!(dir_usage(&dir), (2, 15));
+
+        clear_dir(&dir).unwrap();
+        assert_eq!(dir_usage(&dir), (0, 0));
+        assert!(dir.is_dir(), "the directory itself is kept");
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(dir_usage(&dir), (0, 0));
+    }
+}
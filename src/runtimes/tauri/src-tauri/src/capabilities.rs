@@ -0,0 +1,41 @@
+//! What the running backend says it can do.
+//!
+//! Backends differ: one may decode video through an external player,
+//! another may lack vocal removal.  `get_backend_capabilities` asks once
+//! per backend and caches the answer, and commands for optional features
+//! check it first so the UI can hide what wouldn't work.  A backend too
+//! old to answer is treated as supporting everything, as before.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendCapabilities {
+    /// File formats the backend can play, e.g. `mp3` or `cdg`.
+    pub codecs: Vec<String>,
+    /// Optional features it handles, e.g. `vocal_removal` or `waveform`.
+    pub features: Vec<String>,
+    /// Optional modules it found installed, e.g. `mutagen`.
+    pub plugins: Vec<String>,
+}
+
+impl BackendCapabilities {
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|known| known == feature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn missing_lists_are_empty() {
+        let capabilities: BackendCapabilities =
+            serde_json::from_value(json!({ "codecs": ["cdg", "mp3"], "features": ["waveform"] })).unwrap();
+        assert!(capabilities.supports("waveform"));
+        assert!(!capabilities.supports("vocal_removal"));
+        assert!(capabilities.plugins.is_empty());
+    }
+}
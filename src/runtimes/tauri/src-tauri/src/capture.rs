@@ -0,0 +1,123 @@
+//! Recording a session's backend traffic to a file for a bug report.
+//!
+//! While a capture runs, every message the backend sends on stdout (events
+//! and responses alike) and every stderr line is appended to the file the
+//! user picked, one JSON object per line:
+//!
+//! ```text
+//! {"at_ms":1700000000000,"source":"stdout","message":{"type":"event",...}}
+//! {"at_ms":1700000000004,"source":"stderr","message":"Traceback ..."}
+//! ```
+//!
+//! The capture is shared across backend restarts, so a crash and the
+//! reconnect after it land in the same file.  Lines are flushed as they
+//! are written, so the file is complete up to the last line even if the
+//! app dies mid-capture.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a finished capture wrote.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaptureSummary {
+    pub path: PathBuf,
+    pub lines: u64,
+}
+
+#[derive(Debug)]
+struct CaptureFile {
+    path: PathBuf,
+    writer: LineWriter<File>,
+    lines: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct SessionCapture {
+    file: Option<CaptureFile>,
+}
+
+#[derive(Serialize)]
+struct CaptureLine<'a> {
+    at_ms: u64,
+    source: &'a str,
+    message: &'a serde_json::Value,
+}
+
+impl SessionCapture {
+    /// Start capturing to `path`, truncating it.  A capture already
+    /// running is finished first and returned.
+    pub fn start(&mut self, path: &Path) -> Result<Option<CaptureSummary>, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+        let previous = self.stop()?;
+        self.file = Some(CaptureFile { path: path.to_path_buf(), writer: LineWriter::new(file), lines: 0 });
+        Ok(previous)
+    }
+
+    /// Flush and close the capture; `None` if none was running.
+    pub fn stop(&mut self) -> Result<Option<CaptureSummary>, String> {
+        let Some(mut capture) = self.file.take() else {
+            return Ok(None);
+        };
+        capture
+            .writer
+            .flush()
+            .and_then(|()| capture.writer.get_ref().sync_all())
+            .map_err(|e| format!("Failed to finish {}: {e}", capture.path.display()))?;
+        Ok(Some(CaptureSummary { path: capture.path, lines: capture.lines }))
+    }
+
+    /// Append `message` from `source` (`stdout` or `stderr`).  A capture
+    /// that can't be written to any more is dropped rather than retried on
+    /// every line.
+    pub fn record(&mut self, source: &str, message: &serde_json::Value) {
+        let Some(capture) = self.file.as_mut() else {
+            return;
+        };
+        let at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let line = CaptureLine { at_ms, source, message };
+        let written = serde_json::to_writer(&mut capture.writer, &line)
+            .map_err(std::io::Error::from)
+            .and_then(|()| capture.writer.write_all(b"\n"));
+        match written {
+            Ok(()) => capture.lines += 1,
+            Err(e) => {
+                tracing::warn!("Stopping session capture to {}: {e}", capture.path.display());
+                self.file = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn captured_lines_are_ndjson() {
+        let path = std::env::temp_dir().join(format!("pykaraoke-capture-{}.ndjson", std::process::id()));
+        let mut capture = SessionCapture::default();
+        capture.record("stderr", &json!("before the capture"));
+        assert_eq!(capture.start(&path).unwrap(), None);
+        capture.record("stdout", &json!({ "type": "event", "event": { "type": "song_started" } }));
+        capture.record("stderr", &json!("Traceback"));
+
+        let summary = capture.stop().unwrap().unwrap();
+        assert_eq!((summary.path.as_path(), summary.lines), (path.as_path(), 2));
+        capture.record("stderr", &json!("after the capture"));
+        assert_eq!(capture.stop().unwrap(), None);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["message"]["event"]["type"], "song_started");
+        assert_eq!((&lines[1]["source"], &lines[1]["message"]), (&json!("stderr"), &json!("Traceback")));
+    }
+}
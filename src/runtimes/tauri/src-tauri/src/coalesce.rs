@@ -0,0 +1,182 @@
+//! Coalescing of high-frequency backend events.
+//!
+//! During a library scan the backend can emit thousands of `scan_progress`
+//! events per second.  Forwarding each one with `emit_all` floods the
+//! webview, so events of a coalesced type are held back and only the most
+//! recent one per type is emitted on a timer.  Every other event passes
+//! straight through.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Event types coalesced out of the box.
+pub const DEFAULT_COALESCED_EVENTS: &[&str] = &["scan_progress"];
+
+/// How often held events are flushed to the webview by default.
+pub const DEFAULT_COALESCE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// User-facing coalescing settings, as accepted and returned by the
+/// `set_event_coalescing` command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoalescingConfig {
+    pub event_types: Vec<String>,
+    pub interval_ms: u64,
+}
+
+/// Holds back the latest event of each coalesced type until the next flush.
+#[derive(Debug)]
+pub struct EventCoalescer {
+    event_types: Vec<String>,
+    interval: Duration,
+    /// Latest held event per type, in the order the types first arrived.
+    held: Vec<(String, serde_json::Value)>,
+}
+
+impl Default for EventCoalescer {
+    fn default() -> Self {
+        EventCoalescer {
+            event_types: DEFAULT_COALESCED_EVENTS.iter().map(|t| t.to_string()).collect(),
+            interval: DEFAULT_COALESCE_INTERVAL,
+            held: Vec::new(),
+        }
+    }
+}
+
+impl EventCoalescer {
+    /// Replace the set of coalesced event types and the flush interval.
+    pub fn configure(&mut self, event_types: Vec<String>, interval: Duration) {
+        self.event_types = event_types;
+        self.interval = interval;
+    }
+
+    pub fn config(&self) -> CoalescingConfig {
+        CoalescingConfig {
+            event_types: self.event_types.clone(),
+            interval_ms: self.interval.as_millis() as u64,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Offer an event and return the events that should be emitted now.
+    ///
+    /// A coalesced event replaces any held event of the same type and
+    /// nothing is emitted.  Any other event is emitted immediately, preceded
+    /// by whatever is held so the UI never sees e.g. a scan finish before
+    /// its final progress update.
+    pub fn push(&mut self, event: serde_json::Value) -> Vec<serde_json::Value> {
+        let event_type = event["type"].as_str().unwrap_or_default();
+        if self.event_types.iter().any(|t| t == event_type) {
+            let event_type = event_type.to_string();
+            match self.held.iter_mut().find(|(t, _)| *t == event_type) {
+                Some(slot) => slot.1 = event,
+                None => self.held.push((event_type, event)),
+            }
+            return Vec::new();
+        }
+        let mut out = self.drain();
+        out.push(event);
+        out
+    }
+
+    /// Take every held event, oldest type first.
+    pub fn drain(&mut self) -> Vec<serde_json::Value> {
+        self.held.drain(..).map(|(_, event)| event).collect()
+    }
+}
+
+/// Spawn the timer thread that periodically emits held events.
+///
+/// The thread exits (after a final flush) once `done` is set by the
+/// stdout reader thread.
+pub fn spawn_flusher<F>(coalescer: Arc<Mutex<EventCoalescer>>, done: Arc<AtomicBool>, emit: F)
+where
+    F: Fn(serde_json::Value) + Send + 'static,
+{
+    std::thread::spawn(move || loop {
+        let interval = coalescer.lock().unwrap().interval();
+        std::thread::sleep(interval);
+        let finished = done.load(Ordering::Acquire);
+        for event in coalescer.lock().unwrap().drain() {
+            emit(event);
+        }
+        if finished {
+            break;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn non_coalesced_events_pass_through() {
+        let mut c = EventCoalescer::default();
+        let out = c.push(json!({"type": "state_changed", "data": {}}));
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0]["type"], "state_changed");
+    }
+
+    #[test]
+    fn scan_progress_is_held_and_latest_wins() {
+        let mut c = EventCoalescer::default();
+        for i in 0..100 {
+            assert!(c.push(json!({"type": "scan_progress", "data": {"done": i}})).is_empty());
+        }
+        let flushed = c.drain();
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(flushed[0]["data"]["done"], 99);
+        assert!(c.drain().is_empty());
+    }
+
+    #[test]
+    fn pass_through_event_flushes_held_events_first() {
+        let mut c = EventCoalescer::default();
+        c.push(json!({"type": "scan_progress", "data": {"done": 7}}));
+        let out = c.push(json!({"type": "scan_complete"}));
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0]["type"], "scan_progress");
+        assert_eq!(out[1]["type"], "scan_complete");
+    }
+
+    #[test]
+    fn configure_replaces_coalesced_types() {
+        let mut c = EventCoalescer::default();
+        c.configure(vec!["state_changed".to_string()], Duration::from_millis(200));
+        assert_eq!(c.push(json!({"type": "scan_progress"})).len(), 1);
+        assert!(c.push(json!({"type": "state_changed"})).is_empty());
+        assert_eq!(
+            c.config(),
+            CoalescingConfig {
+                event_types: vec!["state_changed".to_string()],
+                interval_ms: 200
+            }
+        );
+    }
+
+    #[test]
+    fn flusher_emits_held_events_and_stops_when_done() {
+        let coalescer = Arc::new(Mutex::new(EventCoalescer::default()));
+        coalescer.lock().unwrap().configure(
+            vec!["scan_progress".to_string()],
+            Duration::from_millis(5),
+        );
+        coalescer.lock().unwrap().push(json!({"type": "scan_progress", "data": {"done": 1}}));
+
+        let done = Arc::new(AtomicBool::new(true));
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn_flusher(coalescer, done, move |event| {
+            tx.send(event).ok();
+        });
+        let event = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(event["data"]["done"], 1);
+        // The thread exits after its final flush, dropping the sender.
+        assert!(rx.recv_timeout(Duration::from_secs(2)).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! App preferences persisted as JSON in the app config directory.
+//!
+//! Some preferences must be known before the webview exists (e.g. the
+//! Linux DMA-BUF renderer workaround is an environment variable WebKitGTK
+//! reads once at startup), so `main` loads this file before building the
+//! Tauri app.  A missing or unreadable file means defaults.
+//!
+//! The same fallback means a bad hand edit is silently dropped, so
+//! `validate_config` lists what is wrong with a config before it is
+//! written, and `update_config` refuses to write one that has problems.
+
+use crate::display;
+use crate::excludes;
+use crate::framing::{DEFAULT_READER_CAPACITY, MAX_READER_CAPACITY, MIN_READER_CAPACITY};
+use crate::geometry::WindowGeometry;
+use crate::hotkeys::{self, HotkeyAction};
+use crate::resources::ResourceLimits;
+use crate::playback::{Normalization, MAX_AV_SYNC_OFFSET_MS, MAX_CROSSFADE_MS, MAX_TARGET_LUFS, MIN_TARGET_LUFS};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "config.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Disable WebKitGTK's DMA-BUF renderer on Linux.  On by default since
+    /// it prevents blank windows where GPU buffer allocation is denied.
+    pub renderer_workaround: bool,
+    /// Global shortcut accelerator for each hotkey action.
+    pub hotkeys: BTreeMap<HotkeyAction, String>,
+    /// Show the system tray icon (see `set_tray_icon`); applied at launch.
+    pub tray_icon: bool,
+    /// Capacity in bytes of the buffer backend stdout is read through;
+    /// applies to the next backend started.
+    pub reader_buffer_bytes: usize,
+    /// Check the bundled backend against the build's hashes before
+    /// starting it.  Off by default, for development checkouts.
+    pub verify_backend_integrity: bool,
+    /// Per-user audio/video sync calibration, in milliseconds; sent to
+    /// every backend started.
+    pub av_sync_offset_ms: i32,
+    /// Loudness normalization (see `set_normalization`); sent to every
+    /// backend started while enabled.
+    pub normalization: Normalization,
+    /// Fade between songs, in milliseconds (see `set_crossfade`); sent to
+    /// every backend started while set.
+    pub crossfade_ms: u64,
+    /// Language lyrics are rendered in (see `set_lyrics_language`); the
+    /// backend's default when unset.
+    pub lyrics_language: Option<String>,
+    /// Colour scheme lyrics are drawn in (see `set_theme`); the backend's
+    /// default when unset.
+    pub theme: Option<String>,
+    /// Glob patterns of folders library scans skip (see
+    /// `set_scan_excludes`).
+    pub scan_excludes: Vec<String>,
+    /// Folders the library is built from (see `add_library_root`); the
+    /// backend's own folder list until one is added or removed.
+    pub library_roots: Option<Vec<String>>,
+    /// Where the main window was last left; applied at launch.
+    pub window_geometry: Option<WindowGeometry>,
+    /// Windows kept above all others, by label (see `set_always_on_top`);
+    /// applied whenever the window opens.
+    pub always_on_top: BTreeMap<String, bool>,
+    /// Backend memory and CPU use warned about (see
+    /// `set_resource_limits`).
+    pub resource_limits: ResourceLimits,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            renderer_workaround: true,
+            hotkeys: hotkeys::default_bindings(),
+            tray_icon: true,
+            reader_buffer_bytes: DEFAULT_READER_CAPACITY,
+            verify_backend_integrity: false,
+            av_sync_offset_ms: 0,
+            normalization: Normalization::default(),
+            crossfade_ms: 0,
+            lyrics_language: None,
+            theme: None,
+            scan_excludes: Vec::new(),
+            library_roots: None,
+            window_geometry: None,
+            always_on_top: BTreeMap::new(),
+            resource_limits: ResourceLimits::default(),
+        }
+    }
+}
+
+/// Something wrong with a config, found by [`AppConfig::validate`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigProblem {
+    /// The setting at fault, e.g. `library_roots[1]`; empty when the
+    /// config as a whole is unreadable.
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ConfigProblem { field: field.into(), message: message.into() }
+    }
+}
+
+impl std::fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.field.as_str() {
+            "" => write!(f, "{}", self.message),
+            field => write!(f, "{field}: {}", self.message),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Read `json` as a config and list its problems, without saving
+    /// anything.  The config is `None` when `json` doesn't parse as one.
+    pub fn validate(json: &serde_json::Value) -> (Option<AppConfig>, Vec<ConfigProblem>) {
+        let Some(settings) = json.as_object() else {
+            return (None, vec![ConfigProblem::new("", "the config must be a JSON object")]);
+        };
+        // Unknown keys would be dropped on load, typos included.
+        let known = serde_json::to_value(AppConfig::default()).unwrap_or_default();
+        let mut problems: Vec<_> = settings
+            .keys()
+            .filter(|key| known.get(key.as_str()).is_none())
+            .map(|key| ConfigProblem::new(key.as_str(), "unknown setting"))
+            .collect();
+        let config = match AppConfig::deserialize(json) {
+            Ok(config) => config,
+            Err(e) => {
+                problems.push(ConfigProblem::new("", e.to_string()));
+                return (None, problems);
+            }
+        };
+        if !(MIN_READER_CAPACITY..=MAX_READER_CAPACITY).contains(&config.reader_buffer_bytes) {
+            let message = format!("must be between {MIN_READER_CAPACITY} and {MAX_READER_CAPACITY}");
+            problems.push(ConfigProblem::new("reader_buffer_bytes", message));
+        }
+        if i64::from(config.av_sync_offset_ms).abs() > MAX_AV_SYNC_OFFSET_MS {
+            let message = format!("must be between -{MAX_AV_SYNC_OFFSET_MS} and {MAX_AV_SYNC_OFFSET_MS}");
+            problems.push(ConfigProblem::new("av_sync_offset_ms", message));
+        }
+        if !(MIN_TARGET_LUFS..=MAX_TARGET_LUFS).contains(&config.normalization.target_lufs) {
+            let message = format!("must be between {MIN_TARGET_LUFS} and {MAX_TARGET_LUFS}");
+            problems.push(ConfigProblem::new("normalization.target_lufs", message));
+        }
+        if config.crossfade_ms > MAX_CROSSFADE_MS {
+            problems.push(ConfigProblem::new("crossfade_ms", format!("must be between 0 and {MAX_CROSSFADE_MS}")));
+        }
+        for (action, accelerator) in &config.hotkeys {
+            let name = serde_json::to_value(action).unwrap_or_default();
+            let field = format!("hotkeys.{}", name.as_str().unwrap_or_default());
+            if accelerator.trim().is_empty() {
+                problems.push(ConfigProblem::new(field, "shortcut can't be empty"));
+            } else if config.hotkeys.iter().any(|(other, bound)| other < action && bound == accelerator) {
+                problems.push(ConfigProblem::new(field, format!("{accelerator} is already bound")));
+            }
+        }
+        if let Err((field, message)) = config.resource_limits.check() {
+            problems.push(ConfigProblem::new(format!("resource_limits.{field}"), message));
+        }
+        for label in config.always_on_top.keys() {
+            if let Err(message) = display::window_label(Some(label)) {
+                problems.push(ConfigProblem::new(format!("always_on_top.{label}"), message));
+            }
+        }
+        for (i, pattern) in config.scan_excludes.iter().enumerate() {
+            if let Err(message) = excludes::check_pattern(pattern) {
+                problems.push(ConfigProblem::new(format!("scan_excludes[{i}]"), message));
+            }
+        }
+        for (i, root) in config.library_roots.iter().flatten().enumerate() {
+            let message = match (Path::new(root).metadata(), excludes::excluded_by(&config.scan_excludes, root)) {
+                (Ok(metadata), None) if metadata.is_dir() => continue,
+                (Ok(_), Some(pattern)) => format!("{root} matches the scan exclude {pattern}, so scans skip it"),
+                (Ok(_), None) => format!("{root} is not a folder"),
+                (Err(_), _) => format!("{root} does not exist"),
+            };
+            problems.push(ConfigProblem::new(format!("library_roots[{i}]"), message));
+        }
+        (Some(config), problems)
+    }
+
+    /// Where the config lives for an app with `config`.
+    pub fn path(config: &tauri::Config) -> Option<PathBuf> {
+        tauri::api::path::app_config_dir(config).map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    /// Read the config at `path`, falling back to defaults.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// Load the config for an app with `config`, apply `change` and save
+    /// it back.  Returns the updated config.
+    pub fn update(config: &tauri::Config, change: impl FnOnce(&mut AppConfig)) -> Result<AppConfig, String> {
+        let path = AppConfig::path(config)
+            .ok_or_else(|| "No app config directory on this system".to_string())?;
+        let mut app_config = AppConfig::load(&path);
+        change(&mut app_config);
+        app_config.save(&path)?;
+        Ok(app_config)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_config(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("pykaraoke-ng-config-{name}-{}", std::process::id()))
+            .join(CONFIG_FILE)
+    }
+
+    #[test]
+    fn renderer_preference_round_trips() {
+        let path = temp_config("roundtrip");
+        let mut config = AppConfig {
+            renderer_workaround: false,
+            tray_icon: false,
+            reader_buffer_bytes: 1024 * 1024,
+            av_sync_offset_ms: -120,
+            lyrics_language: Some("ja".to_string()),
+            ..Default::default()
+        };
+        config.hotkeys.insert(HotkeyAction::PlayPause, "CmdOrCtrl+Shift+P".to_string());
+        config.save(&path).unwrap();
+        assert_eq!(AppConfig::load(&path), config);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn validation_lists_every_problem() {
+        let dir = std::env::temp_dir();
+        let excluded = dir.join(format!("pykaraoke-config-{}", std::process::id())).join("ok");
+        std::fs::create_dir_all(&excluded).unwrap();
+        let json = serde_json::json!({
+            "av_sync_offset_ms": 900,
+            "normalization": { "enabled": true, "target_lufs": 0.0 },
+            "crossfade_ms": 60000,
+            "always_on_top": { "lyrics": true, "queue": true },
+            "resource_limits": { "max_rss_mb": 0 },
+            "scan_excludes": ["ok", "[unclosed"],
+            "library_roots": [dir, "/no/such/karaoke/folder", excluded],
+            "renderer_workaroud": false,
+        });
+        let (config, problems) = AppConfig::validate(&json);
+        std::fs::remove_dir_all(excluded.parent().unwrap()).ok();
+        assert!(config.is_some());
+        let fields: Vec<_> = problems.iter().map(|problem| problem.field.as_str()).collect();
+        let expected = [
+            "renderer_workaroud",
+            "av_sync_offset_ms",
+            "normalization.target_lufs",
+            "crossfade_ms",
+            "resource_limits.max_rss_mb",
+            "always_on_top.queue",
+            "scan_excludes[1]",
+            "library_roots[1]",
+            "library_roots[2]",
+        ];
+        assert_eq!(fields, expected);
+
+        let (config, problems) = AppConfig::validate(&serde_json::json!({ "av_sync_offset_ms": "late" }));
+        assert!(config.is_none());
+        assert_eq!(problems.len(), 1);
+        assert!(AppConfig::validate(&serde_json::to_value(AppConfig::default()).unwrap()).1.is_empty());
+    }
+
+    #[test]
+    fn missing_or_corrupt_config_uses_defaults() {
+        let path = temp_config("corrupt");
+        assert!(AppConfig::load(&path).renderer_workaround);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(AppConfig::load(&path), AppConfig::default());
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
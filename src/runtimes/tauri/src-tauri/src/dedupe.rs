@@ -0,0 +1,88 @@
+//! Which commands share a round trip with an identical one in flight.
+//!
+//! A UI polling `get_state` at 10 Hz can fire again before the previous
+//! answer is back.  For read-only actions in the configured set, a request
+//! identical to the newest unanswered one (same action and params) waits on
+//! that response instead of writing a duplicate to stdin; see
+//! `PendingRequests::register_shared`.
+
+use serde_json::Value;
+
+/// Read-only actions deduplicated out of the box.
+pub const DEFAULT_DEDUPLICATED_ACTIONS: &[&str] = &[
+    "get_state",
+    "get_settings",
+    "get_library",
+    "search_songs",
+    "get_supported_formats",
+    "list_audio_devices",
+    "version",
+    "ping",
+];
+
+#[derive(Debug)]
+pub struct Deduplication {
+    actions: Vec<String>,
+}
+
+impl Default for Deduplication {
+    fn default() -> Self {
+        Deduplication {
+            actions: DEFAULT_DEDUPLICATED_ACTIONS.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+}
+
+impl Deduplication {
+    /// Replace the deduplicated actions; an empty list turns it off.
+    pub fn set_actions(&mut self, actions: Vec<String>) {
+        self.actions = actions;
+    }
+
+    pub fn actions(&self) -> &[String] {
+        &self.actions
+    }
+
+    /// The key identical requests for `action` share, if it is
+    /// deduplicated.  Param objects are keyed with their fields sorted, so
+    /// the key doesn't depend on the order the UI wrote them in.
+    pub fn key(&self, action: &str, params: Option<&Value>) -> Option<String> {
+        self.actions
+            .iter()
+            .any(|a| a == action)
+            .then(|| format!("{action} {}", sorted(params.unwrap_or(&Value::Null))))
+    }
+}
+
+/// `value` with object fields in key order at every level (serde_json
+/// keeps insertion order in this build).
+fn sorted(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut fields: Vec<_> = map.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(fields.into_iter().map(|(k, v)| (k.clone(), sorted(v))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn only_configured_actions_get_a_key() {
+        let mut dedupe = Deduplication::default();
+        let a = dedupe.key("search_songs", Some(&json!({"query": "abba", "limit": 5})));
+        let b = dedupe.key("search_songs", Some(&json!({"limit": 5, "query": "abba"})));
+        assert_eq!(a, b);
+        assert_ne!(a, dedupe.key("search_songs", Some(&json!({"query": "queen"}))));
+        assert_eq!(dedupe.key("play", None), None);
+
+        dedupe.set_actions(Vec::new());
+        assert_eq!(dedupe.key("get_state", None), None);
+    }
+}
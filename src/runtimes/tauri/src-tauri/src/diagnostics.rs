@@ -0,0 +1,94 @@
+//! The report `run_diagnostics` returns for the diagnostics button.
+//!
+//! Each step of the IPC chain (interpreter resolution, spawn, handshake,
+//! ping, a command round trip) is timed and recorded as passed, failed or
+//! skipped.  Once a step fails the remaining ones are skipped, since they
+//! depend on it, so the first failure is the one to act on.
+
+use serde::Serialize;
+use std::time::Instant;
+
+/// Detail for steps not run because an earlier one failed.
+pub const AFTER_FAILURE: &str = "An earlier step failed";
+
+/// Detail for steps not run because the backend is answering a long
+/// command first.
+pub const WHILE_BUSY: &str = "The backend is busy with a long command, such as a library scan";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticStep {
+    pub name: &'static str,
+    pub status: StepStatus,
+    pub duration_ms: u64,
+    /// What the step found, or why it failed or was skipped.
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiagnosticsReport {
+    /// Whether the steps ran against the already running backend rather
+    /// than a throwaway one.
+    pub used_running_backend: bool,
+    pub steps: Vec<DiagnosticStep>,
+}
+
+impl DiagnosticsReport {
+    /// Record a step that started at `started`.
+    pub fn record(&mut self, name: &'static str, started: Instant, result: Result<String, String>) {
+        let (status, detail) = match result {
+            Ok(detail) => (StepStatus::Passed, detail),
+            Err(reason) => (StepStatus::Failed, reason),
+        };
+        self.steps.push(DiagnosticStep {
+            name,
+            status,
+            duration_ms: started.elapsed().as_millis() as u64,
+            detail,
+        });
+    }
+
+    pub fn skip(&mut self, name: &'static str, reason: &str) {
+        self.steps.push(DiagnosticStep {
+            name,
+            status: StepStatus::Skipped,
+            duration_ms: 0,
+            detail: reason.to_string(),
+        });
+    }
+
+    /// Whether no step has failed, i.e. the next one should run.
+    pub fn ok(&self) -> bool {
+        self.steps.iter().all(|step| step.status != StepStatus::Failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_fails_once_any_step_fails() {
+        let mut report = DiagnosticsReport::default();
+        report.record("resolve", Instant::now(), Ok("python3".to_string()));
+        report.skip("spawn", "Backend already running");
+        assert!(report.ok());
+        report.record("handshake", Instant::now(), Err("timed out".to_string()));
+        assert!(!report.ok());
+        report.skip("ping", AFTER_FAILURE);
+
+        let statuses: Vec<StepStatus> = report.steps.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            [StepStatus::Passed, StepStatus::Skipped, StepStatus::Failed, StepStatus::Skipped]
+        );
+        assert_eq!(report.steps[2].detail, "timed out");
+    }
+}
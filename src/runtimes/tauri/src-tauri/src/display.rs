@@ -0,0 +1,174 @@
+//! Putting the lyrics display on the right monitor.
+//!
+//! During a performance the lyrics go fullscreen on a second screen (the
+//! TV), while the host keeps the queue on the laptop.  Monitors can be
+//! unplugged between picking one and using it, so indexes are checked
+//! against the current list.
+//!
+//! The main or lyrics window can also be kept above notifications and
+//! other apps with `set_always_on_top`; the choice is saved per window
+//! label and applied again whenever that window is opened.
+
+use serde::Serialize;
+use std::path::Path;
+
+/// Label of the app's main window, from `tauri.conf.json`.
+pub const MAIN_WINDOW: &str = "main";
+/// Label of the dedicated lyrics window, when the frontend opened one.
+pub const LYRICS_WINDOW: &str = "lyrics";
+
+/// Windows `set_always_on_top` can pin.
+pub const WINDOW_LABELS: [&str; 2] = [MAIN_WINDOW, LYRICS_WINDOW];
+
+/// The known window `label` names, the main window when `None`.
+pub fn window_label(label: Option<&str>) -> Result<&'static str, String> {
+    let Some(label) = label else {
+        return Ok(MAIN_WINDOW);
+    };
+    WINDOW_LABELS
+        .into_iter()
+        .find(|known| *known == label)
+        .ok_or_else(|| format!("must be one of {}", WINDOW_LABELS.join(", ")))
+}
+
+/// A connected monitor, e.g. the one a window went fullscreen on, so the
+/// backend can size the CDG render target to match.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonitorInfo {
+    /// Position in `available_monitors()`, as `monitor_index` arguments
+    /// take it.
+    pub index: usize,
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    pub scale_factor: f64,
+    pub is_primary: bool,
+}
+
+impl MonitorInfo {
+    pub fn new(index: usize, monitor: &tauri::Monitor) -> Self {
+        MonitorInfo {
+            index,
+            name: monitor.name().cloned(),
+            width: monitor.size().width,
+            height: monitor.size().height,
+            x: monitor.position().x,
+            y: monitor.position().y,
+            scale_factor: monitor.scale_factor(),
+            is_primary: false,
+        }
+    }
+
+    /// Whether both describe the same screen; indexes shift with hotplug,
+    /// so name and geometry decide.
+    fn same_screen(&self, other: &MonitorInfo) -> bool {
+        (&self.name, self.x, self.y, self.width, self.height)
+            == (&other.name, other.x, other.y, other.width, other.height)
+    }
+}
+
+/// Describe `monitors` in order, flagging the one that is `primary`.
+pub fn describe_monitors(monitors: &[tauri::Monitor], primary: Option<&tauri::Monitor>) -> Vec<MonitorInfo> {
+    let mut infos: Vec<MonitorInfo> = monitors
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| MonitorInfo::new(index, monitor))
+        .collect();
+    if let Some(primary) = primary {
+        mark_primary(&mut infos, &MonitorInfo::new(0, primary));
+    }
+    infos
+}
+
+fn mark_primary(infos: &mut [MonitorInfo], primary: &MonitorInfo) {
+    for info in infos {
+        info.is_primary = info.same_screen(primary);
+    }
+}
+
+/// The entry in `infos` for the screen `monitor` describes, with its
+/// index; `monitor` itself if it isn't listed.
+pub fn find_monitor(infos: &[MonitorInfo], monitor: MonitorInfo) -> MonitorInfo {
+    infos
+        .iter()
+        .find(|info| info.same_screen(&monitor))
+        .cloned()
+        .unwrap_or(monitor)
+}
+
+/// Take monitor `index` out of `monitors`, or explain that it's gone.
+pub fn select_monitor<T>(monitors: Vec<T>, index: usize) -> Result<T, String> {
+    let count = monitors.len();
+    monitors
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| format!("Monitor {index} is not available ({count} connected)"))
+}
+
+/// Check that `path` names a PNG file, as `capture_lyrics_frame` writes,
+/// and create its directory.
+pub fn prepare_capture_path(path: &Path) -> Result<(), String> {
+    let is_png = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    if !is_png {
+        return Err("must end in .png".to_string());
+    }
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => std::fs::create_dir_all(dir).map_err(|e| e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_monitor_by_index() {
+        assert_eq!(select_monitor(vec!["laptop", "tv"], 1).unwrap(), "tv");
+    }
+
+    #[test]
+    fn primary_monitor_is_flagged_by_geometry() {
+        let screen = |index, name: &str, x| MonitorInfo {
+            index,
+            name: Some(name.to_string()),
+            width: 1920,
+            height: 1080,
+            x,
+            y: 0,
+            scale_factor: 1.0,
+            is_primary: false,
+        };
+        let mut infos = vec![screen(0, "laptop", 0), screen(1, "tv", 1920)];
+        mark_primary(&mut infos, &screen(0, "laptop", 0));
+        assert_eq!(infos.iter().map(|m| m.is_primary).collect::<Vec<_>>(), [true, false]);
+        assert_eq!(find_monitor(&infos, screen(0, "tv", 1920)).index, 1);
+    }
+
+    #[test]
+    fn captures_must_be_png_files() {
+        let dir = std::env::temp_dir().join(format!("pykaraoke-capture-{}", std::process::id()));
+        assert_eq!(prepare_capture_path(&dir.join("frame.jpg")), Err("must end in .png".to_string()));
+        assert!(!dir.exists());
+        prepare_capture_path(&dir.join("frame.PNG")).unwrap();
+        assert!(dir.is_dir());
+        std::fs::remove_dir(&dir).ok();
+    }
+
+    #[test]
+    fn window_labels_default_to_the_main_window() {
+        assert_eq!(window_label(None), Ok(MAIN_WINDOW));
+        assert_eq!(window_label(Some("lyrics")), Ok(LYRICS_WINDOW));
+        assert_eq!(window_label(Some("queue")), Err("must be one of main, lyrics".to_string()));
+    }
+
+    #[test]
+    fn unplugged_monitor_is_reported() {
+        let err = select_monitor(vec!["laptop"], 1).unwrap_err();
+        assert_eq!(err, "Monitor 1 is not available (1 connected)");
+    }
+}
@@ -0,0 +1,271 @@
+//! Errors returned by backend IPC commands.
+//!
+//! `BackendError` serializes as `{"kind", "message", "details"}` so the
+//! frontend can branch on `kind` while `errorMessage()` in app.js keeps
+//! showing the human-readable `message`.
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendError {
+    /// No backend script exists at any of the candidate paths.
+    ScriptNotFound {
+        checked: Vec<String>,
+        resource_dir_resolved: bool,
+    },
+    /// The script passed as `backend_script` does not exist.
+    ScriptOverrideMissing(String),
+    /// No usable interpreter was found, or the old process couldn't be
+    /// stopped.
+    LaunchFailed(String),
+    /// The backend speaks a different protocol version than this app.
+    ProtocolMismatch { frontend: u64, backend: u64 },
+    /// Spawning the backend process failed.
+    SpawnFailed {
+        program: String,
+        args: Vec<String>,
+        os_code: Option<i32>,
+        not_found: bool,
+        reason: String,
+    },
+    /// No backend process is running.
+    NotRunning,
+    /// The backend process exited since the last command.
+    ProcessExited,
+    /// `try_wait()` on the backend process failed.
+    ProcessCheckFailed,
+    /// The command could not be serialized to JSON.
+    Serialize(String),
+    /// Writing to the backend's stdin failed.
+    PipeBroken { during: &'static str, reason: String },
+    /// The backend did not answer in time and was killed.
+    Timeout { secs: u64 },
+    /// The stdout reader went away before a response arrived.
+    Disconnected,
+    /// The backend answered with something that isn't a `CommandResponse`.
+    InvalidResponse(String),
+    /// The request was cancelled before the backend answered.
+    Cancelled,
+    /// `clear_pending_requests` gave up on the request.
+    Abandoned,
+    /// No pending request has the given id.
+    UnknownRequest { request_id: u64 },
+    /// The backend answered `"status": "error"`.
+    Rejected { action: String, message: String },
+    /// A command's params failed validation before being sent.
+    InvalidParams {
+        action: String,
+        field: String,
+        reason: String,
+    },
+    /// A saved playlist couldn't be read or written.
+    PlaylistFile { path: String, reason: String },
+    /// A song file is missing or can't be read.
+    FileNotFound { path: String, reason: String },
+    /// The backend couldn't load a song; `reason` is its code for why,
+    /// e.g. `unsupported_format` or `missing_companion`.
+    LoadFailed {
+        path: String,
+        reason: String,
+        message: String,
+    },
+    /// A command needs a window (by label) that isn't open.
+    WindowMissing(&'static str),
+    /// A bundled backend file is missing (`actual` is `None`) or differs
+    /// from the build's manifest.
+    IntegrityCheckFailed {
+        path: String,
+        expected: String,
+        actual: Option<String>,
+    },
+    /// The OS wouldn't say how much memory or CPU the backend uses.
+    ResourcesUnavailable { pid: Option<u32>, reason: String },
+}
+
+impl BackendError {
+    /// Stable machine-readable identifier for the error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BackendError::ScriptNotFound { .. } => "script_not_found",
+            BackendError::ScriptOverrideMissing(_) => "script_override_missing",
+            BackendError::LaunchFailed(_) => "launch_failed",
+            BackendError::SpawnFailed { .. } => "spawn_failed",
+            BackendError::ProtocolMismatch { .. } => "protocol_mismatch",
+            BackendError::NotRunning => "not_running",
+            BackendError::ProcessExited => "process_exited",
+            BackendError::ProcessCheckFailed => "process_check_failed",
+            BackendError::Serialize(_) => "serialize",
+            BackendError::PipeBroken { .. } => "pipe_broken",
+            BackendError::Timeout { .. } => "timeout",
+            BackendError::Disconnected => "disconnected",
+            BackendError::InvalidResponse(_) => "invalid_response",
+            BackendError::Cancelled => "cancelled",
+            BackendError::Abandoned => "abandoned",
+            BackendError::UnknownRequest { .. } => "unknown_request",
+            BackendError::Rejected { .. } => "rejected",
+            BackendError::InvalidParams { .. } => "invalid_params",
+            BackendError::PlaylistFile { .. } => "playlist_file",
+            BackendError::FileNotFound { .. } => "file_not_found",
+            BackendError::LoadFailed { .. } => "load_failed",
+            BackendError::WindowMissing(_) => "window_missing",
+            BackendError::IntegrityCheckFailed { .. } => "integrity_check_failed",
+            BackendError::ResourcesUnavailable { .. } => "resources_unavailable",
+        }
+    }
+
+    /// Structured fields accompanying the message, if any.
+    fn details(&self) -> serde_json::Value {
+        match self {
+            BackendError::ScriptNotFound { checked, resource_dir_resolved } => json!({
+                "checked": checked,
+                "resource_dir_resolved": resource_dir_resolved,
+            }),
+            BackendError::ScriptOverrideMissing(path) => json!({ "path": path }),
+            BackendError::SpawnFailed { program, args, os_code, .. } => json!({
+                "program": program,
+                "args": args,
+                "os_code": os_code,
+            }),
+            BackendError::ProtocolMismatch { frontend, backend } => json!({
+                "frontend": frontend,
+                "backend": backend,
+            }),
+            BackendError::Timeout { secs } => json!({ "secs": secs }),
+            BackendError::UnknownRequest { request_id } => json!({ "request_id": request_id }),
+            BackendError::Rejected { action, .. } => json!({ "action": action }),
+            BackendError::InvalidParams { action, field, reason } => json!({
+                "action": action,
+                "field": field,
+                "reason": reason,
+            }),
+            BackendError::PlaylistFile { path, .. } | BackendError::FileNotFound { path, .. } => {
+                json!({ "path": path })
+            }
+            BackendError::LoadFailed { path, reason, .. } => json!({ "path": path, "reason": reason }),
+            BackendError::WindowMissing(label) => json!({ "window": label }),
+            BackendError::IntegrityCheckFailed { path, expected, actual } => json!({
+                "path": path,
+                "expected": expected,
+                "actual": actual,
+            }),
+            BackendError::ResourcesUnavailable { pid, .. } => json!({ "pid": pid }),
+            _ => serde_json::Value::Null,
+        }
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendError::ScriptNotFound { checked, resource_dir_resolved } => {
+                write!(f, "Backend script not found. Checked:")?;
+                for path in checked {
+                    write!(f, "\n  {path}")?;
+                }
+                if !resource_dir_resolved {
+                    write!(f, "\n(resource directory could not be resolved; searched relative to the current directory)")?;
+                }
+                Ok(())
+            }
+            BackendError::ScriptOverrideMissing(path) => {
+                write!(f, "Backend script override not found: {path}")
+            }
+            BackendError::LaunchFailed(reason) => write!(f, "{reason}"),
+            BackendError::ProtocolMismatch { frontend, backend } => write!(
+                f,
+                "Backend speaks protocol version {backend} but this app requires {frontend}; \
+                 please update PyKaraoke so both parts match"
+            ),
+            BackendError::SpawnFailed { program, args, not_found, reason, .. } => {
+                write!(f, "Failed to start backend `{program}")?;
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+                write!(f, "`: {reason}")?;
+                if *not_found {
+                    write!(f, ". The Python interpreter could not be located; install Python 3 or set PYKARAOKE_PYTHON")?;
+                }
+                Ok(())
+            }
+            BackendError::NotRunning => write!(f, "Backend not running"),
+            BackendError::ProcessExited => write!(f, "Backend process has exited"),
+            BackendError::ProcessCheckFailed => write!(f, "Backend process check failed"),
+            BackendError::Serialize(e) => write!(f, "Failed to serialize command: {e}"),
+            BackendError::PipeBroken { during, reason } => {
+                write!(f, "Backend process died ({during}): {reason}")
+            }
+            BackendError::Timeout { secs } => {
+                write!(f, "Backend did not respond within {secs} seconds")
+            }
+            BackendError::Disconnected => write!(f, "Backend process disconnected"),
+            BackendError::InvalidResponse(raw) => {
+                write!(f, "Failed to parse backend response: {raw}")
+            }
+            BackendError::Cancelled => write!(f, "Command was cancelled"),
+            BackendError::Abandoned => write!(f, "Command was abandoned before the backend answered"),
+            BackendError::UnknownRequest { request_id } => {
+                write!(f, "No pending request with id {request_id}")
+            }
+            BackendError::Rejected { action, message } => write!(f, "{action} failed: {message}"),
+            BackendError::InvalidParams { action, field, reason } => {
+                write!(f, "Invalid '{field}' for {action}: {reason}")
+            }
+            BackendError::PlaylistFile { path, reason } => write!(f, "Playlist file {path}: {reason}"),
+            BackendError::FileNotFound { path, reason } => write!(f, "Cannot load {path}: {reason}"),
+            BackendError::LoadFailed { path, message, .. } => write!(f, "Cannot load {path}: {message}"),
+            BackendError::WindowMissing(label) => write!(f, "The {label} window is not open"),
+            BackendError::IntegrityCheckFailed { path, actual: None, .. } => {
+                write!(f, "Backend file {path} is missing; reinstall PyKaraoke")
+            }
+            BackendError::IntegrityCheckFailed { path, .. } => {
+                write!(f, "Backend file {path} doesn't match this build; reinstall PyKaraoke")
+            }
+            BackendError::ResourcesUnavailable { reason, .. } => {
+                write!(f, "Can't read the backend's resource usage: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+impl Serialize for BackendError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        map.serialize_entry("details", &self.details())?;
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_kind_message_and_details() {
+        let err = BackendError::InvalidParams {
+            action: "set_volume".to_string(),
+            field: "volume".to_string(),
+            reason: "must be a number".to_string(),
+        };
+        let j = serde_json::to_value(&err).unwrap();
+        assert_eq!(j["kind"], "invalid_params");
+        assert_eq!(j["message"], "Invalid 'volume' for set_volume: must be a number");
+        assert_eq!(j["details"]["field"], "volume");
+    }
+
+    #[test]
+    fn messages_match_legacy_strings() {
+        // These messages predate BackendError; keep them stable for the UI.
+        assert_eq!(BackendError::NotRunning.to_string(), "Backend not running");
+        assert_eq!(
+            BackendError::Timeout { secs: 5 }.to_string(),
+            "Backend did not respond within 5 seconds"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! The event types the backend emits.
+//!
+//! Each arrives on stdout as `{"type":"event","event":{"type":...}}` with
+//! its payload under `data`.  The exception is [`ERROR`], a failure the
+//! user should hear about: it brings `code` and `message` next to its type
+//! and is re-emitted to the webview as `backend-error`, apart from the
+//! `backend-log` stream of stderr lines, so the UI can show it without
+//! parsing log text.  Likewise [`LOAD_FAILED`], a song `load_song` gave
+//! up on, is re-emitted as `song-load-failed` with its reason code.
+
+use serde::Serialize;
+use serde_json::Value;
+
+pub const STATE_CHANGED: &str = "state_changed";
+pub const SONG_STARTED: &str = "song_started";
+pub const SONG_FINISHED: &str = "song_finished";
+pub const PLAYBACK_ERROR: &str = "playback_error";
+pub const PLAYLIST_UPDATED: &str = "playlist_updated";
+pub const VOLUME_CHANGED: &str = "volume_changed";
+pub const LYRIC_TICK: &str = "lyric_tick";
+pub const SCAN_PROGRESS: &str = "scan_progress";
+pub const SCAN_COMPLETE: &str = "scan_complete";
+pub const SCAN_CANCELLED: &str = "scan_cancelled";
+pub const LOAD_FAILED: &str = "load_failed";
+pub const AUDIO_READY: &str = "audio_ready";
+pub const ERROR: &str = "error";
+
+/// Every event type the backend emits.
+pub const EVENT_TYPES: &[&str] = &[
+    STATE_CHANGED,
+    SONG_STARTED,
+    SONG_FINISHED,
+    PLAYBACK_ERROR,
+    PLAYLIST_UPDATED,
+    VOLUME_CHANGED,
+    LYRIC_TICK,
+    SCAN_PROGRESS,
+    SCAN_COMPLETE,
+    SCAN_CANCELLED,
+    LOAD_FAILED,
+    AUDIO_READY,
+    ERROR,
+];
+
+/// The `backend-error` payload.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackendErrorEvent {
+    /// Machine-readable kind of failure, e.g. `playback_failed`.
+    pub code: String,
+    pub message: String,
+    /// Seconds since the Unix epoch, as the backend stamped it.
+    pub timestamp: Option<f64>,
+    /// The command the failure belongs to, if any.
+    pub request_id: Option<u64>,
+}
+
+impl BackendErrorEvent {
+    /// The payload for an [`ERROR`] event; `None` for any other event.
+    pub fn from_event(event: &Value) -> Option<Self> {
+        if event["type"] != ERROR {
+            return None;
+        }
+        Some(BackendErrorEvent {
+            code: event["code"].as_str().unwrap_or("unknown").to_string(),
+            message: event["message"].as_str().unwrap_or("Unknown backend error").to_string(),
+            timestamp: event["timestamp"].as_f64(),
+            request_id: event["request_id"].as_u64(),
+        })
+    }
+}
+
+/// The `song-load-failed` payload.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadFailedEvent {
+    pub path: String,
+    /// `unsupported_format`, `missing_companion`, `permission_denied` or
+    /// `decode_error`.
+    pub reason: String,
+    pub message: String,
+}
+
+impl LoadFailedEvent {
+    /// The payload for a [`LOAD_FAILED`] event; `None` for any other event.
+    pub fn from_event(event: &Value) -> Option<Self> {
+        if event["type"] != LOAD_FAILED {
+            return None;
+        }
+        let data = &event["data"];
+        Some(LoadFailedEvent {
+            path: data["filepath"].as_str().unwrap_or_default().to_string(),
+            reason: data["reason"].as_str().unwrap_or("unknown").to_string(),
+            message: data["message"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn only_error_events_become_backend_errors() {
+        let event = json!({ "type": "error", "code": "playback_failed", "message": "corrupt file", "timestamp": 1.5 });
+        let error = BackendErrorEvent::from_event(&event).unwrap();
+        assert_eq!((error.code.as_str(), error.message.as_str()), ("playback_failed", "corrupt file"));
+        assert_eq!((error.timestamp, error.request_id), (Some(1.5), None));
+
+        assert_eq!(BackendErrorEvent::from_event(&json!({ "type": "error" })).unwrap().code, "unknown");
+        assert!(BackendErrorEvent::from_event(&json!({ "type": "playback_error", "data": {} })).is_none());
+    }
+
+    #[test]
+    fn load_failures_keep_their_reason() {
+        let data = json!({ "filepath": "/a.cdg", "reason": "missing_companion", "message": "No audio" });
+        let failed = LoadFailedEvent::from_event(&json!({ "type": "load_failed", "data": data })).unwrap();
+        assert_eq!((failed.path.as_str(), failed.reason.as_str()), ("/a.cdg", "missing_companion"));
+        assert!(LoadFailedEvent::from_event(&json!({ "type": "error", "code": "load_failed" })).is_none());
+    }
+}
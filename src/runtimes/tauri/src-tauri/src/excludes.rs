@@ -0,0 +1,138 @@
+//! Folders library scans skip.
+//!
+//! Patterns are shell globs (`*`, `?`, `[...]`) matched by the backend
+//! against both a folder's full path and its name, so `/proc` skips one
+//! tree while `node_modules` or `.*` skip every folder by that name.  They
+//! are saved in the config and added to every `scan_library` and
+//! `add_folder` request that doesn't bring its own.  A library root they
+//! match would be skipped whole, so one can't be added while it does.
+
+use serde_json::Value;
+use std::path::Path;
+
+/// Check that `pattern` is a glob the backend can use.
+pub fn check_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.trim().is_empty() {
+        return Err("patterns can't be empty".to_string());
+    }
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '[' {
+            continue;
+        }
+        // A `]` straight after `[` (or `[!`) is part of the set.
+        chars.next_if_eq(&'!');
+        chars.next_if_eq(&']');
+        if !chars.by_ref().any(|c| c == ']') {
+            return Err(format!("{pattern}: unclosed ["));
+        }
+    }
+    Ok(())
+}
+
+/// The first of `patterns` that makes scans skip `folder`, matched the way
+/// the backend's `fnmatch` does: against the whole path and the folder's
+/// name, with `*` also crossing `/`.
+pub fn excluded_by<'a>(patterns: &'a [String], folder: &str) -> Option<&'a str> {
+    let path = folder.trim_end_matches(['/', '\\']);
+    let name = Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or(path);
+    patterns
+        .iter()
+        .map(String::as_str)
+        .find(|pattern| glob_match(pattern, path) || glob_match(pattern, name))
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches_from(&pattern, &text)
+}
+
+fn matches_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| matches_from(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && matches_from(rest, &text[1..]),
+        Some(('[', rest)) => match (text.split_first(), set_match(rest, text.first().copied())) {
+            (Some((_, tail)), Some((true, after))) => matches_from(after, tail),
+            (_, Some((false, _))) | (None, _) => false,
+            // An unclosed `[` is a literal, as in fnmatch.
+            (Some((&c, tail)), None) => c == '[' && matches_from(rest, tail),
+        },
+        Some((&c, rest)) => text.first() == Some(&c) && matches_from(rest, &text[1..]),
+    }
+}
+
+/// Whether `c` is in the set opening `set` (just after its `[`), and the
+/// pattern after the closing `]`; `None` when the set isn't closed.
+fn set_match(set: &[char], c: Option<char>) -> Option<(bool, &[char])> {
+    let (negated, set) = match set.split_first() {
+        Some(('!', rest)) => (true, rest),
+        _ => (false, set),
+    };
+    // A `]` straight after `[` (or `[!`) is part of the set.
+    let close = set.iter().skip(1).position(|&c| c == ']')? + 1;
+    let (members, after) = (&set[..close], &set[close + 1..]);
+    let c = c?;
+    let mut found = false;
+    let mut i = 0;
+    while i < members.len() {
+        if i + 2 < members.len() && members[i + 1] == '-' {
+            found |= members[i] <= c && c <= members[i + 2];
+            i += 3;
+        } else {
+            found |= members[i] == c;
+            i += 1;
+        }
+    }
+    Some((found != negated, after))
+}
+
+/// `params` with `excludes` set to `excludes`, unless they name their own
+/// or there are none.
+pub fn with_excludes(params: Option<Value>, excludes: &[String]) -> Option<Value> {
+    if excludes.is_empty() {
+        return params;
+    }
+    let mut params = params.unwrap_or_else(|| Value::Object(Default::default()));
+    if let Value::Object(fields) = &mut params {
+        fields.entry("excludes").or_insert_with(|| excludes.into());
+    }
+    Some(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn unclosed_sets_are_rejected() {
+        assert!(check_pattern("/proc").is_ok());
+        assert!(check_pattern("[!.]*").is_ok());
+        assert!(check_pattern("[]]").is_ok());
+        assert_eq!(check_pattern("tmp[0-9"), Err("tmp[0-9: unclosed [".to_string()));
+        assert!(check_pattern("  ").is_err());
+    }
+
+    #[test]
+    fn folders_match_like_fnmatch() {
+        let patterns: Vec<String> = ["/mnt/nas*", "node_modules", ".*", "tmp[0-9]"].map(String::from).into();
+        assert_eq!(excluded_by(&patterns, "/mnt/nas2/karaoke"), Some("/mnt/nas*"));
+        assert_eq!(excluded_by(&patterns, "/home/me/app/node_modules/"), Some("node_modules"));
+        assert_eq!(excluded_by(&patterns, "/home/me/.cache"), Some(".*"));
+        assert_eq!(excluded_by(&patterns, "/srv/tmp7"), Some("tmp[0-9]"));
+        assert_eq!(excluded_by(&patterns, "/home/me/karaoke"), None);
+        assert_eq!(excluded_by(&["[!k]*".to_string()], "karaoke"), None);
+        assert_eq!(excluded_by(&["k[]x]*".to_string()], "k]aoke"), Some("k[]x]*"));
+    }
+
+    #[test]
+    fn excludes_fill_in_unless_given() {
+        let excludes = vec!["/proc".to_string()];
+        assert_eq!(with_excludes(None, &excludes), Some(json!({ "excludes": ["/proc"] })));
+        let own = json!({ "folder": "/songs", "excludes": [] });
+        assert_eq!(with_excludes(Some(own.clone()), &excludes), Some(own));
+        assert_eq!(with_excludes(None, &[]), None);
+    }
+}
@@ -0,0 +1,174 @@
+//! Message framing on the backend's stdin/stdout pipes.
+//!
+//! NDJSON (one JSON object per line) is the default and what every backend
+//! build understands.  Length-prefixed framing (a 4-byte big-endian length
+//! followed by the UTF-8 payload) is opt-in via `start_backend` and passed
+//! to the backend as `--framing length-prefixed`; it tolerates newlines
+//! inside payloads, e.g. a pretty-printed object.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Read};
+
+/// Frames larger than this are treated as a corrupt stream rather than
+/// allocated.
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Default capacity of the stdout reader's buffer.  Larger than
+/// `BufReader`'s 8 KiB so a multi-megabyte `get_library` line takes fewer
+/// reads from the pipe.
+pub const DEFAULT_READER_CAPACITY: usize = 64 * 1024;
+pub const MIN_READER_CAPACITY: usize = 8 * 1024;
+pub const MAX_READER_CAPACITY: usize = 16 * 1024 * 1024;
+
+/// `bytes` clamped to a sensible stdout reader capacity.
+pub fn reader_capacity(bytes: usize) -> usize {
+    bytes.clamp(MIN_READER_CAPACITY, MAX_READER_CAPACITY)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Framing {
+    #[default]
+    Ndjson,
+    LengthPrefixed,
+}
+
+impl Framing {
+    /// Arguments telling the backend which framing to speak.  Empty for
+    /// NDJSON so older backends keep working.
+    pub fn cli_args(self) -> &'static [&'static str] {
+        match self {
+            Framing::Ndjson => &[],
+            Framing::LengthPrefixed => &["--framing", "length-prefixed"],
+        }
+    }
+
+    /// Frame one serialized message for writing.
+    pub fn encode(self, payload: &str) -> Vec<u8> {
+        match self {
+            Framing::Ndjson => {
+                let mut out = Vec::with_capacity(payload.len() + 1);
+                out.extend_from_slice(payload.as_bytes());
+                out.push(b'\n');
+                out
+            }
+            Framing::LengthPrefixed => {
+                let mut out = Vec::with_capacity(payload.len() + 4);
+                out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+                out.extend_from_slice(payload.as_bytes());
+                out
+            }
+        }
+    }
+
+    /// Read the next message, decoding it lossily.  The flag reports
+    /// whether invalid UTF-8 was replaced.  Returns `None` at EOF, on a
+    /// read error, or on an oversized frame.
+    pub fn read_message<R: BufRead>(self, reader: &mut R, buf: &mut Vec<u8>) -> Option<(String, bool)> {
+        match self {
+            Framing::Ndjson => read_line_lossy(reader, buf),
+            Framing::LengthPrefixed => read_frame_lossy(reader, buf),
+        }
+    }
+}
+
+/// Read one line of backend stdout, decoding it lossily.
+///
+/// `BufRead::lines` fails on invalid UTF-8 (e.g. a song title in a legacy
+/// encoding), which used to end the reader loop and silently stop all
+/// event delivery.  Undecodable bytes become U+FFFD instead; the flag
+/// reports whether that happened.  Returns `None` at EOF or on a read error.
+pub fn read_line_lossy<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> Option<(String, bool)> {
+    buf.clear();
+    match reader.read_until(b'\n', buf) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => {
+            while matches!(buf.last(), Some(b'\n' | b'\r')) {
+                buf.pop();
+            }
+            Some(decode_lossy(buf))
+        }
+    }
+}
+
+fn read_frame_lossy<R: Read>(reader: &mut R, buf: &mut Vec<u8>) -> Option<(String, bool)> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len).ok()?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return None;
+    }
+    buf.clear();
+    buf.resize(len, 0);
+    reader.read_exact(buf).ok()?;
+    Some(decode_lossy(buf))
+}
+
+fn decode_lossy(bytes: &[u8]) -> (String, bool) {
+    match String::from_utf8_lossy(bytes) {
+        std::borrow::Cow::Borrowed(text) => (text.to_string(), false),
+        std::borrow::Cow::Owned(text) => (text, true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader_survives_invalid_utf8_lines() {
+        let mut input: &[u8] = b"{\"type\":\"event\"}\r\n{\"title\":\"caf\xe9\"}\nlast";
+        let mut buf = Vec::new();
+        assert_eq!(
+            read_line_lossy(&mut input, &mut buf),
+            Some((r#"{"type":"event"}"#.to_string(), false))
+        );
+        let (line, lossy) = read_line_lossy(&mut input, &mut buf).unwrap();
+        assert!(lossy);
+        assert_eq!(line, "{\"title\":\"caf\u{FFFD}\"}");
+        assert!(serde_json::from_str::<serde_json::Value>(&line).is_ok());
+        assert_eq!(read_line_lossy(&mut input, &mut buf), Some(("last".to_string(), false)));
+        assert_eq!(read_line_lossy(&mut input, &mut buf), None);
+    }
+
+    #[test]
+    fn length_prefixed_frames_round_trip_embedded_newlines() {
+        let pretty = "{\n  \"type\": \"event\"\n}";
+        let mut stream = Framing::LengthPrefixed.encode(pretty);
+        stream.extend(Framing::LengthPrefixed.encode("{}"));
+
+        let mut input = stream.as_slice();
+        let mut buf = Vec::new();
+        let read = |input: &mut &[u8], buf: &mut Vec<u8>| {
+            Framing::LengthPrefixed.read_message(input, buf)
+        };
+        assert_eq!(read(&mut input, &mut buf), Some((pretty.to_string(), false)));
+        assert_eq!(read(&mut input, &mut buf), Some(("{}".to_string(), false)));
+        assert_eq!(read(&mut input, &mut buf), None);
+    }
+
+    #[test]
+    fn truncated_or_oversized_frames_end_the_stream() {
+        let mut buf = Vec::new();
+        let mut truncated: &[u8] = &[0, 0, 0, 10, b'{'];
+        assert_eq!(Framing::LengthPrefixed.read_message(&mut truncated, &mut buf), None);
+        let mut oversized: &[u8] = &[0xff, 0xff, 0xff, 0xff];
+        assert_eq!(Framing::LengthPrefixed.read_message(&mut oversized, &mut buf), None);
+    }
+
+    #[test]
+    fn reader_capacity_is_clamped() {
+        assert_eq!(reader_capacity(DEFAULT_READER_CAPACITY), DEFAULT_READER_CAPACITY);
+        assert_eq!(reader_capacity(0), MIN_READER_CAPACITY);
+        assert_eq!(reader_capacity(usize::MAX), MAX_READER_CAPACITY);
+    }
+
+    #[test]
+    fn ndjson_is_the_default_and_needs_no_backend_flag() {
+        assert_eq!(Framing::default(), Framing::Ndjson);
+        assert!(Framing::Ndjson.cli_args().is_empty());
+        assert_eq!(Framing::Ndjson.encode("{}"), b"{}\n");
+        let parsed: Framing = serde_json::from_str("\"length_prefixed\"").unwrap();
+        assert_eq!(parsed, Framing::LengthPrefixed);
+    }
+}
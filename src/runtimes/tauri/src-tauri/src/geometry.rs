@@ -0,0 +1,124 @@
+//! Reopening the main window where the user left it.
+//!
+//! The window's position and size are saved in the config as it is moved
+//! and resized, and applied again at launch.  The screen it was on may
+//! have been unplugged since, so the saved geometry is first fitted onto
+//! the monitors connected now: a window whose top edge no longer lies on
+//! any of them goes back to the monitor it was saved on if that is still
+//! there, else to the primary one, and is shrunk to fit.
+//!
+//! Drags and resizes arrive as a stream of events, so saving waits for
+//! [`SAVE_DELAY`] of quiet.  While maximized only the flag is saved, so
+//! un-maximizing after a restart returns to the size the window had.
+//! All values are physical pixels, as `tauri::Window` reports them.
+
+use crate::display::MonitorInfo;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Quiet time after the last move or resize before the geometry is saved.
+pub const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+    /// Name of the monitor the window was on, if the platform names them.
+    pub monitor: Option<String>,
+}
+
+impl WindowGeometry {
+    /// This geometry moved and shrunk as needed to lie on one of
+    /// `monitors`; unchanged when the monitors can't be listed.
+    pub fn fit(&self, monitors: &[MonitorInfo]) -> WindowGeometry {
+        let top_middle = (i64::from(self.x) + i64::from(self.width / 2), i64::from(self.y));
+        let target = monitors
+            .iter()
+            .find(|monitor| contains(monitor, top_middle))
+            .or_else(|| monitors.iter().find(|monitor| monitor.name.is_some() && monitor.name == self.monitor))
+            .or_else(|| monitors.iter().find(|monitor| monitor.is_primary))
+            .or_else(|| monitors.first());
+        let Some(target) = target else {
+            return self.clone();
+        };
+        let width = self.width.min(target.width);
+        let height = self.height.min(target.height);
+        let clamp = |at: i32, start: i32, span: u32, size: u32| {
+            let end = i64::from(start) + i64::from(span - size);
+            i64::from(at).clamp(i64::from(start), end) as i32
+        };
+        WindowGeometry {
+            x: clamp(self.x, target.x, target.width, width),
+            y: clamp(self.y, target.y, target.height, height),
+            width,
+            height,
+            maximized: self.maximized,
+            monitor: target.name.clone(),
+        }
+    }
+}
+
+fn contains(monitor: &MonitorInfo, (x, y): (i64, i64)) -> bool {
+    let (left, top) = (i64::from(monitor.x), i64::from(monitor.y));
+    (left..left + i64::from(monitor.width)).contains(&x) && (top..top + i64::from(monitor.height)).contains(&y)
+}
+
+/// Tickets for the debounced save; only the latest move or resize saves.
+#[derive(Debug, Default)]
+pub struct SaveDebounce {
+    latest: AtomicU64,
+}
+
+impl SaveDebounce {
+    pub fn ticket(&self) -> u64 {
+        self.latest.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    pub fn is_latest(&self, ticket: u64) -> bool {
+        self.latest.load(Ordering::Acquire) == ticket
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(index: usize, name: &str, x: i32, width: u32, height: u32) -> MonitorInfo {
+        MonitorInfo {
+            index,
+            name: Some(name.to_string()),
+            width,
+            height,
+            x,
+            y: 0,
+            scale_factor: 1.0,
+            is_primary: index == 0,
+        }
+    }
+
+    fn window(x: i32, y: i32, width: u32, height: u32, monitor: &str) -> WindowGeometry {
+        WindowGeometry { x, y, width, height, maximized: false, monitor: Some(monitor.to_string()) }
+    }
+
+    #[test]
+    fn window_on_a_connected_monitor_stays_put() {
+        let monitors = [screen(0, "laptop", 0, 1920, 1080), screen(1, "tv", 1920, 3840, 2160)];
+        let saved = window(2500, 100, 400, 800, "tv");
+        assert_eq!(saved.fit(&monitors), saved);
+        // Hanging off the right edge of the TV is pulled back onto it.
+        assert_eq!(window(5600, 100, 400, 800, "tv").fit(&monitors).x, 5360);
+    }
+
+    #[test]
+    fn window_from_an_unplugged_monitor_moves_to_the_primary() {
+        let monitors = [screen(0, "laptop", 0, 1280, 720)];
+        let fitted = window(2500, 100, 400, 800, "tv").fit(&monitors);
+        assert_eq!(fitted, window(880, 0, 400, 720, "laptop"));
+        let saved = window(2500, 100, 400, 800, "tv");
+        assert_eq!(saved.fit(&[]), saved);
+    }
+}
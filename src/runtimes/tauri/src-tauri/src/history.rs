@@ -0,0 +1,100 @@
+//! Bounded record of recent backend events for postmortem debugging.
+//!
+//! When a user reports "the lyrics froze" the history shows what the
+//! backend actually sent.  Entries keep both the parsed event and the raw
+//! stdout line, stamped with wall-clock time.  It is shared across backend
+//! restarts so a crash doesn't wipe the evidence.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_HISTORY_CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    /// Milliseconds since the Unix epoch when the event was received.
+    pub received_at_ms: u64,
+    pub event: serde_json::Value,
+    pub raw: String,
+}
+
+#[derive(Debug)]
+pub struct EventHistory {
+    capacity: usize,
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl Default for EventHistory {
+    fn default() -> Self {
+        EventHistory::with_capacity(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl EventHistory {
+    pub fn with_capacity(capacity: usize) -> Self {
+        EventHistory {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Change how many entries are kept, dropping the oldest if shrinking.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    pub fn record(&mut self, event: serde_json::Value, raw: String) {
+        let received_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        self.entries.push_back(HistoryEntry {
+            received_at_ms,
+            event,
+            raw,
+        });
+        self.trim();
+    }
+
+    /// Entries oldest first.
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.entries.iter().cloned().collect()
+    }
+
+    fn trim(&mut self) {
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_only_the_most_recent_entries() {
+        let mut history = EventHistory::with_capacity(2);
+        for n in 0..3 {
+            history.record(json!({ "n": n }), format!("line {n}"));
+        }
+        let entries = history.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].event["n"], 1);
+        assert_eq!(entries[1].raw, "line 2");
+        assert!(entries[1].received_at_ms >= entries[0].received_at_ms);
+    }
+
+    #[test]
+    fn shrinking_capacity_drops_oldest() {
+        let mut history = EventHistory::default();
+        for n in 0..10 {
+            history.record(json!({ "n": n }), String::new());
+        }
+        history.set_capacity(3);
+        assert_eq!(history.entries()[0].event["n"], 7);
+    }
+}
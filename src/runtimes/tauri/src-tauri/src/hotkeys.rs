@@ -0,0 +1,59 @@
+//! Global hotkeys for controlling playback from across the room.
+//!
+//! Bindings map a [`HotkeyAction`] to a Tauri accelerator string and are
+//! stored in the app config; `main` registers them at startup and
+//! `register_hotkey` / `unregister_hotkey` change them at runtime.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    /// Pause while playing, play otherwise.
+    PlayPause,
+    Next,
+    Previous,
+}
+
+impl HotkeyAction {
+    /// The backend action to send, given the current playback state.
+    pub fn backend_action(self, playback_state: Option<&str>) -> &'static str {
+        match self {
+            HotkeyAction::PlayPause if playback_state == Some("playing") => "pause",
+            HotkeyAction::PlayPause => "play",
+            HotkeyAction::Next => "next",
+            HotkeyAction::Previous => "previous",
+        }
+    }
+}
+
+/// The media keys most keyboards have.
+pub fn default_bindings() -> BTreeMap<HotkeyAction, String> {
+    BTreeMap::from([
+        (HotkeyAction::PlayPause, "MediaPlayPause".to_string()),
+        (HotkeyAction::Next, "MediaNextTrack".to_string()),
+        (HotkeyAction::Previous, "MediaPreviousTrack".to_string()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn play_pause_toggles_on_state() {
+        assert_eq!(HotkeyAction::PlayPause.backend_action(Some("playing")), "pause");
+        assert_eq!(HotkeyAction::PlayPause.backend_action(Some("paused")), "play");
+        assert_eq!(HotkeyAction::PlayPause.backend_action(None), "play");
+        assert_eq!(HotkeyAction::Next.backend_action(Some("playing")), "next");
+    }
+
+    #[test]
+    fn bindings_serialize_by_action_name() {
+        let json = serde_json::to_value(default_bindings()).unwrap();
+        assert_eq!(json["play_pause"], "MediaPlayPause");
+        let parsed: BTreeMap<HotkeyAction, String> = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, default_bindings());
+    }
+}
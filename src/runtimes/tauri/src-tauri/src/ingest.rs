@@ -0,0 +1,173 @@
+//! Sorting files dropped onto the window into ones the backend can play.
+//!
+//! Dropped paths are checked against the extensions the backend reports
+//! through `get_supported_formats`, or [`DEFAULT_EXTENSIONS`] if it
+//! doesn't answer.  The default mirrors the backend's stock CDG, KAR and
+//! MPG extension lists plus `.zip` archives.  Dropped folders are handed to the
+//! backend's `add_folder` scan instead.  Anything else is rejected with a
+//! reason the UI can show.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Lower-case extensions, without the dot, accepted for `add_to_playlist`
+/// when the backend can't be asked.
+pub const DEFAULT_EXTENSIONS: &[&str] = &[
+    "cdg", "kar", "mid", "mpg", "mpeg", "avi", "divx", "xvid", "mp3", "ogg", "zip",
+];
+
+/// A dropped path that won't be queued.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Rejection {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Payload of the `files_added` event.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DropSummary {
+    pub accepted: usize,
+    pub rejected: usize,
+    pub rejections: Vec<Rejection>,
+}
+
+impl DropSummary {
+    /// Whether no files were dropped, e.g. when only folders were.
+    pub fn is_empty(&self) -> bool {
+        self.accepted == 0 && self.rejected == 0
+    }
+
+    pub fn accept(&mut self) {
+        self.accepted += 1;
+    }
+
+    pub fn reject(&mut self, path: &Path, reason: impl Into<String>) {
+        self.rejected += 1;
+        self.rejections.push(Rejection {
+            path: path.display().to_string(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// A drop sorted by what the backend should do with each path.
+#[derive(Debug, Default)]
+pub struct Dropped {
+    /// Files to queue with `add_to_playlist`.
+    pub files: Vec<PathBuf>,
+    /// Folders to scan with `add_folder`.
+    pub folders: Vec<PathBuf>,
+    pub summary: DropSummary,
+}
+
+/// Split `paths` into files to queue, folders to scan and a summary of
+/// the rejects.  `extensions` are lower case without the dot.
+pub fn partition_dropped(paths: Vec<PathBuf>, extensions: &[String]) -> Dropped {
+    let mut dropped = Dropped::default();
+    for path in paths {
+        if path.is_dir() {
+            dropped.folders.push(path);
+            continue;
+        }
+        match check_dropped(&path, extensions) {
+            Ok(()) => dropped.files.push(path),
+            Err(reason) => dropped.summary.reject(&path, reason),
+        }
+    }
+    dropped
+}
+
+/// Check that `path` names an existing directory before asking the
+/// backend to scan it.
+pub fn ensure_directory(path: &Path) -> Result<(), String> {
+    if path.is_dir() {
+        Ok(())
+    } else if path.exists() {
+        Err("Not a folder".to_string())
+    } else {
+        Err("Folder does not exist".to_string())
+    }
+}
+
+/// Check that `path` names a file that can be opened before asking the
+/// backend to load it.
+pub fn ensure_readable_file(path: &Path) -> Result<(), String> {
+    if path.is_dir() {
+        return Err("Not a file".to_string());
+    }
+    if !path.exists() {
+        return Err("File does not exist".to_string());
+    }
+    std::fs::File::open(path).map(drop).map_err(|e| e.to_string())
+}
+
+/// [`DEFAULT_EXTENSIONS`] as owned strings.
+pub fn default_extensions() -> Vec<String> {
+    DEFAULT_EXTENSIONS.iter().map(|ext| ext.to_string()).collect()
+}
+
+fn check_dropped(path: &Path, extensions: &[String]) -> Result<(), String> {
+    let supported = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_ascii_lowercase()));
+    if supported {
+        Ok(())
+    } else {
+        Err("Not a supported karaoke file".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_supported_files_case_insensitively() {
+        let paths = vec![
+            PathBuf::from("/songs/queen.CDG"),
+            PathBuf::from("/songs/abba.kar"),
+            PathBuf::from("/songs/pack.zip"),
+            PathBuf::from("/songs/notes.txt"),
+            PathBuf::from("/songs/README"),
+        ];
+        let Dropped { files, folders, summary } = partition_dropped(paths, &default_extensions());
+        assert_eq!(files.len(), 3);
+        assert!(folders.is_empty());
+        assert_eq!((summary.accepted, summary.rejected), (0, 2));
+        assert_eq!(summary.rejections[0].path, "/songs/notes.txt");
+    }
+
+    #[test]
+    fn backend_formats_replace_the_default() {
+        let extensions = vec!["cdg".to_string()];
+        let dropped = partition_dropped(
+            vec![PathBuf::from("/songs/queen.cdg"), PathBuf::from("/songs/abba.kar")],
+            &extensions,
+        );
+        assert_eq!(dropped.files, [PathBuf::from("/songs/queen.cdg")]);
+        assert_eq!(dropped.summary.rejections[0].path, "/songs/abba.kar");
+    }
+
+    #[test]
+    fn directories_are_scanned_not_queued() {
+        let dir = std::env::temp_dir();
+        let dropped = partition_dropped(vec![dir.clone()], &default_extensions());
+        assert!(dropped.files.is_empty());
+        assert_eq!(dropped.folders, [dir.clone()]);
+        assert_eq!(dropped.summary.rejected, 0);
+
+        assert!(ensure_directory(&dir).is_ok());
+        assert_eq!(ensure_directory(&dir.join("no-such-folder")).unwrap_err(), "Folder does not exist");
+    }
+
+    #[test]
+    fn only_existing_files_are_loadable() {
+        let file = std::env::temp_dir().join(format!("pykaraoke-ingest-{}.cdg", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        assert!(ensure_readable_file(&file).is_ok());
+        assert_eq!(ensure_readable_file(file.parent().unwrap()).unwrap_err(), "Not a file");
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(ensure_readable_file(&file).unwrap_err(), "File does not exist");
+    }
+}
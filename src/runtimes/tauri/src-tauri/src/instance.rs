@@ -0,0 +1,199 @@
+//! Keeping to a single running app.
+//!
+//! A second launch would start a second backend, and two players fighting
+//! over one audio device is chaos.  The running app records itself in
+//! [`INSTANCE_FILE`] in the app data directory: its pid and the local port
+//! it takes "focus" requests on.  The file is created exclusively, so of
+//! two apps launched together only one claims it.  A launch that finds a
+//! live instance there asks it to bring its window forward and exits
+//! instead.
+
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const INSTANCE_FILE: &str = "instance.json";
+/// What a second launch sends to the running instance.
+const FOCUS_REQUEST: &str = "focus";
+/// How long a second launch waits to reach the running instance.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceFile {
+    /// Process id of the app.
+    pub pid: u32,
+    /// Loopback port the app takes focus requests on.
+    pub port: u16,
+}
+
+/// What [`claim`] found.
+#[derive(Debug, PartialEq)]
+pub enum Claim {
+    /// The instance file is now this app's.
+    Claimed,
+    /// Another live instance holds it.
+    Running(InstanceFile),
+}
+
+impl InstanceFile {
+    /// Where the instance file lives for an app with `config`.
+    pub fn path(config: &tauri::Config) -> Option<PathBuf> {
+        tauri::api::path::app_data_dir(config).map(|dir| dir.join(INSTANCE_FILE))
+    }
+
+    pub fn read(path: &Path) -> Option<Self> {
+        let text = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    /// Write the file at `path`, failing with `AlreadyExists` rather than
+    /// replacing one that is there.
+    fn create(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text = serde_json::to_string(self).map_err(std::io::Error::other)?;
+        OpenOptions::new().write(true).create_new(true).open(path)?.write_all(text.as_bytes())
+    }
+}
+
+/// Record `own` as the running instance at `path`, unless another live
+/// instance already is.  A file left by a dead instance is replaced.
+pub fn claim(path: &Path, own: &InstanceFile) -> Result<Claim, String> {
+    let fail = |e: std::io::Error| format!("Failed to write {}: {e}", path.display());
+    match own.create(path) {
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+        result => return result.map(|()| Claim::Claimed).map_err(fail),
+    }
+    if let Some(other) = is_another_instance_running(path) {
+        return Ok(Claim::Running(other));
+    }
+    // Stale.  Should another launch replace it first, this fails rather
+    // than overwrite that one's claim.
+    std::fs::remove_file(path).ok();
+    own.create(path).map(|()| Claim::Claimed).map_err(fail)
+}
+
+/// The instance recorded at `path`, if it is another process that is
+/// still alive.
+pub fn is_another_instance_running(path: &Path) -> Option<InstanceFile> {
+    InstanceFile::read(path).filter(|other| other.pid != std::process::id() && is_alive(other.pid))
+}
+
+/// Remove the instance file at `path` if this app wrote it.
+pub fn remove_own(path: &Path) {
+    if InstanceFile::read(path).is_some_and(|own| own.pid == std::process::id()) {
+        std::fs::remove_file(path).ok();
+    }
+}
+
+/// A loopback port for focus requests, bound before the instance file is
+/// claimed so the file can name it.
+pub fn bind_focus_listener() -> std::io::Result<(TcpListener, u16)> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let port = listener.local_addr()?.port();
+    Ok((listener, port))
+}
+
+/// Accept focus requests on `listener`, calling `on_focus` for each.
+pub fn serve_focus(listener: TcpListener, on_focus: impl Fn() + Send + 'static) {
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line).ok();
+            if line.trim() == FOCUS_REQUEST {
+                on_focus();
+            }
+        }
+    });
+}
+
+/// Ask the instance listening on `port` to bring its window forward.  An
+/// error means nothing answered there, so the instance file is stale
+/// (e.g. its pid was reused after a crash).
+pub fn request_focus(port: u16) -> Result<(), String> {
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).map_err(|e| e.to_string())?;
+    writeln!(stream, "{FOCUS_REQUEST}").map_err(|e| e.to_string())
+}
+
+/// Whether a process with `pid` exists.
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(windows)]
+fn is_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH", "/FO", "CSV"])
+        .output()
+        .is_ok_and(|output| tasklist_has_pid(&String::from_utf8_lossy(&output.stdout), pid))
+}
+
+/// Whether `tasklist /FO CSV` output lists `pid` in its PID column.  With
+/// no match tasklist prints an "INFO:" line instead, and a plain substring
+/// test would take pid 12 for 123 or a memory figure.
+#[cfg(any(windows, test))]
+fn tasklist_has_pid(output: &str, pid: u32) -> bool {
+    let pid = pid.to_string();
+    output
+        .lines()
+        .any(|line| line.split(',').nth(1).is_some_and(|column| column.trim_matches('"') == pid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn only_other_live_instances_count() {
+        let path = std::env::temp_dir().join(format!("pykaraoke-instance-{}.json", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let own = InstanceFile { pid: std::process::id(), port: 1 };
+        assert_eq!(claim(&path, &own).unwrap(), Claim::Claimed);
+        assert_eq!(is_another_instance_running(&path), None, "this process isn't another instance");
+
+        let dead = InstanceFile { pid: u32::MAX - 1, port: 2 };
+        std::fs::write(&path, serde_json::to_string(&dead).unwrap()).unwrap();
+        assert_eq!(is_another_instance_running(&path), None);
+        remove_own(&path);
+        assert!(path.exists(), "another instance's file is left alone");
+        assert_eq!(claim(&path, &own).unwrap(), Claim::Claimed, "a dead instance's file is replaced");
+        assert_eq!(InstanceFile::read(&path).as_ref(), Some(&own));
+
+        #[cfg(unix)]
+        {
+            let parent = InstanceFile { pid: std::os::unix::process::parent_id(), port: 3 };
+            std::fs::write(&path, serde_json::to_string(&parent).unwrap()).unwrap();
+            assert_eq!(claim(&path, &own).unwrap(), Claim::Running(parent));
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn tasklist_matches_the_whole_pid_column() {
+        let output = "\"pykaraoke-ng.exe\",\"1234\",\"Console\",\"1\",\"12,345 K\"\r\n";
+        assert!(tasklist_has_pid(output, 1234));
+        assert!(!tasklist_has_pid(output, 123));
+        assert!(!tasklist_has_pid(output, 12));
+        assert!(!tasklist_has_pid("INFO: No tasks are running which match the specified criteria.", 1234));
+    }
+
+    #[test]
+    fn focus_requests_reach_the_listener() {
+        let (tx, rx) = mpsc::channel();
+        let (listener, port) = bind_focus_listener().unwrap();
+        serve_focus(listener, move || tx.send(()).unwrap());
+        request_focus(port).unwrap();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+//! Checking the bundled backend against hashes taken at build time.
+//!
+//! A partial update or a damaged install can leave the bundled backend
+//! half-written, which otherwise shows up only as an obscure Python error
+//! some time after launch.  `build.rs` records the SHA-256 of every file in
+//! the backend tree; with `verify_backend_integrity` on in the config, the
+//! installed copy is checked against that before a bundled backend is
+//! started.  It is off by default because a development checkout runs a
+//! script that is edited all the time.  Files the manifest doesn't list,
+//! like Python's bytecode caches, are ignored.
+//!
+//! The manifest also says which backend version was bundled, and whether
+//! it was only the placeholder a plain `cargo build` stages;
+//! `get_backend_manifest` returns it as is.
+
+use crate::error::BackendError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// `backend_manifest.json` as `build.rs` wrote it for this app.
+const MANIFEST: &str = include_str!(concat!(env!("OUT_DIR"), "/backend_manifest.json"));
+
+/// The backend tree an app was built with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackendManifest {
+    pub app_version: String,
+    /// `pykaraoke.__version__` of the staged backend; `None` for a
+    /// PyInstaller build.
+    pub backend_version: Option<String>,
+    /// Whether only the placeholder `build.rs` creates for plain `cargo`
+    /// builds was bundled, rather than a real backend.
+    pub placeholder: bool,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// `/`-separated, relative to the backend directory.
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// The manifest built into this app.
+pub fn bundled() -> Result<BackendManifest, BackendError> {
+    serde_json::from_str(MANIFEST)
+        .map_err(|e| BackendError::InvalidResponse(format!("Unreadable backend manifest: {e}")))
+}
+
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let data = std::fs::read(path)?;
+    Ok(Sha256::digest(data).iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Check the files `manifest` lists against their copies under `root`;
+/// returns how many were checked, or the first missing or changed one.
+pub fn verify(root: &Path, manifest: &BackendManifest) -> Result<usize, BackendError> {
+    for file in &manifest.files {
+        let path = file.path.split('/').fold(root.to_path_buf(), |path, part| path.join(part));
+        let actual = sha256_file(&path).ok();
+        if actual.as_deref() != Some(file.sha256.as_str()) {
+            return Err(BackendError::IntegrityCheckFailed {
+                path: path.display().to_string(),
+                expected: file.sha256.clone(),
+                actual,
+            });
+        }
+    }
+    Ok(manifest.files.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_and_missing_files_fail() {
+        let root = std::env::temp_dir().join(format!("pykaraoke-integrity-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("core")).unwrap();
+        std::fs::write(root.join("core").join("backend.py"), "print('hi')\n").unwrap();
+        let hash = sha256_file(&root.join("core").join("backend.py")).unwrap();
+        let manifest = BackendManifest {
+            app_version: "0.0.0".to_string(),
+            backend_version: None,
+            placeholder: false,
+            files: vec![ManifestFile { path: "core/backend.py".to_string(), sha256: hash, size: 12 }],
+        };
+        assert_eq!(verify(&root, &manifest), Ok(1));
+
+        std::fs::write(root.join("core").join("backend.py"), "print('bye')\n").unwrap();
+        let err = verify(&root, &manifest).unwrap_err();
+        assert_eq!(err.kind(), "integrity_check_failed");
+        std::fs::remove_dir_all(&root).ok();
+        let BackendError::IntegrityCheckFailed { actual, .. } = verify(&root, &manifest).unwrap_err() else {
+            panic!("expected an integrity failure");
+        };
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn build_manifest_covers_the_staged_backend() {
+        let manifest = bundled().unwrap();
+        assert_eq!(manifest.app_version, env!("CARGO_PKG_VERSION"));
+        let backend = Path::new(env!("CARGO_MANIFEST_DIR")).join("backend");
+        assert_eq!(verify(&backend, &manifest), Ok(manifest.files.len()));
+    }
+}
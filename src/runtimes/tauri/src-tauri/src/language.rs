@@ -0,0 +1,41 @@
+//! Choosing the language lyrics are rendered in.
+//!
+//! The language decides how lyric text in `.kar` files is decoded and
+//! which font draws it, since CJK text needs both a multibyte encoding and
+//! a font with those glyphs.  The backend lists what it supports through
+//! `get_supported_languages`, and a choice is checked against that list
+//! before it is sent.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LyricsLanguage {
+    /// Language code, e.g. `ja`.
+    pub id: String,
+    /// Name to show in the picker.
+    pub name: String,
+}
+
+/// Check that `lang` is one of the `supported` language ids.
+pub fn check_language(lang: &str, supported: &[LyricsLanguage]) -> Result<(), String> {
+    if supported.iter().any(|language| language.id == lang) {
+        return Ok(());
+    }
+    let ids: Vec<&str> = supported.iter().map(|language| language.id.as_str()).collect();
+    Err(format!("must be one of {}", ids.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_supported_languages_are_accepted() {
+        let supported = [
+            LyricsLanguage { id: "en".to_string(), name: "Western European".to_string() },
+            LyricsLanguage { id: "ja".to_string(), name: "Japanese".to_string() },
+        ];
+        assert!(check_language("ja", &supported).is_ok());
+        assert_eq!(check_language("xx", &supported), Err("must be one of en, ja".to_string()));
+    }
+}
@@ -0,0 +1,100 @@
+//! Filtered, sorted and paged views of the song library.
+//!
+//! `get_library` forwards the filter, sort and page to the backend, which
+//! answers `{songs, total}`.  Backends from before paging send the whole
+//! library and no `total`; the page is then cut here, unfiltered and in
+//! the backend's order.
+
+use crate::playback::SongInfo;
+use serde::{Deserialize, Serialize};
+
+/// Page size used when the caller doesn't give one.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+/// Largest page the library view may ask for.
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// The `sort_by` values the backend understands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibrarySort {
+    #[default]
+    Title,
+    Artist,
+    /// The order songs entered the library.
+    DateAdded,
+}
+
+impl LibrarySort {
+    /// Every accepted `sort_by` value, for param validation.
+    pub const NAMES: &'static [&'static str] = &["title", "artist", "date_added"];
+}
+
+/// One page of the library returned by `get_library`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LibraryPage {
+    /// Number of songs matching the filter, before paging.
+    pub total: usize,
+    pub items: Vec<SongInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawPage {
+    #[serde(default)]
+    songs: Vec<SongInfo>,
+    total: Option<usize>,
+}
+
+/// Clamp a requested page size to `1..=MAX_PAGE_SIZE`.
+pub fn page_size(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+impl LibraryPage {
+    /// Build from the `data` field of a `get_library` response for the
+    /// page starting at `offset`.
+    pub fn from_backend(data: &serde_json::Value, offset: usize, limit: usize) -> Result<Self, serde_json::Error> {
+        let raw = RawPage::deserialize(data)?;
+        Ok(match raw.total {
+            Some(total) => LibraryPage { total, items: raw.songs },
+            None => LibraryPage {
+                total: raw.songs.len(),
+                items: raw.songs.into_iter().skip(offset).take(limit).collect(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sort_names_match_serde() {
+        for (sort, name) in [LibrarySort::Title, LibrarySort::Artist, LibrarySort::DateAdded]
+            .iter()
+            .zip(LibrarySort::NAMES)
+        {
+            assert_eq!(serde_json::to_value(sort).unwrap(), *name);
+        }
+        assert!(serde_json::from_value::<LibrarySort>(json!("bpm")).is_err());
+    }
+
+    #[test]
+    fn page_size_is_clamped() {
+        assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size(Some(0)), 1);
+        assert_eq!(page_size(Some(10_000)), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn unpaged_libraries_are_cut_locally() {
+        let songs: Vec<_> = (0..4).map(|i| json!({ "title": format!("Song {i}") })).collect();
+        let page = LibraryPage::from_backend(&json!({ "songs": songs }), 1, 2).unwrap();
+        assert_eq!(page.total, 4);
+        assert_eq!(page.items[1].title, "Song 2");
+
+        let paged = LibraryPage::from_backend(&json!({ "songs": [], "total": 9 }), 0, 2).unwrap();
+        assert_eq!(paged.total, 9);
+    }
+}
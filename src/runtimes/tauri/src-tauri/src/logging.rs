@@ -0,0 +1,105 @@
+//! Where the app's own log lines go.
+//!
+//! Release builds on Windows have no console, so stderr is lost for the
+//! users most likely to need it.  Log lines are written to [`LOG_FILE`] in
+//! the app log directory, the one `open_log_dir` reveals, as well as to
+//! stderr.  A file over [`MAX_LOG_BYTES`] at launch is kept as
+//! `pykaraoke.log.1`, replacing the one kept before, so the directory
+//! holds at most two sessions' worth.
+//!
+//! Debug builds log at debug level, release builds at info; set
+//! `PYKARAOKE_LOG` to `debug` or `trace` for more.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::Level;
+
+pub const LOG_FILE: &str = "pykaraoke.log";
+
+pub const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Every line goes to stderr, and to the log file when it could be opened.
+struct LogWriter {
+    file: Option<File>,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stderr().write_all(buf).ok();
+        if let Some(file) = self.file.as_mut() {
+            if file.write_all(buf).is_err() {
+                // A full or vanished disk shouldn't fail every later line.
+                self.file = None;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Open `dir/LOG_FILE` for appending, first moving it aside if it has grown
+/// past `max_bytes`.
+pub fn open_log_file(dir: &Path, max_bytes: u64) -> io::Result<File> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(LOG_FILE);
+    if std::fs::metadata(&path).is_ok_and(|meta| meta.len() > max_bytes) {
+        std::fs::rename(&path, dir.join(format!("{LOG_FILE}.1")))?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn level() -> Level {
+    match std::env::var("PYKARAOKE_LOG").as_deref() {
+        Ok("trace") => Level::TRACE,
+        Ok("debug") => Level::DEBUG,
+        _ if cfg!(debug_assertions) => Level::DEBUG,
+        _ => Level::INFO,
+    }
+}
+
+/// Install the subscriber for the whole process; returns the log file, or
+/// `None` when only stderr could be used.
+pub fn init(dir: Option<PathBuf>) -> Option<PathBuf> {
+    let file = dir.as_deref().and_then(|dir| match open_log_file(dir, MAX_LOG_BYTES) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            eprintln!("Logging to stderr only; can't open {}: {e}", dir.join(LOG_FILE).display());
+            None
+        }
+    });
+    let path = file.as_ref().and(dir).map(|dir| dir.join(LOG_FILE));
+    tracing_subscriber::fmt()
+        .with_max_level(level())
+        .with_ansi(false)
+        .with_writer(Mutex::new(LogWriter { file }))
+        .try_init()
+        .ok()?;
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_large_log_is_kept_aside_once() {
+        let dir = std::env::temp_dir().join(format!("pykaraoke-logging-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(LOG_FILE), "x".repeat(20)).unwrap();
+        writeln!(open_log_file(&dir, 100).unwrap(), "kept").unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join(LOG_FILE)).unwrap().len(), 25, "appended below the limit");
+
+        writeln!(open_log_file(&dir, 10).unwrap(), "fresh").unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join(LOG_FILE)).unwrap(), "fresh\n");
+        assert_eq!(std::fs::read_to_string(dir.join(format!("{LOG_FILE}.1"))).unwrap().len(), 25);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,178 @@
+//! M3U playlists for interop with other players.
+//!
+//! Exports are extended M3U: an `#EXTM3U` header, then an `#EXTINF` line
+//! naming each song ahead of its path.  Durations aren't known without
+//! opening the files, so they are written as `-1` (unknown).
+//!
+//! Imports accept plain and extended M3U.  `#` lines are comments or
+//! metadata and are ignored; every other non-blank line is a track, with
+//! relative paths taken from the playlist's own directory.  Files that
+//! aren't valid UTF-8 (older `.m3u` files usually aren't) are read as
+//! Latin-1.
+
+use crate::playback::SongInfo;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// What `export_playlist_m3u` wrote.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct M3uExport {
+    /// Number of songs written.
+    pub written: usize,
+    /// One message per song left out.
+    pub warnings: Vec<String>,
+}
+
+/// What `import_playlist_m3u` queued.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct M3uImport {
+    pub imported: usize,
+    pub skipped: usize,
+    /// The entries that were skipped, as written in the playlist.
+    pub skipped_entries: Vec<String>,
+}
+
+impl M3uImport {
+    pub fn skip(&mut self, entry: &str) {
+        self.skipped += 1;
+        self.skipped_entries.push(entry.to_string());
+    }
+}
+
+/// The track entries of an M3U playlist, in order, as written.
+pub fn parse(bytes: &[u8]) -> Vec<String> {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| char::from(b)).collect(),
+    };
+    text.trim_start_matches('\u{FEFF}')
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Where `entry` from a playlist in `dir` points.  `file://` URLs are
+/// accepted and percent-decoded (`%20` and the like); other URLs are not
+/// local files and give `None`.
+pub fn resolve(entry: &str, dir: &Path) -> Option<PathBuf> {
+    if entry.starts_with("file://") {
+        return Url::parse(entry).ok()?.to_file_path().ok();
+    }
+    if entry.contains("://") {
+        return None;
+    }
+    Some(dir.join(entry))
+}
+
+/// Render `songs` as an extended M3U playlist.  Songs without a file path
+/// are skipped and reported in the returned export summary.
+pub fn render(songs: &[SongInfo]) -> (String, M3uExport) {
+    let mut text = String::from("#EXTM3U\n");
+    let mut export = M3uExport::default();
+    for (index, song) in songs.iter().enumerate() {
+        if song.filepath.is_empty() {
+            export.warnings.push(format!("Skipped entry {index} ({}): no file path", display_name(song)));
+            continue;
+        }
+        text.push_str(&format!("#EXTINF:-1,{}\n{}\n", one_line(&display_name(song)), one_line(&song.filepath)));
+        export.written += 1;
+    }
+    (text, export)
+}
+
+/// "Artist - Title", falling back to whichever is known, then the
+/// filename.
+fn display_name(song: &SongInfo) -> String {
+    match (song.artist.is_empty(), song.title.is_empty()) {
+        (false, false) => format!("{} - {}", song.artist, song.title),
+        (true, false) => song.title.clone(),
+        (false, true) => song.artist.clone(),
+        (true, true) => song.filename.clone(),
+    }
+}
+
+/// A line break inside a title or path would split the entry.
+fn one_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(title: &str, artist: &str, filepath: &str) -> SongInfo {
+        SongInfo {
+            title: title.to_string(),
+            artist: artist.to_string(),
+            filepath: filepath.to_string(),
+            ..SongInfo::default()
+        }
+    }
+
+    const SAMPLE: &str = "#EXTM3U\n\
+        #EXTINF:354,Queen - Bohemian Rhapsody\n\
+        /songs/queen.cdg\n\
+        \n\
+        # a comment\n\
+        #EXTINF:-1,ABBA - Dancing Queen\r\n\
+        abba/dancing-queen.kar\r\n\
+        http://radio.example/stream\n";
+
+    #[test]
+    fn parses_extended_m3u_and_resolves_relative_paths() {
+        let entries = parse(SAMPLE.as_bytes());
+        assert_eq!(entries, ["/songs/queen.cdg", "abba/dancing-queen.kar", "http://radio.example/stream"]);
+
+        let dir = Path::new("/playlists");
+        assert_eq!(resolve(&entries[0], dir).unwrap(), Path::new("/songs/queen.cdg"));
+        assert_eq!(resolve(&entries[1], dir).unwrap(), Path::new("/playlists/abba/dancing-queen.kar"));
+        assert_eq!(resolve(&entries[2], dir), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn decodes_file_urls() {
+        let dir = Path::new("/playlists");
+        assert_eq!(resolve("file:///songs/a.mp3", dir).unwrap(), Path::new("/songs/a.mp3"));
+        let encoded = "file:///songs/Caf%C3%A9%20Song.mp3";
+        assert_eq!(resolve(encoded, dir).unwrap(), Path::new("/songs/Caf\u{e9} Song.mp3"));
+    }
+
+    #[test]
+    fn parses_utf8_with_bom_and_latin1() {
+        let utf8 = "\u{FEFF}#EXTM3U\nCaf\u{e9}.cdg\n";
+        assert_eq!(parse(utf8.as_bytes()), ["Caf\u{e9}.cdg"]);
+        let latin1 = b"Caf\xe9.cdg\nplain.kar\n";
+        assert_eq!(parse(latin1), ["Caf\u{e9}.cdg", "plain.kar"]);
+    }
+
+    #[test]
+    fn exports_import_back() {
+        let (text, _) = render(&[song("Dancing Queen", "ABBA", "/songs/abba.cdg")]);
+        assert_eq!(parse(text.as_bytes()), ["/songs/abba.cdg"]);
+    }
+
+    #[test]
+    fn writes_extended_m3u() {
+        let (text, export) = render(&[
+            song("Dancing Queen", "ABBA", "/songs/abba.cdg"),
+            song("Intro", "", "/songs/intro.mp3"),
+        ]);
+        assert_eq!(
+            text,
+            "#EXTM3U\n#EXTINF:-1,ABBA - Dancing Queen\n/songs/abba.cdg\n#EXTINF:-1,Intro\n/songs/intro.mp3\n"
+        );
+        assert_eq!(export, M3uExport { written: 2, warnings: Vec::new() });
+    }
+
+    #[test]
+    fn songs_without_a_path_are_skipped_with_a_warning() {
+        let (text, export) = render(&[song("Lost", "Nobody", ""), song("Found", "", "/f.kar")]);
+        assert!(!text.contains("Lost"));
+        assert_eq!(export.written, 1);
+        assert_eq!(export.warnings, ["Skipped entry 0 (Nobody - Lost): no file path"]);
+    }
+}
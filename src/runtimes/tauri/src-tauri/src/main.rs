@@ -1,30 +1,360 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod advance;
+mod audio;
+mod benchmark;
+mod cache;
+mod capabilities;
+mod capture;
+mod coalesce;
+mod config;
+mod dedupe;
+mod diagnostics;
+mod display;
+mod error;
+mod events;
+mod excludes;
+mod framing;
+mod geometry;
+mod history;
+mod hotkeys;
+mod integrity;
+mod ingest;
+mod instance;
+mod language;
+mod library;
+mod logging;
+mod m3u;
+mod metrics;
+mod mock;
+mod pending;
+mod playback;
+mod playlist;
+mod plays;
+mod probe;
+mod reconnect;
+mod reload;
+mod resources;
+mod roots;
+mod search;
+mod session;
+mod sleep;
+mod stderr;
+mod subscriptions;
+mod support;
+mod theme;
+#[cfg(feature = "system-tray")]
+mod tray;
+mod validation;
+mod volume;
+mod watchdog;
+mod waveform;
+
+use advance::{AutoAdvance, RepeatMode};
+use audio::{AudioDevice, AudioReady, AudioStats, MicMonitoring, AUDIO_READY_TIMEOUT};
+use benchmark::{IpcBenchmark, BENCHMARK_TIMEOUT, MAX_BENCHMARK_PINGS};
+use cache::{CacheKind, CacheStats};
+use capabilities::BackendCapabilities;
+use capture::{CaptureSummary, SessionCapture};
+use coalesce::{CoalescingConfig, EventCoalescer};
+use config::{AppConfig, ConfigProblem};
+use dedupe::Deduplication;
+use diagnostics::DiagnosticsReport;
+use display::MonitorInfo;
+use error::BackendError;
+use events::{BackendErrorEvent, LoadFailedEvent};
+use framing::Framing;
+use geometry::{SaveDebounce, WindowGeometry};
+use history::{EventHistory, HistoryEntry};
+use integrity::BackendManifest;
+use instance::InstanceFile;
+use hotkeys::HotkeyAction;
+use language::LyricsLanguage;
+use library::{LibraryPage, LibrarySort};
+use m3u::{M3uExport, M3uImport};
+use metrics::{BackendMetrics, MetricsSnapshot};
+use pending::{PendingRequests, PendingSummary, ResponseResult};
+use playback::{
+    AppliedNormalization, AppliedVocalRemoval, Crossfade, FadeStop, Normalization, PlaybackState, Preload, SongInfo,
+    VocalRemoval, MAX_FEED_INTERVAL_MS, MAX_PREVIEW_MS, MAX_TEMPO, MIN_FEED_INTERVAL_MS, MIN_PREVIEW_MS, MIN_TEMPO,
+};
+use plays::{PlayHistoryEntry, PlaySort};
+use probe::{FileCache, ProbeCache, ProbedSong};
+use reconnect::Reconnect;
+use reload::{ConfigReload, LoadedConfig, RestartRequired};
+use resources::{ProcessResources, ResourceLimits, ResourceMonitor};
+use search::{SearchResults, DEFAULT_SEARCH_LIMIT};
+use session::{RestoreReport, SessionSnapshot};
+use sleep::{SleepTimer, SLEEP_FADE_MS};
+use stderr::{BurstDetector, BurstThreshold};
+use subscriptions::EventSubscriptions;
+use support::RevealedDir;
+use theme::Theme;
+use volume::{Mute, MuteState, VolumeDebounce, VOLUME_DEBOUNCE};
+use watchdog::{MissCounter, WatchdogSettings};
+use waveform::Waveform;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::process::{Child, Command, Stdio};
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufReader, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::sync::mpsc;
-use std::time::Duration;
-use tauri::{Manager, State};
-use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tauri::{GlobalShortcutManager, Manager, State};
+use std::path::{Path, PathBuf};
 
 /// Backend state shared across the application
+#[derive(Default)]
 struct BackendState {
     process: Option<Child>,
     stdin: Option<std::process::ChildStdin>,
-    response_rx: Option<mpsc::Receiver<serde_json::Value>>,
+    /// Requests awaiting a response.  Replaced on every spawn, so a stale
+    /// reader thread can never resolve requests sent to a newer process.
+    pending: Arc<Mutex<PendingRequests>>,
+    /// Shared with the stdout reader so coalescing settings apply live and
+    /// survive backend restarts.
+    coalescer: Arc<Mutex<EventCoalescer>>,
+    /// IPC counters for `get_backend_metrics`, reset on every start.
+    metrics: Arc<BackendMetrics>,
+    /// Recent events for `get_event_history`; survives backend restarts.
+    history: Arc<Mutex<EventHistory>>,
+    /// The `start_session_capture` file, if any; shared with the readers
+    /// and likewise kept across restarts.
+    capture: Arc<Mutex<SessionCapture>>,
+    /// Latest playback snapshot, from `get_playback_state` or a
+    /// `state_changed` event; shared with the reader.
+    playback: Arc<Mutex<Option<PlaybackState>>>,
+    /// Stderr lines per second that count as a runaway error loop;
+    /// shared with the stderr reader.  0 disables the alert.
+    stderr_burst_threshold: Arc<BurstThreshold>,
+    /// Whether the app advances the playlist on `song_finished`, and the
+    /// repeat mode.
+    auto_advance: AutoAdvance,
+    /// Last shuffle setting the backend accepted.
+    shuffle: bool,
+    /// Automatic restarts after the backend dies.
+    reconnect: Reconnect,
+    /// Pinging started backends to catch one that hangs.
+    watchdog: WatchdogSettings,
+    /// Dropped to stop the current backend's watchdog task.
+    watchdog_stop: Option<oneshot::Sender<()>>,
+    /// Dropped to stop the `now-playing` feed task.
+    now_playing_stop: Option<oneshot::Sender<()>>,
+    /// Pending and last sent `set_volume` values.
+    volume: VolumeDebounce,
+    /// Level to restore on unmuting, while muted.
+    mute: Mute,
+    /// Which event types each window asked for; shared with the reader.
+    subscriptions: Arc<Mutex<EventSubscriptions>>,
+    /// Set instead of `process`/`stdin` when running the in-process mock.
+    mock: Option<mock::MockBackend>,
+    /// Set while `start_backend` is resolving and spawning, so a concurrent
+    /// call doesn't spawn a second process.
+    starting: bool,
+    /// Framing spoken on the current backend's pipes.
+    framing: Framing,
+    /// Options the backend was last started with, reused by
+    /// `restart_backend`.
+    last_launch: LaunchOptions,
+    /// Script the current backend runs; `None` for the mock and the
+    /// bundled executable.
+    script: Option<PathBuf>,
+    /// Program the current backend was launched with.
+    interpreter: Option<String>,
+    /// Cached `get_backend_version` answer for the current backend.
+    version: Option<BackendVersion>,
+    /// Cached `get_supported_formats` answer for the current backend.
+    supported_formats: Option<Vec<String>>,
+    /// Cached `get_backend_capabilities` answer for the current backend.
+    capabilities: Option<BackendCapabilities>,
+    /// Set by `on_suspend` when it paused playback for system sleep.
+    paused_for_suspend: bool,
+    /// Audio/video sync offset in milliseconds the backend last accepted.
+    av_sync_offset_ms: i32,
+    /// `probe_song` results by path and modification time.
+    probes: ProbeCache,
+    /// `get_waveform` results by path and modification time, with the
+    /// bucket count they were drawn at.
+    waveforms: FileCache<(usize, Waveform)>,
+    /// Request id of the `scan_library`/`add_folder` awaiting its
+    /// response, for `cancel_scan`.
+    active_scan: Option<u64>,
+    /// Requests for actions that may keep the backend's command loop busy
+    /// past `RESPONSE_TIMEOUT`; the commands queued behind them are slow,
+    /// not hung.
+    long_requests: BTreeSet<u64>,
+    /// Read actions that join an identical request in flight.
+    deduplication: Deduplication,
+    /// Cached `get_supported_languages` answer for the current backend.
+    supported_languages: Option<Vec<LyricsLanguage>>,
+    /// Lyrics language the backend last accepted.
+    lyrics_language: Option<String>,
+    /// Cached `get_available_themes` answer for the current backend.
+    available_themes: Option<Vec<Theme>>,
+    /// Log level set with `set_backend_log_level`, re-sent to restarted
+    /// backends.
+    log_level: Option<&'static str>,
+    /// Folder patterns added to scans that don't give their own.
+    scan_excludes: Vec<String>,
+    /// Set while `stop_with_fade` is fading out.
+    fading: bool,
+    /// Song the backend has read ahead and not yet started.
+    preloaded: Option<Preload>,
+    /// Dropped to cancel a `set_sleep_timer` duration.
+    sleep_timer_stop: Option<oneshot::Sender<()>>,
+    /// Set by `set_sleep_timer` to stop once the current song finishes.
+    stop_after_current: bool,
+    /// Set while `preview_song` plays a snippet.
+    previewing: bool,
+    /// Dropped to end a `preview_song` snippet early, e.g. when the user
+    /// takes over playback.
+    preview_stop: Option<oneshot::Sender<()>>,
+    /// Set while `play_test_tone`'s tone plays.
+    test_tone: bool,
+    /// Dropped to forget a playing test tone, when its backend goes away.
+    test_tone_stop: Option<oneshot::Sender<()>>,
+    /// Last `get_audio_stats` answer from the current backend.
+    audio_stats: Option<AudioStats>,
+    /// Microphone chosen with `set_input_device` on the current backend.
+    input_device: Option<String>,
+    /// Woken by the next `audio_ready` event, for `reinit_audio`.
+    audio_ready: Arc<Mutex<Option<oneshot::Sender<AudioReady>>>>,
+    /// Dropped to stop the automatic session snapshots.
+    snapshot_stop: Option<oneshot::Sender<()>>,
+    /// Usage `resource-warning` is emitted above (see
+    /// `set_resource_limits`).
+    resource_limits: ResourceLimits,
+    resources: ResourceMonitor,
+    /// Dropped to stop checking the backend against `resource_limits`.
+    resource_check_stop: Option<oneshot::Sender<()>>,
+}
+
+impl BackendState {
+    fn is_running(&self) -> bool {
+        self.stdin.is_some() || self.mock.is_some()
+    }
+
+    /// Whether a long-running request is keeping the backend busy.
+    fn busy_with_long_request(&self) -> bool {
+        !self.long_requests.is_empty()
+    }
+
+    /// Forget the current process after it died or was killed, failing any
+    /// requests still waiting on it.
+    fn clear_process(&mut self) {
+        self.stdin = None;
+        self.process = None;
+        self.pending.lock().unwrap().fail_all(BackendError::Disconnected);
+    }
+
+    /// Write one NDJSON command line to the backend.  On failure the
+    /// process has most likely exited, so the state is torn down.
+    fn write_command(&mut self, command: &CommandRequest) -> Result<(), BackendError> {
+        if let Some(ref mut mock) = self.mock {
+            self.metrics.command_sent();
+            let response = mock.handle(&command.action, command.params.as_ref());
+            self.metrics.response_received();
+            let output = serde_json::json!({
+                "type": "response",
+                "request_id": command.request_id,
+                "response": response,
+            });
+            self.capture.lock().unwrap().record("stdout", &output);
+            self.pending.lock().unwrap().resolve(command.request_id, response);
+            return Ok(());
+        }
+        let command_json = serde_json::to_string(command)
+            .map_err(|e| BackendError::Serialize(e.to_string()))?;
+        let frame = self.framing.encode(&command_json);
+        let Some(ref mut stdin) = self.stdin else {
+            return Err(BackendError::NotRunning);
+        };
+        if let Err(e) = stdin.write_all(&frame) {
+            self.clear_process();
+            return Err(BackendError::PipeBroken { during: "send", reason: e.to_string() });
+        }
+        if let Err(e) = stdin.flush() {
+            self.clear_process();
+            return Err(BackendError::PipeBroken { during: "flush", reason: e.to_string() });
+        }
+        self.metrics.command_sent();
+        Ok(())
+    }
 }
 
 /// Wrapper for thread-safe backend state
 type SafeBackendState = Arc<Mutex<BackendState>>;
 
+/// How `start_backend` should launch the backend.
+#[derive(Debug, Clone, Default)]
+struct LaunchOptions {
+    /// Use the in-process mock instead of spawning anything.
+    mock: bool,
+    /// Run this script instead of searching the default locations.
+    backend_script: Option<PathBuf>,
+    framing: Framing,
+}
+
+impl LaunchOptions {
+    /// Options from the optional `start_backend`-style command arguments.
+    fn from_args(mock: Option<bool>, backend_script: Option<PathBuf>, framing: Option<Framing>) -> Self {
+        LaunchOptions {
+            mock: mock.unwrap_or(false),
+            backend_script,
+            framing: framing.unwrap_or_default(),
+        }
+    }
+}
+
+/// Version of the stdio command protocol this app speaks; must match the
+/// backend's `PROTOCOL_VERSION`.
+const PROTOCOL_VERSION: u64 = 1;
+
+/// How long a command may wait for its response before the backend is
+/// considered hung.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Actions that legitimately run for minutes (walking a whole library) and
+/// are cancelled via `cancel_command` rather than timed out.
+const LONG_RUNNING_ACTIONS: &[&str] = &["scan_library", "add_folder"];
+const LONG_RUNNING_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// `waveform` decodes a whole song before it can answer.
+const WAVEFORM_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+/// The handshake is the first command a fresh backend reads, so it also
+/// covers the interpreter's startup (importing pygame, loading the song
+/// database).
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Whether `action` may keep the backend's command loop busy for longer
+/// than [`RESPONSE_TIMEOUT`].
+fn is_long_running(action: &str) -> bool {
+    LONG_RUNNING_ACTIONS.contains(&action) || action == "waveform"
+}
+
+fn response_timeout(action: &str) -> Duration {
+    if LONG_RUNNING_ACTIONS.contains(&action) {
+        LONG_RUNNING_TIMEOUT
+    } else if action == "waveform" {
+        WAVEFORM_TIMEOUT
+    } else if action == "handshake" {
+        HANDSHAKE_TIMEOUT
+    } else {
+        RESPONSE_TIMEOUT
+    }
+}
+
 /// Command request structure
 #[derive(Debug, Serialize, Deserialize)]
 struct CommandRequest {
     action: String,
     params: Option<serde_json::Value>,
+    /// Correlates the backend's response with the waiting caller.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_id: Option<u64>,
 }
 
 /// Command response structure
@@ -35,6 +365,40 @@ struct CommandResponse {
     data: Option<serde_json::Value>,
 }
 
+impl CommandResponse {
+    /// Unwrap the `data` payload of a successful response, turning a
+    /// backend-reported `"status": "error"` into `BackendError::Rejected`.
+    fn into_data(self, action: &str) -> Result<serde_json::Value, BackendError> {
+        if self.status != "ok" {
+            return Err(BackendError::Rejected {
+                action: action.to_string(),
+                message: self.message.unwrap_or_else(|| "unknown error".to_string()),
+            });
+        }
+        Ok(self.data.unwrap_or(serde_json::Value::Null))
+    }
+
+    /// `BackendError::LoadFailed` for a `load_song` of `path` refused with a
+    /// reason code; `None` if it succeeded or the backend gave no reason.
+    fn load_failure(&self, path: &Path) -> Option<BackendError> {
+        let reason = self.data.as_ref()?["reason"].as_str().filter(|_| self.status != "ok")?;
+        Some(BackendError::LoadFailed {
+            path: path.display().to_string(),
+            reason: reason.to_string(),
+            message: self.message.clone().unwrap_or_else(|| "unknown error".to_string()),
+        })
+    }
+}
+
+/// Everything an "About" dialog or support ticket needs to identify the
+/// running build.
+#[derive(Debug, Clone, Serialize)]
+struct BackendVersion {
+    backend_version: String,
+    interpreter: Option<String>,
+    app_version: &'static str,
+}
+
 fn command_works(program: &str, args: &[&str]) -> bool {
     Command::new(program)
         .args(args)
@@ -45,11 +409,19 @@ fn command_works(program: &str, args: &[&str]) -> bool {
         .unwrap_or(false)
 }
 
-fn python_has_backend_deps(program: &str, prefix_args: &[&str]) -> bool {
-    let mut args: Vec<&str> = prefix_args.to_vec();
-    args.push("-c");
-    args.push("import pygame, numpy, mutagen");
-    command_works(program, &args)
+/// The interpreter `program` actually runs (`sys.executable`), or `None`
+/// when it lacks the backend's dependencies.  A bare `python3` or `py -3`
+/// says little in a bug report; the resolved path says which install it was.
+fn python_with_backend_deps(program: &str, prefix_args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(prefix_args)
+        .args(["-c", "import pygame, numpy, mutagen, sys; print(sys.executable)"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let executable = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(if executable.is_empty() { program.to_string() } else { executable })
 }
 
 fn venv_python_candidates_from_ancestors(start: &std::path::Path) -> Vec<PathBuf> {
@@ -63,10 +435,81 @@ fn venv_python_candidates_from_ancestors(start: &std::path::Path) -> Vec<PathBuf
     out
 }
 
-fn resolve_python_launcher() -> Result<(String, Vec<String>), String> {
+/// Places a backend script may live, in the order they are tried: the
+/// bundled resources first, then a development checkout.
+///
+/// In development `resource_dir` is `.../src-tauri/target/debug`, six
+/// levels below the project root, and `cwd` (when `tauri dev` is run from
+/// `src/runtimes/tauri`) is three levels below, with four as a fallback.
+/// Each location is tried with the layered `interfaces/backend_api.py`
+/// before the legacy `core/backend.py`.
+fn backend_script_candidates(resource_dir: &Path, cwd: &Path) -> Vec<PathBuf> {
+    let package = |dir: PathBuf| dir.join("pykaraoke");
+    let ancestor = |dir: &Path, levels: usize| {
+        let mut p = dir.to_path_buf();
+        for _ in 0..levels {
+            p.pop();
+        }
+        p.join("src")
+    };
+
+    let bundled = package(resource_dir.join("backend"));
+    let mut candidates = vec![
+        bundled.join("interfaces").join("backend_api.py"),
+        bundled.join("core").join("backend.py"),
+        // Flat bundled resource
+        resource_dir.join("backend.py"),
+    ];
+    for src in [ancestor(resource_dir, 6), ancestor(cwd, 3), ancestor(cwd, 4)] {
+        let dev = package(src);
+        candidates.push(dev.join("interfaces").join("backend_api.py"));
+        candidates.push(dev.join("core").join("backend.py"));
+    }
+    candidates
+}
+
+/// The first candidate that exists.
+fn pick_backend_script(candidates: &[PathBuf]) -> Option<PathBuf> {
+    candidates.iter().find(|p| p.exists()).cloned()
+}
+
+/// Pick the first existing backend script, or explain where we looked.
+fn find_backend_script(
+    candidates: &[PathBuf],
+    resource_dir_resolved: bool,
+) -> Result<PathBuf, BackendError> {
+    pick_backend_script(candidates)
+        .ok_or_else(|| BackendError::ScriptNotFound {
+            checked: candidates.iter().map(|p| p.display().to_string()).collect(),
+            resource_dir_resolved,
+        })
+}
+
+/// Interpreters on PATH to probe, in order, with the arguments that must
+/// precede the script.
+///
+/// On Windows `python3` is rarely on PATH (and is often the Store stub),
+/// so the `py -3` launcher goes first.
+fn interpreter_candidates() -> &'static [(&'static str, &'static [&'static str])] {
+    if cfg!(target_os = "windows") {
+        &[("py", &["-3"]), ("python.exe", &[]), ("python", &[]), ("python3", &[])]
+    } else {
+        &[("python3", &[]), ("python", &[]), ("py", &["-3"])]
+    }
+}
+
+/// How to run Python: the program and leading arguments to spawn, and the
+/// interpreter that ends up running.
+struct PythonLauncher {
+    program: String,
+    prefix_args: Vec<String>,
+    executable: String,
+}
+
+fn resolve_python_launcher() -> Result<PythonLauncher, String> {
     if let Ok(py) = std::env::var("PYKARAOKE_PYTHON") {
         if !py.trim().is_empty() {
-            return Ok((py, vec![]));
+            return Ok(PythonLauncher { program: py.clone(), prefix_args: vec![], executable: py });
         }
     }
 
@@ -76,20 +519,25 @@ fn resolve_python_launcher() -> Result<(String, Vec<String>), String> {
     for p in path_candidates {
         if p.exists() {
             let program = p.to_string_lossy().to_string();
-            if python_has_backend_deps(&program, &[]) {
-                return Ok((program, vec![]));
+            if let Some(executable) = python_with_backend_deps(&program, &[]) {
+                return Ok(PythonLauncher { program, prefix_args: vec![], executable });
             }
         }
     }
 
-    if command_works("python3", &["--version"]) && python_has_backend_deps("python3", &[]) {
-        return Ok(("python3".to_string(), vec![]));
-    }
-    if command_works("python", &["--version"]) && python_has_backend_deps("python", &[]) {
-        return Ok(("python".to_string(), vec![]));
-    }
-    if command_works("py", &["-3", "--version"]) && python_has_backend_deps("py", &["-3"]) {
-        return Ok(("py".to_string(), vec!["-3".to_string()]));
+    for (program, prefix_args) in interpreter_candidates() {
+        let mut version_args = prefix_args.to_vec();
+        version_args.push("--version");
+        if !command_works(program, &version_args) {
+            continue;
+        }
+        if let Some(executable) = python_with_backend_deps(program, prefix_args) {
+            return Ok(PythonLauncher {
+                program: program.to_string(),
+                prefix_args: prefix_args.iter().map(|a| a.to_string()).collect(),
+                executable,
+            });
+        }
     }
 
     Err(
@@ -97,46 +545,643 @@ fn resolve_python_launcher() -> Result<(String, Vec<String>), String> {
     )
 }
 
+/// The `backend-event` payload for an event envelope.  A `request_id` on
+/// the envelope (set while the backend handles a command) is copied into
+/// the event so the UI can tie progress events to the originating command.
+fn event_payload(envelope: &serde_json::Value) -> serde_json::Value {
+    let mut event = envelope["event"].clone();
+    if let (Some(request_id), Some(fields)) = (
+        envelope.get("request_id").filter(|id| !id.is_null()),
+        event.as_object_mut(),
+    ) {
+        fields
+            .entry("request_id")
+            .or_insert_with(|| request_id.clone());
+    }
+    event
+}
+
+/// Whether an envelope carries a `lyric_tick` timing event.
+fn is_lyric_tick(envelope: &serde_json::Value) -> bool {
+    envelope["type"] == "event" && envelope["event"]["type"] == events::LYRIC_TICK
+}
+
+/// The app event a scan summary is re-emitted as: `scan-complete`, or
+/// `scan-cancelled` for a scan stopped by `cancel_scan`.
+fn scan_summary_event(event_type: &str) -> Option<&'static str> {
+    match event_type {
+        events::SCAN_COMPLETE => Some("scan-complete"),
+        events::SCAN_CANCELLED => Some("scan-cancelled"),
+        _ => None,
+    }
+}
+
+/// Re-emit a scan summary event on its own (see `scan_summary_event`),
+/// and answer the scan command it belongs to with that summary without
+/// waiting for the backend's own reply.
+fn forward_scan_summary(
+    app_handle: &tauri::AppHandle,
+    pending: &Mutex<PendingRequests>,
+    name: &str,
+    event: &serde_json::Value,
+) {
+    app_handle.emit_all(name, &event["data"]).ok();
+    if let Some(request_id) = event["request_id"].as_u64() {
+        let response = serde_json::json!({ "status": "ok", "data": event["data"] });
+        pending.lock().unwrap().answer(request_id, response);
+    }
+}
+
+/// Fast path for `lyric_tick`: emitted as `lyric_tick` straight to the
+/// window showing the lyrics (see `lyrics_window`), bypassing history,
+/// coalescing and subscriptions so synced lyrics don't lag.
+/// The time since the line was read is logged at debug level (see
+/// `logging.rs`).
+fn forward_lyric_tick(
+    app_handle: &tauri::AppHandle,
+    metrics: &BackendMetrics,
+    event: &serde_json::Value,
+    received: Instant,
+) {
+    if let Some(window) = lyrics_window(app_handle) {
+        window.emit("lyric_tick", event).ok();
+    }
+    metrics.event_emitted();
+    tracing::debug!(
+        latency_us = received.elapsed().as_micros() as u64,
+        "forwarded lyric_tick"
+    );
+}
+
+/// Emit a `backend-event` to each window that wants its type; windows
+/// without a subscription get everything.
+fn emit_backend_event(
+    app_handle: &tauri::AppHandle,
+    subscriptions: &Mutex<EventSubscriptions>,
+    metrics: &BackendMetrics,
+    event: serde_json::Value,
+) {
+    metrics.event_emitted();
+    let event_type = event["type"].as_str().unwrap_or_default();
+    let subscriptions = subscriptions.lock().unwrap();
+    for (label, window) in app_handle.windows() {
+        if subscriptions.wants(&label, event_type) {
+            window.emit("backend-event", &event).ok();
+        }
+    }
+}
+
+/// React to `song_finished`: if auto-advance is on, send `next`, or with
+/// repeat-one replay the song that finished, and tell the UI with
+/// `playlist_advanced` once the backend accepted it.
+fn auto_advance(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<SafeBackendState>().inner().clone();
+    let (repeat, finished) = {
+        let mut backend = state.lock().unwrap();
+        if !backend.auto_advance.should_advance(Instant::now()) {
+            return;
+        }
+        // Read now: the backend's next `state_changed` clears the song.
+        let finished = backend.playback.lock().unwrap().clone();
+        (backend.auto_advance.repeat(), finished)
+    };
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let advanced = advance_playlist(&state, repeat, finished.as_ref()).await;
+        // At the end of the playlist the backend rejects `next`; nothing
+        // to announce then.
+        if advanced.is_ok() {
+            app_handle.emit_all("playlist_advanced", ()).ok();
+        }
+    });
+}
+
+fn is_previewing(app_handle: &tauri::AppHandle) -> bool {
+    app_handle.state::<SafeBackendState>().lock().unwrap().previewing
+}
+
+/// React to `song_finished` while the sleep timer waits for the current
+/// song: stop instead of advancing, and emit `sleep-timer-fired`.  Returns
+/// whether it did.
+fn stop_after_song(app_handle: &tauri::AppHandle) -> bool {
+    let state = app_handle.state::<SafeBackendState>().inner().clone();
+    if !std::mem::take(&mut state.lock().unwrap().stop_after_current) {
+        return false;
+    }
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let reason = SleepTimer::AfterCurrent.reason();
+        app_handle.emit_all("sleep-timer-fired", serde_json::json!({ "reason": reason })).ok();
+        let stopped = dispatch_command(&state, "stop".to_string(), None).await.and_then(|r| r.into_data("stop"));
+        if let Err(e) = stopped {
+            tracing::warn!("Sleep timer failed to stop playback: {e}");
+        }
+        if let Err(e) = restore_backend_advance(&state).await {
+            tracing::warn!("Failed to turn backend advancing back on: {e}");
+        }
+    });
+    true
+}
+
+/// React to `song_started` or `playlist_updated`: the preload is spent or
+/// may now point at another song, so forget it, and once a song starts
+/// read ahead the one the repeat mode plays after it.
+fn auto_preload(app_handle: &tauri::AppHandle, event: &serde_json::Value) {
+    let state = app_handle.state::<SafeBackendState>().inner().clone();
+    let next = {
+        let mut backend = state.lock().unwrap();
+        backend.preloaded = None;
+        let Some(started) = event["data"]["playlist_index"].as_u64() else {
+            return;
+        };
+        let playlist_len = backend.playback.lock().unwrap().as_ref().map(|playback| playback.playlist_len);
+        match (backend.auto_advance.repeat(), started as usize + 1) {
+            (RepeatMode::One, _) => started as usize,
+            (RepeatMode::All, next) if playlist_len == Some(next) => 0,
+            (_, next) if playlist_len.is_some_and(|len| next >= len) => return,
+            (_, next) => next,
+        }
+    };
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        match preload_song(&state, Some(next)).await {
+            Ok(preload) if !preload.already_preloaded => {
+                app_handle.emit_all("preload-complete", &preload).ok();
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to preload playlist entry {next}: {e}"),
+        }
+    });
+}
+
+/// Move on from the `finished` song according to `repeat`.
+async fn advance_playlist(
+    state: &SafeBackendState,
+    repeat: RepeatMode,
+    finished: Option<&PlaybackState>,
+) -> Result<(), BackendError> {
+    let play_index = |index: usize| {
+        dispatch_command(state, "play".to_string(), Some(serde_json::json!({ "playlist_index": index })))
+    };
+    match (repeat, finished.and_then(|playback| playback.playlist_index)) {
+        (RepeatMode::One, Some(index)) => play_index(index).await?.into_data("play").map(drop),
+        (RepeatMode::One, None) => {
+            let Some(song) = finished.and_then(|playback| playback.current_song.as_ref()) else {
+                return Err(BackendError::Rejected {
+                    action: "play".to_string(),
+                    message: "No song to repeat".to_string(),
+                });
+            };
+            let params = serde_json::json!({ "filepath": song.filepath });
+            dispatch_command(state, "load_song".to_string(), Some(params))
+                .await?
+                .into_data("load_song")?;
+            dispatch_command(state, "play".to_string(), None).await?.into_data("play").map(drop)
+        }
+        (mode, _) => {
+            let next = dispatch_command(state, "next".to_string(), None)
+                .await?
+                .into_data("next")
+                .map(drop);
+            match next {
+                Err(BackendError::Rejected { .. }) if mode == RepeatMode::All => {
+                    play_index(0).await?.into_data("play").map(drop)
+                }
+                other => other,
+            }
+        }
+    }
+}
+
+/// Called by the stdout reader once its backend's output ends.  If that
+/// process is still the current backend nobody stopped it, so with
+/// auto-restart on a restart is scheduled.
+fn backend_exited(app_handle: &tauri::AppHandle, pending: &Arc<Mutex<PendingRequests>>) {
+    let state = app_handle.state::<SafeBackendState>().inner().clone();
+    let delay = {
+        let mut backend = state.lock().unwrap();
+        let current = backend.process.is_some() && Arc::ptr_eq(&backend.pending, pending);
+        if !current || !backend.reconnect.enabled() {
+            return;
+        }
+        backend.reconnect.next_attempt()
+    };
+    schedule_reconnect(app_handle, delay);
+}
+
+/// Restart the backend after `delay`, emitting `backend-reconnecting`
+/// first and `backend-ready` on success.  A failed attempt schedules the
+/// next one; with no attempts left (`None`) emit `backend-gave-up`.
+fn schedule_reconnect(app_handle: &tauri::AppHandle, delay: Option<Duration>) {
+    let state = app_handle.state::<SafeBackendState>().inner().clone();
+    let attempt = state.lock().unwrap().reconnect.consecutive_failures();
+    let Some(delay) = delay else {
+        app_handle
+            .emit_all("backend-gave-up", serde_json::json!({ "attempts": attempt }))
+            .ok();
+        return;
+    };
+    app_handle
+        .emit_all(
+            "backend-reconnecting",
+            serde_json::json!({ "attempt": attempt, "delay_ms": delay.as_millis() as u64 }),
+        )
+        .ok();
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(delay).await;
+        let (options, exiting) = {
+            let mut backend = state.lock().unwrap();
+            if !backend.reconnect.take_scheduled() {
+                return;
+            }
+            (backend.last_launch.clone(), detach_backend(&mut backend))
+        };
+        // Reap the dead process before starting a new one.
+        exiting.finish(SHUTDOWN_GRACE).ok();
+        match start_and_handshake(&state, &app_handle, options).await {
+            Ok(_) => {
+                let pid = state.lock().unwrap().process.as_ref().map(Child::id);
+                app_handle
+                    .emit_all("backend-ready", serde_json::json!({ "pid": pid }))
+                    .ok();
+            }
+            Err(e) => {
+                tracing::warn!("Backend restart attempt {attempt} failed: {e}");
+                let delay = state.lock().unwrap().reconnect.next_attempt();
+                schedule_reconnect(&app_handle, delay);
+            }
+        }
+    });
+}
+
+/// Start pinging the current backend if the watchdog is on, replacing
+/// (and so stopping) any previous watchdog task.  A backend that misses
+/// `missed_pings` pings in a row is killed, `backend-unresponsive` is
+/// emitted with `missed`, and it is restarted as if it had crashed.
+fn start_watchdog(state: &SafeBackendState, app_handle: &tauri::AppHandle) {
+    let (settings, mut stop) = {
+        let mut backend = state.lock().unwrap();
+        if !backend.watchdog.enabled {
+            backend.watchdog_stop = None;
+            return;
+        }
+        let (tx, rx) = oneshot::channel::<()>();
+        backend.watchdog_stop = Some(tx);
+        (backend.watchdog, rx)
+    };
+    let state = state.clone();
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut misses = MissCounter::new(settings.missed_pings);
+        loop {
+            tokio::select! {
+                _ = &mut stop => return,
+                _ = tokio::time::sleep(settings.interval()) => {}
+            }
+            // The stdio loop answers in order, so a ping queued behind a
+            // scan or another long command would be missed.
+            if state.lock().unwrap().busy_with_long_request() {
+                continue;
+            }
+            let answered = tokio::select! {
+                _ = &mut stop => return,
+                answered = ping_within(&state, settings.interval()) => answered,
+            };
+            // Not running any more: crash detection takes it from here.
+            let Ok(answered) = answered else { return };
+            if misses.record(answered) {
+                break;
+            }
+        }
+        let delay = {
+            let mut backend = state.lock().unwrap();
+            if let Some(mut child) = backend.process.take() {
+                child.kill().ok();
+            }
+            backend.clear_process();
+            backend.reconnect.next_attempt()
+        };
+        app_handle
+            .emit_all("backend-unresponsive", serde_json::json!({ "missed": misses.missed() }))
+            .ok();
+        schedule_reconnect(&app_handle, delay);
+    });
+}
+
+/// Ping the backend and report whether it answered within `limit`.
+/// Unlike `PendingResponse::wait`, a late answer leaves the process alone;
+/// the ping's slot is cancelled rather than dropped so that answer is
+/// absorbed instead of being routed to the next request.
+async fn ping_within(state: &SafeBackendState, limit: Duration) -> Result<bool, BackendError> {
+    let pending = submit_command(state, "ping".to_string(), None)?;
+    let answered = matches!(tokio::time::timeout(limit, pending.rx).await, Ok(Ok(Ok(_))));
+    if !answered {
+        pending.table.lock().unwrap().cancel(pending.request_id);
+    }
+    Ok(answered)
+}
+
+/// Send `count` pings (1 to 10000) back to back and time their replies,
+/// to tell whether a sluggish UI is waiting on IPC.  Pings are never
+/// deduplicated here, and a slow reply doesn't count against the backend.
+#[tauri::command]
+async fn benchmark_ipc(state: State<'_, SafeBackendState>, count: usize) -> Result<IpcBenchmark, BackendError> {
+    run_ipc_benchmark(&state, count).await
+}
+
+async fn run_ipc_benchmark(state: &SafeBackendState, count: usize) -> Result<IpcBenchmark, BackendError> {
+    if !(1..=MAX_BENCHMARK_PINGS).contains(&count) {
+        return Err(BackendError::InvalidParams {
+            action: "benchmark_ipc".to_string(),
+            field: "count".to_string(),
+            reason: format!("must be between 1 and {MAX_BENCHMARK_PINGS}"),
+        });
+    }
+    let started = Instant::now();
+    let (table, mut pipe, framing, metrics) = {
+        let backend = state.lock().unwrap();
+        if !backend.is_running() {
+            return Err(BackendError::NotRunning);
+        }
+        let pipe = backend.stdin.as_ref().map(clone_stdin).transpose()?;
+        (backend.pending.clone(), pipe, backend.framing, backend.metrics.clone())
+    };
+    let mut replies = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (request_id, rx) = table.lock().unwrap().register();
+        let ping = CommandRequest { action: "ping".to_string(), params: None, request_id: Some(request_id) };
+        // Written without the state lock: a full pipe waits on the backend,
+        // which may itself be waiting on an event handler that needs it.
+        let sent = match pipe.as_mut() {
+            Some(pipe) => serde_json::to_string(&ping)
+                .map_err(|e| BackendError::Serialize(e.to_string()))
+                .and_then(|json| {
+                    pipe.write_all(&framing.encode(&json))
+                        .map_err(|e| BackendError::PipeBroken { during: "send", reason: e.to_string() })
+                })
+                .map(|()| metrics.command_sent()),
+            None => state.lock().unwrap().write_command(&ping),
+        };
+        if let Err(e) = sent {
+            table.lock().unwrap().remove(request_id);
+            return Err(e);
+        }
+        replies.push((request_id, rx));
+    }
+    let deadline = tokio::time::Instant::now() + BENCHMARK_TIMEOUT;
+    let (mut received, mut elapsed) = (0, Duration::ZERO);
+    for (request_id, rx) in replies {
+        match tokio::time::timeout_at(deadline, rx).await {
+            Ok(Ok(Ok(_))) => {
+                received += 1;
+                elapsed = started.elapsed();
+            }
+            Ok(_) => {}
+            Err(_) => table.lock().unwrap().remove(request_id),
+        }
+    }
+    Ok(IpcBenchmark::new(count, received, elapsed))
+}
+
+/// A second handle on the backend's stdin, for writing to it without
+/// holding the state lock.
+fn clone_stdin(stdin: &std::process::ChildStdin) -> Result<std::fs::File, BackendError> {
+    #[cfg(unix)]
+    let handle = std::os::fd::AsFd::as_fd(stdin).try_clone_to_owned();
+    #[cfg(windows)]
+    let handle = std::os::windows::io::AsHandle::as_handle(stdin).try_clone_to_owned();
+    handle
+        .map(std::fs::File::from)
+        .map_err(|e| BackendError::PipeBroken { during: "send", reason: e.to_string() })
+}
+
+/// Remember the snapshot carried by a `state_changed` event, and the
+/// playlist length from a `playlist_updated` one.
+fn cache_playback(playback: &Mutex<Option<PlaybackState>>, event: &serde_json::Value) {
+    let mut cached = playback.lock().unwrap();
+    if let Some(snapshot) = PlaybackState::from_event(event) {
+        *cached = Some(snapshot.carry_adjustments(cached.as_ref()));
+    } else if event["type"] == events::PLAYLIST_UPDATED {
+        if let (Some(snapshot), Some(songs)) = (cached.as_mut(), event["data"]["playlist"].as_array()) {
+            snapshot.playlist_len = songs.len();
+        }
+    }
+}
+
+/// Write the playlist carried by a `playlist_updated` event to the
+/// autosave file.
+fn autosave_playlist(path: &Path, event: &serde_json::Value) {
+    let saved = playlist::songs_in(&event["data"])
+        .map_err(|e| BackendError::InvalidResponse(e.to_string()))
+        .and_then(|songs| playlist::save(path, &songs));
+    if let Err(e) = saved {
+        tracing::warn!("Failed to autosave playlist: {e}");
+    }
+}
+
+/// A thread handing each event sent to it to `write` along with `path`,
+/// e.g. `record_play` or `autosave_playlist`, so the stdout reader never
+/// waits on the file.  One thread keeps the writes in order; it ends when
+/// the sender is dropped.
+fn spawn_file_writer(
+    path: PathBuf,
+    write: fn(&Path, &serde_json::Value),
+) -> std::sync::mpsc::Sender<serde_json::Value> {
+    let (tx, rx) = std::sync::mpsc::channel::<serde_json::Value>();
+    std::thread::spawn(move || {
+        for event in rx {
+            write(&path, &event);
+        }
+    });
+    tx
+}
+
+/// Count the song a `song_started` event names in the play history file.
+fn record_play(path: &Path, event: &serde_json::Value) {
+    let Ok(song) = SongInfo::deserialize(&event["data"]["song"]) else {
+        tracing::warn!("song_started without a song: {event}");
+        return;
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let mut entries = plays::load(path);
+    plays::record(&mut entries, song, now);
+    if let Err(e) = plays::save(path, &entries) {
+        tracing::warn!("Failed to save play history: {e}");
+    }
+}
+
+/// Describe a failed spawn with the full command line that was attempted.
+fn spawn_error(cmd: &Command, error: &std::io::Error) -> BackendError {
+    BackendError::SpawnFailed {
+        program: cmd.get_program().to_string_lossy().into_owned(),
+        args: cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect(),
+        os_code: error.raw_os_error(),
+        not_found: error.kind() == std::io::ErrorKind::NotFound,
+        reason: error.to_string(),
+    }
+}
+
 /// Spawn a child process and wire up stdin/stdout JSON IPC.
 /// Used by both the bundled backend.exe and the Python dev launcher.
 fn spawn_backend_process(
     cmd: &mut Command,
     app_handle: &tauri::AppHandle,
     backend: &mut BackendState,
-) -> Result<String, String> {
+) -> Result<String, BackendError> {
+    let framing = backend.framing;
     let mut child = cmd
+        .args(framing.cli_args())
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
+        .stderr(Stdio::piped())
         .spawn()
-        .map_err(|e| format!("Failed to start backend: {}", e))?;
+        .map_err(|e| spawn_error(cmd, &e))?;
 
     let stdin = child.stdin.take();
     let stdout = child.stdout.take();
 
-    let (response_tx, response_rx) = mpsc::channel::<serde_json::Value>();
+    if let Some(stderr) = child.stderr.take() {
+        let app_handle = app_handle.clone();
+        let threshold = backend.stderr_burst_threshold.clone();
+        let capture = backend.capture.clone();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stderr);
+            let mut buf = Vec::new();
+            let mut detector = BurstDetector::default();
+            while let Some((line, _)) = framing::read_line_lossy(&mut reader, &mut buf) {
+                // Pass the output through as when stderr was inherited.
+                tracing::info!(target: "backend", "{line}");
+                capture.lock().unwrap().record("stderr", &serde_json::Value::String(line.clone()));
+                let burst = detector.line(&line, Instant::now(), threshold.get());
+                if let Some(burst) = burst {
+                    app_handle.emit_all("backend-stderr-burst", burst).ok();
+                }
+            }
+        });
+    }
+
+    let pending = Arc::new(Mutex::new(PendingRequests::default()));
 
     if let Some(stdout) = stdout {
         let app_handle_clone = app_handle.clone();
+        let pending = pending.clone();
+        let coalescer = backend.coalescer.clone();
+        let subscriptions = backend.subscriptions.clone();
+        let history = backend.history.clone();
+        let playback = backend.playback.clone();
+        let metrics = backend.metrics.clone();
+        let capture = backend.capture.clone();
+        let audio_ready = backend.audio_ready.clone();
+        let autosave = playlist::autosave_path(&app_handle.config())
+            .map(|path| spawn_file_writer(path, autosave_playlist));
+        let plays = plays::history_path(&app_handle.config()).map(|path| spawn_file_writer(path, record_play));
+        let reader_done = Arc::new(AtomicBool::new(false));
+
+        let flush_handle = app_handle.clone();
+        let flush_subscriptions = subscriptions.clone();
+        let flush_metrics = metrics.clone();
+        coalesce::spawn_flusher(coalescer.clone(), reader_done.clone(), move |event| {
+            emit_backend_event(&flush_handle, &flush_subscriptions, &flush_metrics, event);
+        });
+
+        let capacity = AppConfig::path(&app_handle.config())
+            .map_or(framing::DEFAULT_READER_CAPACITY, |path| AppConfig::load(&path).reader_buffer_bytes);
+
         std::thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    if let Ok(output) = serde_json::from_str::<serde_json::Value>(&line) {
-                        if output["type"] == "event" {
-                            app_handle_clone.emit_all("backend-event", output["event"].clone()).ok();
-                        } else if output["type"] == "response" {
-                            response_tx.send(output["response"].clone()).ok();
+            // The line buffer is reused, so after the largest response it
+            // stops reallocating.
+            let mut reader = BufReader::with_capacity(framing::reader_capacity(capacity), stdout);
+            let mut buf = Vec::new();
+            while let Some((line, lossy)) = framing.read_message(&mut reader, &mut buf) {
+                let received = Instant::now();
+                if lossy {
+                    app_handle_clone
+                        .emit_all(
+                            "backend-log",
+                            serde_json::json!({
+                                "level": "warn",
+                                "message": "Backend output contained invalid UTF-8; replaced undecodable bytes",
+                            }),
+                        )
+                        .ok();
+                }
+                if let Ok(output) = serde_json::from_str::<serde_json::Value>(&line) {
+                    capture.lock().unwrap().record("stdout", &output);
+                    if is_lyric_tick(&output) {
+                        forward_lyric_tick(&app_handle_clone, &metrics, &output["event"], received);
+                    } else if output["type"] == "event" {
+                        let event = event_payload(&output);
+                        history.lock().unwrap().record(event.clone(), line.clone());
+                        cache_playback(&playback, &event);
+                        #[cfg(feature = "system-tray")]
+                        if event["type"] == events::STATE_CHANGED {
+                            update_tray_tooltip(&app_handle_clone, &playback);
+                        }
+                        // A preview neither counts as a play nor moves the
+                        // playlist on.
+                        let started = event["type"] == events::SONG_STARTED;
+                        let finished = event["type"] == events::SONG_FINISHED;
+                        let previewing = (started || finished) && is_previewing(&app_handle_clone);
+                        if finished && !previewing && !stop_after_song(&app_handle_clone) {
+                            auto_advance(&app_handle_clone);
+                        }
+                        if !previewing && (started || event["type"] == events::PLAYLIST_UPDATED) {
+                            auto_preload(&app_handle_clone, &event);
+                        }
+                        if let Some(name) = event["type"].as_str().and_then(scan_summary_event) {
+                            forward_scan_summary(&app_handle_clone, &pending, name, &event);
+                        }
+                        if let Some(autosaver) = &autosave {
+                            if event["type"] == events::PLAYLIST_UPDATED {
+                                autosaver.send(event.clone()).ok();
+                            }
+                        }
+                        if let Some(recorder) = &plays {
+                            if started && !previewing {
+                                recorder.send(event.clone()).ok();
+                            }
+                        }
+                        if let Some(error) = BackendErrorEvent::from_event(&event) {
+                            app_handle_clone.emit_all("backend-error", &error).ok();
                         }
+                        if let Some(failed) = LoadFailedEvent::from_event(&event) {
+                            app_handle_clone.emit_all("song-load-failed", &failed).ok();
+                        }
+                        confirm_audio_ready(&audio_ready, &event);
+                        let ready = coalescer.lock().unwrap().push(event);
+                        for event in ready {
+                            emit_backend_event(&app_handle_clone, &subscriptions, &metrics, event);
+                        }
+                    } else if output["type"] == "response" {
+                        metrics.response_received();
+                        let request_id = output
+                            .get("request_id")
+                            .or_else(|| output["response"].get("request_id"))
+                            .and_then(serde_json::Value::as_u64);
+                        pending.lock().unwrap().resolve(request_id, output["response"].clone());
                     }
+                } else {
+                    metrics.parse_error();
                 }
             }
+            pending.lock().unwrap().fail_all(BackendError::Disconnected);
+            reader_done.store(true, Ordering::Release);
+            backend_exited(&app_handle_clone, &pending);
         });
     }
 
     backend.process = Some(child);
     backend.stdin = stdin;
-    backend.response_rx = Some(response_rx);
+    backend.pending = pending;
+    backend.version = None;
+    backend.supported_formats = None;
+    backend.capabilities = None;
+    backend.supported_languages = None;
+    backend.available_themes = None;
 
     Ok("Backend started successfully".to_string())
 }
@@ -146,581 +1191,5828 @@ fn spawn_backend_process(
 /// Two modes:
 ///  1. Production – launches the bundled backend.exe (PyInstaller)
 ///  2. Development – finds a Python interpreter and runs backend.py
+///
+/// `backend_script` runs that script with the Python launcher instead,
+/// skipping both of the above; it must exist.
+///
+/// With `mock: true` no process is spawned; commands are answered by the
+/// in-process `mock::MockBackend` instead, for UI work without Python.
+///
+/// `framing` selects the pipe framing (`"ndjson"`, the default, or
+/// `"length_prefixed"`).
+///
+/// A freshly started backend must pass the protocol handshake; otherwise
+/// it is stopped again and `BackendError::ProtocolMismatch` is returned.
+/// It is then refilled with the playlist autosaved by the last session.
+///
+/// Returns a `StartedBackend` naming the script and interpreter in use.
 #[tauri::command]
-fn start_backend(state: State<SafeBackendState>, app_handle: tauri::AppHandle) -> Result<String, String> {
-    let mut backend = state.lock().unwrap();
+async fn start_backend(
+    state: State<'_, SafeBackendState>,
+    app_handle: tauri::AppHandle,
+    mock: Option<bool>,
+    backend_script: Option<PathBuf>,
+    framing: Option<Framing>,
+) -> Result<StartedBackend, BackendError> {
+    let options = LaunchOptions::from_args(mock, backend_script, framing);
+    start_and_handshake(&state, &app_handle, options).await
+}
+
+/// What `start_and_wait_ready` reports once the backend is usable.
+#[derive(Debug, Clone, Serialize)]
+struct BackendStatus {
+    /// `None` for the mock backend.
+    pid: Option<u32>,
+    protocol: u64,
+    version: String,
+}
 
-    if backend.process.is_some() {
-        return Ok("Backend already running".to_string());
+/// Start the backend and wait until it can take commands, in one call.
+///
+/// Takes the same arguments as `start_backend`.  The backend counts as
+/// ready once it has answered the handshake (bounded by
+/// `HANDSHAKE_TIMEOUT`) and the version query.  If any step fails, a
+/// backend started by this call is shut down again so no half-started
+/// process is left behind.  An already running backend is only queried.
+#[tauri::command]
+async fn start_and_wait_ready(
+    state: State<'_, SafeBackendState>,
+    app_handle: tauri::AppHandle,
+    mock: Option<bool>,
+    backend_script: Option<PathBuf>,
+    framing: Option<Framing>,
+) -> Result<BackendStatus, BackendError> {
+    let options = LaunchOptions::from_args(mock, backend_script, framing);
+    let started = match launch_backend(&state, &app_handle, options)? {
+        LaunchOutcome::Started(_) => true,
+        LaunchOutcome::AlreadyRunning => false,
+        LaunchOutcome::Starting => {
+            return Err(BackendError::LaunchFailed(
+                "Backend is already being started".to_string(),
+            ))
+        }
+    };
+    let status = wait_ready(&state, started).await?;
+    if started {
+        if let Err(e) = prepare_started(&state, &app_handle).await {
+            shutdown_managed_backend(&state, SHUTDOWN_GRACE).ok();
+            return Err(e);
+        }
     }
+    Ok(status)
+}
 
-    let resource_dir = app_handle.path_resolver()
-        .resource_dir()
-        .unwrap_or_else(|| std::env::current_dir().unwrap());
+/// Handshake and fetch the version; on failure stop the backend if
+/// `started` says this call launched it.
+async fn wait_ready(state: &SafeBackendState, started: bool) -> Result<BackendStatus, BackendError> {
+    let status = async {
+        let protocol = handshake(state).await?;
+        let version = backend_version(state).await?.backend_version;
+        let pid = state.lock().unwrap().process.as_ref().map(Child::id);
+        Ok(BackendStatus { pid, protocol, version })
+    }
+    .await;
 
-    // ── 1. Try bundled backend.exe (production build with PyInstaller) ──
-    let bundled_exe = resource_dir.join("backend").join("backend.exe");
-    if bundled_exe.exists() {
-        let mut cmd = Command::new(&bundled_exe);
-        return spawn_backend_process(&mut cmd, &app_handle, &mut backend);
+    if status.is_err() && started {
+        shutdown_managed_backend(state, SHUTDOWN_GRACE).ok();
     }
+    status
+}
 
-    // ── 2. Fall back to Python launcher (dev mode) ──────────────────────
-    //
-    //   resource_dir layout (dev):
-    //     .../pykaraoke-ng/src/runtimes/tauri/src-tauri/target/debug
-    //   Five levels up = project root: .../pykaraoke-ng/
-    //
-    //   current_dir layout (when `tauri dev` is run from src/runtimes/tauri):
-    //     .../pykaraoke-ng/src/runtimes/tauri
-    //   Three levels up = project root: .../pykaraoke-ng/
-    //
-    let candidates: Vec<PathBuf> = vec![
-        // Bundled resource — layered path
-        resource_dir.join("backend").join("pykaraoke").join("interfaces").join("backend_api.py"),
-        // Bundled resource — legacy path
-        resource_dir.join("backend").join("pykaraoke").join("core").join("backend.py"),
-        // Flat bundled resource
-        resource_dir.join("backend.py"),
-        // Development: resource_dir -> layered path (5 levels up)
-        {
-            let mut p = resource_dir.clone();
-            for _ in 0..5 { p.pop(); }
-            p.join("src").join("pykaraoke").join("interfaces").join("backend_api.py")
-        },
-        // Development: resource_dir -> legacy path (5 levels up)
-        {
-            let mut p = resource_dir.clone();
-            for _ in 0..5 { p.pop(); }
-            p.join("src").join("pykaraoke").join("core").join("backend.py")
-        },
-        // Development: CWD -> layered path (3 levels up)
-        {
-            let mut p = std::env::current_dir().unwrap_or_default();
-            for _ in 0..3 { p.pop(); }
-            p.join("src").join("pykaraoke").join("interfaces").join("backend_api.py")
-        },
-        // Development: CWD -> legacy path (3 levels up)
-        {
-            let mut p = std::env::current_dir().unwrap_or_default();
-            for _ in 0..3 { p.pop(); }
-            p.join("src").join("pykaraoke").join("core").join("backend.py")
-        },
-        // Development: CWD -> layered path (4 levels up, fallback)
-        {
-            let mut p = std::env::current_dir().unwrap_or_default();
-            for _ in 0..4 { p.pop(); }
-            p.join("src").join("pykaraoke").join("interfaces").join("backend_api.py")
-        },
-        // Development: CWD -> legacy path (4 levels up, fallback)
-        {
-            let mut p = std::env::current_dir().unwrap_or_default();
-            for _ in 0..4 { p.pop(); }
-            p.join("src").join("pykaraoke").join("core").join("backend.py")
-        },
-    ];
+/// `start_backend`'s answer: the human-readable message plus which script
+/// and interpreter the backend runs, for "wrong backend.py loaded" reports.
+#[derive(Debug, Clone, Serialize)]
+struct StartedBackend {
+    message: String,
+    script: Option<String>,
+    interpreter: Option<String>,
+}
 
-    let backend_script = candidates.iter()
-        .find(|p| p.exists())
-        .cloned()
-        .unwrap_or_else(|| candidates[0].clone());
+impl StartedBackend {
+    fn new(message: String, backend: &BackendState) -> Self {
+        StartedBackend {
+            message,
+            script: backend.script.as_ref().map(|p| p.display().to_string()),
+            interpreter: backend.interpreter.clone(),
+        }
+    }
+}
 
-    let python_path = backend_script
-        .parent()
-        .and_then(|p| p.parent())
-        .and_then(|p| p.parent())
-        .unwrap_or_else(|| std::path::Path::new("."))
-        .to_path_buf();
+async fn start_and_handshake(
+    state: &SafeBackendState,
+    app_handle: &tauri::AppHandle,
+    options: LaunchOptions,
+) -> Result<StartedBackend, BackendError> {
+    let outcome = launch_backend(state, app_handle, options)?;
+    if let LaunchOutcome::Started(_) = outcome {
+        handshake(state).await?;
+        prepare_started(state, app_handle).await?;
+    }
+    let backend = state.lock().unwrap();
+    Ok(StartedBackend::new(outcome.message(), &backend))
+}
 
-    let (python_exe, python_prefix_args) = resolve_python_launcher()?;
+/// Bring a freshly started backend in line with the app: take over
+/// auto-advance again if the app manages it, and restore the autosaved
+/// playlist (not for the mock, whose songs aren't real files).
+async fn prepare_started(state: &SafeBackendState, app_handle: &tauri::AppHandle) -> Result<(), BackendError> {
+    start_watchdog(state, app_handle);
+    // A fresh backend advances on its own again.
+    if state.lock().unwrap().auto_advance.host_managed() {
+        set_backend_advance(state, false).await?;
+    }
+    let repeat = state.lock().unwrap().auto_advance.repeat();
+    if repeat != RepeatMode::Off {
+        send_repeat_mode(state, repeat).await?;
+    }
+    let config = AppConfig::path(&app_handle.config()).map(|path| AppConfig::load(&path)).unwrap_or_default();
+    state.lock().unwrap().scan_excludes = config.scan_excludes;
+    if config.av_sync_offset_ms != 0 {
+        send_av_sync_offset(state, config.av_sync_offset_ms).await?;
+    }
+    if config.normalization.enabled {
+        send_normalization(state, config.normalization).await?;
+    }
+    if config.crossfade_ms != 0 {
+        send_crossfade(state, config.crossfade_ms).await?;
+    }
+    if let Some(lang) = config.lyrics_language {
+        // A language this backend doesn't know shouldn't stop it starting.
+        if let Err(e) = send_lyrics_language(state, &lang).await {
+            tracing::warn!("Not restoring lyrics language {lang}: {e}");
+        }
+    }
+    if let Some(name) = config.theme {
+        if let Err(e) = send_theme(state, &name).await {
+            tracing::warn!("Not restoring theme {name}: {e}");
+        }
+    }
+    let log_level = state.lock().unwrap().log_level;
+    if let Some(level) = log_level {
+        send_log_level(state, level).await?;
+    }
+    if let Some(roots) = &config.library_roots {
+        send_library_roots(state, roots).await?;
+    }
+    state.lock().unwrap().resource_limits = config.resource_limits;
+    start_resource_checks(state, app_handle);
+    if state.lock().unwrap().mock.is_some() {
+        return Ok(());
+    }
+    if let Some(path) = session::snapshot_path(&app_handle.config()) {
+        start_autosnapshot(state, path);
+    }
+    if let Some(path) = playlist::autosave_path(&app_handle.config()) {
+        queue_songs(state, &playlist::load_autosave(&path)).await?;
+    }
+    Ok(())
+}
 
-    let mut cmd = Command::new(&python_exe);
-    for arg in &python_prefix_args {
-        cmd.arg(arg);
+/// Send `add_to_playlist` for each of `songs`, skipping (and logging) any
+/// the backend rejects, e.g. files that have since been moved.  Returns
+/// how many were queued.
+async fn queue_songs(state: &SafeBackendState, songs: &[SongInfo]) -> Result<usize, BackendError> {
+    let mut queued = 0;
+    for song in songs {
+        let params = serde_json::json!({ "filepath": song.filepath, "singer": song.singer });
+        match dispatch_command(state, "add_to_playlist".to_string(), Some(params))
+            .await?
+            .into_data("add_to_playlist")
+        {
+            Ok(_) => queued += 1,
+            Err(e) => tracing::warn!("Skipping {}: {e}", song.filepath),
+        }
     }
-    cmd.arg(&backend_script)
-        .env("PYTHONPATH", &python_path);
+    Ok(queued)
+}
 
-    spawn_backend_process(&mut cmd, &app_handle, &mut backend)
+/// The songs queued in the backend, from `get_state`.
+async fn fetch_playlist(state: &SafeBackendState) -> Result<Vec<SongInfo>, BackendError> {
+    let data = dispatch_command(state, "get_state".to_string(), None)
+        .await?
+        .into_data("get_state")?;
+    playlist::songs_in(&data).map_err(|e| BackendError::InvalidResponse(e.to_string()))
 }
 
-/// Send a command to the Python backend
+/// Queue the song at `path`, optionally for `singer`, and return the
+/// playlist with each entry's singer.  The singer is stored on the
+/// backend's entry, so it follows the song through reorders and removals.
 #[tauri::command]
-async fn send_command(
+async fn add_to_playlist(
     state: State<'_, SafeBackendState>,
-    action: String,
-    params: Option<serde_json::Value>,
-) -> Result<CommandResponse, String> {
-    let mut backend = state.lock().unwrap();
-    
-    if backend.stdin.is_none() {
-        return Err("Backend not running".to_string());
+    path: PathBuf,
+    singer: Option<String>,
+) -> Result<Vec<SongInfo>, BackendError> {
+    add_to_playlist_for(&state, &path, singer, None).await
+}
+
+/// Queue the song at `path`, optionally for `singer`, right after the
+/// current song rather than at the end (at the top when nothing is
+/// playing), and return the playlist.
+#[tauri::command]
+async fn play_next(
+    state: State<'_, SafeBackendState>,
+    path: PathBuf,
+    singer: Option<String>,
+) -> Result<Vec<SongInfo>, BackendError> {
+    let cached = state.lock().unwrap().playback.lock().unwrap().clone();
+    let playback = match cached {
+        Some(playback) => playback,
+        None => fetch_playback_state(&state).await?,
+    };
+    add_to_playlist_for(&state, &path, singer, Some(next_slot(&playback)?)).await
+}
+
+/// Queue the song at `path` at playlist position `index`, or at the end
+/// without one.
+async fn add_to_playlist_for(
+    state: &SafeBackendState,
+    path: &Path,
+    singer: Option<String>,
+    index: Option<usize>,
+) -> Result<Vec<SongInfo>, BackendError> {
+    ingest::ensure_readable_file(path).map_err(|reason| BackendError::FileNotFound {
+        path: path.display().to_string(),
+        reason,
+    })?;
+    let singer = singer.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let mut params = serde_json::json!({ "filepath": path.display().to_string(), "singer": singer });
+    let action = match index {
+        Some(index) => {
+            params["index"] = serde_json::json!(index);
+            "insert_in_playlist"
+        }
+        None => "add_to_playlist",
+    };
+    dispatch_command(state, action.to_string(), Some(params))
+        .await?
+        .into_data(action)?;
+    fetch_playlist(state).await
+}
+
+/// Where `play_next` queues a song: just after the current one, or at the
+/// top with none.
+fn next_slot(playback: &PlaybackState) -> Result<usize, BackendError> {
+    let index = playback.playlist_index.map_or(0, |current| current + 1);
+    if index > playback.playlist_len {
+        return Err(BackendError::InvalidParams {
+            action: "play_next".to_string(),
+            field: "index".to_string(),
+            reason: format!("{index} is past the end of the playlist ({} songs)", playback.playlist_len),
+        });
     }
-    
-    // Check if the child process is still alive.  If it has exited
-    // (crashed, killed, etc.) clean up the stale state immediately
-    // so the frontend can restart it.
-    if let Some(ref mut child) = backend.process {
-        match child.try_wait() {
-            Ok(Some(_status)) => {
-                backend.stdin = None;
-                backend.process = None;
-                backend.response_rx = None;
-                return Err("Backend process has exited".to_string());
-            }
-            Ok(None) => {} // still running
-            Err(_e) => {
-                backend.stdin = None;
-                backend.process = None;
-                backend.response_rx = None;
-                return Err("Backend process check failed".to_string());
+    Ok(index)
+}
+
+/// The queued songs, each annotated with the singer it was added for
+/// (`singer` is left out for entries added without one).
+#[tauri::command]
+async fn get_playlist_with_singers(state: State<'_, SafeBackendState>) -> Result<Vec<SongInfo>, BackendError> {
+    fetch_playlist(&state).await
+}
+
+/// The next `count` songs after the current one (at most 50), for a
+/// "coming up" banner.  Empty at the end of the playlist.
+#[tauri::command]
+async fn get_upcoming(state: State<'_, SafeBackendState>, count: usize) -> Result<Vec<SongInfo>, BackendError> {
+    let data = dispatch_command(&state, "get_state".to_string(), None)
+        .await?
+        .into_data("get_state")?;
+    playlist::upcoming(&data, count).map_err(|e| BackendError::InvalidResponse(e.to_string()))
+}
+
+/// Write the current playlist to `path` as JSON; returns how many songs
+/// were saved.
+#[tauri::command]
+async fn save_playlist(state: State<'_, SafeBackendState>, path: PathBuf) -> Result<usize, BackendError> {
+    let songs = fetch_playlist(&state).await?;
+    playlist::save(&path, &songs)?;
+    Ok(songs.len())
+}
+
+/// Write the current playlist to `path` as an extended M3U file for other
+/// players.  Songs without a file path are left out and listed in
+/// `warnings`.
+#[tauri::command]
+async fn export_playlist_m3u(state: State<'_, SafeBackendState>, path: PathBuf) -> Result<M3uExport, BackendError> {
+    let songs = fetch_playlist(&state).await?;
+    let (text, export) = m3u::render(&songs);
+    std::fs::write(&path, text).map_err(|e| BackendError::PlaylistFile {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })?;
+    Ok(export)
+}
+
+/// Queue the tracks of the M3U/M3U8 playlist at `path`.  Relative entries
+/// are resolved against the playlist's directory; entries whose file
+/// doesn't exist, or that the backend rejects, are skipped and counted.
+#[tauri::command]
+async fn import_playlist_m3u(state: State<'_, SafeBackendState>, path: PathBuf) -> Result<M3uImport, BackendError> {
+    let bytes = std::fs::read(&path).map_err(|e| BackendError::PlaylistFile {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut import = M3uImport::default();
+    for entry in m3u::parse(&bytes) {
+        let Some(track) = m3u::resolve(&entry, dir).filter(|track| track.is_file()) else {
+            import.skip(&entry);
+            continue;
+        };
+        let params = serde_json::json!({ "filepath": track.display().to_string() });
+        match dispatch_command(&state, "add_to_playlist".to_string(), Some(params))
+            .await?
+            .into_data("add_to_playlist")
+        {
+            Ok(_) => import.imported += 1,
+            Err(_) => import.skip(&entry),
+        }
+    }
+    Ok(import)
+}
+
+/// Append the songs saved at `path` to the playlist; returns how many the
+/// backend accepted.
+#[tauri::command]
+async fn load_playlist(state: State<'_, SafeBackendState>, path: PathBuf) -> Result<usize, BackendError> {
+    let songs = playlist::load(&path)?;
+    queue_songs(&state, &songs).await
+}
+
+/// Write the session (the playlist, the current song and how far into it,
+/// the volume, shuffle and repeat) to `path`, or to the automatic snapshot
+/// file when no path is given, and return what was written.
+#[tauri::command]
+async fn snapshot_state(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    path: Option<PathBuf>,
+) -> Result<SessionSnapshot, BackendError> {
+    let path = session_path(&app_handle, path)?;
+    let snapshot = take_snapshot(&state).await?;
+    session::save(&path, &snapshot)?;
+    Ok(snapshot)
+}
+
+/// Replace the playlist with the session saved at `path` (the automatic
+/// snapshot when no path is given) and pick up where it left off: the
+/// current song is loaded at its position, and keeps playing if it was.
+/// Songs whose files have moved, or that the backend rejects, are
+/// skipped and listed in `skipped`.
+#[tauri::command]
+async fn restore_state(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    path: Option<PathBuf>,
+) -> Result<RestoreReport, BackendError> {
+    let snapshot = session::load(&session_path(&app_handle, path)?)?;
+    restore_session(&state, &snapshot).await
+}
+
+fn session_path(app_handle: &tauri::AppHandle, path: Option<PathBuf>) -> Result<PathBuf, BackendError> {
+    path.or_else(|| session::snapshot_path(&app_handle.config()))
+        .ok_or_else(|| BackendError::PlaylistFile {
+            path: session::SNAPSHOT_FILE.to_string(),
+            reason: "No app data directory".to_string(),
+        })
+}
+
+async fn take_snapshot(state: &SafeBackendState) -> Result<SessionSnapshot, BackendError> {
+    let data = dispatch_command(state, "get_state".to_string(), None)
+        .await?
+        .into_data("get_state")?;
+    let invalid = |e: serde_json::Error| BackendError::InvalidResponse(e.to_string());
+    let songs = playlist::songs_in(&data).map_err(invalid)?;
+    let playback = PlaybackState::from_backend(&data).map_err(invalid)?;
+    let backend = state.lock().unwrap();
+    let mut snapshot = SessionSnapshot::new(songs, &playback, backend.shuffle, backend.auto_advance.repeat());
+    // Muted is a moment's choice; the session keeps the level underneath.
+    if let Some(volume) = backend.mute.saved() {
+        snapshot.volume = volume;
+    }
+    Ok(snapshot)
+}
+
+async fn restore_session(state: &SafeBackendState, snapshot: &SessionSnapshot) -> Result<RestoreReport, BackendError> {
+    let send = |action: &'static str, params: Option<serde_json::Value>| async move {
+        dispatch_command(state, action.to_string(), params).await?.into_data(action).map(drop)
+    };
+    send("clear_playlist", None).await?;
+    // Shuffling the empty playlist leaves the songs added after it in the
+    // order they were saved in.
+    send("set_shuffle", Some(serde_json::json!({ "enabled": snapshot.shuffle }))).await?;
+    state.lock().unwrap().shuffle = snapshot.shuffle;
+    send_repeat_mode(state, snapshot.repeat).await?;
+    state.lock().unwrap().auto_advance.set_repeat(snapshot.repeat);
+
+    let mut report = RestoreReport::default();
+    for (index, song) in snapshot.songs.iter().enumerate() {
+        if !Path::new(&song.filepath).is_file() {
+            report.skip(song);
+            continue;
+        }
+        let params = serde_json::json!({ "filepath": song.filepath, "singer": song.singer });
+        match send("add_to_playlist", Some(params)).await {
+            Ok(()) => report.restore(snapshot, index),
+            Err(BackendError::Rejected { message, .. }) => {
+                tracing::warn!("Skipping {}: {message}", song.filepath);
+                report.skip(song);
             }
+            Err(e) => return Err(e),
         }
     }
-    
-    let command = CommandRequest { action, params };
-    let command_json = serde_json::to_string(&command)
-        .map_err(|e| format!("Failed to serialize command: {}", e))?;
-    
-    // Send command to backend.  If the write fails the Python process has
-    // most likely exited; tear down the backend state immediately so that
-    // every subsequent call returns "Backend not running" rather than
-    // retrying a dead pipe.
-    if let Some(ref mut stdin) = backend.stdin {
-        if let Err(e) = writeln!(stdin, "{}", command_json) {
-            backend.stdin = None;
-            backend.process = None;
-            backend.response_rx = None;
-            return Err(format!("Backend process died (send): {}", e));
+
+    let volume = snapshot.volume.clamp(0.0, 1.0);
+    send("set_volume", Some(serde_json::json!({ "volume": volume }))).await?;
+    {
+        let mut backend = state.lock().unwrap();
+        backend.volume.mark_sent(volume);
+        backend.mute.clear();
+    }
+    if let Some(index) = report.resumed_at {
+        send("play", Some(serde_json::json!({ "playlist_index": index }))).await?;
+        if snapshot.position_ms > 0 {
+            send("seek", Some(serde_json::json!({ "position_ms": snapshot.position_ms }))).await?;
         }
-        if let Err(e) = stdin.flush() {
-            backend.stdin = None;
-            backend.process = None;
-            backend.response_rx = None;
-            return Err(format!("Backend process died (flush): {}", e));
+        if !snapshot.playing {
+            send("pause", None).await?;
         }
     }
-    
-    // Read the actual response from the Python backend via the channel.
-    if let Some(ref rx) = backend.response_rx {
-        match rx.recv_timeout(Duration::from_secs(5)) {
-            Ok(value) => {
-                serde_json::from_value::<CommandResponse>(value.clone()).map_err(|_| {
-                    format!("Failed to parse backend response: {}", value)
-                })
+    Ok(report)
+}
+
+/// Snapshot the session to `path` every [`session::AUTOSNAPSHOT_INTERVAL`]
+/// until the backend stops.
+fn start_autosnapshot(state: &SafeBackendState, path: PathBuf) {
+    let mut stop = {
+        let (tx, rx) = oneshot::channel::<()>();
+        state.lock().unwrap().snapshot_stop = Some(tx);
+        rx
+    };
+    let state = state.clone();
+    tauri::async_runtime::spawn(async move {
+        let interval = session::AUTOSNAPSHOT_INTERVAL;
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        loop {
+            tokio::select! {
+                _ = &mut stop => return,
+                _ = ticks.tick() => {}
             }
-            Err(mpsc::RecvTimeoutError::Timeout) => {
-                // Kill the unresponsive process so the frontend's retry
-                // loop can start fresh instead of hanging forever.
-                if let Some(mut child) = backend.process.take() {
-                    child.kill().ok();
-                }
-                backend.process = None;
-                backend.stdin = None;
-                backend.response_rx = None;
-                Err("Backend did not respond within 5 seconds".to_string())
+            autosnapshot(&state, &path).await;
+        }
+    });
+}
+
+/// Check the backend against its resource limits every
+/// `resources::CHECK_INTERVAL` while any are set, replacing checks already
+/// running.
+fn start_resource_checks(state: &SafeBackendState, app_handle: &tauri::AppHandle) {
+    let mut stop = {
+        let mut backend = state.lock().unwrap();
+        if !backend.resource_limits.any() {
+            backend.resource_check_stop = None;
+            return;
+        }
+        let (tx, rx) = oneshot::channel::<()>();
+        backend.resource_check_stop = Some(tx);
+        rx
+    };
+    let state = state.clone();
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let interval = resources::CHECK_INTERVAL;
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        loop {
+            tokio::select! {
+                _ = &mut stop => return,
+                _ = ticks.tick() => {}
             }
-            Err(mpsc::RecvTimeoutError::Disconnected) => {
-                backend.stdin = None;
-                backend.process = None;
-                backend.response_rx = None;
-                Err("Backend process disconnected".to_string())
+            match check_resources(&state, &app_handle) {
+                Ok(_) | Err(BackendError::NotRunning) => {}
+                Err(e) => {
+                    tracing::warn!("Stopping resource checks: {e}");
+                    return;
+                }
             }
         }
-    } else {
-        Err("No response channel available".to_string())
+    });
+}
+
+/// Read the backend's memory and CPU use, emitting `resource-warning` for
+/// each limit it has newly gone over.
+fn check_resources(state: &SafeBackendState, app_handle: &tauri::AppHandle) -> Result<ProcessResources, BackendError> {
+    let (pid, limits) = {
+        let backend = state.lock().unwrap();
+        if !backend.is_running() {
+            return Err(BackendError::NotRunning);
+        }
+        (backend.process.as_ref().map(Child::id), backend.resource_limits)
+    };
+    let Some(pid) = pid else {
+        let reason = "the mock backend has no process".to_string();
+        return Err(BackendError::ResourcesUnavailable { pid: None, reason });
+    };
+    let sample =
+        resources::sample(pid).map_err(|reason| BackendError::ResourcesUnavailable { pid: Some(pid), reason })?;
+    let (usage, warnings) = state.lock().unwrap().resources.record(pid, sample, Instant::now(), &limits);
+    for warning in warnings {
+        app_handle.emit_all("resource-warning", &warning).ok();
     }
+    Ok(usage)
 }
 
-/// Stop the Python backend process
-#[tauri::command]
-fn stop_backend(state: State<SafeBackendState>) -> Result<String, String> {
+/// Write the automatic snapshot, unless no playlist song is loaded or a
+/// preview is playing in place of the session's song.
+async fn autosnapshot(state: &SafeBackendState, path: &Path) {
+    if state.lock().unwrap().previewing {
+        return;
+    }
+    let saved = match take_snapshot(state).await {
+        Ok(snapshot) if snapshot.playlist_index.is_some() => session::save(path, &snapshot),
+        Ok(_) | Err(BackendError::NotRunning | BackendError::ProcessExited) => Ok(()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        tracing::warn!("Failed to snapshot the session: {e}");
+    }
+}
+
+/// Take the automatic snapshot one last time before the app exits.
+fn snapshot_on_exit(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<SafeBackendState>().inner().clone();
+    let running = state.lock().is_ok_and(|backend| backend.is_running());
+    let Some(path) = session::snapshot_path(&app_handle.config()).filter(|_| running) else {
+        return;
+    };
+    tauri::async_runtime::block_on(async {
+        tokio::time::timeout(SHUTDOWN_GRACE, autosnapshot(&state, &path)).await.ok();
+    });
+}
+
+/// Check that the backend speaks our protocol version, stopping it if not.
+///
+/// Backends that predate the handshake answer "Unknown action" and are
+/// reported as protocol 0.
+async fn handshake(state: &SafeBackendState) -> Result<u64, BackendError> {
+    let response = dispatch_command(
+        state,
+        "handshake".to_string(),
+        Some(serde_json::json!({ "protocol": PROTOCOL_VERSION })),
+    )
+    .await?;
+    let backend = backend_protocol(response);
+    if backend == PROTOCOL_VERSION {
+        state.lock().unwrap().reconnect.reset();
+        return Ok(backend);
+    }
+
+    shutdown_managed_backend(state, SHUTDOWN_GRACE).ok();
+    Err(BackendError::ProtocolMismatch {
+        frontend: PROTOCOL_VERSION,
+        backend,
+    })
+}
+
+/// The protocol version in a handshake response; 0 if the backend didn't
+/// understand the handshake.
+fn backend_protocol(response: CommandResponse) -> u64 {
+    response
+        .into_data("handshake")
+        .ok()
+        .and_then(|data| data["protocol"].as_u64())
+        .unwrap_or(0)
+}
+
+/// Result of `launch_backend`.
+enum LaunchOutcome {
+    Started(String),
+    AlreadyRunning,
+    Starting,
+}
+
+impl LaunchOutcome {
+    fn message(self) -> String {
+        match self {
+            LaunchOutcome::Started(message) => message,
+            LaunchOutcome::AlreadyRunning => "Backend already running".to_string(),
+            LaunchOutcome::Starting => "Backend starting".to_string(),
+        }
+    }
+}
+
+/// Outcome of trying to claim the right to start a backend.
+enum StartAttempt {
+    Proceed(StartGuard),
+    AlreadyRunning,
+    Starting,
+}
+
+/// Holds `BackendState::starting` while a start is in progress and clears
+/// it when dropped, including on error paths.
+struct StartGuard {
+    state: SafeBackendState,
+}
+
+impl Drop for StartGuard {
+    fn drop(&mut self) {
+        let mut backend = match self.state.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        backend.starting = false;
+    }
+}
+
+fn begin_start(state: &SafeBackendState) -> StartAttempt {
     let mut backend = state.lock().unwrap();
-    
-    if let Some(mut child) = backend.process.take() {
-        child.kill().map_err(|e| format!("Failed to kill backend: {}", e))?;
-        backend.stdin = None;
-        backend.response_rx = None;
-        Ok("Backend stopped".to_string())
-    } else {
-        Err("Backend not running".to_string())
+    if backend.starting {
+        return StartAttempt::Starting;
     }
+    if backend.process.is_some() || backend.mock.is_some() {
+        return StartAttempt::AlreadyRunning;
+    }
+    backend.starting = true;
+    StartAttempt::Proceed(StartGuard { state: state.clone() })
 }
 
-fn main() {
-    // Work around blank/empty WebKitGTK windows on Linux systems where
-    // GPU buffer allocation (GBM/DRM) is denied.  This tells WebKit to
-    // fall back to a shared-memory renderer instead of DMA-BUF, which
-    // avoids the "DRM_IOCTL_MODE_CREATE_DUMB failed: Permission denied"
-    // and "Failed to create GBM buffer" errors that cause a blank window.
-    #[cfg(target_os = "linux")]
-    {
-        if std::env::var("WEBKIT_DISABLE_DMABUF_RENDERER").is_err() {
-            std::env::set_var("WEBKIT_DISABLE_DMABUF_RENDERER", "1");
+/// Start a backend unless one is running or already being started.
+///
+/// Locating the script and probing interpreters runs several subprocesses,
+/// so it happens without the state lock held; the `starting` flag keeps a
+/// concurrent call (a double-click, or a restart racing a start) from
+/// spawning a duplicate.
+fn launch_backend(
+    state: &SafeBackendState,
+    app_handle: &tauri::AppHandle,
+    options: LaunchOptions,
+) -> Result<LaunchOutcome, BackendError> {
+    let guard = match begin_start(state) {
+        StartAttempt::Proceed(guard) => guard,
+        StartAttempt::AlreadyRunning => return Ok(LaunchOutcome::AlreadyRunning),
+        StartAttempt::Starting => return Ok(LaunchOutcome::Starting),
+    };
+
+    let result = prepare_launch(app_handle, &options).and_then(|launch| {
+        let mut backend = state.lock().unwrap();
+        finish_launch(&mut backend, app_handle, options, launch)
+    });
+    drop(guard);
+    result.map(LaunchOutcome::Started)
+}
+
+/// What `prepare_launch` decided to run.
+enum Launch {
+    Mock,
+    Process {
+        cmd: Box<Command>,
+        script: Option<PathBuf>,
+        /// The executable reported as the backend's interpreter.
+        interpreter: String,
+    },
+}
+
+/// Work out how to launch the backend.  Doesn't touch `BackendState`.
+fn prepare_launch(
+    app_handle: &tauri::AppHandle,
+    options: &LaunchOptions,
+) -> Result<Launch, BackendError> {
+    if options.mock {
+        return Ok(Launch::Mock);
+    }
+
+    if let Some(script) = &options.backend_script {
+        if !script.exists() {
+            return Err(BackendError::ScriptOverrideMissing(script.display().to_string()));
         }
-        // Suppress "Couldn't connect to accessibility bus" warnings from
-        // WebKitGTK / at-spi2 when the D-Bus a11y socket is unavailable.
-        if std::env::var("NO_AT_BRIDGE").is_err() {
-            std::env::set_var("NO_AT_BRIDGE", "1");
+        return python_launch(script);
+    }
+
+    let resolved_resource_dir = app_handle.path_resolver().resource_dir();
+    let resource_dir_resolved = resolved_resource_dir.is_some();
+    let resource_dir = resolve_resource_dir(
+        resolved_resource_dir,
+        std::env::current_dir().ok(),
+        std::env::current_exe().ok(),
+    )?;
+
+    // A damaged install fails here rather than deep inside Python.
+    let bundle = resource_dir.join("backend");
+    let verify = AppConfig::path(&app_handle.config())
+        .is_some_and(|path| AppConfig::load(&path).verify_backend_integrity);
+    let check_bundle = |launched: &Path| {
+        if verify && launched.starts_with(&bundle) {
+            integrity::verify(&bundle, &integrity::bundled()?)?;
         }
+        Ok::<_, BackendError>(())
+    };
+
+    // ── 1. Try bundled backend.exe (production build with PyInstaller) ──
+    let bundled_exe = bundle.join("backend.exe");
+    if bundled_exe.exists() {
+        check_bundle(&bundled_exe)?;
+        return Ok(Launch::Process {
+            cmd: Box::new(Command::new(&bundled_exe)),
+            script: None,
+            interpreter: bundled_exe.display().to_string(),
+        });
     }
 
-    tauri::Builder::default()
-        .manage(Arc::new(Mutex::new(BackendState {
-            process: None,
-            stdin: None,
-            response_rx: None,
-        })))
-        .invoke_handler(tauri::generate_handler![
-            start_backend,
-            send_command,
-            stop_backend
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+    // ── 2. Fall back to Python launcher (dev mode) ──────────────────────
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let candidates = backend_script_candidates(&resource_dir, &cwd);
+
+    // Fail with the full list of checked paths rather than letting the
+    // spawn produce a bare "file not found".
+    let backend_script = find_backend_script(&candidates, resource_dir_resolved)?;
+    check_bundle(&backend_script)?;
+    python_launch(&backend_script)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
+/// Check the bundled backend against the hashes taken when this app was
+/// built, whether or not `verify_backend_integrity` is on.  Returns how
+/// many files were checked; fails with `integrity_check_failed` naming the
+/// first file missing or changed.
+#[tauri::command]
+fn check_backend_integrity(app_handle: tauri::AppHandle) -> Result<usize, BackendError> {
+    let resource_dir = resolve_resource_dir(
+        app_handle.path_resolver().resource_dir(),
+        std::env::current_dir().ok(),
+        std::env::current_exe().ok(),
+    )?;
+    integrity::verify(&resource_dir.join("backend"), &integrity::bundled()?)
+}
 
-    // ── CommandRequest serialization ──────────────────────────────
+/// The manifest `build.rs` recorded for the backend bundled with this
+/// app: its version, whether it is only the placeholder, and every file's
+/// hash and size.
+#[tauri::command]
+fn get_backend_manifest() -> Result<BackendManifest, BackendError> {
+    integrity::bundled()
+}
 
-    #[test]
-    fn command_request_serializes_with_action_only() {
-        let req = CommandRequest {
-            action: "play".to_string(),
-            params: None,
-        };
-        let j = serde_json::to_value(&req).unwrap();
-        assert_eq!(j["action"], "play");
-        assert!(j["params"].is_null());
+/// The directory searched for the bundled backend: Tauri's resource
+/// directory, else the working directory, else the executable's directory.
+/// The working directory may be unavailable (deleted, or denied by a
+/// sandbox), which must not crash the app.
+fn resolve_resource_dir(
+    resource_dir: Option<PathBuf>,
+    cwd: Option<PathBuf>,
+    current_exe: Option<PathBuf>,
+) -> Result<PathBuf, BackendError> {
+    resource_dir
+        .or(cwd)
+        .or_else(|| current_exe.and_then(|exe| exe.parent().map(PathBuf::from)))
+        .ok_or_else(|| {
+            BackendError::LaunchFailed(
+                "Cannot locate the backend: no resource directory, working directory or executable path"
+                    .to_string(),
+            )
+        })
+}
+
+/// Install the chosen backend into `backend`.
+fn finish_launch(
+    backend: &mut BackendState,
+    app_handle: &tauri::AppHandle,
+    options: LaunchOptions,
+    launch: Launch,
+) -> Result<String, BackendError> {
+    backend.framing = options.framing;
+    backend.last_launch = options;
+    backend.metrics.reset();
+
+    match launch {
+        Launch::Mock => {
+            let emit_handle = app_handle.clone();
+            let subscriptions = backend.subscriptions.clone();
+            let history = backend.history.clone();
+            let playback = backend.playback.clone();
+            let metrics = backend.metrics.clone();
+            let capture = backend.capture.clone();
+            let audio_ready = backend.audio_ready.clone();
+            backend.pending = Arc::new(Mutex::new(PendingRequests::default()));
+            backend.mock = Some(mock::MockBackend::new(move |event| {
+                capture.lock().unwrap().record("stdout", &serde_json::json!({ "type": "event", "event": event }));
+                history.lock().unwrap().record(event.clone(), event.to_string());
+                cache_playback(&playback, &event);
+                #[cfg(feature = "system-tray")]
+                if event["type"] == events::STATE_CHANGED {
+                    update_tray_tooltip(&emit_handle, &playback);
+                }
+                if let Some(name) = event["type"].as_str().and_then(scan_summary_event) {
+                    emit_handle.emit_all(name, &event["data"]).ok();
+                }
+                if let Some(failed) = LoadFailedEvent::from_event(&event) {
+                    emit_handle.emit_all("song-load-failed", &failed).ok();
+                }
+                confirm_audio_ready(&audio_ready, &event);
+                emit_backend_event(&emit_handle, &subscriptions, &metrics, event);
+            }));
+            backend.script = None;
+            backend.interpreter = Some("mock".to_string());
+            backend.version = None;
+            backend.supported_formats = None;
+            backend.capabilities = None;
+            backend.supported_languages = None;
+            backend.available_themes = None;
+            Ok("Mock backend started".to_string())
+        }
+        Launch::Process { mut cmd, script, interpreter } => {
+            backend.script = script;
+            let started = spawn_backend_process(&mut cmd, app_handle, backend)?;
+            backend.interpreter = Some(interpreter);
+            Ok(started)
+        }
+    }
+}
+
+fn python_launch(backend_script: &std::path::Path) -> Result<Launch, BackendError> {
+    let (cmd, interpreter) = python_command(backend_script)?;
+    Ok(Launch::Process {
+        cmd: Box::new(cmd),
+        script: Some(backend_script.to_path_buf()),
+        interpreter,
+    })
+}
+
+/// Build the command running `backend_script` under the resolved Python
+/// interpreter, and name the interpreter.
+fn python_command(backend_script: &std::path::Path) -> Result<(Command, String), BackendError> {
+    let python_path = backend_script
+        .parent()
+        .and_then(|p| p.parent())
+        .and_then(|p| p.parent())
+        .unwrap_or_else(|| std::path::Path::new("."))
+        .to_path_buf();
+
+    let launcher = resolve_python_launcher().map_err(BackendError::LaunchFailed)?;
+
+    let mut cmd = Command::new(&launcher.program);
+    for arg in &launcher.prefix_args {
+        cmd.arg(arg);
+    }
+    cmd.arg(backend_script)
+        .env("PYTHONPATH", &python_path);
+    Ok((cmd, launcher.executable))
+}
+
+/// Send a command to the Python backend
+///
+/// A `backend-request` event carrying the `request_id` is emitted once the
+/// command is written, so the UI can offer `cancel_command` for it.
+#[tauri::command]
+async fn send_command(
+    state: State<'_, SafeBackendState>,
+    app_handle: tauri::AppHandle,
+    action: String,
+    params: Option<serde_json::Value>,
+) -> Result<CommandResponse, BackendError> {
+    take_over_from_preview(&state, &action);
+    let volume = params.as_ref().and_then(|params| params["volume"].as_f64()).filter(|_| action == "set_volume");
+    let pending = submit_command(&state, action, params)?;
+    app_handle
+        .emit_all(
+            "backend-request",
+            serde_json::json!({ "request_id": pending.request_id, "action": pending.action }),
+        )
+        .ok();
+    let response = pending.wait(&state).await?;
+    if let Some(volume) = volume.filter(|_| response.status == "ok") {
+        // The same as a `set_volume` call, so muting stays in step.
+        let mut backend = state.lock().unwrap();
+        backend.volume.mark_sent(volume);
+        backend.mute.clear();
+    }
+    Ok(response)
+}
+
+/// Fetch the backend's state as a typed `PlaybackState`, giving the
+/// frontend a stable contract instead of the raw `get_state` blob.
+#[tauri::command]
+async fn get_playback_state(
+    state: State<'_, SafeBackendState>,
+) -> Result<PlaybackState, BackendError> {
+    fetch_playback_state(&state).await
+}
+
+async fn fetch_playback_state(state: &SafeBackendState) -> Result<PlaybackState, BackendError> {
+    let data = dispatch_command(state, "get_state".to_string(), None)
+        .await?
+        .into_data("get_state")?;
+    let snapshot = PlaybackState::from_backend(&data)
+        .map_err(|e| BackendError::InvalidResponse(e.to_string()))?;
+    let playback = state.lock().unwrap().playback.clone();
+    let mut cached = playback.lock().unwrap();
+    let snapshot = snapshot.carry_adjustments(cached.as_ref());
+    *cached = Some(snapshot.clone());
+    Ok(snapshot)
+}
+
+/// Load the song at `path` without playing it and return the playback
+/// state once the backend confirms.  The file must exist and be readable,
+/// else `BackendError::FileNotFound`.  Transpose and tempo start over for
+/// the loaded song.
+#[tauri::command]
+async fn load_song(state: State<'_, SafeBackendState>, path: PathBuf) -> Result<PlaybackState, BackendError> {
+    take_over_from_preview(&state, "load_song");
+    load_song_at(&state, &path).await
+}
+
+async fn load_song_at(state: &SafeBackendState, path: &Path) -> Result<PlaybackState, BackendError> {
+    ingest::ensure_readable_file(path).map_err(|reason| BackendError::FileNotFound {
+        path: path.display().to_string(),
+        reason,
+    })?;
+    let params = serde_json::json!({ "filepath": path.display().to_string() });
+    let response = dispatch_command(state, "load_song".to_string(), Some(params)).await?;
+    if let Some(failure) = response.load_failure(path) {
+        return Err(failure);
+    }
+    response.into_data("load_song")?;
+    // Drop the cached snapshot so reloading the same song doesn't carry
+    // its old adjustments over.
+    let playback = state.lock().unwrap().playback.clone();
+    *playback.lock().unwrap() = None;
+    fetch_playback_state(state).await
+}
+
+/// Title, artist, length and format of the song at `path`, without
+/// loading it.  Results are cached by path and modification time, so
+/// re-probing a folder only asks the backend about files that changed.
+#[tauri::command]
+async fn probe_song(state: State<'_, SafeBackendState>, path: PathBuf) -> Result<ProbedSong, BackendError> {
+    probe_song_at(&state, &path).await
+}
+
+async fn probe_song_at(state: &SafeBackendState, path: &Path) -> Result<ProbedSong, BackendError> {
+    let not_found = |reason: String| BackendError::FileNotFound { path: path.display().to_string(), reason };
+    ingest::ensure_readable_file(path).map_err(not_found)?;
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| not_found(e.to_string()))?;
+    if let Some(song) = state.lock().unwrap().probes.get(path, modified) {
+        return Ok(song);
+    }
+    let params = serde_json::json!({ "filepath": path.display().to_string() });
+    let data = dispatch_command(state, "probe".to_string(), Some(params))
+        .await?
+        .into_data("probe")?;
+    let song: ProbedSong = serde_json::from_value(data).map_err(|e| BackendError::InvalidResponse(e.to_string()))?;
+    state.lock().unwrap().probes.insert(path.to_path_buf(), modified, song.clone());
+    Ok(song)
+}
+
+/// Peak levels (0..1) of the song at `path` split into `buckets` (1 to
+/// 4096) slices, for drawing a waveform under the seek bar.  When the
+/// backend can't draw one (a MIDI song has no audio, say) the peaks are
+/// empty and `note` says why.  Results are cached until the file changes.
+#[tauri::command]
+async fn get_waveform(
+    state: State<'_, SafeBackendState>,
+    path: PathBuf,
+    buckets: usize,
+) -> Result<Waveform, BackendError> {
+    waveform_at(&state, &path, buckets).await
+}
+
+async fn waveform_at(state: &SafeBackendState, path: &Path, buckets: usize) -> Result<Waveform, BackendError> {
+    let not_found = |reason: String| BackendError::FileNotFound { path: path.display().to_string(), reason };
+    ingest::ensure_readable_file(path).map_err(not_found)?;
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| not_found(e.to_string()))?;
+    let cached = state.lock().unwrap().waveforms.get(path, modified);
+    if let Some((_, waveform)) = cached.filter(|(drawn_at, _)| *drawn_at == buckets) {
+        return Ok(waveform);
+    }
+    let params = serde_json::json!({ "filepath": path.display().to_string(), "buckets": buckets });
+    let data = match dispatch_command(state, "waveform".to_string(), Some(params)).await?.into_data("waveform") {
+        Ok(data) => data,
+        Err(BackendError::Rejected { message, .. }) => return Ok(Waveform::unavailable(&message)),
+        Err(e) => return Err(e),
+    };
+    let waveform = Waveform::from_backend(&data).map_err(BackendError::InvalidResponse)?;
+    state.lock().unwrap().waveforms.insert(path.to_path_buf(), modified, (buckets, waveform.clone()));
+    Ok(waveform)
+}
+
+/// Entry counts and sizes of the `probe` and `waveform` caches (kept in
+/// memory, so their sizes are estimates) and of the `disk` cache, the app
+/// cache directory.
+#[tauri::command]
+fn get_cache_stats(app_handle: tauri::AppHandle, state: State<SafeBackendState>) -> Vec<CacheStats> {
+    let cache_dir = tauri::api::path::app_cache_dir(&app_handle.config());
+    CacheKind::ALL.into_iter().map(|kind| cache_stats(&state, kind, cache_dir.as_deref())).collect()
+}
+
+/// Empty the cache of `kind` (`probe`, `waveform` or `disk`) and return
+/// what it held.
+#[tauri::command]
+fn clear_cache(
+    app_handle: tauri::AppHandle,
+    state: State<SafeBackendState>,
+    kind: String,
+) -> Result<CacheStats, BackendError> {
+    let kind = CacheKind::parse(&kind).map_err(|reason| BackendError::InvalidParams {
+        action: "clear_cache".to_string(),
+        field: "kind".to_string(),
+        reason,
+    })?;
+    let cache_dir = tauri::api::path::app_cache_dir(&app_handle.config());
+    empty_cache(&state, kind, cache_dir.as_deref())
+}
+
+fn cache_stats(state: &SafeBackendState, kind: CacheKind, cache_dir: Option<&Path>) -> CacheStats {
+    let backend = state.lock().unwrap();
+    let path_bytes = |path: &Path| path.as_os_str().len();
+    let sizes: Vec<usize> = match kind {
+        CacheKind::Probe => backend
+            .probes
+            .entries()
+            .map(|(path, song)| path_bytes(path) + song.title.len() + song.artist.len() + song.format.len())
+            .collect(),
+        CacheKind::Waveform => backend
+            .waveforms
+            .entries()
+            .map(|(path, (_, waveform))| path_bytes(path) + waveform.peaks.len() * std::mem::size_of::<f32>())
+            .collect(),
+        CacheKind::Disk => {
+            let (entries, bytes) = cache_dir.map_or((0, 0), cache::dir_usage);
+            return CacheStats { kind, entries, bytes };
+        }
+    };
+    CacheStats { kind, entries: sizes.len() as u64, bytes: sizes.iter().sum::<usize>() as u64 }
+}
+
+fn empty_cache(
+    state: &SafeBackendState,
+    kind: CacheKind,
+    cache_dir: Option<&Path>,
+) -> Result<CacheStats, BackendError> {
+    let held = cache_stats(state, kind, cache_dir);
+    match kind {
+        CacheKind::Probe => state.lock().unwrap().probes.clear(),
+        CacheKind::Waveform => state.lock().unwrap().waveforms.clear(),
+        CacheKind::Disk => {
+            if let Some(dir) = cache_dir {
+                cache::clear_dir(dir)
+                    .map_err(|message| BackendError::Rejected { action: "clear_cache".to_string(), message })?;
+            }
+        }
     }
+    Ok(held)
+}
+
+/// Search the library for `query`, returning the page of `limit` matches
+/// (default 50, at most 500) starting at `offset`, plus the total number
+/// of matches.
+#[tauri::command]
+async fn search_songs(
+    state: State<'_, SafeBackendState>,
+    query: String,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<SearchResults, BackendError> {
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let offset = offset.unwrap_or(0);
+    let params = serde_json::json!({ "query": query, "limit": limit, "offset": offset });
+    let data = dispatch_command(&state, "search_songs".to_string(), Some(params))
+        .await?
+        .into_data("search_songs")?;
+    SearchResults::from_backend(&data, offset, limit).map_err(|e| BackendError::InvalidResponse(e.to_string()))
+}
+
+/// A page of the library: songs whose title, artist or filename contains
+/// `filter`, sorted by `sort_by` (`title`, the default, `artist` or
+/// `date_added`), ascending unless `ascending` is false.  `limit` is
+/// clamped to 1–500 (default 100).
+#[tauri::command]
+async fn get_library(
+    state: State<'_, SafeBackendState>,
+    filter: Option<String>,
+    sort_by: Option<LibrarySort>,
+    ascending: Option<bool>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<LibraryPage, BackendError> {
+    let limit = library::page_size(limit);
+    let offset = offset.unwrap_or(0);
+    let params = serde_json::json!({
+        "filter": filter,
+        "sort_by": sort_by.unwrap_or_default(),
+        "ascending": ascending.unwrap_or(true),
+        "limit": limit,
+        "offset": offset,
+    });
+    let data = dispatch_command(&state, "get_library".to_string(), Some(params))
+        .await?
+        .into_data("get_library")?;
+    LibraryPage::from_backend(&data, offset, limit).map_err(|e| BackendError::InvalidResponse(e.to_string()))
+}
+
+/// How often and when each song was played, across sessions: most played
+/// first (`sort_by: "play_count"`, the default) or most recent first
+/// (`"last_played"`), cut to `limit` when given.  `last_played` is in
+/// seconds since the Unix epoch.
+#[tauri::command]
+fn get_play_history(
+    app_handle: tauri::AppHandle,
+    sort_by: Option<PlaySort>,
+    limit: Option<usize>,
+) -> Vec<PlayHistoryEntry> {
+    let entries = plays::history_path(&app_handle.config()).map(|path| plays::load(&path)).unwrap_or_default();
+    plays::sorted(entries, sort_by.unwrap_or_default(), limit)
+}
+
+/// Forget every play counted so far.
+#[tauri::command]
+fn reset_play_history(app_handle: tauri::AppHandle) -> Result<(), String> {
+    let Some(path) = plays::history_path(&app_handle.config()) else {
+        return Ok(());
+    };
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove {}: {e}", path.display())),
+        _ => Ok(()),
+    }
+}
+
+/// Shift the current song's key by `semitones` (-12 to 12) and return the
+/// applied value, which `get_playback_state` reports as `transpose` until
+/// another song is loaded.  Backends without the `transpose` feature reject
+/// it up front.
+#[tauri::command]
+async fn set_transpose(state: State<'_, SafeBackendState>, semitones: i32) -> Result<i32, BackendError> {
+    require_feature(&state, "set_transpose", "transpose").await?;
+    dispatch_command(
+        &state,
+        "set_transpose".to_string(),
+        Some(serde_json::json!({ "semitones": semitones })),
+    )
+    .await?
+    .into_data("set_transpose")?;
+    update_cached_playback(&state, |snapshot| snapshot.transpose = semitones).await?;
+    Ok(semitones)
+}
+
+/// Set the current song's playback speed relative to normal, clamped to
+/// `0.5..=1.5`, and return the applied ratio.  `get_playback_state`
+/// reports it as `tempo` until another song is loaded.  Backends without
+/// the `tempo` feature reject it up front.
+#[tauri::command]
+async fn set_tempo(state: State<'_, SafeBackendState>, ratio: f64) -> Result<f64, BackendError> {
+    let ratio = tempo_ratio(ratio)?;
+    require_feature(&state, "set_tempo", "tempo").await?;
+    dispatch_command(
+        &state,
+        "set_tempo".to_string(),
+        Some(serde_json::json!({ "ratio": ratio })),
+    )
+    .await?
+    .into_data("set_tempo")?;
+    update_cached_playback(&state, |snapshot| snapshot.tempo = ratio).await?;
+    Ok(ratio)
+}
+
+/// Turn vocal attenuation for the current song on or off, with `strength`
+/// from 0 to 1 (for backends that support it).  Returns the applied
+/// settings, reported as `vocal_removal` by `get_playback_state` until
+/// another song is loaded, and whether the filter is `active`: a song
+/// with nothing to filter, like a CDG's backing track, leaves it off and
+/// says why in `note`.
+#[tauri::command]
+async fn set_vocal_removal(
+    state: State<'_, SafeBackendState>,
+    enabled: bool,
+    strength: f64,
+) -> Result<AppliedVocalRemoval, BackendError> {
+    apply_vocal_removal(&state, VocalRemoval { enabled, strength }).await
+}
+
+async fn apply_vocal_removal(
+    state: &SafeBackendState,
+    settings: VocalRemoval,
+) -> Result<AppliedVocalRemoval, BackendError> {
+    require_feature(state, "set_vocal_removal", "vocal_removal").await?;
+    let params = serde_json::json!({ "enabled": settings.enabled, "strength": settings.strength });
+    let data = dispatch_command(state, "set_vocal_removal".to_string(), Some(params))
+        .await?
+        .into_data("set_vocal_removal")?;
+    update_cached_playback(state, |snapshot| snapshot.vocal_removal = settings).await?;
+    Ok(AppliedVocalRemoval {
+        settings,
+        // Older answers without `active` mean the backend applied it.
+        active: data["active"].as_bool().unwrap_or(settings.enabled),
+        note: data["note"].as_str().map(str::to_string),
+    })
+}
+
+/// Clamp a requested tempo into range; NaN and infinities are rejected
+/// since they have no sensible clamp.
+fn tempo_ratio(ratio: f64) -> Result<f64, BackendError> {
+    if !ratio.is_finite() {
+        return Err(BackendError::InvalidParams {
+            action: "set_tempo".to_string(),
+            field: "ratio".to_string(),
+            reason: "must be a finite number".to_string(),
+        });
+    }
+    Ok(ratio.clamp(MIN_TEMPO, MAX_TEMPO))
+}
+
+/// Shift lyrics against the audio by `offset_ms` (-500 to 500; positive
+/// shows them later) to correct drift on some systems, and save it to the
+/// config as the user's calibration, applied to every backend started.
+/// `set_av_sync_offset(0)` resets it.  Returns the applied offset.
+#[tauri::command]
+async fn set_av_sync_offset(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    offset_ms: i32,
+) -> Result<i32, BackendError> {
+    send_av_sync_offset(&state, offset_ms).await?;
+    if let Err(e) = save_config(&app_handle, |config| config.av_sync_offset_ms = offset_ms) {
+        tracing::warn!("Failed to save the A/V sync offset: {e}");
+    }
+    Ok(offset_ms)
+}
+
+/// Send `offset_ms` to the backend (range-checked by its schema) and
+/// cache it once accepted.
+async fn send_av_sync_offset(state: &SafeBackendState, offset_ms: i32) -> Result<(), BackendError> {
+    dispatch_command(
+        state,
+        "set_av_sync_offset".to_string(),
+        Some(serde_json::json!({ "offset_ms": offset_ms })),
+    )
+    .await?
+    .into_data("set_av_sync_offset")?;
+    state.lock().unwrap().av_sync_offset_ms = offset_ms;
+    Ok(())
+}
+
+/// Bring every song to `target_lufs` (-30 to -5) by the ReplayGain gain it
+/// is tagged with, and save the setting to the config, applied to every
+/// backend started.  The backend re-applies it to each song loaded;
+/// untagged songs play unscaled, and `has_gain_metadata` says whether the
+/// current song is tagged.
+#[tauri::command]
+async fn set_normalization(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    enabled: bool,
+    target_lufs: f64,
+) -> Result<AppliedNormalization, BackendError> {
+    let settings = Normalization { enabled, target_lufs };
+    let applied = send_normalization(&state, settings).await?;
+    if let Err(e) = save_config(&app_handle, |config| config.normalization = settings) {
+        tracing::warn!("Failed to save the normalization setting: {e}");
+    }
+    Ok(applied)
+}
+
+async fn send_normalization(
+    state: &SafeBackendState,
+    settings: Normalization,
+) -> Result<AppliedNormalization, BackendError> {
+    let params = serde_json::json!({ "enabled": settings.enabled, "target_lufs": settings.target_lufs });
+    let data = dispatch_command(state, "set_normalization".to_string(), Some(params))
+        .await?
+        .into_data("set_normalization")?;
+    Ok(AppliedNormalization {
+        settings,
+        has_gain_metadata: data["has_gain_metadata"].as_bool().unwrap_or(false),
+        gain_db: data["gain_db"].as_f64(),
+    })
+}
+
+/// Fade the last `duration_ms` (0 to 10000; 0 turns it off) of every song
+/// out and the song auto-advance moves on to in, instead of a hard stop
+/// and start.  Saved to the config and sent to every backend started;
+/// `supported` is false when the backend's capabilities don't list
+/// `crossfade`, and nothing is sent to it then.
+#[tauri::command]
+async fn set_crossfade(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    duration_ms: u64,
+) -> Result<Crossfade, BackendError> {
+    validation::validate_params("set_crossfade", Some(&serde_json::json!({ "duration_ms": duration_ms })))?;
+    let applied = send_crossfade(&state, duration_ms).await?;
+    if let Err(e) = save_config(&app_handle, |config| config.crossfade_ms = duration_ms) {
+        tracing::warn!("Failed to save the crossfade setting: {e}");
+    }
+    Ok(applied)
+}
+
+async fn send_crossfade(state: &SafeBackendState, duration_ms: u64) -> Result<Crossfade, BackendError> {
+    let supported = match backend_capabilities(state).await {
+        Ok(capabilities) => capabilities.supports("crossfade"),
+        // Backends from before capabilities can't crossfade either.
+        Err(BackendError::Rejected { .. }) => false,
+        Err(e) => return Err(e),
+    };
+    if supported {
+        let params = serde_json::json!({ "duration_ms": duration_ms });
+        dispatch_command(state, "set_crossfade".to_string(), Some(params))
+            .await?
+            .into_data("set_crossfade")?;
+    }
+    Ok(Crossfade { duration_ms, supported })
+}
+
+/// Emit `now-playing` with the playback state every `interval_ms` (100 to
+/// 60000), for overlays that redraw on their own schedule rather than on
+/// backend events.  Replaces a feed already running; the feed ends with
+/// `stop_now_playing_feed` or when the backend stops.
+#[tauri::command]
+fn start_now_playing_feed(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    interval_ms: u64,
+) -> Result<u64, BackendError> {
+    if !(MIN_FEED_INTERVAL_MS..=MAX_FEED_INTERVAL_MS).contains(&interval_ms) {
+        return Err(BackendError::InvalidParams {
+            action: "start_now_playing_feed".to_string(),
+            field: "interval_ms".to_string(),
+            reason: format!("must be between {MIN_FEED_INTERVAL_MS} and {MAX_FEED_INTERVAL_MS}"),
+        });
+    }
+    let stop = {
+        let mut backend = state.lock().unwrap();
+        if !backend.is_running() {
+            return Err(BackendError::NotRunning);
+        }
+        let (tx, rx) = oneshot::channel();
+        backend.now_playing_stop = Some(tx);
+        rx
+    };
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn(async move {
+        let emit = |playback: &PlaybackState| {
+            app_handle.emit_all("now-playing", playback).ok();
+        };
+        now_playing_feed(&state, Duration::from_millis(interval_ms), stop, emit).await;
+    });
+    Ok(interval_ms)
+}
+
+/// End the `now-playing` feed; returns whether one was running.
+#[tauri::command]
+fn stop_now_playing_feed(state: State<SafeBackendState>) -> bool {
+    state.lock().unwrap().now_playing_stop.take().is_some()
+}
+
+/// Poll the playback state each `interval` and pass it to `emit` until
+/// `stop` fires or is dropped, or the backend goes away.  A failed poll
+/// skips that tick.
+async fn now_playing_feed(
+    state: &SafeBackendState,
+    interval: Duration,
+    mut stop: oneshot::Receiver<()>,
+    emit: impl Fn(&PlaybackState),
+) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = &mut stop => return,
+            _ = ticks.tick() => {}
+        }
+        let polled = tokio::select! {
+            _ = &mut stop => return,
+            polled = fetch_playback_state(state) => polled,
+        };
+        match polled {
+            Ok(playback) => emit(&playback),
+            Err(BackendError::NotRunning | BackendError::ProcessExited) => return,
+            Err(e) => tracing::warn!("now-playing poll failed: {e}"),
+        }
+    }
+}
+
+/// Fade the audio out over `duration_ms` (100 to 10000), then stop.  If
+/// the backend can't fade (an older backend, or nothing playing), stops
+/// immediately and says why in `note`.  Emits `playback-fading` with
+/// `fading` and `duration_ms` as the fade starts and ends, so the UI can
+/// disable its transport controls meanwhile.
+#[tauri::command]
+async fn stop_with_fade(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    duration_ms: u64,
+) -> Result<FadeStop, BackendError> {
+    let emit = |fading: bool| {
+        let payload = serde_json::json!({ "fading": fading, "duration_ms": duration_ms });
+        app_handle.emit_all("playback-fading", payload).ok();
+    };
+    take_over_from_preview(&state, "stop");
+    fade_and_stop(&state, duration_ms, emit).await
+}
+
+async fn fade_and_stop(
+    state: &SafeBackendState,
+    duration_ms: u64,
+    emit: impl Fn(bool),
+) -> Result<FadeStop, BackendError> {
+    {
+        let mut backend = state.lock().unwrap();
+        if backend.fading {
+            return Err(BackendError::Rejected {
+                action: "stop_with_fade".to_string(),
+                message: "A fade is already in progress".to_string(),
+            });
+        }
+        backend.fading = true;
+    }
+    emit(true);
+    let result = fade_then_stop(state, duration_ms).await;
+    state.lock().unwrap().fading = false;
+    emit(false);
+    result
+}
+
+async fn fade_then_stop(state: &SafeBackendState, duration_ms: u64) -> Result<FadeStop, BackendError> {
+    let params = serde_json::json!({ "duration_ms": duration_ms });
+    let faded = match dispatch_command(state, "fade_out".to_string(), Some(params)).await?.into_data("fade_out") {
+        Ok(_) => {
+            tokio::time::sleep(Duration::from_millis(duration_ms)).await;
+            FadeStop { faded: true, note: None }
+        }
+        Err(BackendError::Rejected { message, .. }) => FadeStop {
+            faded: false,
+            note: Some(format!("Stopped without fading: {message}")),
+        },
+        Err(e) => return Err(e),
+    };
+    dispatch_command(state, "stop".to_string(), None)
+        .await?
+        .into_data("stop")?;
+    Ok(faded)
+}
+
+/// Have the backend read the song at `playlist_index`, by default the one
+/// after the current song, into memory so starting it doesn't wait on the
+/// disk.  Nothing is sent if that song is already preloaded.  Emits
+/// `preload-complete` with `playlist_index` and `bytes` once it is read.
+#[tauri::command]
+async fn preload_next(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    playlist_index: Option<usize>,
+) -> Result<Preload, BackendError> {
+    let preload = preload_song(&state, playlist_index).await?;
+    if !preload.already_preloaded {
+        app_handle.emit_all("preload-complete", &preload).ok();
+    }
+    Ok(preload)
+}
+
+async fn preload_song(state: &SafeBackendState, playlist_index: Option<usize>) -> Result<Preload, BackendError> {
+    let index = match playlist_index {
+        Some(index) => index,
+        None => fetch_playback_state(state).await?.playlist_index.map(|current| current + 1).ok_or_else(|| {
+            BackendError::InvalidParams {
+                action: "preload_next".to_string(),
+                field: "playlist_index".to_string(),
+                reason: "required when no playlist entry is playing".to_string(),
+            }
+        })?,
+    };
+    if let Some(preloaded) = state.lock().unwrap().preloaded.clone().filter(|p| p.playlist_index == index) {
+        return Ok(Preload { already_preloaded: true, ..preloaded });
+    }
+    let params = serde_json::json!({ "playlist_index": index });
+    let data = dispatch_command(state, "preload".to_string(), Some(params))
+        .await?
+        .into_data("preload")?;
+    let preload = Preload {
+        playlist_index: index,
+        bytes: data["bytes"].as_u64().unwrap_or(0),
+        already_preloaded: false,
+    };
+    state.lock().unwrap().preloaded = Some(preload.clone());
+    Ok(preload)
+}
+
+/// Stop playback later: when the current song finishes (`after_current`),
+/// or after `duration_ms` (1000 to 86400000) with a fade like
+/// `stop_with_fade`.  Replaces a timer already set.  Emits
+/// `sleep-timer-fired` with `reason` (`after_current` or `duration`) as
+/// it stops playback.  Returns the timer that was set.
+#[tauri::command]
+async fn set_sleep_timer(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    after_current: Option<bool>,
+    duration_ms: Option<u64>,
+) -> Result<SleepTimer, BackendError> {
+    let timer = SleepTimer::new(after_current, duration_ms).map_err(|(field, reason)| BackendError::InvalidParams {
+        action: "set_sleep_timer".to_string(),
+        field: field.to_string(),
+        reason,
+    })?;
+    if !state.lock().unwrap().is_running() {
+        return Err(BackendError::NotRunning);
+    }
+    clear_sleep_timer(&state).await?;
+    match timer {
+        SleepTimer::AfterCurrent => {
+            // The backend would start the next song before the app sees
+            // `song_finished`.
+            if !state.lock().unwrap().auto_advance.host_managed() {
+                set_backend_advance(&state, false).await?;
+            }
+            state.lock().unwrap().stop_after_current = true;
+        }
+        SleepTimer::Duration(ms) => {
+            let (tx, rx) = oneshot::channel();
+            state.lock().unwrap().sleep_timer_stop = Some(tx);
+            let state = state.inner().clone();
+            tauri::async_runtime::spawn(async move {
+                let fired = || {
+                    app_handle.emit_all("sleep-timer-fired", serde_json::json!({ "reason": timer.reason() })).ok();
+                };
+                let emit_fading = |fading: bool| {
+                    let payload = serde_json::json!({ "fading": fading, "duration_ms": SLEEP_FADE_MS });
+                    app_handle.emit_all("playback-fading", payload).ok();
+                };
+                let slept = sleep_then_stop(&state, Duration::from_millis(ms), SLEEP_FADE_MS, rx, fired, emit_fading);
+                if let Err(e) = slept.await {
+                    tracing::warn!("Sleep timer failed to stop playback: {e}");
+                }
+            });
+        }
+    }
+    Ok(timer)
+}
+
+/// Cancel the sleep timer; returns whether one was set.
+#[tauri::command]
+async fn cancel_sleep_timer(state: State<'_, SafeBackendState>) -> Result<bool, BackendError> {
+    clear_sleep_timer(&state).await
+}
+
+async fn clear_sleep_timer(state: &SafeBackendState) -> Result<bool, BackendError> {
+    let (duration, after_current) = {
+        let mut backend = state.lock().unwrap();
+        (backend.sleep_timer_stop.take().is_some(), std::mem::take(&mut backend.stop_after_current))
+    };
+    if after_current {
+        restore_backend_advance(state).await?;
+    }
+    Ok(duration || after_current)
+}
+
+/// Undo an after-current timer turning the backend's advancing off,
+/// unless the app advances by itself anyway.
+async fn restore_backend_advance(state: &SafeBackendState) -> Result<(), BackendError> {
+    if state.lock().unwrap().auto_advance.host_managed() {
+        return Ok(());
+    }
+    set_backend_advance(state, true).await
+}
+
+/// Wait `after`, then call `fired` and fade out over `fade_ms` and stop,
+/// unless `stop` fires or is dropped first.  Returns whether the timer
+/// fired.
+async fn sleep_then_stop(
+    state: &SafeBackendState,
+    after: Duration,
+    fade_ms: u64,
+    mut stop: oneshot::Receiver<()>,
+    fired: impl FnOnce(),
+    emit_fading: impl Fn(bool),
+) -> Result<bool, BackendError> {
+    tokio::select! {
+        _ = &mut stop => return Ok(false),
+        _ = tokio::time::sleep(after) => {}
+    }
+    {
+        // Checked under the lock, as replacing the timer drops its sender.
+        let mut backend = state.lock().unwrap();
+        if !matches!(stop.try_recv(), Err(oneshot::error::TryRecvError::Empty)) {
+            return Ok(false);
+        }
+        backend.sleep_timer_stop = None;
+    }
+    fired();
+    fade_and_stop(state, fade_ms, emit_fading).await?;
+    Ok(true)
+}
+
+/// Play `duration_ms` (1000 to 60000) of the song at `path` from
+/// `start_ms`, e.g. for a preview button in search results, then put
+/// playback back the way it was: the previous song reloaded and, if it was
+/// playing or paused, resumed at its position.  The playlist position is
+/// left alone, and the preview isn't counted as a play.  Returns once the
+/// snippet is playing; emits `preview-started` then and `preview-ended`
+/// with `restored` (false if putting things back failed) afterwards.
+///
+/// A transport command (`play`, `seek`, `load_song` and the like) during
+/// the snippet ends it early and leaves playback as that command made it;
+/// `preview-ended` then has `cut_short` set and `restored` false.
+#[tauri::command]
+async fn preview_song(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    path: PathBuf,
+    start_ms: u64,
+    duration_ms: u64,
+) -> Result<(), BackendError> {
+    if !(MIN_PREVIEW_MS..=MAX_PREVIEW_MS).contains(&duration_ms) {
+        return Err(BackendError::InvalidParams {
+            action: "preview_song".to_string(),
+            field: "duration_ms".to_string(),
+            reason: format!("must be between {MIN_PREVIEW_MS} and {MAX_PREVIEW_MS}"),
+        });
+    }
+    ingest::ensure_readable_file(&path)
+        .map_err(|reason| BackendError::FileNotFound { path: path.display().to_string(), reason })?;
+    let prior = start_preview(&state, &path, start_ms).await?;
+    let song = path.display().to_string();
+    let started = serde_json::json!({ "path": song, "start_ms": start_ms, "duration_ms": duration_ms });
+    app_handle.emit_all("preview-started", started).ok();
+    let (tx, mut stop) = oneshot::channel::<()>();
+    state.lock().unwrap().preview_stop = Some(tx);
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn(async move {
+        let cut_short = tokio::select! {
+            _ = &mut stop => true,
+            _ = tokio::time::sleep(Duration::from_millis(duration_ms)) => false,
+        };
+        let restored = if cut_short { abandon_preview(&state).await.map(|()| false) } else {
+            end_preview(&state, &prior).await.map(|()| true)
+        };
+        if let Err(e) = &restored {
+            tracing::warn!("Failed to restore playback after a preview: {e}");
+        }
+        let restored = restored == Ok(true);
+        let ended = serde_json::json!({ "path": song, "restored": restored, "cut_short": cut_short });
+        app_handle.emit_all("preview-ended", ended).ok();
+    });
+    Ok(())
+}
+
+/// Play a sine tone of `frequency_hz` (20 to 20000) for `duration_ms` (100
+/// to 10000) on the `left`, `right` or `both` speakers, to check the audio
+/// routing before a show.  A playing song is paused for the tone and
+/// resumed after it, unless it was stopped or played on meanwhile; the
+/// playlist isn't touched.  Returns once the tone is playing and emits
+/// `test-tone-ended` with `resumed` afterwards, straight away (and without
+/// resuming) if the backend is stopped meanwhile.
+#[tauri::command]
+async fn play_test_tone(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    frequency_hz: f64,
+    duration_ms: u64,
+    channel: String,
+) -> Result<(), BackendError> {
+    let params = serde_json::json!({ "frequency_hz": frequency_hz, "duration_ms": duration_ms, "channel": channel });
+    let paused = start_test_tone(&state, params).await?;
+    let (tx, mut stop) = oneshot::channel::<()>();
+    state.lock().unwrap().test_tone_stop = Some(tx);
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn(async move {
+        let resumed = tokio::select! {
+            _ = &mut stop => Ok(false),
+            _ = tokio::time::sleep(Duration::from_millis(duration_ms)) => end_test_tone(&state, paused).await,
+        };
+        if let Err(e) = &resumed {
+            tracing::warn!("Failed to resume playback after the test tone: {e}");
+        }
+        let resumed = resumed == Ok(true);
+        let ended = serde_json::json!({ "frequency_hz": frequency_hz, "channel": channel, "resumed": resumed });
+        app_handle.emit_all("test-tone-ended", ended).ok();
+    });
+    Ok(())
+}
+
+/// Pause the song if one is playing and start the tone.  Returns whether
+/// the song was paused, for [`end_test_tone`].
+async fn start_test_tone(state: &SafeBackendState, params: serde_json::Value) -> Result<bool, BackendError> {
+    validation::validate_params("play_test_tone", Some(&params))?;
+    require_feature(state, "play_test_tone", "test_tone").await?;
+    {
+        let mut backend = state.lock().unwrap();
+        if backend.test_tone || backend.previewing {
+            let playing = if backend.test_tone { "A test tone" } else { "A preview" };
+            return Err(BackendError::Rejected {
+                action: "play_test_tone".to_string(),
+                message: format!("{playing} is already playing"),
+            });
+        }
+        backend.test_tone = true;
+    }
+    let started = pause_for_tone(state, params).await;
+    if started.is_err() {
+        state.lock().unwrap().test_tone = false;
+    }
+    started
+}
+
+async fn pause_for_tone(state: &SafeBackendState, params: serde_json::Value) -> Result<bool, BackendError> {
+    let send = |action: &'static str, params: Option<serde_json::Value>| async move {
+        dispatch_command(state, action.to_string(), params).await?.into_data(action).map(drop)
+    };
+    let playing = fetch_playback_state(state).await?.state == "playing";
+    if playing {
+        send("pause", None).await?;
+    }
+    if let Err(e) = send("play_test_tone", Some(params)).await {
+        if playing {
+            send("play", None).await.ok();
+        }
+        return Err(e);
+    }
+    Ok(playing)
+}
+
+/// Resume the song paused for the tone if it is still paused.  Returns
+/// whether it was resumed.
+async fn end_test_tone(state: &SafeBackendState, paused: bool) -> Result<bool, BackendError> {
+    let resumed = async {
+        if !paused || fetch_playback_state(state).await?.state != "paused" {
+            return Ok(false);
+        }
+        dispatch_command(state, "play".to_string(), None).await?.into_data("play").map(|_| true)
+    }
+    .await;
+    state.lock().unwrap().test_tone = false;
+    resumed
+}
+
+/// Note the playback state, then load and play the song at `path` from
+/// `start_ms`.  Returns the state to put back with [`end_preview`].
+async fn start_preview(state: &SafeBackendState, path: &Path, start_ms: u64) -> Result<PlaybackState, BackendError> {
+    {
+        let mut backend = state.lock().unwrap();
+        if backend.previewing || backend.test_tone {
+            let playing = if backend.test_tone { "A test tone" } else { "A preview" };
+            return Err(BackendError::Rejected {
+                action: "preview_song".to_string(),
+                message: format!("{playing} is already playing"),
+            });
+        }
+        backend.previewing = true;
+    }
+    let prior = match fetch_playback_state(state).await {
+        Ok(prior) => prior,
+        Err(e) => {
+            state.lock().unwrap().previewing = false;
+            return Err(e);
+        }
+    };
+    if let Err(e) = play_preview(state, path, start_ms).await {
+        end_preview(state, &prior).await.ok();
+        return Err(e);
+    }
+    Ok(prior)
+}
+
+async fn play_preview(state: &SafeBackendState, path: &Path, start_ms: u64) -> Result<(), BackendError> {
+    // The backend would move on to the next playlist entry if the snippet
+    // ran to the end of the song.
+    if !state.lock().unwrap().auto_advance.host_managed() {
+        set_backend_advance(state, false).await?;
+    }
+    let send = |action: &'static str, params: Option<serde_json::Value>| async move {
+        dispatch_command(state, action.to_string(), params).await?.into_data(action).map(drop)
+    };
+    send("load_song", Some(serde_json::json!({ "filepath": path.display().to_string() }))).await?;
+    send("play", None).await?;
+    if start_ms > 0 {
+        send("seek", Some(serde_json::json!({ "position_ms": start_ms }))).await?;
+    }
+    Ok(())
+}
+
+/// Stop the preview and bring back the `prior` song, playing or paused at
+/// its old position if it was.
+async fn end_preview(state: &SafeBackendState, prior: &PlaybackState) -> Result<(), BackendError> {
+    state.lock().unwrap().preview_stop = None;
+    let restored = restore_after_preview(state, prior).await;
+    state.lock().unwrap().previewing = false;
+    restored
+}
+
+/// End a preview cut short, leaving playback alone; only the backend's own
+/// advancing is put back.  Nothing is left to do once the backend is gone.
+async fn abandon_preview(state: &SafeBackendState) -> Result<(), BackendError> {
+    if !state.lock().unwrap().previewing {
+        return Ok(());
+    }
+    let restored = resume_backend_advance(state).await;
+    state.lock().unwrap().previewing = false;
+    restored
+}
+
+/// Actions that take playback over from a preview that is playing.
+const TRANSPORT_ACTIONS: [&str; 7] = ["play", "pause", "stop", "next", "previous", "seek", "load_song"];
+
+/// Cut a playing preview short if `action` is a transport command.
+fn take_over_from_preview(state: &SafeBackendState, action: &str) {
+    if TRANSPORT_ACTIONS.contains(&action) {
+        state.lock().unwrap().preview_stop = None;
+    }
+}
+
+async fn restore_after_preview(state: &SafeBackendState, prior: &PlaybackState) -> Result<(), BackendError> {
+    let send = |action: &'static str, params: Option<serde_json::Value>| async move {
+        dispatch_command(state, action.to_string(), params).await?.into_data(action).map(drop)
+    };
+    send("stop", None).await?;
+    if let Some(song) = &prior.current_song {
+        send("load_song", Some(serde_json::json!({ "filepath": song.filepath }))).await?;
+        if prior.state == "playing" || prior.state == "paused" {
+            send("play", None).await?;
+            if prior.position_ms > 0 {
+                send("seek", Some(serde_json::json!({ "position_ms": prior.position_ms }))).await?;
+            }
+            if prior.state == "paused" {
+                send("pause", None).await?;
+            }
+        }
+    }
+    resume_backend_advance(state).await
+}
+
+/// Turn the backend's own advancing back on after a preview, unless the
+/// app advances or an after-current sleep timer still needs it off.
+async fn resume_backend_advance(state: &SafeBackendState) -> Result<(), BackendError> {
+    let keep_off = {
+        let backend = state.lock().unwrap();
+        backend.auto_advance.host_managed() || backend.stop_after_current
+    };
+    if !keep_off {
+        set_backend_advance(state, true).await?;
+    }
+    Ok(())
+}
+
+/// Apply `change` to the cached playback snapshot, fetching one first if
+/// nothing is cached.
+async fn update_cached_playback(
+    state: &SafeBackendState,
+    change: impl FnOnce(&mut PlaybackState),
+) -> Result<(), BackendError> {
+    let cached = state.lock().unwrap().playback.lock().unwrap().is_some();
+    if !cached {
+        fetch_playback_state(state).await?;
+    }
+    let playback = state.lock().unwrap().playback.clone();
+    if let Some(snapshot) = playback.lock().unwrap().as_mut() {
+        change(snapshot);
+    }
+    Ok(())
+}
+
+/// Seek the current song to `position_ms`.
+///
+/// The position is checked against the song's duration from the cached
+/// playback state (fetched first if nothing is cached), so seeks past the
+/// end are rejected before reaching the backend.  Returns the position
+/// that was requested.
+#[tauri::command]
+async fn seek(state: State<'_, SafeBackendState>, position_ms: u64) -> Result<u64, BackendError> {
+    take_over_from_preview(&state, "seek");
+    let cached = state.lock().unwrap().playback.lock().unwrap().clone();
+    let playback = match cached {
+        Some(playback) => playback,
+        None => fetch_playback_state(&state).await?,
+    };
+    let position_ms = seek_position(position_ms, &playback)?;
+    dispatch_command(
+        &state,
+        "seek".to_string(),
+        Some(serde_json::json!({ "position_ms": position_ms })),
+    )
+    .await?
+    .into_data("seek")?;
+    Ok(position_ms)
+}
+
+/// Set the playback volume, clamped to `0.0..=1.0`.
+///
+/// Calls arriving within `VOLUME_DEBOUNCE` of each other collapse into
+/// one: only the last value is sent, without waiting for the backend's
+/// answer, and not at all if the backend already has it.  Setting the
+/// volume while muted unmutes without restoring the old level, even when
+/// it is the 0 muting set; so does a `set_volume` through `send_command`.
+/// Returns the clamped value.
+#[tauri::command]
+async fn set_volume(state: State<'_, SafeBackendState>, volume: f64) -> Result<f64, BackendError> {
+    debounce_volume(&state, volume).await
+}
+
+async fn debounce_volume(state: &SafeBackendState, volume: f64) -> Result<f64, BackendError> {
+    let volume = volume.clamp(0.0, 1.0);
+    let ticket = state.lock().unwrap().volume.propose(volume);
+    tokio::time::sleep(VOLUME_DEBOUNCE).await;
+
+    let mut backend = state.lock().unwrap();
+    let latest = backend.volume.is_latest(ticket);
+    if let Some(volume) = backend.volume.take_if_latest(ticket) {
+        send_detached(
+            &mut backend,
+            "set_volume".to_string(),
+            Some(serde_json::json!({ "volume": volume })),
+        )?;
+    }
+    if latest {
+        backend.mute.clear();
+    }
+    Ok(volume)
+}
+
+/// Mute (volume 0, remembering the current level) or unmute (restoring
+/// it).  Asking for the state already in effect changes nothing.
+#[tauri::command]
+async fn set_mute(state: State<'_, SafeBackendState>, muted: bool) -> Result<MuteState, BackendError> {
+    apply_mute(&state, muted).await
+}
+
+/// Flip between muted and unmuted, like `set_mute`.
+#[tauri::command]
+async fn toggle_mute(state: State<'_, SafeBackendState>) -> Result<MuteState, BackendError> {
+    let muted = state.lock().unwrap().mute.is_muted();
+    apply_mute(&state, !muted).await
+}
+
+async fn apply_mute(state: &SafeBackendState, muted: bool) -> Result<MuteState, BackendError> {
+    let saved = state.lock().unwrap().mute.saved();
+    let (volume, restore) = match (muted, saved) {
+        (true, None) => (0.0, Some(fetch_playback_state(state).await?.volume)),
+        (false, Some(level)) => (level, None),
+        (true, Some(_)) => return Ok(MuteState { muted, volume: 0.0 }),
+        (false, None) => return Ok(MuteState { muted, volume: fetch_playback_state(state).await?.volume }),
+    };
+    dispatch_command(state, "set_volume".to_string(), Some(serde_json::json!({ "volume": volume })))
+        .await?
+        .into_data("set_volume")?;
+    let mut backend = state.lock().unwrap();
+    backend.volume.mark_sent(volume);
+    match restore {
+        Some(level) => backend.mute.mute(level),
+        None => backend.mute.clear(),
+    }
+    Ok(MuteState { muted, volume })
+}
+
+/// List the audio output devices the backend can play through.
+#[tauri::command]
+async fn list_audio_devices(
+    state: State<'_, SafeBackendState>,
+) -> Result<Vec<AudioDevice>, BackendError> {
+    fetch_audio_devices(&state).await
+}
+
+async fn fetch_audio_devices(state: &SafeBackendState) -> Result<Vec<AudioDevice>, BackendError> {
+    let data = dispatch_command(state, "list_audio_devices".to_string(), None)
+        .await?
+        .into_data("list_audio_devices")?;
+    audio::devices_from_backend(&data).map_err(|e| BackendError::InvalidResponse(e.to_string()))
+}
+
+/// Audio output health: `{underruns, buffer_ms, sample_rate, channels}`,
+/// with `null` for what the backend can't measure.  When the underrun
+/// count rose since the last call, also emits `audio-underrun` with the
+/// total and how many are new, so the UI can warn about an overloaded
+/// system.
+#[tauri::command]
+async fn get_audio_stats(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+) -> Result<AudioStats, BackendError> {
+    let (stats, new_underruns) = fetch_audio_stats(&state).await?;
+    if let Some(new) = new_underruns {
+        let payload = serde_json::json!({ "underruns": stats.underruns, "new": new });
+        app_handle.emit_all("audio-underrun", payload).ok();
+    }
+    Ok(stats)
+}
+
+/// Ask for the audio stats and cache them, returning the underruns since
+/// the cached ones.
+async fn fetch_audio_stats(state: &SafeBackendState) -> Result<(AudioStats, Option<u64>), BackendError> {
+    let data = dispatch_command(state, "get_audio_stats".to_string(), None)
+        .await?
+        .into_data("get_audio_stats")?;
+    let stats = AudioStats::deserialize(&data).map_err(|e| BackendError::InvalidResponse(e.to_string()))?;
+    let mut backend = state.lock().unwrap();
+    let new_underruns = stats.new_underruns(backend.audio_stats.as_ref());
+    backend.audio_stats = Some(stats.clone());
+    Ok((stats, new_underruns))
+}
+
+/// Close and reopen the audio output without restarting the backend, on
+/// `device_id` (one of those `list_audio_devices` reports) if given, e.g.
+/// after a USB device was unplugged.  The playlist is kept and the current
+/// song carries on from where it was.  Resolves once the backend confirms
+/// with `audio_ready`, also emitted as `audio-ready`; on failure emits
+/// `audio-reinit-failed` with `reason`.
+#[tauri::command]
+async fn reinit_audio(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    device_id: Option<String>,
+) -> Result<AudioReady, BackendError> {
+    let reinit = reinit_audio_on(&state, device_id).await;
+    match &reinit {
+        Ok(ready) => app_handle.emit_all("audio-ready", ready),
+        Err(e) => app_handle.emit_all("audio-reinit-failed", serde_json::json!({ "reason": e.to_string() })),
+    }
+    .ok();
+    reinit
+}
+
+async fn reinit_audio_on(state: &SafeBackendState, device_id: Option<String>) -> Result<AudioReady, BackendError> {
+    if let Some(id) = &device_id {
+        let devices = fetch_audio_devices(state).await?;
+        audio::ensure_known_device("reinit_audio", "device_id", id, &devices)?;
+    }
+    let (tx, rx) = oneshot::channel();
+    let waiter = state.lock().unwrap().audio_ready.clone();
+    *waiter.lock().unwrap() = Some(tx);
+    let params = device_id.map(|id| serde_json::json!({ "id": id }));
+    dispatch_command(state, "reinit_audio".to_string(), params)
+        .await?
+        .into_data("reinit_audio")?;
+    match tokio::time::timeout(AUDIO_READY_TIMEOUT, rx).await {
+        Ok(Ok(ready)) => Ok(ready),
+        _ => Err(BackendError::Rejected {
+            action: "reinit_audio".to_string(),
+            message: "The backend didn't confirm the audio is ready".to_string(),
+        }),
+    }
+}
+
+/// Hand an `audio_ready` event to the `reinit_audio` waiting for it.
+fn confirm_audio_ready(waiter: &Mutex<Option<oneshot::Sender<AudioReady>>>, event: &serde_json::Value) {
+    if event["type"] != events::AUDIO_READY {
+        return;
+    }
+    if let Some(tx) = waiter.lock().unwrap().take() {
+        tx.send(AudioReady::deserialize(&event["data"]).unwrap_or_default()).ok();
+    }
+}
+
+/// Switch the audio output to device `id`, which must be one of those
+/// `list_audio_devices` reports.
+#[tauri::command]
+async fn set_audio_device(state: State<'_, SafeBackendState>, id: String) -> Result<String, BackendError> {
+    let devices = fetch_audio_devices(&state).await?;
+    audio::ensure_known_device("set_audio_device", "id", &id, &devices)?;
+    dispatch_command(
+        &state,
+        "set_audio_device".to_string(),
+        Some(serde_json::json!({ "id": id })),
+    )
+    .await?
+    .into_data("set_audio_device")?;
+    Ok(id)
+}
+
+/// List the microphones and other audio inputs the backend can record
+/// from.
+#[tauri::command]
+async fn list_input_devices(state: State<'_, SafeBackendState>) -> Result<Vec<AudioDevice>, BackendError> {
+    fetch_input_devices(&state).await
+}
+
+async fn fetch_input_devices(state: &SafeBackendState) -> Result<Vec<AudioDevice>, BackendError> {
+    let data = dispatch_command(state, "list_input_devices".to_string(), None)
+        .await?
+        .into_data("list_input_devices")?;
+    audio::devices_from_backend(&data).map_err(|e| BackendError::InvalidResponse(e.to_string()))
+}
+
+/// Switch the microphone to input device `id`, which must be one of those
+/// `list_input_devices` reports.  Mic monitoring, if on, carries on from
+/// the new device.
+#[tauri::command]
+async fn set_input_device(state: State<'_, SafeBackendState>, id: String) -> Result<String, BackendError> {
+    apply_input_device(&state, id).await
+}
+
+async fn apply_input_device(state: &SafeBackendState, id: String) -> Result<String, BackendError> {
+    let devices = fetch_input_devices(state).await?;
+    audio::ensure_known_device("set_input_device", "id", &id, &devices)?;
+    dispatch_command(state, "set_input_device".to_string(), Some(serde_json::json!({ "id": id })))
+        .await?
+        .into_data("set_input_device")?;
+    state.lock().unwrap().input_device = Some(id.clone());
+    Ok(id)
+}
+
+/// Play the microphone through the audio output while `enabled`, at `gain`
+/// from 0 to 2 (1 as recorded), for backends that support it.  Returns the
+/// applied settings and the input `device` monitored.
+#[tauri::command]
+async fn set_mic_monitoring(
+    state: State<'_, SafeBackendState>,
+    enabled: bool,
+    gain: f64,
+) -> Result<MicMonitoring, BackendError> {
+    apply_mic_monitoring(&state, enabled, gain).await
+}
+
+async fn apply_mic_monitoring(
+    state: &SafeBackendState,
+    enabled: bool,
+    gain: f64,
+) -> Result<MicMonitoring, BackendError> {
+    let params = serde_json::json!({ "enabled": enabled, "gain": gain });
+    validation::validate_params("set_mic_monitoring", Some(&params))?;
+    require_feature(state, "set_mic_monitoring", "mic_monitoring").await?;
+    let data = dispatch_command(state, "set_mic_monitoring".to_string(), Some(params))
+        .await?
+        .into_data("set_mic_monitoring")?;
+    let mut backend = state.lock().unwrap();
+    // The backend picks its first input when none was chosen.
+    if let Some(device) = data["device"].as_str() {
+        backend.input_device = Some(device.to_string());
+    }
+    Ok(MicMonitoring { enabled, gain, device: backend.input_device.clone() })
+}
+
+/// Write `action` without waiting for its response, which is consumed by
+/// a detached pending slot.
+fn send_detached(
+    backend: &mut BackendState,
+    action: String,
+    params: Option<serde_json::Value>,
+) -> Result<(), BackendError> {
+    if !backend.is_running() {
+        return Err(BackendError::NotRunning);
+    }
+    let table = backend.pending.clone();
+    let request_id = table.lock().unwrap().register_detached();
+    let command = CommandRequest { action, params, request_id: Some(request_id) };
+    backend.write_command(&command).inspect_err(|_| {
+        table.lock().unwrap().remove(request_id);
+    })
+}
+
+/// Move the playlist entry at `from` to position `to`, shifting the
+/// entries in between.  Both indices are checked against the playlist
+/// length in the cached playback state (fetched first if nothing is
+/// cached) before anything is sent.  Returns the reordered playlist.
+#[tauri::command]
+async fn move_playlist_item(
+    state: State<'_, SafeBackendState>,
+    from: usize,
+    to: usize,
+) -> Result<Vec<SongInfo>, BackendError> {
+    let cached = state.lock().unwrap().playback.lock().unwrap().clone();
+    let playback = match cached {
+        Some(playback) => playback,
+        None => fetch_playback_state(&state).await?,
+    };
+    check_playlist_move(from, to, &playback)?;
+    let data = dispatch_command(
+        &state,
+        "move_in_playlist".to_string(),
+        Some(serde_json::json!({ "from": from, "to": to })),
+    )
+    .await?
+    .into_data("move_in_playlist")?;
+    playlist::songs_in(&data).map_err(|e| BackendError::InvalidResponse(e.to_string()))
+}
+
+/// Reject `move_playlist_item` indices past the end of the playlist.
+fn check_playlist_move(from: usize, to: usize, playback: &PlaybackState) -> Result<(), BackendError> {
+    for (field, index) in [("from", from), ("to", to)] {
+        if index >= playback.playlist_len {
+            return Err(BackendError::InvalidParams {
+                action: "move_playlist_item".to_string(),
+                field: field.to_string(),
+                reason: format!("must be less than {} (the playlist's length)", playback.playlist_len),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Validate a seek target against the song's duration.  A duration of 0
+/// means the backend doesn't know it, so any position is let through.
+fn seek_position(position_ms: u64, playback: &PlaybackState) -> Result<u64, BackendError> {
+    if playback.duration_ms > 0 && position_ms > playback.duration_ms {
+        return Err(BackendError::InvalidParams {
+            action: "seek".to_string(),
+            field: "position_ms".to_string(),
+            reason: format!("must be at most {} (the song's length)", playback.duration_ms),
+        });
+    }
+    Ok(position_ms)
+}
+
+/// Report the backend's version alongside the interpreter it runs under
+/// and the app's own version.  Cached until the backend is restarted.
+#[tauri::command]
+async fn get_backend_version(
+    state: State<'_, SafeBackendState>,
+) -> Result<BackendVersion, BackendError> {
+    backend_version(&state).await
+}
+
+async fn backend_version(state: &SafeBackendState) -> Result<BackendVersion, BackendError> {
+    let cached = state.lock().unwrap().version.clone();
+    if let Some(version) = cached {
+        return Ok(version);
+    }
+
+    let data = dispatch_command(state, "version".to_string(), None)
+        .await?
+        .into_data("version")?;
+    let backend_version = data["version"]
+        .as_str()
+        .ok_or_else(|| BackendError::InvalidResponse(data.to_string()))?
+        .to_string();
+
+    let mut backend = state.lock().unwrap();
+    let version = BackendVersion {
+        backend_version,
+        interpreter: backend.interpreter.clone(),
+        app_version: env!("CARGO_PKG_VERSION"),
+    };
+    backend.version = Some(version.clone());
+    Ok(version)
+}
+
+/// File extensions (lower case, without the dot) the backend can play,
+/// as used to filter dropped files.  Cached until the backend is
+/// restarted; a built-in list is returned if the backend doesn't answer.
+#[tauri::command]
+async fn get_supported_formats(state: State<'_, SafeBackendState>) -> Result<Vec<String>, BackendError> {
+    Ok(supported_formats(&state).await)
+}
+
+async fn supported_formats(state: &SafeBackendState) -> Vec<String> {
+    let cached = state.lock().unwrap().supported_formats.clone();
+    if let Some(extensions) = cached {
+        return extensions;
+    }
+
+    let answer = dispatch_command(state, "get_supported_formats".to_string(), None)
+        .await
+        .and_then(|response| response.into_data("get_supported_formats"))
+        .and_then(|data| {
+            Vec::<String>::deserialize(&data["extensions"]).map_err(|e| BackendError::InvalidResponse(e.to_string()))
+        });
+    match answer {
+        Ok(extensions) => {
+            state.lock().unwrap().supported_formats = Some(extensions.clone());
+            extensions
+        }
+        Err(_) => ingest::default_extensions(),
+    }
+}
+
+/// What the backend can play and do, as `{codecs, features, plugins}`,
+/// for hiding UI it doesn't support.  Cached until the backend is
+/// restarted.
+#[tauri::command]
+async fn get_backend_capabilities(state: State<'_, SafeBackendState>) -> Result<BackendCapabilities, BackendError> {
+    backend_capabilities(&state).await
+}
+
+async fn backend_capabilities(state: &SafeBackendState) -> Result<BackendCapabilities, BackendError> {
+    let cached = state.lock().unwrap().capabilities.clone();
+    if let Some(capabilities) = cached {
+        return Ok(capabilities);
+    }
+    let data = dispatch_command(state, "get_backend_capabilities".to_string(), None)
+        .await?
+        .into_data("get_backend_capabilities")?;
+    let capabilities =
+        BackendCapabilities::deserialize(&data).map_err(|e| BackendError::InvalidResponse(e.to_string()))?;
+    state.lock().unwrap().capabilities = Some(capabilities.clone());
+    Ok(capabilities)
+}
+
+/// Refuse `action` unless the backend supports `feature`; a backend that
+/// can't list its capabilities is given the benefit of the doubt.
+async fn require_feature(state: &SafeBackendState, action: &str, feature: &str) -> Result<(), BackendError> {
+    match backend_capabilities(state).await {
+        Ok(capabilities) if !capabilities.supports(feature) => Err(BackendError::Rejected {
+            action: action.to_string(),
+            message: format!("This backend doesn't support {}", feature.replace('_', " ")),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Languages the backend can render lyrics in, as `{id, name}`.  Cached
+/// until the backend is restarted.
+#[tauri::command]
+async fn get_supported_languages(state: State<'_, SafeBackendState>) -> Result<Vec<LyricsLanguage>, BackendError> {
+    supported_languages(&state).await
+}
+
+async fn supported_languages(state: &SafeBackendState) -> Result<Vec<LyricsLanguage>, BackendError> {
+    let cached = state.lock().unwrap().supported_languages.clone();
+    if let Some(languages) = cached {
+        return Ok(languages);
+    }
+    let data = dispatch_command(state, "get_supported_languages".to_string(), None)
+        .await?
+        .into_data("get_supported_languages")?;
+    let languages = Vec::<LyricsLanguage>::deserialize(&data["languages"])
+        .map_err(|e| BackendError::InvalidResponse(e.to_string()))?;
+    state.lock().unwrap().supported_languages = Some(languages.clone());
+    Ok(languages)
+}
+
+/// Render lyrics in `lang`, one of the `get_supported_languages` ids; it
+/// picks the text encoding and font for songs loaded from now on.  Saved
+/// to the config and restored on every backend started.  Returns `lang`.
+#[tauri::command]
+async fn set_lyrics_language(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    lang: String,
+) -> Result<String, BackendError> {
+    send_lyrics_language(&state, &lang).await?;
+    let saved = lang.clone();
+    if let Err(e) = save_config(&app_handle, |config| config.lyrics_language = Some(saved)) {
+        tracing::warn!("Failed to save the lyrics language: {e}");
+    }
+    Ok(lang)
+}
+
+/// The lyrics language chosen with `set_lyrics_language`, or `None` for
+/// the backend's default.
+#[tauri::command]
+fn get_lyrics_language(app_handle: tauri::AppHandle, state: State<SafeBackendState>) -> Option<String> {
+    let cached = state.lock().unwrap().lyrics_language.clone();
+    cached.or_else(|| AppConfig::path(&app_handle.config()).and_then(|path| AppConfig::load(&path).lyrics_language))
+}
+
+/// Colour schemes the backend can draw lyrics in, as `{name, label}`.
+/// Cached until the backend is restarted.
+#[tauri::command]
+async fn get_available_themes(state: State<'_, SafeBackendState>) -> Result<Vec<Theme>, BackendError> {
+    available_themes(&state).await
+}
+
+async fn available_themes(state: &SafeBackendState) -> Result<Vec<Theme>, BackendError> {
+    let cached = state.lock().unwrap().available_themes.clone();
+    if let Some(themes) = cached {
+        return Ok(themes);
+    }
+    let data = dispatch_command(state, "get_available_themes".to_string(), None)
+        .await?
+        .into_data("get_available_themes")?;
+    let themes =
+        Vec::<Theme>::deserialize(&data["themes"]).map_err(|e| BackendError::InvalidResponse(e.to_string()))?;
+    state.lock().unwrap().available_themes = Some(themes.clone());
+    Ok(themes)
+}
+
+/// Draw lyrics in theme `name`, one of the `get_available_themes` names.
+/// Saved to the config and restored on every backend started; emits
+/// `theme-changed` with `name`.  Returns `name`.
+#[tauri::command]
+async fn set_theme(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    name: String,
+) -> Result<String, BackendError> {
+    send_theme(&state, &name).await?;
+    let saved = name.clone();
+    if let Err(e) = save_config(&app_handle, |config| config.theme = Some(saved)) {
+        tracing::warn!("Failed to save the theme: {e}");
+    }
+    app_handle.emit_all("theme-changed", serde_json::json!({ "name": name })).ok();
+    Ok(name)
+}
+
+/// Check `name` against the available themes and send it.
+async fn send_theme(state: &SafeBackendState, name: &str) -> Result<(), BackendError> {
+    let available = available_themes(state).await?;
+    theme::check_theme(name, &available).map_err(|reason| BackendError::InvalidParams {
+        action: "set_theme".to_string(),
+        field: "name".to_string(),
+        reason,
+    })?;
+    dispatch_command(state, "set_theme".to_string(), Some(serde_json::json!({ "name": name })))
+        .await?
+        .into_data("set_theme")
+        .map(drop)
+}
+
+/// Change how much the backend logs to stderr, without restarting it:
+/// `DEBUG`, `INFO` (the default), `WARNING` or `ERROR`, in any case.  Kept
+/// for backends started later in the session.  Returns the level set.
+#[tauri::command]
+async fn set_backend_log_level(state: State<'_, SafeBackendState>, level: String) -> Result<String, BackendError> {
+    let level = stderr::log_level(&level).map_err(|reason| BackendError::InvalidParams {
+        action: "set_backend_log_level".to_string(),
+        field: "level".to_string(),
+        reason,
+    })?;
+    send_log_level(&state, level).await?;
+    state.lock().unwrap().log_level = Some(level);
+    Ok(level.to_string())
+}
+
+async fn send_log_level(state: &SafeBackendState, level: &str) -> Result<(), BackendError> {
+    dispatch_command(state, "set_log_level".to_string(), Some(serde_json::json!({ "level": level })))
+        .await?
+        .into_data("set_log_level")
+        .map(drop)
+}
+
+/// Check `lang` against the supported languages, send it and cache it
+/// once accepted.
+async fn send_lyrics_language(state: &SafeBackendState, lang: &str) -> Result<(), BackendError> {
+    let supported = supported_languages(state).await?;
+    language::check_language(lang, &supported).map_err(|reason| BackendError::InvalidParams {
+        action: "set_lyrics_language".to_string(),
+        field: "lang".to_string(),
+        reason,
+    })?;
+    dispatch_command(
+        state,
+        "set_lyrics_language".to_string(),
+        Some(serde_json::json!({ "lang": lang })),
+    )
+    .await?
+    .into_data("set_lyrics_language")?;
+    state.lock().unwrap().lyrics_language = Some(lang.to_string());
+    Ok(())
+}
+
+/// Cancel an in-flight command.
+///
+/// The awaiting `send_command` resolves immediately with
+/// `BackendError::Cancelled`, and the backend is asked to abort the work
+/// via a `cancel` action naming the original `request_id`, which it reads
+/// at once even while busy: a running scan stops where it is and a
+/// command still queued is skipped.  Other commands already running
+/// finish, and their reply is dropped.
+#[tauri::command]
+fn cancel_command(state: State<SafeBackendState>, request_id: u64) -> Result<String, BackendError> {
+    let mut backend = state.lock().unwrap();
+    if !backend.is_running() {
+        return Err(BackendError::NotRunning);
+    }
+
+    let pending = backend.pending.clone();
+    let cancel_id = {
+        let mut table = pending.lock().unwrap();
+        if !table.cancel(request_id) {
+            return Err(BackendError::UnknownRequest { request_id });
+        }
+        table.register_detached()
+    };
+    backend.write_command(&CommandRequest {
+        action: "cancel".to_string(),
+        params: Some(serde_json::json!({ "request_id": request_id })),
+        request_id: Some(cancel_id),
+    })?;
+    Ok(format!("Cancelled request {request_id}"))
+}
+
+/// The commands the backend hasn't answered yet, oldest first, with how
+/// long each has waited; for debugging a UI stuck on a reply.
+#[tauri::command]
+fn get_pending_requests(state: State<SafeBackendState>) -> Vec<PendingSummary> {
+    let pending = state.lock().unwrap().pending.clone();
+    let summaries = pending.lock().unwrap().summaries();
+    summaries
+}
+
+/// Give up on every unanswered command: each resolves with
+/// `BackendError::Abandoned` and replies arriving for them later are
+/// ignored.  The backend keeps running.  Returns how many were dropped.
+#[tauri::command]
+fn clear_pending_requests(state: State<SafeBackendState>) -> usize {
+    let pending = state.lock().unwrap().pending.clone();
+    let dropped = pending.lock().unwrap().abandon_all();
+    dropped
+}
+
+/// Stop the library scan in progress (`scan_library` or `add_folder`).
+/// The scan's own request then resolves with a summary of what it found
+/// so far, with `cancelled: true`, and `scan-cancelled` is emitted.  A
+/// cancelled rescan leaves the library as it was.  Returns the request id
+/// of the cancelled scan.
+#[tauri::command]
+async fn cancel_scan(state: State<'_, SafeBackendState>) -> Result<u64, BackendError> {
+    cancel_active_scan(&state).await
+}
+
+async fn cancel_active_scan(state: &SafeBackendState) -> Result<u64, BackendError> {
+    let request_id = state.lock().unwrap().active_scan.ok_or_else(|| BackendError::Rejected {
+        action: "cancel_scan".to_string(),
+        message: "No scan in progress".to_string(),
+    })?;
+    let params = serde_json::json!({ "request_id": request_id });
+    dispatch_command(state, "cancel_scan".to_string(), Some(params))
+        .await?
+        .into_data("cancel_scan")?;
+    Ok(request_id)
+}
+
+/// Write `action` to the backend and wait for its response.
+///
+/// Shared by `send_command` and the typed convenience commands.
+async fn dispatch_command(
+    state: &SafeBackendState,
+    action: String,
+    params: Option<serde_json::Value>,
+) -> Result<CommandResponse, BackendError> {
+    submit_command(state, action, params)?.wait(state).await
+}
+
+/// A command that has been written to the backend and awaits its response.
+struct PendingResponse {
+    request_id: u64,
+    action: String,
+    /// The table the request was registered in, identifying the process
+    /// it was sent to.
+    table: Arc<Mutex<PendingRequests>>,
+    rx: oneshot::Receiver<ResponseResult>,
+    metrics: Arc<BackendMetrics>,
+}
+
+/// Validate and write a command, registering it in the pending table.
+///
+/// The state lock is only held while writing; waiting for the response
+/// happens in `PendingResponse::wait` so concurrent commands don't block
+/// each other and `cancel_command` can get in.
+fn submit_command(
+    state: &SafeBackendState,
+    action: String,
+    params: Option<serde_json::Value>,
+) -> Result<PendingResponse, BackendError> {
+    let mut backend = state.lock().unwrap();
+    
+    if !backend.is_running() {
+        return Err(BackendError::NotRunning);
+    }
+    
+    // Check if the child process is still alive.  If it has exited
+    // (crashed, killed, etc.) clean up the stale state immediately
+    // so the frontend can restart it.
+    if let Some(ref mut child) = backend.process {
+        let gone = match child.try_wait() {
+            Ok(Some(_status)) => Some(BackendError::ProcessExited),
+            Ok(None) => None, // still running
+            Err(_e) => Some(BackendError::ProcessCheckFailed),
+        };
+        if let Some(e) = gone {
+            backend.clear_process();
+            return Err(e);
+        }
+    }
+
+    let params = if LONG_RUNNING_ACTIONS.contains(&action.as_str()) {
+        excludes::with_excludes(params, &backend.scan_excludes)
+    } else {
+        params
+    };
+    // Reject malformed params here rather than letting them fail deep
+    // inside the Python player.
+    validation::validate_params(&action, params.as_ref())?;
+
+    let table = backend.pending.clone();
+    let (request_id, rx, joined) = match backend.deduplication.key(&action, params.as_ref()) {
+        Some(key) => table.lock().unwrap().register_shared(key),
+        None => {
+            let (request_id, rx) = table.lock().unwrap().register();
+            (request_id, rx, false)
+        }
+    };
+    if joined {
+        backend.metrics.command_deduplicated();
+        return Ok(PendingResponse {
+            request_id,
+            action,
+            table,
+            rx,
+            metrics: backend.metrics.clone(),
+        });
+    }
+    if LONG_RUNNING_ACTIONS.contains(&action.as_str()) {
+        backend.active_scan = Some(request_id);
+    }
+    if is_long_running(&action) {
+        backend.long_requests.insert(request_id);
+    }
+    let command = CommandRequest {
+        action: action.clone(),
+        params,
+        request_id: Some(request_id),
+    };
+
+    // Send command to backend.  If the write fails the Python process has
+    // most likely exited; `write_command` tears down the backend state so
+    // that every subsequent call returns "Backend not running" rather than
+    // retrying a dead pipe.
+    if let Err(e) = backend.write_command(&command) {
+        table.lock().unwrap().remove(request_id);
+        return Err(e);
+    }
+
+    Ok(PendingResponse {
+        request_id,
+        action,
+        table,
+        rx,
+        metrics: backend.metrics.clone(),
+    })
+}
+
+impl PendingResponse {
+    /// Wait for the backend's response to this request.
+    async fn wait(self, state: &SafeBackendState) -> Result<CommandResponse, BackendError> {
+        let timeout = response_timeout(&self.action);
+        self.wait_within(state, timeout).await
+    }
+
+    async fn wait_within(self, state: &SafeBackendState, timeout: Duration) -> Result<CommandResponse, BackendError> {
+        let outcome = tokio::time::timeout(timeout, self.rx).await;
+        {
+            let mut backend = state.lock().unwrap();
+            if backend.active_scan == Some(self.request_id) {
+                backend.active_scan = None;
+            }
+            backend.long_requests.remove(&self.request_id);
+        }
+
+        match outcome {
+            Ok(Ok(Ok(value))) => serde_json::from_value::<CommandResponse>(value.clone())
+                .map_err(|_| {
+                    self.metrics.parse_error();
+                    BackendError::InvalidResponse(value.to_string())
+                }),
+            Ok(Ok(Err(e))) => Err(e),
+            // The reader thread dropped the table: the process is gone.
+            Ok(Err(_closed)) => Err(BackendError::Disconnected),
+            Err(_elapsed) => {
+                // Kill the unresponsive process so the frontend's retry
+                // loop can start fresh instead of hanging forever.  Only
+                // touch the state if it still belongs to that process.
+                let mut backend = state.lock().unwrap();
+                let same_process = Arc::ptr_eq(&backend.pending, &self.table);
+                let busy = backend.busy_with_long_request();
+                if same_process && !busy {
+                    if let Some(mut child) = backend.process.take() {
+                        child.kill().ok();
+                        child.wait().ok();
+                    }
+                    backend.clear_process();
+                } else if same_process {
+                    // Queued behind a scan: slow, not hung.  The slot stays
+                    // to absorb the late reply.
+                    self.table.lock().unwrap().cancel(self.request_id);
+                }
+                self.metrics.timeout();
+                Err(BackendError::Timeout { secs: timeout.as_secs() })
+            }
+        }
+    }
+}
+
+/// How long the backend gets to exit on its own once stdin is closed.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// Gracefully shut down the backend process.
+///
+/// Closing stdin ends the Python stdio loop, which runs its own cleanup
+/// (stopping the player, closing audio) before exiting.  If the process
+/// is still alive after `grace` it is killed.  Returns whether a process
+/// was running.
+fn shutdown_backend(backend: &mut BackendState, grace: Duration) -> Result<bool, String> {
+    detach_backend(backend).finish(grace)
+}
+
+/// [`shutdown_backend`] for the managed state, waiting out the grace period
+/// without the lock so other commands aren't stalled behind the exit.
+fn shutdown_managed_backend(state: &SafeBackendState, grace: Duration) -> Result<bool, String> {
+    let exiting = detach_backend(&mut state.lock().unwrap());
+    exiting.finish(grace)
+}
+
+/// A backend cut loose from `BackendState`, still to be reaped.
+struct ExitingBackend {
+    child: Option<Child>,
+    was_mock: bool,
+}
+
+impl ExitingBackend {
+    /// Wait up to `grace` for the process to exit, then kill it.  Returns
+    /// whether a backend was running.
+    fn finish(self, grace: Duration) -> Result<bool, String> {
+        let Some(mut child) = self.child else {
+            return Ok(self.was_mock);
+        };
+
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline {
+            match child.try_wait() {
+                Ok(Some(_status)) => return Ok(true),
+                Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+                Err(_e) => break,
+            }
+        }
+
+        child.kill().map_err(|e| format!("Failed to kill backend: {e}"))?;
+        child.wait().ok();
+        Ok(true)
+    }
+}
+
+/// Close the backend's stdin, reset the per-backend state and take the
+/// process out for [`ExitingBackend::finish`].
+fn detach_backend(backend: &mut BackendState) -> ExitingBackend {
+    backend.stdin = None;
+    backend.pending.lock().unwrap().fail_all(BackendError::Disconnected);
+    let was_mock = backend.mock.take().is_some();
+    backend.script = None;
+    backend.interpreter = None;
+    backend.version = None;
+    backend.supported_formats = None;
+    backend.capabilities = None;
+    backend.supported_languages = None;
+    backend.available_themes = None;
+    backend.active_scan = None;
+    backend.long_requests.clear();
+    backend.watchdog_stop = None;
+    backend.now_playing_stop = None;
+    backend.preloaded = None;
+    backend.sleep_timer_stop = None;
+    backend.stop_after_current = false;
+    backend.previewing = false;
+    backend.preview_stop = None;
+    backend.test_tone = false;
+    backend.test_tone_stop = None;
+    backend.snapshot_stop = None;
+    backend.resource_check_stop = None;
+    backend.audio_stats = None;
+    backend.input_device = None;
+    backend.volume.reset();
+    backend.mute.clear();
+    *backend.playback.lock().unwrap() = None;
+    ExitingBackend { child: backend.process.take(), was_mock }
+}
+
+/// Check the whole IPC chain (interpreter resolution, spawn, handshake,
+/// ping and a `get_state` round trip) and report each step's outcome and
+/// timing.
+///
+/// A running backend is only queried, never restarted, and while it is
+/// busy with a long command its probe steps are skipped.  Otherwise a
+/// throwaway backend is launched with the last start options, outside the
+/// app's own state, and shut down again afterwards.
+#[tauri::command]
+async fn run_diagnostics(
+    state: State<'_, SafeBackendState>,
+    app_handle: tauri::AppHandle,
+) -> Result<DiagnosticsReport, BackendError> {
+    let mut report = DiagnosticsReport::default();
+    let (running, options, interpreter) = {
+        let backend = state.lock().unwrap();
+        (backend.is_running(), backend.last_launch.clone(), backend.interpreter.clone())
+    };
+
+    let probe = if running {
+        report.used_running_backend = true;
+        let interpreter = interpreter.unwrap_or_else(|| "the bundled backend".to_string());
+        report.record("resolve_interpreter", Instant::now(), Ok(format!("In use: {interpreter}")));
+        report.skip("spawn", "Backend already running");
+        state.inner().clone()
+    } else {
+        let probe = SafeBackendState::default();
+        let started = Instant::now();
+        match prepare_launch(&app_handle, &options) {
+            Ok(launch) => {
+                report.record("resolve_interpreter", started, Ok(launch_description(&launch)));
+                let started = Instant::now();
+                let spawned = finish_launch(&mut probe.lock().unwrap(), &app_handle, options, launch);
+                report.record("spawn", started, spawned.map_err(|e| e.to_string()));
+            }
+            Err(e) => {
+                report.record("resolve_interpreter", started, Err(e.to_string()));
+                report.skip("spawn", diagnostics::AFTER_FAILURE);
+            }
+        }
+        probe
+    };
+
+    // Probes queue behind a scan and would only time out.
+    let busy = running && probe.lock().unwrap().busy_with_long_request();
+    for (step, action) in [("handshake", "handshake"), ("ping", "ping"), ("round_trip", "get_state")] {
+        if busy {
+            report.skip(step, diagnostics::WHILE_BUSY);
+            continue;
+        }
+        if !report.ok() {
+            report.skip(step, diagnostics::AFTER_FAILURE);
+            continue;
+        }
+        let started = Instant::now();
+        let result = match action {
+            "handshake" => {
+                let params = serde_json::json!({ "protocol": PROTOCOL_VERSION });
+                dispatch_command(&probe, "handshake".to_string(), Some(params))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|response| match backend_protocol(response) {
+                        PROTOCOL_VERSION => Ok(format!("Protocol {PROTOCOL_VERSION}")),
+                        other => Err(format!("Backend speaks protocol {other}, expected {PROTOCOL_VERSION}")),
+                    })
+            }
+            action => {
+                dispatch_command(&probe, action.to_string(), None)
+                    .await
+                    .and_then(|response| response.into_data(action))
+                    .map(|_| format!("{action} answered"))
+                    .map_err(|e| e.to_string())
+            }
+        };
+        report.record(step, started, result);
+    }
+
+    if !running {
+        shutdown_backend(&mut probe.lock().unwrap(), SHUTDOWN_GRACE).ok();
+    }
+    Ok(report)
+}
+
+/// What a `Launch` will run, for the diagnostics report.
+fn launch_description(launch: &Launch) -> String {
+    match launch {
+        Launch::Mock => "Mock backend".to_string(),
+        Launch::Process { cmd, .. } => {
+            let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy()).collect();
+            format!("{} {}", cmd.get_program().to_string_lossy(), args.join(" "))
+        }
+    }
+}
+
+/// Shut down every managed backend instance.
+///
+/// Called from the exit and signal handlers so the Python child is never
+/// orphaned (and left playing audio) when the app goes away without the
+/// frontend calling `stop_backend`.  A session capture still running is
+/// closed after the backend's last output.
+fn shutdown_all_backends(state: &SafeBackendState) {
+    let (exiting, capture) = {
+        let mut backend = match state.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        backend.reconnect.cancel();
+        (detach_backend(&mut backend), backend.capture.clone())
+    };
+    exiting.finish(SHUTDOWN_GRACE).ok();
+    let stopped = capture.lock().unwrap().stop();
+    if let Err(e) = stopped {
+        tracing::warn!("{e}");
+    }
+}
+
+/// Stop the Python backend process
+#[tauri::command]
+fn stop_backend(state: State<SafeBackendState>) -> Result<String, String> {
+    let exiting = {
+        let mut backend = state.lock().unwrap();
+        backend.reconnect.cancel();
+        detach_backend(&mut backend)
+    };
+
+    if exiting.finish(SHUTDOWN_GRACE)? {
+        Ok("Backend stopped".to_string())
+    } else {
+        Err("Backend not running".to_string())
+    }
+}
+
+/// Restart a wedged backend: the graceful `stop_backend` path followed by
+/// `start_backend` with the same options (mode, `backend_script`, framing)
+/// unless the arguments override them.  Starts the backend if it wasn't
+/// running.  Emits `backend-restarting` before and `backend-ready` after,
+/// and returns the new process id (`None` for the mock).  A `backend_script`
+/// that doesn't exist fails before the running backend is stopped.
+#[tauri::command]
+async fn restart_backend(
+    state: State<'_, SafeBackendState>,
+    app_handle: tauri::AppHandle,
+    mock: Option<bool>,
+    backend_script: Option<PathBuf>,
+) -> Result<Option<u32>, BackendError> {
+    // A mistyped script shouldn't cost the running backend.
+    if let Some(script) = backend_script.as_ref().filter(|script| !script.exists()) {
+        return Err(BackendError::ScriptOverrideMissing(script.display().to_string()));
+    }
+    app_handle.emit_all("backend-restarting", ()).ok();
+
+    let (options, exiting) = {
+        let mut backend = state.lock().unwrap();
+        let mut options = backend.last_launch.clone();
+        options.mock = mock.unwrap_or(backend.mock.is_some());
+        if backend_script.is_some() {
+            options.backend_script = backend_script;
+        }
+        (options, detach_backend(&mut backend))
+    };
+    exiting.finish(SHUTDOWN_GRACE).map_err(BackendError::LaunchFailed)?;
+    start_and_handshake(&state, &app_handle, options).await?;
+
+    let pid = state.lock().unwrap().process.as_ref().map(Child::id);
+    app_handle
+        .emit_all("backend-ready", serde_json::json!({ "pid": pid }))
+        .ok();
+    Ok(pid)
+}
+
+/// Id of the backend instance this app manages.  There is only one for
+/// now; commands that act on every instance report results under it.
+const MAIN_INSTANCE: &str = "main";
+
+/// Every managed backend instance, by id.
+fn backend_instances(state: &SafeBackendState) -> Vec<(&'static str, SafeBackendState)> {
+    vec![(MAIN_INSTANCE, state.clone())]
+}
+
+/// Send `action` to every running instance, collecting each answer.  An
+/// instance that fails to answer is reported as an error response rather
+/// than failing the whole call; instances that aren't running are skipped.
+async fn send_to_all(state: &SafeBackendState, action: &str) -> Vec<(String, CommandResponse)> {
+    let mut results = Vec::new();
+    for (id, instance) in backend_instances(state) {
+        if !instance.lock().unwrap().is_running() {
+            continue;
+        }
+        let response = dispatch_command(&instance, action.to_string(), None)
+            .await
+            .unwrap_or_else(|e| CommandResponse {
+                status: "error".to_string(),
+                message: Some(e.to_string()),
+                data: None,
+            });
+        results.push((id.to_string(), response));
+    }
+    results
+}
+
+/// Send `pause` to every running backend instance; returns
+/// `[instance_id, response]` pairs.  There is only one instance,
+/// [`MAIN_INSTANCE`], so this is `pause` with its answer reported under
+/// that id, or nothing when the backend isn't running.
+#[tauri::command]
+async fn pause_all(state: State<'_, SafeBackendState>) -> Result<Vec<(String, CommandResponse)>, BackendError> {
+    Ok(send_to_all(&state, "pause").await)
+}
+
+/// Send `play` to every running backend instance, like [`pause_all`]; with
+/// the one instance this is `play`.
+#[tauri::command]
+async fn resume_all(state: State<'_, SafeBackendState>) -> Result<Vec<(String, CommandResponse)>, BackendError> {
+    Ok(send_to_all(&state, "play").await)
+}
+
+/// What `on_resume` reports, also emitted as `backend-resumed`.
+#[derive(Debug, Clone, Serialize)]
+struct ResumeReport {
+    audio_reopened: bool,
+    /// Why the audio device couldn't be reopened.
+    error: Option<String>,
+    /// Whether `on_suspend` paused playback, so the UI can offer to
+    /// continue.  Playback is not resumed automatically.
+    was_playing: bool,
+}
+
+/// Call before system sleep (e.g. from the frontend's `visibilitychange`
+/// or a platform power event): pauses playback if a song is playing, since
+/// the audio device is torn down while asleep.  Emits `backend-suspended`
+/// with `paused`; returns whether playback was paused.
+#[tauri::command]
+async fn on_suspend(state: State<'_, SafeBackendState>, app_handle: tauri::AppHandle) -> Result<bool, BackendError> {
+    let paused = suspend(&state).await?;
+    app_handle.emit_all("backend-suspended", serde_json::json!({ "paused": paused })).ok();
+    Ok(paused)
+}
+
+async fn suspend(state: &SafeBackendState) -> Result<bool, BackendError> {
+    if !state.lock().unwrap().is_running() {
+        return Ok(false);
+    }
+    let playing = fetch_playback_state(state).await?.state == "playing";
+    if playing {
+        dispatch_command(state, "pause".to_string(), None)
+            .await?
+            .into_data("pause")?;
+    }
+    state.lock().unwrap().paused_for_suspend = playing;
+    Ok(playing)
+}
+
+/// Call after waking from sleep: asks the backend to reopen its audio
+/// device and emits `backend-resumed` with the `ResumeReport`.
+#[tauri::command]
+async fn on_resume(state: State<'_, SafeBackendState>, app_handle: tauri::AppHandle) -> Result<ResumeReport, BackendError> {
+    let report = resume(&state).await;
+    app_handle.emit_all("backend-resumed", &report).ok();
+    Ok(report)
+}
+
+async fn resume(state: &SafeBackendState) -> ResumeReport {
+    let was_playing = std::mem::take(&mut state.lock().unwrap().paused_for_suspend);
+    let reopened = match dispatch_command(state, "reopen_audio".to_string(), None).await {
+        Ok(response) => response.into_data("reopen_audio").map(|_| ()),
+        Err(e) => Err(e),
+    };
+    ResumeReport {
+        audio_reopened: reopened.is_ok(),
+        error: reopened.err().map(|e| e.to_string()),
+        was_playing,
+    }
+}
+
+/// Turn automatic restarts of a backend that dies unexpectedly on or
+/// off.  Restarts back off exponentially from 500ms to 30s; after
+/// `max_retries` (default 5) failures in a row the app gives up and emits
+/// `backend-gave-up`.
+#[tauri::command]
+fn set_auto_restart(state: State<SafeBackendState>, enabled: bool, max_retries: Option<u32>) -> bool {
+    let max_retries = max_retries.unwrap_or(reconnect::DEFAULT_MAX_RETRIES);
+    state.lock().unwrap().reconnect.configure(enabled, max_retries);
+    enabled
+}
+
+/// Set the folders library scans skip, as glob patterns matched against a
+/// folder's full path or its name (e.g. `/proc`, `/mnt/nas*`,
+/// `node_modules`).  Saved to the config and sent with every
+/// `scan_library` and `add_folder` that doesn't give its own `excludes`.
+/// An empty list scans everything.  Returns the saved patterns.
+#[tauri::command]
+fn set_scan_excludes(
+    app_handle: tauri::AppHandle,
+    state: State<SafeBackendState>,
+    patterns: Vec<String>,
+) -> Result<Vec<String>, BackendError> {
+    for pattern in &patterns {
+        excludes::check_pattern(pattern).map_err(|reason| BackendError::InvalidParams {
+            action: "set_scan_excludes".to_string(),
+            field: "patterns".to_string(),
+            reason,
+        })?;
+    }
+    state.lock().unwrap().scan_excludes = patterns.clone();
+    if let Err(e) = save_config(&app_handle, |config| config.scan_excludes = patterns.clone()) {
+        tracing::warn!("Failed to save the scan excludes: {e}");
+    }
+    Ok(patterns)
+}
+
+/// The folders the library is built from, which `scan_library` scans:
+/// the list managed with `add_library_root` and `remove_library_root`, or
+/// until either is first used, the backend's own folder list.
+#[tauri::command]
+async fn get_library_roots(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+) -> Result<Vec<String>, BackendError> {
+    library_roots(&state, saved_library_roots(&app_handle)).await
+}
+
+/// Add the folder at `path`, which must exist, to the library roots.
+/// Saved to the config and sent to the backend; the next `scan_library`
+/// scans it.  A folder the scan excludes match is refused, since no scan
+/// would look inside it.  Emits `library-roots-changed` with the roots and
+/// returns them.
+#[tauri::command]
+async fn add_library_root(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    path: PathBuf,
+) -> Result<Vec<String>, BackendError> {
+    let invalid = |reason| BackendError::InvalidParams {
+        action: "add_library_root".to_string(),
+        field: "path".to_string(),
+        reason,
+    };
+    ingest::ensure_directory(&path).map_err(invalid)?;
+    let root = path.display().to_string();
+    let scan_excludes = AppConfig::path(&app_handle.config())
+        .map(|path| AppConfig::load(&path).scan_excludes)
+        .unwrap_or_default();
+    if let Some(pattern) = excludes::excluded_by(&scan_excludes, &root) {
+        return Err(invalid(format!("{root} matches the scan exclude {pattern}")));
+    }
+    update_library_roots(&app_handle, &state, |roots| {
+        roots::add(roots, root);
+        Ok(())
+    })
+    .await
+}
+
+/// Take `path` off the library roots; its songs leave the library on the
+/// next `scan_library`.  Emits `library-roots-changed` like
+/// `add_library_root` and returns the remaining roots.
+#[tauri::command]
+async fn remove_library_root(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    path: String,
+) -> Result<Vec<String>, BackendError> {
+    update_library_roots(&app_handle, &state, |roots| {
+        if roots::remove(roots, &path) {
+            return Ok(());
+        }
+        Err(BackendError::InvalidParams {
+            action: "remove_library_root".to_string(),
+            field: "path".to_string(),
+            reason: format!("{path} is not a library root"),
+        })
+    })
+    .await
+}
+
+fn saved_library_roots(app_handle: &tauri::AppHandle) -> Option<Vec<String>> {
+    AppConfig::path(&app_handle.config()).and_then(|path| AppConfig::load(&path).library_roots)
+}
+
+/// `saved` roots, or the backend's folder list when none were saved.
+async fn library_roots(state: &SafeBackendState, saved: Option<Vec<String>>) -> Result<Vec<String>, BackendError> {
+    if let Some(roots) = saved {
+        return Ok(roots);
+    }
+    let data = dispatch_command(state, "get_settings".to_string(), None)
+        .await?
+        .into_data("get_settings")?;
+    Vec::<String>::deserialize(&data["folder_list"]).map_err(|e| BackendError::InvalidResponse(e.to_string()))
+}
+
+/// Apply `change` to the library roots, then send them to the backend (if
+/// one is running; otherwise the next one started gets them), save them
+/// and emit `library-roots-changed`.
+async fn update_library_roots(
+    app_handle: &tauri::AppHandle,
+    state: &SafeBackendState,
+    change: impl FnOnce(&mut Vec<String>) -> Result<(), BackendError>,
+) -> Result<Vec<String>, BackendError> {
+    let mut roots = library_roots(state, saved_library_roots(app_handle)).await?;
+    change(&mut roots)?;
+    if state.lock().unwrap().is_running() {
+        send_library_roots(state, &roots).await?;
+    }
+    let saved = roots.clone();
+    if let Err(e) = save_config(app_handle, |config| config.library_roots = Some(saved)) {
+        tracing::warn!("Failed to save the library roots: {e}");
+    }
+    app_handle.emit_all("library-roots-changed", &roots).ok();
+    Ok(roots)
+}
+
+async fn send_library_roots(state: &SafeBackendState, roots: &[String]) -> Result<(), BackendError> {
+    dispatch_command(state, "set_library_roots".to_string(), Some(serde_json::json!({ "roots": roots })))
+        .await?
+        .into_data("set_library_roots")
+        .map(drop)
+}
+
+/// Turn the hung-backend watchdog on or off.  While on, each started
+/// backend is pinged every `interval_ms` (500 to 60000, default 5000); after
+/// `missed_pings` (1 to 10, default 3) unanswered pings in a row it is
+/// killed, `backend-unresponsive` is emitted and it is restarted like a
+/// crashed one.  Applies to the running backend straight away.  Returns
+/// the settings in effect.
+#[tauri::command]
+fn set_watchdog(
+    app_handle: tauri::AppHandle,
+    state: State<SafeBackendState>,
+    enabled: bool,
+    interval_ms: Option<u64>,
+    missed_pings: Option<u32>,
+) -> Result<WatchdogSettings, BackendError> {
+    let settings = WatchdogSettings::new(enabled, interval_ms, missed_pings).map_err(|(field, reason)| {
+        BackendError::InvalidParams {
+            action: "set_watchdog".to_string(),
+            field: field.to_string(),
+            reason,
+        }
+    })?;
+    let running = {
+        let mut backend = state.lock().unwrap();
+        backend.watchdog = settings;
+        backend.is_running()
+    };
+    if running {
+        start_watchdog(&state, &app_handle);
+    }
+    Ok(settings)
+}
+
+/// Set how many backend stderr lines per second trigger a
+/// `backend-stderr-burst` alert (default 100); 0 turns the alert off.
+#[tauri::command]
+fn set_stderr_burst_threshold(state: State<SafeBackendState>, lines_per_sec: u64) -> u64 {
+    state.lock().unwrap().stderr_burst_threshold.set(lines_per_sec);
+    lines_per_sec
+}
+
+/// Turn automatic advancing to the next playlist entry on or off.
+///
+/// From the first call on, the app rather than the backend advances: on
+/// `song_finished` it sends `next` and emits `playlist_advanced`.
+#[tauri::command]
+async fn set_auto_advance(state: State<'_, SafeBackendState>, enabled: bool) -> Result<bool, BackendError> {
+    set_backend_advance(&state, false).await?;
+    state.lock().unwrap().auto_advance.set(enabled);
+    Ok(enabled)
+}
+
+/// Turn shuffling of the upcoming songs on or off.  Turning it off
+/// restores the order they were queued in.  Returns the new setting.
+#[tauri::command]
+async fn set_shuffle(state: State<'_, SafeBackendState>, enabled: bool) -> Result<bool, BackendError> {
+    dispatch_command(&state, "set_shuffle".to_string(), Some(serde_json::json!({ "enabled": enabled })))
+        .await?
+        .into_data("set_shuffle")?;
+    state.lock().unwrap().shuffle = enabled;
+    Ok(enabled)
+}
+
+/// Choose what happens when a song ends: `off` (next song), `one` (replay
+/// it) or `all` (next song, wrapping to the start).  Applies to both
+/// host-driven and backend advancing, and is re-sent to a restarted
+/// backend.  Returns the new mode.
+#[tauri::command]
+async fn set_repeat_mode(state: State<'_, SafeBackendState>, mode: RepeatMode) -> Result<RepeatMode, BackendError> {
+    send_repeat_mode(&state, mode).await?;
+    state.lock().unwrap().auto_advance.set_repeat(mode);
+    Ok(mode)
+}
+
+async fn send_repeat_mode(state: &SafeBackendState, mode: RepeatMode) -> Result<(), BackendError> {
+    dispatch_command(state, "set_repeat_mode".to_string(), Some(serde_json::json!({ "mode": mode })))
+        .await?
+        .into_data("set_repeat_mode")
+        .map(drop)
+}
+
+/// Turn the backend's own advancing on or off; it is turned off while the
+/// app advances, so songs aren't skipped twice.
+async fn set_backend_advance(state: &SafeBackendState, enabled: bool) -> Result<(), BackendError> {
+    dispatch_command(
+        state,
+        "set_auto_advance".to_string(),
+        Some(serde_json::json!({ "enabled": enabled })),
+    )
+    .await?
+    .into_data("set_auto_advance")
+    .map(drop)
+}
+
+/// Configure which backend event types are coalesced before reaching the
+/// webview, and how often held events are flushed.  Pass an empty list to
+/// forward every event immediately.
+#[tauri::command]
+fn set_event_coalescing(
+    state: State<SafeBackendState>,
+    event_types: Vec<String>,
+    interval_ms: Option<u64>,
+) -> Result<CoalescingConfig, String> {
+    let interval = match interval_ms {
+        Some(0) => return Err("interval_ms must be greater than zero".to_string()),
+        Some(ms) => Duration::from_millis(ms),
+        None => coalesce::DEFAULT_COALESCE_INTERVAL,
+    };
+    let backend = state.lock().unwrap();
+    let mut coalescer = backend.coalescer.lock().unwrap();
+    coalescer.configure(event_types, interval);
+    Ok(coalescer.config())
+}
+
+/// Choose which read-only actions share the response of an identical
+/// request (same action and params) still in flight instead of sending a
+/// duplicate; by default `get_state`, `get_settings`, the library reads
+/// and other idempotent queries.  An empty list sends every request.
+/// Returns the new set.
+#[tauri::command]
+fn set_command_deduplication(state: State<SafeBackendState>, actions: Vec<String>) -> Vec<String> {
+    let mut backend = state.lock().unwrap();
+    backend.deduplication.set_actions(actions);
+    backend.deduplication.actions().to_vec()
+}
+
+/// IPC counters since the backend was last started.
+#[tauri::command]
+fn get_backend_metrics(state: State<SafeBackendState>) -> MetricsSnapshot {
+    state.lock().unwrap().metrics.snapshot()
+}
+
+/// Recent backend events, oldest first, with the raw line each came from.
+#[tauri::command]
+fn get_event_history(state: State<SafeBackendState>) -> Vec<HistoryEntry> {
+    let backend = state.lock().unwrap();
+    let history = backend.history.lock().unwrap();
+    history.entries()
+}
+
+/// Change how many events `get_event_history` keeps (default 500).
+#[tauri::command]
+fn set_event_history_capacity(state: State<SafeBackendState>, capacity: usize) -> usize {
+    let backend = state.lock().unwrap();
+    backend.history.lock().unwrap().set_capacity(capacity);
+    capacity
+}
+
+/// Restrict the calling window to `backend-event`s of the given types.
+/// Repeated calls add to the set.  Returns the window's subscription.
+#[tauri::command]
+fn subscribe_events(
+    window: tauri::Window,
+    state: State<SafeBackendState>,
+    event_types: Vec<String>,
+) -> Vec<String> {
+    let backend = state.lock().unwrap();
+    let mut subscriptions = backend.subscriptions.lock().unwrap();
+    subscriptions.subscribe(window.label(), event_types)
+}
+
+/// Every event type the backend emits.
+#[tauri::command]
+fn get_event_types() -> Vec<&'static str> {
+    events::EVENT_TYPES.to_vec()
+}
+
+/// Remove event types from the calling window's subscription.  Without
+/// `event_types` the subscription is dropped and the window receives every
+/// event again.  Returns what is left.
+#[tauri::command]
+fn unsubscribe_events(
+    window: tauri::Window,
+    state: State<SafeBackendState>,
+    event_types: Option<Vec<String>>,
+) -> Vec<String> {
+    let backend = state.lock().unwrap();
+    let mut subscriptions = backend.subscriptions.lock().unwrap();
+    subscriptions.unsubscribe(window.label(), event_types)
+}
+
+/// Shut down the backend and exit when the process receives SIGINT or
+/// SIGTERM, e.g. from `kill` or Ctrl+C in the terminal running `tauri dev`.
+/// Tokio's handler only records the signal and wakes this task; the
+/// logging and shutdown happen here on the async runtime, where they are
+/// safe.
+#[cfg(unix)]
+fn install_signal_handlers(app_handle: tauri::AppHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tauri::async_runtime::spawn(async move {
+        let (mut sigint, mut sigterm) = match (signal(SignalKind::interrupt()), signal(SignalKind::terminate())) {
+            (Ok(sigint), Ok(sigterm)) => (sigint, sigterm),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::error!("Failed to install signal handlers: {e}");
+                return;
+            }
+        };
+        let received = tokio::select! {
+            _ = sigint.recv() => "SIGINT",
+            _ = sigterm.recv() => "SIGTERM",
+        };
+        tracing::info!("Received {received}; shutting down the backend");
+        shutdown_all_backends(app_handle.state::<SafeBackendState>().inner());
+        app_handle.exit(0);
+    });
+}
+
+/// Turn the Linux DMA-BUF renderer workaround on or off.  WebKitGTK only
+/// reads the setting at startup, so it is saved to the config file and
+/// takes effect after `relaunch_app`.  Returns the saved value.
+#[tauri::command]
+fn set_renderer_workaround(app_handle: tauri::AppHandle, enabled: bool) -> Result<bool, String> {
+    save_config(&app_handle, |config| config.renderer_workaround = enabled)?;
+    Ok(enabled)
+}
+
+/// Show or hide the system tray icon.  The tray is put up at launch, so
+/// the setting is saved to the config and takes effect after
+/// `relaunch_app`.  Returns the saved value.
+#[tauri::command]
+fn set_tray_icon(app_handle: tauri::AppHandle, enabled: bool) -> Result<bool, String> {
+    save_config(&app_handle, |config| config.tray_icon = enabled)?;
+    Ok(enabled)
+}
+
+/// Set the buffer capacity, in bytes, that backend stdout is read through
+/// (default 64 KiB).  Larger buffers take fewer reads for the huge single
+/// line a big `get_library` response arrives as.  Clamped to 8 KiB–16 MiB,
+/// saved to the config and applied to the next backend started.  Returns
+/// the saved value.
+#[tauri::command]
+fn set_reader_buffer_size(app_handle: tauri::AppHandle, bytes: usize) -> Result<usize, String> {
+    let bytes = framing::reader_capacity(bytes);
+    save_config(&app_handle, |config| config.reader_buffer_bytes = bytes)?;
+    Ok(bytes)
+}
+
+/// The backend process's resident memory (`rss_bytes`) and CPU use
+/// (`cpu_percent` of one core since the last call), read from the OS.
+/// Emits `resource-warning` for limits newly exceeded, as the periodic
+/// checks do.  Fails with `resources_unavailable` for the mock backend and
+/// where the OS won't say.
+#[tauri::command]
+fn get_process_resources(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+) -> Result<ProcessResources, BackendError> {
+    check_resources(&state, &app_handle)
+}
+
+/// Set the memory (in MB) and CPU (in percent of one core) use above which
+/// `resource-warning` is emitted; `None` removes a limit.  While any limit
+/// is set the backend is checked every 10 seconds.  Saved to the config.
+#[tauri::command]
+fn set_resource_limits(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    max_rss_mb: Option<u64>,
+    max_cpu_percent: Option<f64>,
+) -> Result<ResourceLimits, BackendError> {
+    let limits = ResourceLimits { max_rss_mb, max_cpu_percent };
+    limits.check().map_err(|(field, reason)| BackendError::InvalidParams {
+        action: "set_resource_limits".to_string(),
+        field: field.to_string(),
+        reason,
+    })?;
+    apply_resource_limits(&state, &app_handle, limits);
+    if let Err(e) = save_config(&app_handle, |config| config.resource_limits = limits) {
+        tracing::warn!("Failed to save the resource limits: {e}");
+    }
+    Ok(limits)
+}
+
+fn apply_resource_limits(state: &SafeBackendState, app_handle: &tauri::AppHandle, limits: ResourceLimits) {
+    let running = {
+        let mut backend = state.lock().unwrap();
+        backend.resource_limits = limits;
+        backend.is_running()
+    };
+    if running {
+        start_resource_checks(state, app_handle);
+    }
+}
+
+/// Apply `change` to the saved config and to [`LoadedConfig`], so that
+/// `reload_config` only sees edits made outside the app.
+fn save_config(app_handle: &tauri::AppHandle, change: impl FnOnce(&mut AppConfig)) -> Result<AppConfig, String> {
+    let before = AppConfig::path(&app_handle.config()).map(|path| AppConfig::load(&path)).unwrap_or_default();
+    let saved = AppConfig::update(&app_handle.config(), change)?;
+    app_handle.state::<LoadedConfig>().absorb(&saved, &reload::changed_fields(&before, &saved));
+    Ok(saved)
+}
+
+/// Re-read `config.json` after it was edited outside the app and apply the
+/// settings that changed: hotkeys, always-on-top and scan excludes in the
+/// app, the playback settings by sending them to the backend (or at its
+/// next start if none runs).  The rest are listed in `restart_required`
+/// with whether the backend or the whole app needs restarting.  A config
+/// `validate_config` finds problems with is not applied at all.
+#[tauri::command]
+async fn reload_config(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+) -> Result<ConfigReload, String> {
+    let path = AppConfig::path(&app_handle.config()).ok_or("No app config directory on this system")?;
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let json: serde_json::Value =
+        serde_json::from_str(&text).map_err(|e| format!("Failed to parse {}: {e}", path.display()))?;
+    let (config, problems) = AppConfig::validate(&json);
+    let Some(config) = config.filter(|_| problems.is_empty()) else {
+        let problems: Vec<_> = problems.iter().map(ConfigProblem::to_string).collect();
+        return Err(format!("Config not reloaded: {}", problems.join("; ")));
+    };
+    let loaded = app_handle.state::<LoadedConfig>();
+    let previous = loaded.get();
+    let mut reload = ConfigReload::default();
+    let mut settled = Vec::new();
+    for field in reload::changed_fields(&previous, &config) {
+        match reload::restart_needed(&field, &config) {
+            Some(restart) => reload.restart_required.push(RestartRequired { field: field.clone(), restart }),
+            None => match apply_config_change(&app_handle, &state, &field, &previous, &config).await {
+                Ok(()) => reload.applied.push(field.clone()),
+                Err(message) => {
+                    reload.failed.push(ConfigProblem::new(field, message));
+                    continue;
+                }
+            },
+        }
+        settled.push(field);
+    }
+    loaded.absorb(&config, &settled);
+    Ok(reload)
+}
+
+/// Put the live setting `field` of `config` into effect, `previous` being
+/// what the app ran with until now.
+async fn apply_config_change(
+    app_handle: &tauri::AppHandle,
+    state: &SafeBackendState,
+    field: &str,
+    previous: &AppConfig,
+    config: &AppConfig,
+) -> Result<(), String> {
+    let sent = match field {
+        "av_sync_offset_ms" => send_av_sync_offset(state, config.av_sync_offset_ms).await,
+        "normalization" => send_normalization(state, config.normalization).await.map(drop),
+        "crossfade_ms" => send_crossfade(state, config.crossfade_ms).await.map(drop),
+        "lyrics_language" => match &config.lyrics_language {
+            Some(lang) => send_lyrics_language(state, lang).await,
+            None => Ok(()),
+        },
+        "theme" => match &config.theme {
+            Some(name) => send_theme(state, name).await,
+            None => Ok(()),
+        },
+        "library_roots" => match &config.library_roots {
+            Some(roots) => send_library_roots(state, roots).await,
+            None => Ok(()),
+        },
+        "scan_excludes" => {
+            state.lock().unwrap().scan_excludes = config.scan_excludes.clone();
+            Ok(())
+        }
+        "resource_limits" => {
+            apply_resource_limits(state, app_handle, config.resource_limits);
+            Ok(())
+        }
+        "hotkeys" => return rebind_hotkeys(app_handle, &previous.hotkeys, &config.hotkeys),
+        "always_on_top" => {
+            for (label, window) in app_handle.windows() {
+                let on_top = config.always_on_top.get(&label) == Some(&true);
+                window.set_always_on_top(on_top).map_err(|e| format!("{label} window: {e}"))?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    };
+    match sent {
+        // A backend started later reads the setting from the config.
+        Ok(()) | Err(BackendError::NotRunning) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Swap the global shortcuts in `previous` for those in `hotkeys`.
+fn rebind_hotkeys(
+    app_handle: &tauri::AppHandle,
+    previous: &BTreeMap<HotkeyAction, String>,
+    hotkeys: &BTreeMap<HotkeyAction, String>,
+) -> Result<(), String> {
+    let mut shortcuts = app_handle.global_shortcut_manager();
+    for accelerator in previous.values() {
+        shortcuts.unregister(accelerator).ok();
+    }
+    let failed: Vec<_> = hotkeys
+        .iter()
+        .filter_map(|(action, accelerator)| bind_hotkey(app_handle, *action, accelerator).err())
+        .collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(failed.join("; "))
+    }
+}
+
+/// Check `config`, the contents a `config.json` would have, without
+/// applying it; returns its problems, empty when it is fine to save.
+#[tauri::command]
+fn validate_config(config: serde_json::Value) -> Vec<ConfigProblem> {
+    AppConfig::validate(&config).1
+}
+
+/// Replace the saved config with `config`, unless `validate_config` finds
+/// problems, in which case nothing is written and they are the error.
+/// Settings read at startup apply the next time the app or the backend
+/// starts, as after editing the file.
+#[tauri::command]
+fn update_config(app_handle: tauri::AppHandle, config: serde_json::Value) -> Result<AppConfig, String> {
+    let (config, problems) = AppConfig::validate(&config);
+    let Some(config) = config.filter(|_| problems.is_empty()) else {
+        let problems: Vec<_> = problems.iter().map(ConfigProblem::to_string).collect();
+        return Err(format!("Config not saved: {}", problems.join("; ")));
+    };
+    let path = AppConfig::path(&app_handle.config()).ok_or("No app config directory on this system")?;
+    config.save(&path)?;
+    Ok(config)
+}
+
+/// Record everything the backend sends (events, responses and stderr
+/// lines) to `path` as NDJSON, for attaching to a bug report.  The file is
+/// truncated; a capture already running is finished first.  Capturing
+/// carries on across backend restarts until `stop_session_capture`.
+#[tauri::command]
+fn start_session_capture(state: State<SafeBackendState>, path: PathBuf) -> Result<PathBuf, String> {
+    let capture = state.lock().unwrap().capture.clone();
+    capture.lock().unwrap().start(&path)?;
+    Ok(path)
+}
+
+/// Flush and close the session capture; returns its path and how many
+/// lines it holds, or `None` if no capture was running.
+#[tauri::command]
+fn stop_session_capture(state: State<SafeBackendState>) -> Result<Option<CaptureSummary>, String> {
+    let capture = state.lock().unwrap().capture.clone();
+    let mut capture = capture.lock().unwrap();
+    capture.stop()
+}
+
+/// Reveal the app's log directory in the file manager, creating it if
+/// needed.  The path is returned even if no file manager could be opened.
+#[tauri::command]
+fn open_log_dir(app_handle: tauri::AppHandle) -> Result<RevealedDir, String> {
+    let dir = tauri::api::path::app_log_dir(&app_handle.config());
+    support::reveal("log", dir, support::open_in_file_manager)
+}
+
+/// Reveal the app's config directory (where `config.json` lives) in the
+/// file manager, like `open_log_dir`.
+#[tauri::command]
+fn open_config_dir(app_handle: tauri::AppHandle) -> Result<RevealedDir, String> {
+    let dir = tauri::api::path::app_config_dir(&app_handle.config());
+    support::reveal("config", dir, support::open_in_file_manager)
+}
+
+/// Bind `action` to the global shortcut `accelerator` (e.g.
+/// `"CmdOrCtrl+Shift+Space"`), replacing its previous binding, and save it
+/// to the config.  The old binding is only dropped once the new one is in
+/// place, so a rejected accelerator leaves the action bound as it was.
+#[tauri::command]
+fn register_hotkey(
+    app_handle: tauri::AppHandle,
+    action: HotkeyAction,
+    accelerator: String,
+) -> Result<String, String> {
+    let previous = AppConfig::path(&app_handle.config())
+        .map(|path| AppConfig::load(&path))
+        .unwrap_or_default()
+        .hotkeys
+        .remove(&action);
+    if previous.as_deref() == Some(accelerator.as_str()) {
+        return Ok(accelerator);
+    }
+    bind_hotkey(&app_handle, action, &accelerator)?;
+    if let Some(previous) = previous {
+        app_handle.global_shortcut_manager().unregister(&previous).ok();
+    }
+    save_config(&app_handle, |config| {
+        config.hotkeys.insert(action, accelerator.clone());
+    })?;
+    Ok(accelerator)
+}
+
+/// Remove the global shortcut for `action`, also from the config.
+#[tauri::command]
+fn unregister_hotkey(app_handle: tauri::AppHandle, action: HotkeyAction) -> Result<(), String> {
+    let mut removed = None;
+    save_config(&app_handle, |config| removed = config.hotkeys.remove(&action))?;
+    if let Some(accelerator) = removed {
+        app_handle
+            .global_shortcut_manager()
+            .unregister(&accelerator)
+            .map_err(|e| format!("Failed to unregister {accelerator}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Register one global shortcut sending `action` to the backend.
+fn bind_hotkey(app_handle: &tauri::AppHandle, action: HotkeyAction, accelerator: &str) -> Result<(), String> {
+    let handle = app_handle.clone();
+    app_handle
+        .global_shortcut_manager()
+        .register(accelerator, move || run_hotkey(&handle, action))
+        .map_err(|e| format!("Failed to register {accelerator}: {e}"))
+}
+
+/// Run a hotkey or tray menu `action` in the background, logging a
+/// failure since there is no caller to tell.
+fn run_hotkey(app_handle: &tauri::AppHandle, action: HotkeyAction) {
+    let state = app_handle.state::<SafeBackendState>().inner().clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = hotkey_command(&state, action).await {
+            tracing::warn!("Hotkey {action:?} failed: {e}");
+        }
+    });
+}
+
+/// Send the backend action for `action`, cutting a preview short as the
+/// same command from the UI would.
+async fn hotkey_command(state: &SafeBackendState, action: HotkeyAction) -> Result<(), BackendError> {
+    let playback_state = {
+        let backend = state.lock().unwrap();
+        let playback = backend.playback.lock().unwrap();
+        playback.as_ref().map(|p| p.state.clone())
+    };
+    let backend_action = action.backend_action(playback_state.as_deref());
+    take_over_from_preview(state, backend_action);
+    dispatch_command(state, backend_action.to_string(), None).await?.into_data(backend_action).map(drop)
+}
+
+/// Put up the tray icon, showing the app name until a song plays.
+#[cfg(feature = "system-tray")]
+fn show_tray(app_handle: &tauri::AppHandle, icon: Option<tauri::Icon>) -> tauri::Result<()> {
+    let mut tray = tauri::SystemTray::new().with_id(tray::TRAY_ID).with_menu(tray::menu());
+    if let Some(icon) = icon {
+        tray = tray.with_icon(icon);
+    }
+    let tooltip = tray::tooltip(None);
+    app_handle.state::<tray::Tooltip>().replace(tooltip.clone());
+    tray.with_tooltip(&tooltip).build(app_handle)?;
+    Ok(())
+}
+
+/// Tray menu items run the hotkey actions or quit.  Quitting closes every
+/// window, so the app exits through `RunEvent::Exit` and shuts the backend
+/// down; `AppHandle::exit` would end the process without it.
+#[cfg(feature = "system-tray")]
+fn handle_tray_event(app_handle: &tauri::AppHandle, event: tauri::SystemTrayEvent) {
+    if let tauri::SystemTrayEvent::MenuItemClick { id, .. } = event {
+        match tray::TrayItem::from_id(&id) {
+            Some(tray::TrayItem::Playback(action)) => run_hotkey(app_handle, action),
+            Some(tray::TrayItem::Quit) => {
+                for window in app_handle.windows().values() {
+                    window.close().ok();
+                }
+            }
+            None => tracing::warn!("Unknown tray menu item {id}"),
+        }
+    }
+}
+
+/// Name the song in the tray tooltip, if the tray is up and the song or
+/// its state has changed.
+#[cfg(feature = "system-tray")]
+fn update_tray_tooltip(app_handle: &tauri::AppHandle, playback: &Mutex<Option<PlaybackState>>) {
+    let Some(tray) = app_handle.tray_handle_by_id(tray::TRAY_ID) else {
+        return;
+    };
+    let tooltip = tray::tooltip(playback.lock().unwrap().as_ref());
+    if let Some(tooltip) = app_handle.state::<tray::Tooltip>().replace(tooltip) {
+        if let Err(e) = tray.set_tooltip(&tooltip) {
+            tracing::warn!("Failed to set the tray tooltip: {e}");
+        }
+    }
+}
+
+/// Params for the backend's `add_folder` action.
+fn add_folder_params(path: &Path, recursive: bool) -> serde_json::Value {
+    serde_json::json!({ "folder": path.display().to_string(), "recursive": recursive })
+}
+
+/// Add folder `path` to the library and scan it, descending into
+/// subfolders unless `recursive` is false.  The backend emits
+/// `scan_progress` events while scanning and `scan_complete` with a summary
+/// when done.  Returns the folder scanned.
+#[tauri::command]
+async fn add_folder(
+    state: State<'_, SafeBackendState>,
+    path: PathBuf,
+    recursive: Option<bool>,
+) -> Result<String, BackendError> {
+    ingest::ensure_directory(&path).map_err(|reason| BackendError::InvalidParams {
+        action: "add_folder".to_string(),
+        field: "path".to_string(),
+        reason,
+    })?;
+    let params = add_folder_params(&path, recursive.unwrap_or(true));
+    dispatch_command(&state, "add_folder".to_string(), Some(params))
+        .await?
+        .into_data("add_folder")?;
+    Ok(path.display().to_string())
+}
+
+/// Queue files dropped onto a window, then emit `files_added` with how
+/// many were accepted and why the others were rejected.  Dropped folders
+/// are scanned recursively with `add_folder`.
+fn ingest_dropped(app_handle: &tauri::AppHandle, paths: Vec<PathBuf>) {
+    let state = app_handle.state::<SafeBackendState>().inner().clone();
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let extensions = supported_formats(&state).await;
+        let ingest::Dropped { files, folders, mut summary } = ingest::partition_dropped(paths, &extensions);
+        for path in files {
+            let params = serde_json::json!({ "filepath": path.display().to_string() });
+            let added = dispatch_command(&state, "add_to_playlist".to_string(), Some(params))
+                .await
+                .and_then(|response| response.into_data("add_to_playlist"));
+            match added {
+                Ok(_) => summary.accept(),
+                Err(e) => summary.reject(&path, e.to_string()),
+            }
+        }
+        if !summary.is_empty() {
+            app_handle.emit_all("files_added", summary).ok();
+        }
+        for path in folders {
+            let params = add_folder_params(&path, true);
+            let scanned = dispatch_command(&state, "add_folder".to_string(), Some(params))
+                .await
+                .and_then(|response| response.into_data("add_folder"));
+            if let Err(e) = scanned {
+                tracing::warn!("Failed to scan dropped folder {}: {e}", path.display());
+            }
+        }
+    });
+}
+
+/// List the connected monitors, for picking the one the lyrics go on.
+/// Queried afresh on every call, so plugged-in screens show up.
+#[tauri::command]
+fn list_monitors(window: tauri::Window) -> Result<Vec<MonitorInfo>, String> {
+    monitor_list(&window)
+}
+
+fn monitor_list(window: &tauri::Window) -> Result<Vec<MonitorInfo>, String> {
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+    let primary = window.primary_monitor().map_err(|e| e.to_string())?;
+    Ok(display::describe_monitors(&monitors, primary.as_ref()))
+}
+
+/// The window showing the lyrics: the `lyrics` window when the frontend
+/// opened one, else the main window, which draws them itself.
+fn lyrics_window(app_handle: &tauri::AppHandle) -> Option<tauri::Window> {
+    app_handle
+        .get_window(display::LYRICS_WINDOW)
+        .or_else(|| app_handle.get_window(display::MAIN_WINDOW))
+}
+
+/// Move `window` to monitor `index`, leaving fullscreen first since most
+/// platforms won't move a fullscreen window.
+fn move_to_monitor(window: &tauri::Window, index: usize) -> Result<MonitorInfo, String> {
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+    let monitor = display::select_monitor(monitors, index)?;
+    window.set_fullscreen(false).map_err(|e| e.to_string())?;
+    window.set_position(*monitor.position()).map_err(|e| e.to_string())?;
+    Ok(display::find_monitor(&monitor_list(window)?, MonitorInfo::new(index, &monitor)))
+}
+
+/// Move the `lyrics` window, or the main window when there is none, to
+/// monitor `index` (from `list_monitors`), keeping it fullscreen if it
+/// was.  Returns that monitor.
+#[tauri::command]
+fn move_lyrics_to_monitor(app_handle: tauri::AppHandle, index: usize) -> Result<MonitorInfo, String> {
+    let window = lyrics_window(&app_handle)
+        .ok_or_else(|| BackendError::WindowMissing(display::MAIN_WINDOW).to_string())?;
+    let fullscreen = window.is_fullscreen().map_err(|e| e.to_string())?;
+    let monitor = move_to_monitor(&window, index)?;
+    if fullscreen {
+        window.set_fullscreen(true).map_err(|e| e.to_string())?;
+    }
+    Ok(monitor)
+}
+
+/// Toggle fullscreen for the lyrics display, first moving it to monitor
+/// `monitor_index` (from `list_monitors`) when given.
+///
+/// Acts on the `lyrics` window if there is one, else on the calling
+/// window.  Returns the monitor the window ends up on, so the backend can
+/// size its render target.
+#[tauri::command]
+fn set_lyrics_fullscreen(
+    app_handle: tauri::AppHandle,
+    window: tauri::Window,
+    enabled: bool,
+    monitor_index: Option<usize>,
+) -> Result<MonitorInfo, String> {
+    let window = app_handle.get_window(display::LYRICS_WINDOW).unwrap_or(window);
+    let monitor = match monitor_index {
+        Some(index) => move_to_monitor(&window, index)?,
+        None => {
+            let monitor = window
+                .current_monitor()
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "The window is not on any monitor".to_string())?;
+            display::find_monitor(&monitor_list(&window)?, MonitorInfo::new(0, &monitor))
+        }
+    };
+    window.set_fullscreen(enabled).map_err(|e| e.to_string())?;
+    Ok(monitor)
+}
+
+/// Keep window `window_label` (`lyrics`, or `main` by default) above all
+/// other windows, notifications included, or stop doing so.  Saved to the
+/// config and applied again whenever that window opens.  Fails with
+/// `window_missing` when the window isn't open.
+#[tauri::command]
+fn set_always_on_top(
+    app_handle: tauri::AppHandle,
+    window_label: Option<String>,
+    enabled: bool,
+) -> Result<bool, BackendError> {
+    let label = display::window_label(window_label.as_deref()).map_err(|reason| BackendError::InvalidParams {
+        action: "set_always_on_top".to_string(),
+        field: "window_label".to_string(),
+        reason,
+    })?;
+    let window = app_handle.get_window(label).ok_or(BackendError::WindowMissing(label))?;
+    window.set_always_on_top(enabled).map_err(|e| BackendError::Rejected {
+        action: "set_always_on_top".to_string(),
+        message: e.to_string(),
+    })?;
+    let saved = save_config(&app_handle, |config| {
+        config.always_on_top.insert(label.to_string(), enabled);
+    });
+    if let Err(e) = saved {
+        tracing::warn!("Failed to save always-on-top for the {label} window: {e}");
+    }
+    Ok(enabled)
+}
+
+/// Pin `window` above the others if the config says so; run as each
+/// window's page loads, covering a lyrics window opened later.
+fn restore_always_on_top(window: &tauri::Window) {
+    let config = AppConfig::path(&window.config()).map(|path| AppConfig::load(&path)).unwrap_or_default();
+    if config.always_on_top.get(window.label()) == Some(&true) {
+        if let Err(e) = window.set_always_on_top(true) {
+            tracing::warn!("Failed to keep the {} window on top: {e}", window.label());
+        }
+    }
+}
+
+/// Save the frame currently shown with the lyrics to `path` as a PNG and
+/// return the path, for sharing "now playing".
+///
+/// CDG graphics are drawn on the backend's render surface, so the backend
+/// writes the file; the webview itself can't be captured under Tauri 1.
+/// Fails with `window_missing` when neither the lyrics nor the main window
+/// is open.
+#[tauri::command]
+async fn capture_lyrics_frame(
+    app_handle: tauri::AppHandle,
+    state: State<'_, SafeBackendState>,
+    path: PathBuf,
+) -> Result<PathBuf, BackendError> {
+    if lyrics_window(&app_handle).is_none() {
+        return Err(BackendError::WindowMissing(display::MAIN_WINDOW));
+    }
+    capture_frame_to(&state, path).await
+}
+
+async fn capture_frame_to(state: &SafeBackendState, path: PathBuf) -> Result<PathBuf, BackendError> {
+    display::prepare_capture_path(&path).map_err(|reason| BackendError::InvalidParams {
+        action: "capture_lyrics_frame".to_string(),
+        field: "path".to_string(),
+        reason,
+    })?;
+    let params = serde_json::json!({ "path": path.display().to_string() });
+    dispatch_command(state, "capture_frame".to_string(), Some(params))
+        .await?
+        .into_data("capture_frame")?;
+    Ok(path)
+}
+
+/// Restart the whole app, e.g. to apply `set_renderer_workaround`.  The
+/// backend is shut down first since the exit handler doesn't run.
+#[tauri::command]
+fn relaunch_app(app_handle: tauri::AppHandle, state: State<SafeBackendState>) {
+    shutdown_all_backends(&state);
+    // The new process must not find this one in the instance file.
+    if let Some(path) = InstanceFile::path(&app_handle.config()) {
+        instance::remove_own(&path);
+    }
+    app_handle.restart();
+}
+
+/// Move the main window back to where it was last left, fitted onto the
+/// monitors connected now, and return the geometry applied; `None` when
+/// none was saved.  Done at launch.
+#[tauri::command]
+fn restore_window_geometry(app_handle: tauri::AppHandle) -> Result<Option<WindowGeometry>, String> {
+    let window = app_handle
+        .get_window(display::MAIN_WINDOW)
+        .ok_or_else(|| BackendError::WindowMissing(display::MAIN_WINDOW).to_string())?;
+    restore_geometry(&window)
+}
+
+fn restore_geometry(window: &tauri::Window) -> Result<Option<WindowGeometry>, String> {
+    let saved = AppConfig::path(&window.config()).and_then(|path| AppConfig::load(&path).window_geometry);
+    let Some(saved) = saved else {
+        return Ok(None);
+    };
+    let fitted = saved.fit(&monitor_list(window)?);
+    let failed = |e: tauri::Error| e.to_string();
+    window.set_size(tauri::PhysicalSize::new(fitted.width, fitted.height)).map_err(failed)?;
+    window.set_position(tauri::PhysicalPosition::new(fitted.x, fitted.y)).map_err(failed)?;
+    if fitted.maximized {
+        window.maximize().map_err(failed)?;
+    }
+    Ok(Some(fitted))
+}
+
+/// Save the main window's geometry once it has been still for
+/// [`geometry::SAVE_DELAY`].
+fn schedule_geometry_save(window: &tauri::Window) {
+    let ticket = window.state::<SaveDebounce>().ticket();
+    let window = window.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(geometry::SAVE_DELAY).await;
+        if !window.state::<SaveDebounce>().is_latest(ticket) {
+            return;
+        }
+        if let Err(e) = save_geometry(&window) {
+            tracing::warn!("Failed to save the window geometry: {e}");
+        }
+    });
+}
+
+fn save_geometry(window: &tauri::Window) -> Result<(), String> {
+    let failed = |e: tauri::Error| e.to_string();
+    // Minimized windows report made-up positions on some platforms, and
+    // fullscreen is a performance mode rather than a place to reopen in.
+    if window.is_minimized().map_err(failed)? || window.is_fullscreen().map_err(failed)? {
+        return Ok(());
+    }
+    let maximized = window.is_maximized().map_err(failed)?;
+    let position = window.outer_position().map_err(failed)?;
+    let size = window.inner_size().map_err(failed)?;
+    let monitor = window.current_monitor().map_err(failed)?.and_then(|monitor| monitor.name().cloned());
+    save_config(&window.app_handle(), |config| {
+        let geometry = match config.window_geometry.take() {
+            Some(saved) if maximized => WindowGeometry { maximized, ..saved },
+            _ => WindowGeometry {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                maximized,
+                monitor,
+            },
+        };
+        config.window_geometry = Some(geometry);
+    })?;
+    Ok(())
+}
+
+/// Bring the main window to the front, for a second launch of the app.
+fn focus_main_window(app_handle: &tauri::AppHandle) {
+    if let Some(window) = app_handle.get_window(display::MAIN_WINDOW) {
+        window.unminimize().ok();
+        window.show().ok();
+        window.set_focus().ok();
+    }
+}
+
+/// Record this app in the instance file at `path`, taking focus requests
+/// on `port`.  `false` means another instance is running and has been
+/// asked to come forward, so this launch should exit.
+fn claim_instance(path: &Path, port: u16) -> bool {
+    let own = InstanceFile { pid: std::process::id(), port };
+    for _ in 0..2 {
+        match instance::claim(path, &own) {
+            Ok(instance::Claim::Claimed) => return true,
+            Ok(instance::Claim::Running(other)) => match instance::request_focus(other.port) {
+                Ok(()) => {
+                    tracing::info!("PyKaraoke is already running (pid {}); focused its window", other.pid);
+                    return false;
+                }
+                Err(e) => {
+                    tracing::warn!("Ignoring stale instance file for pid {}: {e}", other.pid);
+                    std::fs::remove_file(path).ok();
+                }
+            },
+            Err(e) => {
+                tracing::warn!("{e}");
+                return true;
+            }
+        }
+    }
+    true
+}
+
+fn main() {
+    let context = tauri::generate_context!();
+    logging::init(tauri::api::path::app_log_dir(context.config()));
+    let instance_path = InstanceFile::path(context.config());
+    let focus_listener = match instance::bind_focus_listener() {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            tracing::warn!("Failed to listen for focus requests: {e}");
+            None
+        }
+    };
+    // A second launch hands over to the running app rather than starting
+    // another backend on the same audio device.
+    if let (Some(path), Some((_, port))) = (&instance_path, &focus_listener) {
+        if !claim_instance(path, *port) {
+            return;
+        }
+    }
+    let config = AppConfig::path(context.config())
+        .map(|path| AppConfig::load(&path))
+        .unwrap_or_default();
+
+    // Work around blank/empty WebKitGTK windows on Linux systems where
+    // GPU buffer allocation (GBM/DRM) is denied.  This tells WebKit to
+    // fall back to a shared-memory renderer instead of DMA-BUF, which
+    // avoids the "DRM_IOCTL_MODE_CREATE_DUMB failed: Permission denied"
+    // and "Failed to create GBM buffer" errors that cause a blank window.
+    #[cfg(target_os = "linux")]
+    {
+        // Can be turned off with `set_renderer_workaround` on systems that
+        // render fine (and faster) with DMA-BUF.
+        if std::env::var("WEBKIT_DISABLE_DMABUF_RENDERER").is_err() && config.renderer_workaround {
+            std::env::set_var("WEBKIT_DISABLE_DMABUF_RENDERER", "1");
+        }
+        // Suppress "Couldn't connect to accessibility bus" warnings from
+        // WebKitGTK / at-spi2 when the D-Bus a11y socket is unavailable.
+        if std::env::var("NO_AT_BRIDGE").is_err() {
+            std::env::set_var("NO_AT_BRIDGE", "1");
+        }
+    }
+
+    #[cfg(feature = "system-tray")]
+    let tray_icon = context.default_window_icon().cloned();
+    let builder = tauri::Builder::default();
+    #[cfg(feature = "system-tray")]
+    let builder = builder.manage(tray::Tooltip::default()).on_system_tray_event(handle_tray_event);
+    builder
+        .manage(Arc::new(Mutex::new(BackendState::default())))
+        .manage(SaveDebounce::default())
+        .manage(LoadedConfig::new(config.clone()))
+        .setup(move |app| {
+            #[cfg(unix)]
+            install_signal_handlers(app.handle());
+            if let Some((listener, _)) = focus_listener {
+                let handle = app.handle();
+                instance::serve_focus(listener, move || focus_main_window(&handle));
+            }
+            if let Some(window) = app.get_window(display::MAIN_WINDOW) {
+                if let Err(e) = restore_geometry(&window) {
+                    tracing::warn!("Failed to restore the window geometry: {e}");
+                }
+            }
+            // A shortcut another app already holds shouldn't stop startup.
+            for (action, accelerator) in &config.hotkeys {
+                if let Err(e) = bind_hotkey(&app.handle(), *action, accelerator) {
+                    tracing::warn!("{e}");
+                }
+            }
+            #[cfg(feature = "system-tray")]
+            if config.tray_icon {
+                if let Err(e) = show_tray(&app.handle(), tray_icon.clone()) {
+                    tracing::warn!("Failed to show the tray icon: {e}");
+                }
+            }
+            Ok(())
+        })
+        .on_page_load(|window, _| restore_always_on_top(&window))
+        .on_window_event(|event| match event.event() {
+            tauri::WindowEvent::FileDrop(tauri::FileDropEvent::Dropped(paths)) => {
+                ingest_dropped(&event.window().app_handle(), paths.clone());
+            }
+            tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_)
+                if event.window().label() == display::MAIN_WINDOW =>
+            {
+                schedule_geometry_save(event.window());
+            }
+            _ => {}
+        })
+        .invoke_handler(tauri::generate_handler![
+            start_backend,
+            start_and_wait_ready,
+            check_backend_integrity,
+            get_backend_manifest,
+            send_command,
+            cancel_command,
+            cancel_scan,
+            get_pending_requests,
+            clear_pending_requests,
+            benchmark_ipc,
+            get_playback_state,
+            seek,
+            move_playlist_item,
+            add_to_playlist,
+            play_next,
+            get_playlist_with_singers,
+            get_upcoming,
+            set_volume,
+            set_mute,
+            toggle_mute,
+            list_audio_devices,
+            set_audio_device,
+            list_input_devices,
+            set_input_device,
+            set_mic_monitoring,
+            reinit_audio,
+            get_audio_stats,
+            get_backend_version,
+            get_supported_formats,
+            get_backend_capabilities,
+            get_supported_languages,
+            set_lyrics_language,
+            get_lyrics_language,
+            get_available_themes,
+            set_theme,
+            set_backend_log_level,
+            stop_backend,
+            restart_backend,
+            set_auto_restart,
+            set_watchdog,
+            set_scan_excludes,
+            get_library_roots,
+            add_library_root,
+            remove_library_root,
+            pause_all,
+            resume_all,
+            on_suspend,
+            on_resume,
+            set_auto_advance,
+            set_shuffle,
+            set_repeat_mode,
+            set_event_coalescing,
+            set_command_deduplication,
+            set_stderr_burst_threshold,
+            subscribe_events,
+            get_event_types,
+            unsubscribe_events,
+            get_backend_metrics,
+            get_event_history,
+            set_event_history_capacity,
+            set_renderer_workaround,
+            set_tray_icon,
+            set_reader_buffer_size,
+            validate_config,
+            update_config,
+            reload_config,
+            get_process_resources,
+            set_resource_limits,
+            open_log_dir,
+            start_session_capture,
+            stop_session_capture,
+            run_diagnostics,
+            open_config_dir,
+            set_lyrics_fullscreen,
+            set_always_on_top,
+            list_monitors,
+            move_lyrics_to_monitor,
+            restore_window_geometry,
+            capture_lyrics_frame,
+            add_folder,
+            load_song,
+            probe_song,
+            get_waveform,
+            get_cache_stats,
+            clear_cache,
+            search_songs,
+            get_library,
+            get_play_history,
+            reset_play_history,
+            set_transpose,
+            set_tempo,
+            set_vocal_removal,
+            set_av_sync_offset,
+            set_normalization,
+            set_crossfade,
+            stop_with_fade,
+            preload_next,
+            set_sleep_timer,
+            preview_song,
+            play_test_tone,
+            cancel_sleep_timer,
+            start_now_playing_feed,
+            stop_now_playing_feed,
+            save_playlist,
+            load_playlist,
+            snapshot_state,
+            restore_state,
+            export_playlist_m3u,
+            import_playlist_m3u,
+            register_hotkey,
+            unregister_hotkey,
+            relaunch_app
+        ])
+        .build(context)
+        .expect("error while running tauri application")
+        .run(move |app_handle, event| {
+            // Closing the last window fires `ExitRequested` and then `Exit`;
+            // shutting down on `Exit` alone does it once.
+            if let tauri::RunEvent::Exit = event {
+                snapshot_on_exit(app_handle);
+                shutdown_all_backends(app_handle.state::<SafeBackendState>().inner());
+                if let Some(path) = &instance_path {
+                    instance::remove_own(path);
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // ── CommandRequest serialization ──────────────────────────────
+
+    #[test]
+    fn command_request_serializes_with_action_only() {
+        let req = CommandRequest {
+            action: "play".to_string(),
+            params: None,
+            request_id: None,
+        };
+        let j = serde_json::to_value(&req).unwrap();
+        assert_eq!(j["action"], "play");
+        assert!(j["params"].is_null());
+    }
+
+    #[test]
+    fn command_request_serializes_with_params() {
+        let req = CommandRequest {
+            action: "set_volume".to_string(),
+            params: Some(json!({"volume": 0.5})),
+            request_id: None,
+        };
+        let j = serde_json::to_value(&req).unwrap();
+        assert_eq!(j["action"], "set_volume");
+        assert_eq!(j["params"]["volume"], 0.5);
+    }
+
+    #[test]
+    fn command_request_roundtrips_through_json() {
+        let original = CommandRequest {
+            action: "search_songs".to_string(),
+            params: Some(json!({"query": "hello world"})),
+            request_id: Some(7),
+        };
+        let serialized = serde_json::to_string(&original).unwrap();
+        let deserialized: CommandRequest = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.action, "search_songs");
+        assert_eq!(deserialized.params.unwrap()["query"], "hello world");
+        assert_eq!(deserialized.request_id, Some(7));
+    }
+
+    #[test]
+    fn command_request_deserializes_without_params_key() {
+        let raw = r#"{"action":"stop"}"#;
+        let req: CommandRequest = serde_json::from_str(raw).unwrap();
+        assert_eq!(req.action, "stop");
+        assert!(req.params.is_none());
+    }
+
+    #[test]
+    fn command_request_deserializes_with_nested_params() {
+        let raw = r#"{"action":"play","params":{"playlist_index":3}}"#;
+        let req: CommandRequest = serde_json::from_str(raw).unwrap();
+        assert_eq!(req.action, "play");
+        assert_eq!(req.params.unwrap()["playlist_index"], 3);
+    }
+
+    // ── CommandResponse serialization ────────────────────────────
+
+    #[test]
+    fn command_response_ok_without_data() {
+        let resp = CommandResponse {
+            status: "ok".to_string(),
+            message: Some("done".to_string()),
+            data: None,
+        };
+        let j = serde_json::to_value(&resp).unwrap();
+        assert_eq!(j["status"], "ok");
+        assert_eq!(j["message"], "done");
+        assert!(j["data"].is_null());
+    }
+
+    #[test]
+    fn command_response_error_with_message() {
+        let resp = CommandResponse {
+            status: "error".to_string(),
+            message: Some("Backend not running".to_string()),
+            data: None,
+        };
+        let j = serde_json::to_value(&resp).unwrap();
+        assert_eq!(j["status"], "error");
+        assert_eq!(j["message"], "Backend not running");
+    }
+
+    #[test]
+    fn command_response_with_data_payload() {
+        let resp = CommandResponse {
+            status: "ok".to_string(),
+            message: None,
+            data: Some(json!({
+                "playback_state": "playing",
+                "volume": 0.75,
+                "playlist": []
+            })),
+        };
+        let j = serde_json::to_value(&resp).unwrap();
+        assert_eq!(j["data"]["playback_state"], "playing");
+        assert_eq!(j["data"]["volume"], 0.75);
+    }
+
+    #[test]
+    fn command_response_roundtrips_through_json() {
+        let original = CommandResponse {
+            status: "ok".to_string(),
+            message: Some("Command sent".to_string()),
+            data: Some(json!({"results": [{"title": "Test"}]})),
+        };
+        let serialized = serde_json::to_string(&original).unwrap();
+        let deserialized: CommandResponse = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.status, "ok");
+        assert_eq!(deserialized.data.unwrap()["results"][0]["title"], "Test");
+    }
+
+    #[test]
+    fn command_response_into_data_unwraps_ok_payload() {
+        let resp: CommandResponse =
+            serde_json::from_value(json!({"status": "ok", "data": {"volume": 0.5}})).unwrap();
+        assert_eq!(resp.into_data("get_state").unwrap()["volume"], 0.5);
+    }
+
+    #[test]
+    fn command_response_into_data_rejects_error_status() {
+        let resp: CommandResponse =
+            serde_json::from_value(json!({"status": "error", "message": "No song loaded"})).unwrap();
+        let err = resp.into_data("play").unwrap_err();
+        assert_eq!(err.kind(), "rejected");
+        assert_eq!(err.to_string(), "play failed: No song loaded");
+    }
+
+    // ── BackendState management ──────────────────────────────────
+
+    #[test]
+    fn backend_state_initializes_with_no_process() {
+        let state = BackendState {
+            process: None,
+            stdin: None,
+            ..Default::default()
+        };
+        assert!(state.process.is_none());
+        assert!(state.stdin.is_none());
+        assert!(state.pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn safe_backend_state_is_mutex_lockable() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            process: None,
+            stdin: None,
+            ..Default::default()
+        }));
+        let guard = state.lock().unwrap();
+        assert!(guard.process.is_none());
+    }
+
+    #[test]
+    fn safe_backend_state_clone_shares_data() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            process: None,
+            stdin: None,
+            ..Default::default()
+        }));
+        let clone = state.clone();
+        assert!(Arc::ptr_eq(&state, &clone));
+    }
+
+    // ── Shutdown ─────────────────────────────────────────────────
+
+    #[cfg(unix)]
+    fn spawn_piped(program: &str, args: &[&str]) -> BackendState {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take();
+        BackendState {
+            process: Some(child),
+            stdin,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn identical_reads_in_flight_share_one_request() {
+        let state: SafeBackendState = Arc::new(Mutex::new(spawn_piped("sleep", &["30"])));
+        let first = submit_command(&state, "get_state".to_string(), None).unwrap();
+        let second = submit_command(&state, "get_state".to_string(), None).unwrap();
+        let other = submit_command(&state, "play".to_string(), None).unwrap();
+        assert_eq!(first.request_id, second.request_id);
+        assert_ne!(other.request_id, first.request_id);
+
+        let metrics = state.lock().unwrap().metrics.snapshot();
+        assert_eq!((metrics.commands_sent, metrics.commands_deduplicated), (2, 1));
+
+        let table = state.lock().unwrap().pending.clone();
+        table.lock().unwrap().resolve(Some(first.request_id), serde_json::json!({ "status": "ok" }));
+        assert_eq!(first.wait(&state).await.unwrap().status, "ok");
+        assert_eq!(second.wait(&state).await.unwrap().status, "ok");
+        shutdown_backend(&mut state.lock().unwrap(), Duration::from_millis(10)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn missed_watchdog_pings_leave_the_process_running() {
+        let state: SafeBackendState = Arc::new(Mutex::new(spawn_piped("sleep", &["30"])));
+        assert!(!ping_within(&state, Duration::from_millis(20)).await.unwrap());
+        assert!(state.lock().unwrap().process.is_some());
+        let table = state.lock().unwrap().pending.clone();
+        let late = submit_command(&state, "get_state".to_string(), None).unwrap();
+        assert!(table.lock().unwrap().resolve(None, serde_json::json!({ "status": "pong" })), "late pong absorbed");
+        assert!(!table.lock().unwrap().is_empty(), "the next request still waits for its own reply");
+        table.lock().unwrap().remove(late.request_id);
+        shutdown_backend(&mut state.lock().unwrap(), Duration::from_millis(10)).unwrap();
+        assert!(ping_within(&state, Duration::from_millis(20)).await.is_err());
+    }
+
+    #[test]
+    fn shutdown_backend_without_process_reports_not_running() {
+        let mut state = BackendState {
+            process: None,
+            stdin: None,
+            ..Default::default()
+        };
+        assert!(!shutdown_backend(&mut state, Duration::from_millis(10)).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shutdown_backend_closes_stdin_for_clean_exit() {
+        // `cat` exits on EOF, like the Python stdio loop does.
+        let mut state = spawn_piped("cat", &[]);
+        let started = Instant::now();
+        assert!(shutdown_backend(&mut state, Duration::from_secs(5)).unwrap());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(state.process.is_none());
+        assert!(state.stdin.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shutdown_backend_kills_process_that_ignores_eof() {
+        let mut state = spawn_piped("sleep", &["30"]);
+        assert!(shutdown_backend(&mut state, Duration::from_millis(100)).unwrap());
+        assert!(state.process.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shutdown_waits_out_the_grace_period_without_the_lock() {
+        let state: SafeBackendState = Arc::new(Mutex::new(spawn_piped("sleep", &["30"])));
+        let stopping = {
+            let state = state.clone();
+            std::thread::spawn(move || shutdown_managed_backend(&state, Duration::from_millis(500)))
+        };
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(state.try_lock().is_ok_and(|backend| backend.process.is_none()));
+        assert!(stopping.join().unwrap().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exit_handler_shuts_down_managed_backend() {
+        let state: SafeBackendState = Arc::new(Mutex::new(spawn_piped("cat", &[])));
+        shutdown_all_backends(&state);
+        let guard = state.lock().unwrap();
+        assert!(guard.process.is_none());
+        assert!(guard.stdin.is_none());
+    }
+
+    #[test]
+    fn exit_handler_is_wired_into_run_loop() {
+        let source = include_str!("main.rs");
+        let builder_pos = source.find("tauri::Builder::default()").unwrap();
+        let run_loop = &source[builder_pos..source.find("#[cfg(test)]\nmod tests").unwrap()];
+        let exit_pos = run_loop.find("RunEvent::Exit =").unwrap();
+        assert!(
+            run_loop[exit_pos..exit_pos + 200].contains("shutdown_all_backends"),
+            "Exit must invoke the graceful shutdown routine"
+        );
+        assert!(
+            !run_loop.contains("RunEvent::ExitRequested {"),
+            "ExitRequested precedes Exit; shutting down on both would do it twice"
+        );
+    }
+
+    // ── JSON protocol contract tests ─────────────────────────────
+
+    #[test]
+    fn frontend_play_command_matches_expected_shape() {
+        // Mirrors the JSON the JS frontend sends via invoke('send_command', ...)
+        let raw = r#"{"action":"play","params":{"playlist_index":0}}"#;
+        let req: CommandRequest = serde_json::from_str(raw).unwrap();
+        assert_eq!(req.action, "play");
+    }
+
+    #[test]
+    fn frontend_search_command_matches_expected_shape() {
+        let raw = r#"{"action":"search_songs","params":{"query":"bohemian"}}"#;
+        let req: CommandRequest = serde_json::from_str(raw).unwrap();
+        assert_eq!(req.action, "search_songs");
+        assert_eq!(req.params.unwrap()["query"], "bohemian");
+    }
+
+    #[test]
+    fn frontend_volume_command_matches_expected_shape() {
+        let raw = r#"{"action":"set_volume","params":{"volume":0.42}}"#;
+        let req: CommandRequest = serde_json::from_str(raw).unwrap();
+        let vol = req.params.unwrap()["volume"].as_f64().unwrap();
+        assert!((vol - 0.42).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn all_known_actions_deserialize() {
+        let actions = vec![
+            "play", "pause", "stop", "next", "previous", "seek",
+            "set_volume", "load_song", "add_to_playlist",
+            "remove_from_playlist", "clear_playlist", "get_state",
+            "search_songs", "get_library", "scan_library",
+            "add_folder", "get_settings", "update_settings",
+        ];
+        for action in actions {
+            let raw = format!(r#"{{"action":"{action}"}}"#);
+            let req: CommandRequest = serde_json::from_str(&raw).unwrap();
+            assert_eq!(req.action, action);
+        }
+    }
+
+    #[test]
+    fn backend_event_envelope_shape() {
+        // The Rust backend wraps Python output in {"type":"event","event":...}
+        let raw = r#"{"type":"event","event":{"type":"state_changed","data":{}}}"#;
+        let parsed: serde_json::Value = serde_json::from_str(raw).unwrap();
+        assert_eq!(parsed["type"], "event");
+        assert_eq!(parsed["event"]["type"], "state_changed");
+    }
+
+    #[test]
+    fn backend_response_envelope_shape() {
+        let raw = r#"{"type":"response","response":{"status":"ok","message":"done"}}"#;
+        let parsed: serde_json::Value = serde_json::from_str(raw).unwrap();
+        assert_eq!(parsed["type"], "response");
+        assert_eq!(parsed["response"]["status"], "ok");
+    }
+
+    // ── Regression: empty-window workaround ──────────────────────
+
+    #[test]
+    fn dmabuf_env_var_is_set_on_linux() {
+        // Regression test for https://github.com/wilsonify/pykaraoke-ng/issues/...
+        // WebKitGTK renders a blank window when GBM buffer creation fails.
+        // The workaround sets WEBKIT_DISABLE_DMABUF_RENDERER=1.
+        //
+        // On non-Linux this test just verifies the source contains the
+        // workaround code (the #[cfg] gate means the code won't execute).
+        let source = include_str!("main.rs");
+        assert!(
+            source.contains("WEBKIT_DISABLE_DMABUF_RENDERER"),
+            "main.rs must set WEBKIT_DISABLE_DMABUF_RENDERER to prevent \
+             blank WebKitGTK windows on Linux"
+        );
+    }
+
+    #[test]
+    fn dmabuf_workaround_is_linux_gated() {
+        let source = include_str!("main.rs");
+        let dmabuf_pos = source.find("WEBKIT_DISABLE_DMABUF_RENDERER").unwrap();
+        let preceding = &source[dmabuf_pos.saturating_sub(300)..dmabuf_pos];
+        assert!(
+            preceding.contains(r#"target_os = "linux""#),
+            "WEBKIT_DISABLE_DMABUF_RENDERER should be behind #[cfg(target_os = \"linux\")]"
+        );
+    }
+
+    #[test]
+    fn dmabuf_workaround_checks_existing_value() {
+        let source = include_str!("main.rs");
+        // Between the cfg gate and the set_var, we should see is_err() check
+        let dmabuf_pos = source.find("WEBKIT_DISABLE_DMABUF_RENDERER").unwrap();
+        let region = &source[dmabuf_pos.saturating_sub(100)..dmabuf_pos + 100];
+        assert!(
+            region.contains("is_err()"),
+            "Should check is_err() before setting the env var, \
+             so user-provided values are respected"
+        );
+    }
+
+    #[test]
+    fn dmabuf_set_before_tauri_builder() {
+        let source = include_str!("main.rs");
+        let dmabuf_pos = source.find("WEBKIT_DISABLE_DMABUF_RENDERER").unwrap();
+        let builder_pos = source.find("tauri::Builder::default()").unwrap();
+        assert!(
+            dmabuf_pos < builder_pos,
+            "WEBKIT_DISABLE_DMABUF_RENDERER must be set before tauri::Builder"
+        );
+    }
+
+    // ── Regression: backend path resolution ──────────────────────
+
+    /// A scratch project tree laid out like a development checkout.
+    fn scratch_checkout(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("pykaraoke-ng-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&root).ok();
+        std::fs::create_dir_all(root.join("src/runtimes/tauri/src-tauri/target/debug")).unwrap();
+        root
+    }
+
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn dev_checkout_backend_is_found_from_resource_dir_and_cwd() {
+        let root = scratch_checkout("dev-checkout");
+        let legacy = root.join("src/pykaraoke/core/backend.py");
+        touch(&legacy);
+        let resource_dir = root.join("src/runtimes/tauri/src-tauri/target/debug");
+        let cwd = root.join("src/runtimes/tauri");
+
+        let from_resources = backend_script_candidates(&resource_dir, Path::new("/nonexistent"));
+        assert_eq!(pick_backend_script(&from_resources), Some(legacy.clone()));
+        let from_cwd = backend_script_candidates(Path::new("/nonexistent"), &cwd);
+        assert_eq!(pick_backend_script(&from_cwd), Some(legacy.clone()));
+
+        // The layered entry point wins over the legacy one.
+        let layered = root.join("src/pykaraoke/interfaces/backend_api.py");
+        touch(&layered);
+        let candidates = backend_script_candidates(&resource_dir, &cwd);
+        assert_eq!(pick_backend_script(&candidates), Some(layered));
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn bundled_backend_is_preferred_over_checkout() {
+        let root = scratch_checkout("bundled");
+        let resource_dir = root.join("src/runtimes/tauri/src-tauri/target/debug");
+        touch(&root.join("src/pykaraoke/core/backend.py"));
+        let bundled = resource_dir.join("backend.py");
+        touch(&bundled);
+
+        let candidates = backend_script_candidates(&resource_dir, &root);
+        assert_eq!(pick_backend_script(&candidates), Some(bundled));
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn no_existing_candidate_picks_nothing() {
+        let candidates = backend_script_candidates(Path::new("/nonexistent/a/b/c/d/e"), Path::new("/nonexistent"));
+        assert_eq!(candidates.len(), 9);
+        assert_eq!(pick_backend_script(&candidates), None);
+    }
+
+    #[test]
+    fn only_lyric_ticks_take_the_fast_path() {
+        assert!(is_lyric_tick(&json!({"type": "event", "event": {"type": "lyric_tick", "data": {"ms": 10}}})));
+        assert!(!is_lyric_tick(&json!({"type": "event", "event": {"type": "scan_progress"}})));
+        assert!(!is_lyric_tick(&json!({"type": "response", "event": {"type": "lyric_tick"}})));
+    }
+
+    #[test]
+    fn event_payload_carries_envelope_request_id() {
+        let envelope = json!({
+            "type": "event",
+            "event": {"type": "scan_progress", "data": {"done": 3}},
+            "request_id": 12
+        });
+        let event = event_payload(&envelope);
+        assert_eq!(event["request_id"], 12);
+        assert_eq!(event["data"]["done"], 3);
+
+        let untagged = event_payload(&json!({"type": "event", "event": {"type": "song_finished"}}));
+        assert!(untagged.get("request_id").is_none());
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn windows_prefers_py_launcher_then_python_exe() {
+        let programs: Vec<&str> = interpreter_candidates().iter().map(|(p, _)| *p).collect();
+        assert_eq!(programs, ["py", "python.exe", "python", "python3"]);
+        assert_eq!(interpreter_candidates()[0].1, ["-3"]);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn unix_prefers_python3() {
+        assert_eq!(interpreter_candidates()[0], ("python3", &[][..]));
+    }
+
+    #[test]
+    fn overlapping_starts_only_proceed_once() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState::default()));
+        let first = begin_start(&state);
+        assert!(matches!(first, StartAttempt::Proceed(_)));
+        assert!(state.lock().unwrap().starting);
+
+        // A second start while the first is still resolving must not spawn.
+        let overlapping = std::thread::spawn({
+            let state = state.clone();
+            move || matches!(begin_start(&state), StartAttempt::Starting)
+        });
+        assert!(overlapping.join().unwrap());
+
+        drop(first);
+        assert!(!state.lock().unwrap().starting);
+
+        // Once a backend is up, further starts report it as running.
+        state.lock().unwrap().mock = Some(mock::MockBackend::new(|_| {}));
+        assert!(matches!(begin_start(&state), StartAttempt::AlreadyRunning));
+    }
+
+    #[tokio::test]
+    async fn handshake_accepts_matching_protocol() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        handshake(&state).await.unwrap();
+        assert!(state.lock().unwrap().is_running());
+    }
+
+    #[tokio::test]
+    async fn pause_all_reports_per_instance_and_skips_stopped_ones() {
+        let stopped: SafeBackendState = Arc::new(Mutex::new(BackendState::default()));
+        assert!(send_to_all(&stopped, "pause").await.is_empty());
+
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let results = send_to_all(&state, "pause").await;
+        assert_eq!(results.len(), 1);
+        let (id, response) = &results[0];
+        assert_eq!(id, MAIN_INSTANCE);
+        assert_eq!(response.status, "error");
+        assert_eq!(response.message.as_deref(), Some("Not playing"));
+    }
+
+    #[tokio::test]
+    async fn load_song_checks_the_file_and_resets_adjustments() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let missing = Path::new("/no/such/song.cdg");
+        let err = load_song_at(&state, missing).await.unwrap_err();
+        assert_eq!(err.kind(), "file_not_found");
+        assert_eq!(state.lock().unwrap().metrics.snapshot().commands_sent, 0);
+
+        let file = std::env::temp_dir().join(format!("pykaraoke-load-{}.cdg", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        load_song_at(&state, &file).await.unwrap();
+        update_cached_playback(&state, |playback| playback.transpose = 3).await.unwrap();
+        let reloaded = load_song_at(&state, &file).await.unwrap();
+        std::fs::remove_file(&file).ok();
+        assert_eq!(reloaded.transpose, 0);
+        assert_eq!(reloaded.current_song.unwrap().filepath, file.display().to_string());
+    }
+
+    #[tokio::test]
+    async fn load_song_failures_carry_the_backend_reason() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let file = std::env::temp_dir().join(format!("pykaraoke-load-{}.txt", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        let err = load_song_at(&state, &file).await.unwrap_err();
+        std::fs::remove_file(&file).ok();
+        assert_eq!(err.kind(), "load_failed");
+        let j = serde_json::to_value(&err).unwrap();
+        assert_eq!(j["details"]["reason"], "unsupported_format");
+        assert_eq!(j["message"], format!("Cannot load {}: This file type can't be played", file.display()));
+    }
+
+    #[tokio::test]
+    async fn reinit_audio_waits_for_the_backend_to_confirm() {
+        let waiter: Arc<Mutex<Option<oneshot::Sender<AudioReady>>>> = Arc::default();
+        let confirm = waiter.clone();
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(move |event| confirm_audio_ready(&confirm, &event))),
+            audio_ready: waiter,
+            ..Default::default()
+        }));
+        let ready = reinit_audio_on(&state, Some("mock-hdmi".to_string())).await.unwrap();
+        assert_eq!(ready, AudioReady { device: Some("mock-hdmi".to_string()), position_ms: 0, resumed: false });
+        let err = reinit_audio_on(&state, Some("usb".to_string())).await.unwrap_err();
+        let message = "Invalid 'device_id' for reinit_audio: 'usb' is not one of the listed audio devices";
+        assert_eq!(err.to_string(), message);
+    }
+
+    #[tokio::test]
+    async fn theme_is_checked_against_the_backend_list() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let err = send_theme(&state, "neon").await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'name' for set_theme: must be one of classic, high_contrast");
+        send_theme(&state, "high_contrast").await.unwrap();
+        assert_eq!(state.lock().unwrap().metrics.snapshot().commands_sent, 2, "the list is fetched once");
+    }
+
+    #[tokio::test]
+    async fn lyrics_language_is_checked_against_the_backend_list() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let err = send_lyrics_language(&state, "xx").await.unwrap_err();
+        assert_eq!(err.kind(), "invalid_params");
+        send_lyrics_language(&state, "ja").await.unwrap();
+        assert_eq!(state.lock().unwrap().lyrics_language.as_deref(), Some("ja"));
+
+        // The list is only fetched once per backend.
+        assert_eq!(state.lock().unwrap().metrics.snapshot().commands_sent, 2);
+    }
+
+    #[tokio::test]
+    async fn active_scan_is_tracked_until_it_answers() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let scan = submit_command(&state, "scan_library".to_string(), None).unwrap();
+        assert_eq!(state.lock().unwrap().active_scan, Some(scan.request_id));
+        let summary = scan.wait(&state).await.unwrap().into_data("scan_library").unwrap();
+        assert_eq!(summary["songs_added"], 4);
+        assert_eq!(state.lock().unwrap().active_scan, None);
+
+        let err = cancel_active_scan(&state).await.unwrap_err();
+        assert_eq!(err.to_string(), "cancel_scan failed: No scan in progress");
+        assert_eq!(scan_summary_event("scan_cancelled"), Some("scan-cancelled"));
+        assert_eq!(scan_summary_event("scan_progress"), None);
+    }
+
+    #[test]
+    fn waveforms_get_longer_than_ordinary_commands() {
+        assert_eq!(response_timeout("waveform"), WAVEFORM_TIMEOUT);
+        assert!(is_long_running("waveform"), "commands queued behind it aren't a hung backend");
+        assert_eq!(response_timeout("get_state"), RESPONSE_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn commands_behind_a_scan_time_out_without_killing_the_backend() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let (table, metrics) = {
+            let backend = state.lock().unwrap();
+            (backend.pending.clone(), backend.metrics.clone())
+        };
+        let unanswered = |action: &str| {
+            let (request_id, rx) = table.lock().unwrap().register();
+            let action = action.to_string();
+            PendingResponse { request_id, action, table: table.clone(), rx, metrics: metrics.clone() }
+        };
+        let scan = unanswered("scan_library");
+        state.lock().unwrap().long_requests.insert(scan.request_id);
+        let poll = unanswered("get_state");
+        let poll_id = poll.request_id;
+
+        let err = poll.wait_within(&state, Duration::from_millis(10)).await.unwrap_err();
+        assert_eq!(err.kind(), "timeout");
+        assert!(state.lock().unwrap().is_running());
+        let waiting: Vec<_> = table.lock().unwrap().summaries().iter().map(|s| (s.request_id, s.waiting)).collect();
+        assert_eq!(waiting, [(scan.request_id, true), (poll_id, false)], "the late reply is absorbed by its own slot");
+
+        scan.wait_within(&state, Duration::from_millis(10)).await.unwrap_err();
+        assert!(table.lock().unwrap().is_empty(), "a hung scan tears the backend down");
+    }
+
+    #[tokio::test]
+    async fn probes_are_cached_until_the_file_changes() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let file = std::env::temp_dir().join(format!("pykaraoke-probe-{}.kar", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        let sent = |state: &SafeBackendState| state.lock().unwrap().metrics.snapshot().commands_sent;
+
+        let song = probe_song_at(&state, &file).await.unwrap();
+        assert_eq!(song.format, "kar");
+        assert_eq!(probe_song_at(&state, &file).await.unwrap(), song);
+        assert_eq!(sent(&state), 1);
+
+        let touched = std::time::SystemTime::now() + Duration::from_secs(60);
+        std::fs::File::options().write(true).open(&file).unwrap().set_modified(touched).unwrap();
+        probe_song_at(&state, &file).await.unwrap();
+        std::fs::remove_file(&file).ok();
+        assert_eq!(sent(&state), 2);
+    }
+
+    #[tokio::test]
+    async fn capabilities_are_cached_and_gate_vocal_removal() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let capabilities = backend_capabilities(&state).await.unwrap();
+        assert!(capabilities.supports("vocal_removal"));
+        assert_eq!(backend_capabilities(&state).await.unwrap(), capabilities);
+        assert_eq!(state.lock().unwrap().metrics.snapshot().commands_sent, 1, "capabilities are fetched once");
+
+        state.lock().unwrap().capabilities = Some(BackendCapabilities::default());
+        let err = apply_vocal_removal(&state, VocalRemoval { enabled: true, strength: 0.7 }).await.unwrap_err();
+        assert_eq!(err.kind(), "rejected");
+        assert_eq!(state.lock().unwrap().metrics.snapshot().commands_sent, 1, "nothing is sent to the backend");
+    }
+
+    #[tokio::test]
+    async fn crossfade_is_only_sent_to_backends_that_support_it() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let sent = |state: &SafeBackendState| state.lock().unwrap().metrics.snapshot().commands_sent;
+        assert_eq!(send_crossfade(&state, 3000).await.unwrap(), Crossfade { duration_ms: 3000, supported: true });
+        assert_eq!(sent(&state), 2);
+
+        state.lock().unwrap().capabilities = Some(BackendCapabilities::default());
+        assert_eq!(send_crossfade(&state, 3000).await.unwrap(), Crossfade { duration_ms: 3000, supported: false });
+        assert_eq!(sent(&state), 2, "nothing is sent to a backend that can't crossfade");
+
+        let params = serde_json::json!({ "duration_ms": 20_000 });
+        let err = dispatch_command(&state, "set_crossfade".to_string(), Some(params)).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'duration_ms' for set_crossfade: must be between 0 and 10000");
+    }
+
+    #[tokio::test]
+    async fn test_tone_pauses_and_resumes_the_song() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let tone = |channel: &str| serde_json::json!({ "frequency_hz": 440, "duration_ms": 500, "channel": channel });
+        let err = start_test_tone(&state, tone("center")).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'channel' for play_test_tone: must be one of left, right, both");
+
+        let params = serde_json::json!({ "filepath": "/mock/abba-dancing-queen.cdg" });
+        dispatch_command(&state, "load_song".to_string(), Some(params)).await.unwrap();
+        dispatch_command(&state, "play".to_string(), None).await.unwrap();
+        assert!(start_test_tone(&state, tone("left")).await.unwrap(), "the playing song is paused");
+        assert_eq!(fetch_playback_state(&state).await.unwrap().state, "paused");
+        assert_eq!(start_test_tone(&state, tone("right")).await.unwrap_err().kind(), "rejected");
+
+        assert_eq!(end_test_tone(&state, true).await, Ok(true));
+        assert_eq!(fetch_playback_state(&state).await.unwrap().state, "playing");
+        assert!(!state.lock().unwrap().test_tone);
+
+        let (tx, mut stop) = oneshot::channel::<()>();
+        state.lock().unwrap().test_tone_stop = Some(tx);
+        detach_backend(&mut state.lock().unwrap()).finish(Duration::ZERO).unwrap();
+        assert!(matches!(stop.try_recv(), Err(oneshot::error::TryRecvError::Closed)), "the tone's task is told");
+    }
+
+    #[tokio::test]
+    async fn input_device_is_checked_and_tracked_for_mic_monitoring() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let err = apply_input_device(&state, "mock-hdmi".to_string()).await.unwrap_err();
+        assert_eq!(err.kind(), "invalid_params");
+        let err = apply_mic_monitoring(&state, true, 2.5).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'gain' for set_mic_monitoring: must be between 0 and 2");
+
+        let monitoring = apply_mic_monitoring(&state, true, 1.5).await.unwrap();
+        assert_eq!(monitoring, MicMonitoring { enabled: true, gain: 1.5, device: Some("mock-mic".to_string()) });
+        assert_eq!(apply_input_device(&state, "mock-usb-mic".to_string()).await.unwrap(), "mock-usb-mic");
+        assert_eq!(state.lock().unwrap().input_device.as_deref(), Some("mock-usb-mic"));
+        assert_eq!(apply_mic_monitoring(&state, false, 1.0).await.unwrap().device.as_deref(), Some("mock-usb-mic"));
+
+        state.lock().unwrap().capabilities = Some(BackendCapabilities::default());
+        assert_eq!(apply_mic_monitoring(&state, true, 1.0).await.unwrap_err().kind(), "rejected");
+    }
+
+    #[tokio::test]
+    async fn vocal_removal_is_checked_and_reports_when_inactive() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let settings = VocalRemoval { enabled: true, strength: 0.7 };
+        let err = apply_vocal_removal(&state, VocalRemoval { strength: 1.5, ..settings }).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'strength' for set_vocal_removal: must be between 0 and 1");
+
+        let songs = [("/mock/neil-diamond-sweet-caroline.mid", true), ("/mock/abba-dancing-queen.cdg", false)];
+        for (path, active) in songs {
+            let params = serde_json::json!({ "filepath": path });
+            dispatch_command(&state, "load_song".to_string(), Some(params)).await.unwrap();
+            let applied = apply_vocal_removal(&state, settings).await.unwrap();
+            assert_eq!((applied.active, applied.note.is_some()), (active, !active), "{path}");
+            assert_eq!(fetch_playback_state(&state).await.unwrap().vocal_removal, settings);
+        }
+    }
+
+    #[tokio::test]
+    async fn library_roots_fall_back_to_the_backend_folder_list() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        assert_eq!(library_roots(&state, None).await.unwrap(), ["/mock"]);
+        let saved = vec!["/mock".to_string(), "/mnt/karaoke".to_string()];
+        assert_eq!(library_roots(&state, Some(saved.clone())).await.unwrap(), saved);
+
+        send_library_roots(&state, &saved).await.unwrap();
+        assert_eq!(library_roots(&state, None).await.unwrap(), saved, "the backend has the saved roots");
+    }
+
+    #[tokio::test]
+    async fn waveforms_are_cached_per_bucket_count_or_explained() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let dir = std::env::temp_dir();
+        let cdg = dir.join(format!("pykaraoke-waveform-{}.cdg", std::process::id()));
+        let kar = cdg.with_extension("kar");
+        std::fs::write(&cdg, b"").unwrap();
+        std::fs::write(&kar, b"").unwrap();
+        let sent = |state: &SafeBackendState| state.lock().unwrap().metrics.snapshot().commands_sent;
+
+        let waveform = waveform_at(&state, &cdg, 8).await.unwrap();
+        assert_eq!(waveform.peaks.len(), 8);
+        assert_eq!(waveform_at(&state, &cdg, 8).await.unwrap(), waveform);
+        assert_eq!(sent(&state), 1);
+        assert_eq!(waveform_at(&state, &cdg, 16).await.unwrap().peaks.len(), 16);
+        assert_eq!(sent(&state), 2);
+
+        let midi = waveform_at(&state, &kar, 8).await.unwrap();
+        assert!(midi.peaks.is_empty());
+        assert_eq!(midi.note.as_deref(), Some("No waveform: MIDI songs have no audio to draw"));
+        let err = waveform_at(&state, &cdg, 0).await.unwrap_err();
+        std::fs::remove_file(&cdg).ok();
+        std::fs::remove_file(&kar).ok();
+        assert_eq!(err.kind(), "invalid_params");
+    }
+
+    #[tokio::test]
+    async fn session_capture_survives_a_backend_restart() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let path = std::env::temp_dir().join(format!("pykaraoke-capture-restart-{}.ndjson", std::process::id()));
+        let capture = state.lock().unwrap().capture.clone();
+        capture.lock().unwrap().start(&path).unwrap();
+        dispatch_command(&state, "ping".to_string(), None).await.unwrap();
+
+        shutdown_backend(&mut state.lock().unwrap(), Duration::ZERO).unwrap();
+        state.lock().unwrap().mock = Some(mock::MockBackend::new(|_| {}));
+        dispatch_command(&state, "version".to_string(), None).await.unwrap();
+
+        let summary = capture.lock().unwrap().stop().unwrap().unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(summary.lines, 2);
+    }
+
+    #[tokio::test]
+    async fn restored_session_skips_moved_songs_and_resumes() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let kept = std::env::temp_dir().join(format!("pykaraoke-session-{}.cdg", std::process::id()));
+        std::fs::write(&kept, b"").unwrap();
+        let song = |path: &str| SongInfo { filepath: path.to_string(), ..Default::default() };
+        let snapshot = SessionSnapshot {
+            songs: vec![song("/moved/away.cdg"), song(&kept.display().to_string())],
+            playlist_index: Some(1),
+            position_ms: 30_000,
+            volume: 0.4,
+            repeat: RepeatMode::One,
+            ..Default::default()
+        };
+
+        let report = restore_session(&state, &snapshot).await.unwrap();
+        assert_eq!((report.restored, report.resumed_at), (1, Some(0)));
+        assert_eq!(report.skipped, ["/moved/away.cdg"]);
+
+        let resumed = take_snapshot(&state).await.unwrap();
+        std::fs::remove_file(&kept).ok();
+        assert_eq!(resumed.songs.len(), 1);
+        assert_eq!((resumed.playlist_index, resumed.position_ms, resumed.playing), (Some(0), 30_000, false));
+        assert_eq!((resumed.volume, resumed.repeat), (0.4, RepeatMode::One));
+    }
+
+    #[tokio::test]
+    async fn cleared_caches_report_what_they_held() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let file = std::env::temp_dir().join(format!("pykaraoke-cache-stats-{}.cdg", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        waveform_at(&state, &file, 100).await.unwrap();
+        std::fs::remove_file(&file).ok();
+
+        let stats = cache_stats(&state, CacheKind::Waveform, None);
+        assert_eq!(stats.entries, 1);
+        assert!(stats.bytes >= 400, "100 peaks of 4 bytes: {stats:?}");
+        assert_eq!(empty_cache(&state, CacheKind::Waveform, None).unwrap(), stats);
+        assert_eq!(cache_stats(&state, CacheKind::Waveform, None).entries, 0);
+        assert_eq!(cache_stats(&state, CacheKind::Disk, None).bytes, 0);
+    }
+
+    #[tokio::test]
+    async fn ipc_benchmark_bypasses_deduplication() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let result = run_ipc_benchmark(&state, 50).await.unwrap();
+        assert_eq!((result.sent, result.received), (50, 50));
+        assert_eq!(state.lock().unwrap().metrics.snapshot().commands_sent, 50);
+        let err = run_ipc_benchmark(&state, 0).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'count' for benchmark_ipc: must be between 1 and 10000");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn ipc_benchmark_leaves_the_state_unlocked_while_writing() {
+        let state: SafeBackendState = Arc::new(Mutex::new(spawn_piped("sleep", &["30"])));
+        let benchmark = tokio::spawn({
+            let state = state.clone();
+            async move { run_ipc_benchmark(&state, MAX_BENCHMARK_PINGS).await }
+        });
+        // `sleep` never reads, so the pings fill the pipe and the write blocks.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        {
+            let mut backend = state.try_lock().expect("the state lock is free while the pipe is full");
+            let child = backend.process.as_mut().unwrap();
+            child.kill().unwrap();
+            child.wait().unwrap();
+        }
+        assert!(matches!(benchmark.await.unwrap(), Err(BackendError::PipeBroken { .. })));
+    }
+
+    #[tokio::test]
+    async fn normalization_target_is_range_checked() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let settings = Normalization { enabled: true, target_lufs: -23.0 };
+        let applied = send_normalization(&state, settings).await.unwrap();
+        assert_eq!(applied, AppliedNormalization { settings, has_gain_metadata: false, gain_db: None });
+        let err = send_normalization(&state, Normalization { target_lufs: 0.0, ..settings }).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'target_lufs' for set_normalization: must be between -30 and -5");
+    }
+
+    #[tokio::test]
+    async fn av_sync_offset_is_range_checked_and_cached() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        send_av_sync_offset(&state, -250).await.unwrap();
+        let err = send_av_sync_offset(&state, 501).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'offset_ms' for set_av_sync_offset: must be between -500 and 500");
+        assert_eq!(state.lock().unwrap().av_sync_offset_ms, -250);
+    }
+
+    #[tokio::test]
+    async fn unmuting_restores_the_volume_before_muting() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let params = serde_json::json!({ "volume": 0.8 });
+        dispatch_command(&state, "set_volume".to_string(), Some(params)).await.unwrap();
+
+        assert_eq!(apply_mute(&state, true).await.unwrap(), MuteState { muted: true, volume: 0.0 });
+        assert_eq!(apply_mute(&state, true).await.unwrap().volume, 0.0);
+        assert_eq!(fetch_playback_state(&state).await.unwrap().volume, 0.0);
+        assert_eq!(apply_mute(&state, false).await.unwrap(), MuteState { muted: false, volume: 0.8 });
+        assert_eq!(fetch_playback_state(&state).await.unwrap().volume, 0.8);
+
+        apply_mute(&state, true).await.unwrap();
+        assert_eq!(debounce_volume(&state, 0.0).await.unwrap(), 0.0);
+        assert!(!state.lock().unwrap().mute.is_muted(), "choosing 0 by hand unmutes too");
+    }
+
+    #[tokio::test]
+    async fn now_playing_feed_emits_until_stopped() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let (stop, rx) = oneshot::channel();
+        let ticks = Mutex::new(0);
+        let feed = now_playing_feed(&state, Duration::from_millis(10), rx, |p| {
+            assert_eq!(p.state, "idle");
+            *ticks.lock().unwrap() += 1;
+        });
+        let stopper = async {
+            tokio::time::sleep(Duration::from_millis(45)).await;
+            drop(stop);
+        };
+        tokio::join!(feed, stopper);
+        assert!(*ticks.lock().unwrap() >= 2);
+    }
+
+    #[tokio::test]
+    async fn stop_with_fade_fades_or_stops_immediately() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let params = serde_json::json!({ "filepath": "/mock/abba-dancing-queen.cdg" });
+        dispatch_command(&state, "add_to_playlist".to_string(), Some(params)).await.unwrap();
+        dispatch_command(&state, "play".to_string(), None).await.unwrap();
+
+        let fading = Mutex::new(Vec::new());
+        let stop = fade_and_stop(&state, 100, |f| fading.lock().unwrap().push(f)).await.unwrap();
+        assert_eq!(stop, FadeStop { faded: true, note: None });
+        assert_eq!(*fading.lock().unwrap(), [true, false]);
+        assert_eq!(fetch_playback_state(&state).await.unwrap().state, "stopped");
+
+        let stop = fade_and_stop(&state, 100, |_| {}).await.unwrap();
+        assert_eq!(stop.note.as_deref(), Some("Stopped without fading: Not playing"));
+        let err = fade_and_stop(&state, 20_000, |_| {}).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'duration_ms' for fade_out: must be between 100 and 10000");
+        assert!(!state.lock().unwrap().fading);
+    }
+
+    #[tokio::test]
+    async fn preloading_defaults_to_the_next_entry_and_is_sent_once() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        for path in ["/mock/abba-dancing-queen.cdg", "/mock/queen-bohemian-rhapsody.cdg"] {
+            let params = serde_json::json!({ "filepath": path });
+            dispatch_command(&state, "add_to_playlist".to_string(), Some(params)).await.unwrap();
+        }
+        let err = preload_song(&state, None).await.unwrap_err();
+        assert_eq!(err.kind(), "invalid_params", "nothing is playing yet");
+
+        dispatch_command(&state, "play".to_string(), None).await.unwrap();
+        let preload = preload_song(&state, None).await.unwrap();
+        assert_eq!((preload.playlist_index, preload.already_preloaded), (1, false));
+        let sent = state.lock().unwrap().metrics.snapshot().commands_sent;
+        assert!(preload_song(&state, Some(1)).await.unwrap().already_preloaded);
+        assert_eq!(state.lock().unwrap().metrics.snapshot().commands_sent, sent);
+
+        let err = preload_song(&state, Some(5)).await.unwrap_err();
+        assert_eq!(err.to_string(), "preload failed: Invalid playlist index");
+    }
+
+    #[tokio::test]
+    async fn sleep_timers_fade_out_unless_cancelled() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let params = serde_json::json!({ "filepath": "/mock/abba-dancing-queen.cdg" });
+        dispatch_command(&state, "add_to_playlist".to_string(), Some(params)).await.unwrap();
+        dispatch_command(&state, "play".to_string(), None).await.unwrap();
+
+        let (tx, rx) = oneshot::channel();
+        drop(tx);
+        let slept = sleep_then_stop(&state, Duration::from_millis(10), 100, rx, || panic!("cancelled"), |_| {});
+        assert!(!slept.await.unwrap());
+        assert_eq!(fetch_playback_state(&state).await.unwrap().state, "playing");
+
+        let (tx, rx) = oneshot::channel();
+        state.lock().unwrap().sleep_timer_stop = Some(tx);
+        let fired = AtomicBool::new(false);
+        let on_fire = || fired.store(true, Ordering::SeqCst);
+        let slept = sleep_then_stop(&state, Duration::from_millis(10), 100, rx, on_fire, |_| {});
+        assert!(slept.await.unwrap());
+        assert!(fired.load(Ordering::SeqCst));
+        assert_eq!(fetch_playback_state(&state).await.unwrap().state, "stopped");
+        assert!(!clear_sleep_timer(&state).await.unwrap(), "a fired timer is no longer set");
+    }
+
+    #[tokio::test]
+    async fn previews_put_the_previous_song_back() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let queen = serde_json::json!({ "filepath": "/mock/queen-bohemian-rhapsody.cdg" });
+        dispatch_command(&state, "add_to_playlist".to_string(), Some(queen)).await.unwrap();
+        dispatch_command(&state, "play".to_string(), None).await.unwrap();
+        let seek = serde_json::json!({ "position_ms": 42_000 });
+        dispatch_command(&state, "seek".to_string(), Some(seek)).await.unwrap();
+
+        let preview = Path::new("/mock/abba-dancing-queen.cdg");
+        let prior = start_preview(&state, preview, 30_000).await.unwrap();
+        let during = fetch_playback_state(&state).await.unwrap();
+        assert_eq!(during.current_song.unwrap().filepath, "/mock/abba-dancing-queen.cdg");
+        assert_eq!((during.position_ms, during.playlist_index), (30_000, Some(0)));
+        let err = start_preview(&state, preview, 0).await.unwrap_err();
+        assert_eq!(err.to_string(), "preview_song failed: A preview is already playing");
 
-    #[test]
-    fn command_request_serializes_with_params() {
-        let req = CommandRequest {
-            action: "set_volume".to_string(),
-            params: Some(json!({"volume": 0.5})),
-        };
-        let j = serde_json::to_value(&req).unwrap();
-        assert_eq!(j["action"], "set_volume");
-        assert_eq!(j["params"]["volume"], 0.5);
+        end_preview(&state, &prior).await.unwrap();
+        let after = fetch_playback_state(&state).await.unwrap();
+        assert_eq!(after.current_song.unwrap().filepath, "/mock/queen-bohemian-rhapsody.cdg");
+        assert_eq!((after.state.as_str(), after.position_ms, after.playlist_index), ("playing", 42_000, Some(0)));
+        assert!(!state.lock().unwrap().previewing);
     }
 
-    #[test]
-    fn command_request_roundtrips_through_json() {
-        let original = CommandRequest {
-            action: "search_songs".to_string(),
-            params: Some(json!({"query": "hello world"})),
-        };
-        let serialized = serde_json::to_string(&original).unwrap();
-        let deserialized: CommandRequest = serde_json::from_str(&serialized).unwrap();
-        assert_eq!(deserialized.action, "search_songs");
-        assert_eq!(deserialized.params.unwrap()["query"], "hello world");
+    #[tokio::test]
+    async fn transport_commands_cut_a_preview_short() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        start_preview(&state, Path::new("/mock/abba-dancing-queen.cdg"), 0).await.unwrap();
+        let (tx, mut stop) = oneshot::channel::<()>();
+        state.lock().unwrap().preview_stop = Some(tx);
+        take_over_from_preview(&state, "get_state");
+        assert!(matches!(stop.try_recv(), Err(oneshot::error::TryRecvError::Empty)));
+        take_over_from_preview(&state, "next");
+        assert!(matches!(stop.try_recv(), Err(oneshot::error::TryRecvError::Closed)));
+
+        let (tx, mut stop) = oneshot::channel::<()>();
+        state.lock().unwrap().preview_stop = Some(tx);
+        hotkey_command(&state, HotkeyAction::PlayPause).await.unwrap();
+        assert!(
+            matches!(stop.try_recv(), Err(oneshot::error::TryRecvError::Closed)),
+            "hotkeys and the tray take over too"
+        );
+
+        abandon_preview(&state).await.unwrap();
+        let after = fetch_playback_state(&state).await.unwrap();
+        assert_eq!(after.current_song.unwrap().filepath, "/mock/abba-dancing-queen.cdg", "left as it was");
+        assert!(!state.lock().unwrap().previewing);
     }
 
-    #[test]
-    fn command_request_deserializes_without_params_key() {
-        let raw = r#"{"action":"stop"}"#;
-        let req: CommandRequest = serde_json::from_str(raw).unwrap();
-        assert_eq!(req.action, "stop");
-        assert!(req.params.is_none());
+    #[tokio::test]
+    async fn frame_captures_are_checked_before_the_backend_renders_them() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let err = capture_frame_to(&state, PathBuf::from("frame.bmp")).await.unwrap_err();
+        assert_eq!(err.kind(), "invalid_params");
+        assert_eq!(state.lock().unwrap().metrics.snapshot().commands_sent, 0);
+
+        // The mock draws nothing, so there is no frame to save.
+        let err = capture_frame_to(&state, PathBuf::from("frame.png")).await.unwrap_err();
+        assert_eq!(err.to_string(), "capture_frame failed: Nothing is being displayed");
+        assert_eq!(BackendError::WindowMissing("lyrics").to_string(), "The lyrics window is not open");
     }
 
-    #[test]
-    fn command_request_deserializes_with_nested_params() {
-        let raw = r#"{"action":"play","params":{"playlist_index":3}}"#;
-        let req: CommandRequest = serde_json::from_str(raw).unwrap();
-        assert_eq!(req.action, "play");
-        assert_eq!(req.params.unwrap()["playlist_index"], 3);
+    #[tokio::test]
+    async fn play_next_queues_after_the_current_song() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let file = std::env::temp_dir().join(format!("pykaraoke-play-next-{}.cdg", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        for song in ["/mock/a.cdg", "/mock/b.cdg"] {
+            let params = serde_json::json!({ "filepath": song });
+            dispatch_command(&state, "add_to_playlist".to_string(), Some(params)).await.unwrap();
+        }
+        dispatch_command(&state, "play".to_string(), None).await.unwrap();
+
+        let index = next_slot(&fetch_playback_state(&state).await.unwrap()).unwrap();
+        let playlist = add_to_playlist_for(&state, &file, Some("Ann".to_string()), Some(index)).await.unwrap();
+        std::fs::remove_file(&file).ok();
+        assert_eq!(playlist[1].singer.as_deref(), Some("Ann"));
+        assert_eq!(playlist[2].filepath, "/mock/b.cdg");
+
+        let stale = PlaybackState { playlist_index: Some(3), playlist_len: 3, ..Default::default() };
+        assert!(matches!(next_slot(&stale), Err(BackendError::InvalidParams { .. })));
+        assert_eq!(next_slot(&PlaybackState::default()).unwrap(), 0);
     }
 
-    // ── CommandResponse serialization ────────────────────────────
+    #[tokio::test]
+    async fn singers_follow_their_entries_through_reorders() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let file = std::env::temp_dir().join(format!("pykaraoke-singer-{}.cdg", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        add_to_playlist_for(&state, &file, Some(" Ann ".to_string()), None).await.unwrap();
+        let playlist = add_to_playlist_for(&state, &file, Some(String::new()), None).await.unwrap();
+        std::fs::remove_file(&file).ok();
+        let singers: Vec<Option<&str>> = playlist.iter().map(|s| s.singer.as_deref()).collect();
+        assert_eq!(singers, [Some("Ann"), None]);
 
-    #[test]
-    fn command_response_ok_without_data() {
-        let resp = CommandResponse {
-            status: "ok".to_string(),
-            message: Some("done".to_string()),
-            data: None,
-        };
-        let j = serde_json::to_value(&resp).unwrap();
-        assert_eq!(j["status"], "ok");
-        assert_eq!(j["message"], "done");
-        assert!(j["data"].is_null());
+        let params = serde_json::json!({ "from": 0, "to": 1 });
+        dispatch_command(&state, "move_in_playlist".to_string(), Some(params)).await.unwrap();
+        let playlist = fetch_playlist(&state).await.unwrap();
+        let singers: Vec<Option<&str>> = playlist.iter().map(|s| s.singer.as_deref()).collect();
+        assert_eq!(singers, [None, Some("Ann")]);
     }
 
-    #[test]
-    fn command_response_error_with_message() {
-        let resp = CommandResponse {
-            status: "error".to_string(),
-            message: Some("Backend not running".to_string()),
-            data: None,
-        };
-        let j = serde_json::to_value(&resp).unwrap();
-        assert_eq!(j["status"], "error");
-        assert_eq!(j["message"], "Backend not running");
+    #[tokio::test]
+    async fn repeat_modes_replay_or_wrap_the_playlist() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        for filepath in ["/mock/abba-dancing-queen.cdg", "/mock/queen-bohemian-rhapsody.cdg"] {
+            let params = serde_json::json!({ "filepath": filepath });
+            dispatch_command(&state, "add_to_playlist".to_string(), Some(params)).await.unwrap();
+        }
+        let play = serde_json::json!({ "playlist_index": 1 });
+        dispatch_command(&state, "play".to_string(), Some(play)).await.unwrap();
+        let finished = fetch_playback_state(&state).await.unwrap();
+        assert_eq!(finished.playlist_index, Some(1));
+
+        advance_playlist(&state, RepeatMode::One, Some(&finished)).await.unwrap();
+        assert_eq!(fetch_playback_state(&state).await.unwrap().playlist_index, Some(1));
+
+        assert!(advance_playlist(&state, RepeatMode::Off, Some(&finished)).await.is_err());
+        advance_playlist(&state, RepeatMode::All, Some(&finished)).await.unwrap();
+        assert_eq!(fetch_playback_state(&state).await.unwrap().playlist_index, Some(0));
     }
 
-    #[test]
-    fn command_response_with_data_payload() {
-        let resp = CommandResponse {
-            status: "ok".to_string(),
-            message: None,
-            data: Some(json!({
-                "playback_state": "playing",
-                "volume": 0.75,
-                "playlist": []
-            })),
-        };
-        let j = serde_json::to_value(&resp).unwrap();
-        assert_eq!(j["data"]["playback_state"], "playing");
-        assert_eq!(j["data"]["volume"], 0.75);
+    #[tokio::test]
+    async fn suspend_pauses_playback_and_resume_reopens_audio() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let params = serde_json::json!({ "filepath": "/mock/abba-dancing-queen.cdg" });
+        dispatch_command(&state, "add_to_playlist".to_string(), Some(params)).await.unwrap();
+        dispatch_command(&state, "play".to_string(), None).await.unwrap();
+
+        assert!(suspend(&state).await.unwrap());
+        assert_eq!(fetch_playback_state(&state).await.unwrap().state, "paused");
+
+        let report = resume(&state).await;
+        assert!(report.audio_reopened && report.was_playing);
+        assert!(!resume(&state).await.was_playing);
     }
 
-    #[test]
-    fn command_response_roundtrips_through_json() {
-        let original = CommandResponse {
-            status: "ok".to_string(),
-            message: Some("Command sent".to_string()),
-            data: Some(json!({"results": [{"title": "Test"}]})),
-        };
-        let serialized = serde_json::to_string(&original).unwrap();
-        let deserialized: CommandResponse = serde_json::from_str(&serialized).unwrap();
-        assert_eq!(deserialized.status, "ok");
-        assert_eq!(deserialized.data.unwrap()["results"][0]["title"], "Test");
+    #[tokio::test]
+    async fn wait_ready_reports_combined_status() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        }));
+        let status = wait_ready(&state, true).await.unwrap();
+        assert_eq!((status.pid, status.protocol), (None, PROTOCOL_VERSION));
+        assert_eq!(status.version, "mock");
+        assert!(state.lock().unwrap().is_running());
     }
 
-    // ── BackendState management ──────────────────────────────────
+    #[tokio::test]
+    async fn wait_ready_failure_leaves_no_backend() {
+        let state: SafeBackendState = Arc::new(Mutex::new(spawn_piped("sleep", &["30"])));
+        // A backend that never answers; fail the handshake by hand rather
+        // than waiting out the real timeout.
+        let waiter = tokio::spawn({
+            let state = state.clone();
+            async move { wait_ready(&state, true).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        state.lock().unwrap().pending.lock().unwrap().fail_all(BackendError::Disconnected);
+        assert!(waiter.await.unwrap().is_err());
+        let backend = state.lock().unwrap();
+        assert!(backend.process.is_none() && backend.stdin.is_none());
+    }
 
     #[test]
-    fn backend_state_initializes_with_no_process() {
-        let state = BackendState {
-            process: None,
-            stdin: None,
-            response_rx: None,
+    fn backends_without_handshake_report_protocol_zero() {
+        let unknown = CommandResponse {
+            status: "error".to_string(),
+            message: Some("Unknown action: handshake".to_string()),
+            data: None,
         };
-        assert!(state.process.is_none());
-        assert!(state.stdin.is_none());
-        assert!(state.response_rx.is_none());
+        assert_eq!(backend_protocol(unknown), 0);
+        let current = CommandResponse {
+            status: "ok".to_string(),
+            message: None,
+            data: Some(json!({"protocol": PROTOCOL_VERSION})),
+        };
+        assert_eq!(backend_protocol(current), PROTOCOL_VERSION);
     }
 
-    #[test]
-    fn safe_backend_state_is_mutex_lockable() {
+    #[tokio::test]
+    async fn mock_backend_answers_commands_without_a_process() {
         let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
-            process: None,
-            stdin: None,
-            response_rx: None,
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
         }));
-        let guard = state.lock().unwrap();
-        assert!(guard.process.is_none());
+        let response = dispatch_command(&state, "get_state".to_string(), None).await.unwrap();
+        assert_eq!(response.status, "ok");
+        assert_eq!(response.data.unwrap()["playback_state"], "idle");
+        let metrics = state.lock().unwrap().metrics.snapshot();
+        assert_eq!((metrics.commands_sent, metrics.responses_received), (1, 1));
+
+        let mut backend = state.lock().unwrap();
+        assert!(shutdown_backend(&mut backend, SHUTDOWN_GRACE).unwrap());
+        assert!(!backend.is_running());
     }
 
-    #[test]
-    fn safe_backend_state_clone_shares_data() {
+    #[tokio::test]
+    async fn backend_version_is_cached_for_the_session() {
         let state: SafeBackendState = Arc::new(Mutex::new(BackendState {
-            process: None,
-            stdin: None,
-            response_rx: None,
+            mock: Some(mock::MockBackend::new(|_| {})),
+            interpreter: Some("mock".to_string()),
+            ..Default::default()
         }));
-        let clone = state.clone();
-        assert!(Arc::ptr_eq(&state, &clone));
-    }
-
-    // ── JSON protocol contract tests ─────────────────────────────
+        let version = backend_version(&state).await.unwrap();
+        assert_eq!(version.app_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(version.interpreter.as_deref(), Some("mock"));
 
-    #[test]
-    fn frontend_play_command_matches_expected_shape() {
-        // Mirrors the JSON the JS frontend sends via invoke('send_command', ...)
-        let raw = r#"{"action":"play","params":{"playlist_index":0}}"#;
-        let req: CommandRequest = serde_json::from_str(raw).unwrap();
-        assert_eq!(req.action, "play");
+        // A cached answer doesn't need a live backend.
+        state.lock().unwrap().mock = None;
+        assert_eq!(backend_version(&state).await.unwrap().backend_version, version.backend_version);
     }
 
     #[test]
-    fn frontend_search_command_matches_expected_shape() {
-        let raw = r#"{"action":"search_songs","params":{"query":"bohemian"}}"#;
-        let req: CommandRequest = serde_json::from_str(raw).unwrap();
-        assert_eq!(req.action, "search_songs");
-        assert_eq!(req.params.unwrap()["query"], "bohemian");
-    }
+    fn seek_past_the_end_is_rejected() {
+        let playback = PlaybackState { duration_ms: 180_000, ..Default::default() };
+        assert_eq!(seek_position(180_000, &playback).unwrap(), 180_000);
+        let err = seek_position(180_001, &playback).unwrap_err();
+        assert!(matches!(err, BackendError::InvalidParams { ref field, .. } if field == "position_ms"));
 
-    #[test]
-    fn frontend_volume_command_matches_expected_shape() {
-        let raw = r#"{"action":"set_volume","params":{"volume":0.42}}"#;
-        let req: CommandRequest = serde_json::from_str(raw).unwrap();
-        let vol = req.params.unwrap()["volume"].as_f64().unwrap();
-        assert!((vol - 0.42).abs() < f64::EPSILON);
+        let unknown_length = PlaybackState::default();
+        assert_eq!(seek_position(5_000, &unknown_length).unwrap(), 5_000);
     }
 
     #[test]
-    fn all_known_actions_deserialize() {
-        let actions = vec![
-            "play", "pause", "stop", "next", "previous", "seek",
-            "set_volume", "load_song", "add_to_playlist",
-            "remove_from_playlist", "clear_playlist", "get_state",
-            "search_songs", "get_library", "scan_library",
-            "add_folder", "get_settings", "update_settings",
-        ];
-        for action in actions {
-            let raw = format!(r#"{{"action":"{}"}}"#, action);
-            let req: CommandRequest = serde_json::from_str(&raw).unwrap();
-            assert_eq!(req.action, action);
-        }
+    fn playlist_moves_must_stay_in_range() {
+        let playback = PlaybackState { playlist_len: 3, ..Default::default() };
+        assert!(check_playlist_move(0, 2, &playback).is_ok());
+        let err = check_playlist_move(1, 3, &playback).unwrap_err();
+        assert!(matches!(err, BackendError::InvalidParams { ref field, .. } if field == "to"));
+        let err = check_playlist_move(5, 0, &PlaybackState::default()).unwrap_err();
+        assert!(matches!(err, BackendError::InvalidParams { ref field, .. } if field == "from"));
     }
 
     #[test]
-    fn backend_event_envelope_shape() {
-        // The Rust backend wraps Python output in {"type":"event","event":...}
-        let raw = r#"{"type":"event","event":{"type":"state_changed","data":{}}}"#;
-        let parsed: serde_json::Value = serde_json::from_str(raw).unwrap();
-        assert_eq!(parsed["type"], "event");
-        assert_eq!(parsed["event"]["type"], "state_changed");
+    fn playlist_updates_refresh_the_cached_length() {
+        let playback = Mutex::new(Some(PlaybackState { playlist_len: 1, ..Default::default() }));
+        cache_playback(&playback, &json!({"type": "playlist_updated", "data": {"playlist": [{}, {}]}}));
+        assert_eq!(playback.lock().unwrap().as_ref().unwrap().playlist_len, 2);
     }
 
     #[test]
-    fn backend_response_envelope_shape() {
-        let raw = r#"{"type":"response","response":{"status":"ok","message":"done"}}"#;
-        let parsed: serde_json::Value = serde_json::from_str(raw).unwrap();
-        assert_eq!(parsed["type"], "response");
-        assert_eq!(parsed["response"]["status"], "ok");
+    fn tempo_is_clamped_and_must_be_finite() {
+        assert_eq!(tempo_ratio(0.9).unwrap(), 0.9);
+        assert_eq!(tempo_ratio(3.0).unwrap(), MAX_TEMPO);
+        assert_eq!(tempo_ratio(0.0).unwrap(), MIN_TEMPO);
+        for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(tempo_ratio(bad).unwrap_err().kind(), "invalid_params");
+        }
     }
 
-    // ── Regression: empty-window workaround ──────────────────────
+    #[tokio::test]
+    async fn playback_cache_follows_events_and_fetches() {
+        let state: SafeBackendState = Arc::new(Mutex::new(BackendState::default()));
+        let playback = state.lock().unwrap().playback.clone();
+        let mut backend = mock::MockBackend::new(move |event| cache_playback(&playback, &event));
+        backend.handle("load_song", Some(&json!({ "filepath": "/mock/abba-dancing-queen.cdg" })));
+        state.lock().unwrap().mock = Some(backend);
 
-    #[test]
-    fn dmabuf_env_var_is_set_on_linux() {
-        // Regression test for https://github.com/wilsonify/pykaraoke-ng/issues/...
-        // WebKitGTK renders a blank window when GBM buffer creation fails.
-        // The workaround sets WEBKIT_DISABLE_DMABUF_RENDERER=1.
-        //
-        // On non-Linux this test just verifies the source contains the
-        // workaround code (the #[cfg] gate means the code won't execute).
-        let source = include_str!("main.rs");
-        assert!(
-            source.contains("WEBKIT_DISABLE_DMABUF_RENDERER"),
-            "main.rs must set WEBKIT_DISABLE_DMABUF_RENDERER to prevent \
-             blank WebKitGTK windows on Linux"
-        );
+        let playback = state.lock().unwrap().playback.clone();
+        let cached = playback.lock().unwrap().take().unwrap();
+        assert_eq!(cached.current_song.unwrap().artist, "ABBA");
+
+        fetch_playback_state(&state).await.unwrap();
+        assert_eq!(playback.lock().unwrap().as_ref().unwrap().duration_ms, cached.duration_ms);
     }
 
     #[test]
-    fn dmabuf_workaround_is_linux_gated() {
-        let source = include_str!("main.rs");
-        let dmabuf_pos = source.find("WEBKIT_DISABLE_DMABUF_RENDERER").unwrap();
-        let preceding = &source[dmabuf_pos.saturating_sub(300)..dmabuf_pos];
-        assert!(
-            preceding.contains(r#"target_os = "linux""#),
-            "WEBKIT_DISABLE_DMABUF_RENDERER should be behind #[cfg(target_os = \"linux\")]"
-        );
+    fn detached_commands_reach_the_backend_without_waiting() {
+        let mut backend = BackendState {
+            mock: Some(mock::MockBackend::new(|_| {})),
+            ..Default::default()
+        };
+        send_detached(&mut backend, "set_volume".to_string(), Some(json!({ "volume": 0.8 }))).unwrap();
+        let response = backend.mock.as_mut().unwrap().handle("get_state", None);
+        assert_eq!(response["data"]["volume"], 0.8);
+        assert!(backend.pending.lock().unwrap().is_empty());
+
+        assert!(matches!(
+            send_detached(&mut BackendState::default(), "set_volume".to_string(), None),
+            Err(BackendError::NotRunning)
+        ));
     }
 
     #[test]
-    fn dmabuf_workaround_checks_existing_value() {
-        let source = include_str!("main.rs");
-        // Between the cfg gate and the set_var, we should see is_err() check
-        let dmabuf_pos = source.find("WEBKIT_DISABLE_DMABUF_RENDERER").unwrap();
-        let region = &source[dmabuf_pos.saturating_sub(100)..dmabuf_pos + 100];
-        assert!(
-            region.contains("is_err()"),
-            "Should check is_err() before setting the env var, \
-             so user-provided values are respected"
+    fn started_backend_reports_script_and_interpreter() {
+        let backend = BackendState {
+            script: Some(PathBuf::from("/opt/pykaraoke/backend.py")),
+            interpreter: Some("python3".to_string()),
+            ..Default::default()
+        };
+        let started = StartedBackend::new("Backend started successfully".to_string(), &backend);
+        assert_eq!(
+            serde_json::to_value(started).unwrap(),
+            json!({
+                "message": "Backend started successfully",
+                "script": "/opt/pykaraoke/backend.py",
+                "interpreter": "python3",
+            })
         );
     }
 
     #[test]
-    fn dmabuf_set_before_tauri_builder() {
-        let source = include_str!("main.rs");
-        let dmabuf_pos = source.find("WEBKIT_DISABLE_DMABUF_RENDERER").unwrap();
-        let builder_pos = source.find("tauri::Builder::default()").unwrap();
-        assert!(
-            dmabuf_pos < builder_pos,
-            "WEBKIT_DISABLE_DMABUF_RENDERER must be set before tauri::Builder"
+    fn resource_dir_falls_back_to_cwd_then_executable_dir() {
+        let resources = PathBuf::from("/opt/pykaraoke/resources");
+        let cwd = PathBuf::from("/home/user");
+        let exe = PathBuf::from("/opt/pykaraoke/bin/pykaraoke-ng");
+        assert_eq!(
+            resolve_resource_dir(Some(resources.clone()), Some(cwd.clone()), Some(exe.clone())).unwrap(),
+            resources
         );
+        assert_eq!(resolve_resource_dir(None, Some(cwd.clone()), Some(exe.clone())).unwrap(), cwd);
+        assert_eq!(
+            resolve_resource_dir(None, None, Some(exe)).unwrap(),
+            PathBuf::from("/opt/pykaraoke/bin")
+        );
+        assert!(matches!(
+            resolve_resource_dir(None, None, None),
+            Err(BackendError::LaunchFailed(_))
+        ));
     }
 
-    // ── Regression: backend path resolution ──────────────────────
-
     #[test]
-    fn backend_path_uses_multiple_candidates() {
-        let source = include_str!("main.rs");
-        let count = source.matches(r#".join("backend.py")"#).count();
-        assert!(
-            count >= 2,
-            "start_backend should try at least 2 candidate paths for \
-             backend.py (found {count})"
-        );
+    fn missing_backend_script_lists_checked_paths() {
+        let dir = std::env::temp_dir().join("pykaraoke-ng-no-such-dir");
+        let candidates = vec![dir.join("backend.py"), dir.join("backend_api.py")];
+        let err = find_backend_script(&candidates, false).unwrap_err();
+        let message = err.to_string();
+        for path in &candidates {
+            assert!(message.contains(&path.display().to_string()), "{message}");
+        }
+        assert!(message.contains("resource directory could not be resolved"));
+        let j = serde_json::to_value(&err).unwrap();
+        assert_eq!(j["kind"], "script_not_found");
+        assert_eq!(j["details"]["checked"].as_array().unwrap().len(), 2);
+        assert_eq!(j["details"]["resource_dir_resolved"], false);
     }
 
     #[test]
-    fn backend_path_checks_exists() {
-        let source = include_str!("main.rs");
-        // The candidates should be filtered with .exists()
-        assert!(
-            source.contains(".exists()"),
-            "start_backend should verify candidate paths with .exists()"
-        );
+    fn spawn_failure_names_the_attempted_command() {
+        let mut cmd = Command::new("/nonexistent/python3");
+        cmd.arg("backend.py");
+        let e = cmd.spawn().unwrap_err();
+        let err = spawn_error(&cmd, &e);
+        let message = err.to_string();
+        assert!(message.contains("/nonexistent/python3 backend.py"), "{message}");
+        assert!(message.contains("Python interpreter could not be located"), "{message}");
+        let j = serde_json::to_value(&err).unwrap();
+        assert_eq!(j["kind"], "spawn_failed");
+        assert_eq!(j["details"]["args"][0], "backend.py");
+        assert_eq!(j["details"]["os_code"], e.raw_os_error().unwrap());
     }
 
     #[test]
-    fn backend_path_uses_resource_dir() {
-        let source = include_str!("main.rs");
-        assert!(
-            source.contains("resource_dir"),
-            "start_backend should use resource_dir() for bundled installs"
-        );
+    fn existing_backend_script_is_picked() {
+        let here = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src").join("main.rs");
+        let candidates = vec![PathBuf::from("/nonexistent/backend.py"), here.clone()];
+        assert_eq!(find_backend_script(&candidates, true).unwrap(), here);
     }
 
     // ── Regression: backend timeout cleanup ────────────────────────
 
-    #[test]
-    fn send_command_timeout_kills_backend() {
-        let source = include_str!("main.rs");
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn send_command_timeout_kills_backend() {
         // When a command times out, the backend process must be killed
         // and cleaned up so the frontend can retry from a clean state.
-        let timeout_pos = source.find("RecvTimeoutError::Timeout").unwrap();
-        let cleanup_region = &source[timeout_pos..timeout_pos + 400];
-        assert!(
-            cleanup_region.contains("child.kill()"),
-            "Timeout handler must kill the unresponsive backend process "
-        );
-        assert!(
-            cleanup_region.contains("backend.stdin = None"),
-            "Timeout handler must clear stdin so retry can spawn fresh"
-        );
-        assert!(
-            cleanup_region.contains("backend.process = None"),
-            "Timeout handler must clear process so start_backend can restart"
-        );
-    }
+        let state: SafeBackendState = Arc::new(Mutex::new(spawn_piped("sleep", &["30"])));
+        let pid = state.lock().unwrap().process.as_ref().unwrap().id();
+        let stuck = submit_command(&state, "get_state".to_string(), None).unwrap();
+        let queued = submit_command(&state, "play".to_string(), None).unwrap();
 
-    #[test]
-    fn send_command_checks_process_alive() {
-        let source = include_str!("main.rs");
-        // Before sending a command we should check if the child process
-        // is still alive via try_wait().
-        assert!(
-            source.contains("try_wait()"),
-            "send_command should check if child process is still alive with try_wait()"
-        );
+        let err = stuck.wait_within(&state, Duration::from_millis(20)).await.unwrap_err();
+        assert_eq!(err.kind(), "timeout");
+        assert!(!Path::new(&format!("/proc/{pid}")).exists(), "the unresponsive backend is killed");
+        {
+            let backend = state.lock().unwrap();
+            assert!(backend.process.is_none() && backend.stdin.is_none(), "so start_backend can restart");
+        }
+        assert_eq!(queued.wait(&state).await.unwrap_err().kind(), "disconnected");
     }
 
+    #[cfg(unix)]
     #[test]
     fn send_command_cleans_up_dead_process() {
-        let source = include_str!("main.rs");
-        // When try_wait() returns that the child has exited, the backend
-        // state should be cleaned up immediately.
-        let wait_pos = source.find("try_wait()").unwrap();
-        let cleanup_region = &source[wait_pos..wait_pos + 300];
-        assert!(
-            cleanup_region.contains("backend.stdin = None"),
-            "Dead process cleanup must clear stdin"
-        );
-        assert!(
-            cleanup_region.contains("backend.process = None"),
-            "Dead process cleanup must clear process"
-        );
+        let state: SafeBackendState = Arc::new(Mutex::new(spawn_piped("true", &[])));
+        let (table, waiting) = {
+            let mut backend = state.lock().unwrap();
+            backend.process.as_mut().unwrap().wait().unwrap();
+            let table = backend.pending.clone();
+            let (_, rx) = table.lock().unwrap().register();
+            (table, rx)
+        };
+
+        let err = submit_command(&state, "get_state".to_string(), None).err().unwrap();
+        assert_eq!(err.kind(), "process_exited");
+        let backend = state.lock().unwrap();
+        assert!(backend.process.is_none() && backend.stdin.is_none());
+        assert!(table.lock().unwrap().is_empty());
+        assert!(matches!(waiting.blocking_recv(), Ok(Err(BackendError::Disconnected))));
     }
 }
@@ -0,0 +1,109 @@
+//! IPC health counters for the diagnostics panel.
+//!
+//! Shared between command dispatch and the stdout reader thread, so plain
+//! atomics rather than a lock.  Reset whenever a backend is started.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Default)]
+pub struct BackendMetrics {
+    commands_sent: AtomicU64,
+    commands_deduplicated: AtomicU64,
+    responses_received: AtomicU64,
+    timeouts: AtomicU64,
+    parse_errors: AtomicU64,
+    events_emitted: AtomicU64,
+}
+
+/// Point-in-time copy returned by `get_backend_metrics`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MetricsSnapshot {
+    pub commands_sent: u64,
+    /// Requests that shared an identical in-flight request's response.
+    pub commands_deduplicated: u64,
+    pub responses_received: u64,
+    pub timeouts: u64,
+    pub parse_errors: u64,
+    pub events_emitted: u64,
+}
+
+impl BackendMetrics {
+    pub fn command_sent(&self) {
+        self.commands_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn command_deduplicated(&self) {
+        self.commands_deduplicated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn response_received(&self) {
+        self.responses_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn timeout(&self) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn parse_error(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn event_emitted(&self) {
+        self.events_emitted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            commands_sent: self.commands_sent.load(Ordering::Relaxed),
+            commands_deduplicated: self.commands_deduplicated.load(Ordering::Relaxed),
+            responses_received: self.responses_received.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            events_emitted: self.events_emitted.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        for counter in [
+            &self.commands_sent,
+            &self.commands_deduplicated,
+            &self.responses_received,
+            &self.timeouts,
+            &self.parse_errors,
+            &self.events_emitted,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_resets() {
+        let metrics = BackendMetrics::default();
+        metrics.command_sent();
+        metrics.command_sent();
+        metrics.command_deduplicated();
+        metrics.response_received();
+        metrics.timeout();
+        metrics.parse_error();
+        metrics.event_emitted();
+        assert_eq!(
+            metrics.snapshot(),
+            MetricsSnapshot {
+                commands_sent: 2,
+                commands_deduplicated: 1,
+                responses_received: 1,
+                timeouts: 1,
+                parse_errors: 1,
+                events_emitted: 1,
+            }
+        );
+        metrics.reset();
+        assert_eq!(metrics.snapshot(), MetricsSnapshot::default());
+    }
+}
@@ -0,0 +1,516 @@
+//! In-process stand-in for the Python backend.
+//!
+//! Started with `start_backend({ mock: true })`, it answers the same
+//! actions as `PyKaraokeBackend.handle_command` from a small canned
+//! library and emits the matching events, so the UI can be exercised in CI
+//! or on machines without pygame/numpy installed.  Nothing is played.
+
+use crate::ingest::DEFAULT_EXTENSIONS;
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MOCK_LIBRARY: &[(&str, &str, &str)] = &[
+    ("Bohemian Rhapsody", "Queen", "/mock/queen-bohemian-rhapsody.cdg"),
+    ("Dancing Queen", "ABBA", "/mock/abba-dancing-queen.cdg"),
+    ("Don't Stop Believin'", "Journey", "/mock/journey-dont-stop-believin.kar"),
+    ("Sweet Caroline", "Neil Diamond", "/mock/neil-diamond-sweet-caroline.mid"),
+];
+
+/// Length reported for every mock song.
+const MOCK_DURATION_MS: u64 = 180_000;
+/// Size `preload` reports for every mock song.
+const MOCK_SONG_BYTES: u64 = 65_536;
+
+type Emit = Box<dyn Fn(Value) + Send>;
+
+pub struct MockBackend {
+    state: &'static str,
+    playlist: Vec<Value>,
+    playlist_index: i64,
+    current_song: Option<Value>,
+    volume: f64,
+    position_ms: u64,
+    transpose: i64,
+    tempo: f64,
+    vocal_removal: Value,
+    input_device: Value,
+    settings: Value,
+    emit: Emit,
+}
+
+impl MockBackend {
+    /// Create a mock that delivers its events through `emit`.
+    pub fn new(emit: impl Fn(Value) + Send + 'static) -> Self {
+        MockBackend {
+            state: "idle",
+            playlist: Vec::new(),
+            playlist_index: -1,
+            current_song: None,
+            volume: 0.5,
+            position_ms: 0,
+            transpose: 0,
+            tempo: 1.0,
+            vocal_removal: json!({ "enabled": false, "strength": 0.0 }),
+            input_device: json!("mock-mic"),
+            settings: json!({ "folder_list": ["/mock"] }),
+            emit: Box::new(emit),
+        }
+    }
+
+    /// Answer one command and emit any events it triggers.
+    pub fn handle(&mut self, action: &str, params: Option<&Value>) -> Value {
+        let (response, events) = self.respond(action, params.unwrap_or(&Value::Null));
+        for event in events {
+            (self.emit)(event);
+        }
+        response
+    }
+
+    fn respond(&mut self, action: &str, params: &Value) -> (Value, Vec<Value>) {
+        let mut events = Vec::new();
+        let response = match action {
+            "get_state" => json!({ "status": "ok", "data": self.snapshot() }),
+            "play" => {
+                if let Some(index) = params["playlist_index"].as_i64() {
+                    match self.playlist.get(index as usize).cloned() {
+                        Some(song) if index >= 0 => {
+                            self.playlist_index = index;
+                            self.current_song = Some(song);
+                        }
+                        _ => return (error("Invalid playlist index"), events),
+                    }
+                } else if self.current_song.is_none() {
+                    let Some(first) = self.playlist.first().cloned() else {
+                        return (error("No song loaded"), events);
+                    };
+                    self.playlist_index = 0;
+                    self.current_song = Some(first);
+                }
+                self.state = "playing";
+                events.push(self.state_changed());
+                ok()
+            }
+            "pause" if self.state == "playing" => {
+                self.state = "paused";
+                events.push(self.state_changed());
+                ok()
+            }
+            "pause" => error("Not playing"),
+            "fade_out" if self.state == "playing" => ok(),
+            "fade_out" => error("Not playing"),
+            "play_test_tone" => ok(),
+            "stop" => {
+                self.state = "stopped";
+                self.position_ms = 0;
+                events.push(self.state_changed());
+                ok()
+            }
+            "next" | "previous" => {
+                let step = if action == "next" { 1 } else { -1 };
+                let index = self.playlist_index + step;
+                match usize::try_from(index).ok().and_then(|i| self.playlist.get(i)).cloned() {
+                    Some(song) => {
+                        self.playlist_index = index;
+                        self.current_song = Some(song);
+                        self.position_ms = 0;
+                        self.state = "playing";
+                        events.push(self.state_changed());
+                        ok()
+                    }
+                    None => error(if step > 0 { "End of playlist" } else { "Start of playlist" }),
+                }
+            }
+            "preload" => {
+                let index = params["playlist_index"].as_u64().unwrap_or(u64::MAX);
+                match usize::try_from(index).ok().and_then(|i| self.playlist.get(i)) {
+                    Some(_) => json!({
+                        "status": "ok",
+                        "data": { "playlist_index": index, "bytes": MOCK_SONG_BYTES },
+                    }),
+                    None => error("Invalid playlist index"),
+                }
+            }
+            "seek" => {
+                self.position_ms = params["position_ms"].as_f64().unwrap_or(0.0).max(0.0) as u64;
+                ok()
+            }
+            "set_transpose" => {
+                self.transpose = params["semitones"].as_i64().unwrap_or(0);
+                ok()
+            }
+            "set_tempo" => {
+                self.tempo = params["ratio"].as_f64().unwrap_or(1.0);
+                ok()
+            }
+            "set_log_level" => json!({ "status": "ok", "data": { "level": params["level"] } }),
+            "set_vocal_removal" => {
+                let Some(song) = &self.current_song else {
+                    return (error("No song loaded"), events);
+                };
+                // A CDG's audio is already a backing track.
+                let cdg = song["filepath"].as_str().is_some_and(|path| path.ends_with(".cdg"));
+                self.vocal_removal = json!({
+                    "enabled": params["enabled"].as_bool().unwrap_or(false),
+                    "strength": params["strength"].as_f64().unwrap_or(0.0),
+                });
+                let active = !cdg && self.vocal_removal["enabled"] == true;
+                let note = cdg.then_some("CDG songs have no vocals to remove");
+                json!({ "status": "ok", "data": { "active": active, "note": note } })
+            }
+            "set_volume" => {
+                self.volume = params["volume"].as_f64().unwrap_or(self.volume).clamp(0.0, 1.0);
+                events.push(event("volume_changed", json!({ "volume": self.volume })));
+                ok()
+            }
+            "load_song" | "add_to_playlist" => {
+                let Some(filepath) = params["filepath"].as_str() else {
+                    return (error("filepath required"), events);
+                };
+                let extension = std::path::Path::new(filepath).extension().and_then(|ext| ext.to_str());
+                let playable = extension.is_some_and(|ext| DEFAULT_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+                if action == "load_song" && !playable {
+                    let message = "This file type can't be played";
+                    let data = json!({ "filepath": filepath, "reason": "unsupported_format", "message": message });
+                    events.push(event("load_failed", data));
+                    let refused = json!({ "reason": "unsupported_format" });
+                    return (json!({ "status": "error", "message": message, "data": refused }), events);
+                }
+                let mut song = song_for(filepath);
+                if let Some(singer) = params["singer"].as_str() {
+                    song["singer"] = json!(singer);
+                }
+                if action == "load_song" {
+                    self.current_song = Some(song);
+                    self.transpose = 0;
+                    self.tempo = 1.0;
+                    self.vocal_removal = json!({ "enabled": false, "strength": 0.0 });
+                    self.state = "stopped";
+                    events.push(self.state_changed());
+                } else {
+                    self.playlist.push(song);
+                    events.push(self.playlist_updated());
+                }
+                ok()
+            }
+            "insert_in_playlist" => {
+                let Some(filepath) = params["filepath"].as_str() else {
+                    return (error("filepath required"), events);
+                };
+                let index = params["index"].as_u64().map(|i| i as usize).filter(|&i| i <= self.playlist.len());
+                let Some(index) = index else {
+                    return (error("Invalid index"), events);
+                };
+                let mut song = song_for(filepath);
+                if let Some(singer) = params["singer"].as_str() {
+                    song["singer"] = json!(singer);
+                }
+                self.playlist.insert(index, song);
+                if index as i64 <= self.playlist_index {
+                    self.playlist_index += 1;
+                }
+                events.push(self.playlist_updated());
+                ok()
+            }
+            "remove_from_playlist" => {
+                let index = params["index"].as_i64().unwrap_or(-1);
+                if index < 0 || index as usize >= self.playlist.len() {
+                    return (error("Invalid index"), events);
+                }
+                self.playlist.remove(index as usize);
+                events.push(self.playlist_updated());
+                ok()
+            }
+            "move_in_playlist" => {
+                let len = self.playlist.len() as i64;
+                let (from, to) = (params["from"].as_i64().unwrap_or(-1), params["to"].as_i64().unwrap_or(-1));
+                if !(0..len).contains(&from) || !(0..len).contains(&to) {
+                    return (error("Invalid index"), events);
+                }
+                let song = self.playlist.remove(from as usize);
+                self.playlist.insert(to as usize, song);
+                // The current song keeps playing wherever it ends up.
+                if self.playlist_index == from {
+                    self.playlist_index = to;
+                } else if from < self.playlist_index && self.playlist_index <= to {
+                    self.playlist_index -= 1;
+                } else if to <= self.playlist_index && self.playlist_index < from {
+                    self.playlist_index += 1;
+                }
+                events.push(self.playlist_updated());
+                json!({ "status": "ok", "data": { "playlist": self.playlist } })
+            }
+            "clear_playlist" => {
+                self.playlist.clear();
+                self.playlist_index = -1;
+                events.push(self.playlist_updated());
+                ok()
+            }
+            "search_songs" => {
+                let query = params["query"].as_str().unwrap_or("").to_lowercase();
+                let results: Vec<Value> = library()
+                    .filter(|s| {
+                        let haystack = format!("{} {}", s["title"], s["artist"]).to_lowercase();
+                        haystack.contains(&query)
+                    })
+                    .collect();
+                json!({ "status": "ok", "data": { "results": results } })
+            }
+            "get_library" => json!({ "status": "ok", "data": { "songs": library().collect::<Vec<_>>() } }),
+            "scan_library" | "add_folder" => {
+                let summary = json!({
+                    "song_count": MOCK_LIBRARY.len(),
+                    "songs_added": if action == "scan_library" { MOCK_LIBRARY.len() } else { 0 },
+                    "errors": [],
+                    "duration_ms": 0,
+                    "cancelled": false,
+                });
+                events.push(event("scan_progress", json!({ "label": "Scanning", "progress": 0.0 })));
+                events.push(event("scan_complete", summary.clone()));
+                json!({ "status": "ok", "data": summary })
+            }
+            "get_settings" => json!({ "status": "ok", "data": self.settings }),
+            "set_library_roots" => {
+                let Some(roots) = params["roots"].as_array() else {
+                    return (error("roots must be a list of folders"), events);
+                };
+                self.settings["folder_list"] = json!(roots);
+                json!({ "status": "ok", "data": { "roots": roots } })
+            }
+            "update_settings" => {
+                if let (Some(settings), Some(updates)) = (self.settings.as_object_mut(), params.as_object()) {
+                    settings.extend(updates.clone());
+                }
+                ok()
+            }
+            "version" => json!({ "status": "ok", "data": { "version": "mock" } }),
+            "handshake" => json!({ "status": "ok", "data": { "protocol": crate::PROTOCOL_VERSION } }),
+            "list_audio_devices" => json!({ "status": "ok", "data": { "devices": [
+                { "id": "mock-speakers", "name": "Mock Speakers", "is_default": true },
+                { "id": "mock-hdmi", "name": "Mock HDMI", "is_default": false },
+            ]}}),
+            "list_input_devices" => json!({ "status": "ok", "data": { "devices": [
+                { "id": "mock-mic", "name": "Mock Microphone", "is_default": true },
+                { "id": "mock-usb-mic", "name": "Mock USB Microphone", "is_default": false },
+            ]}}),
+            "set_mic_monitoring" => json!({ "status": "ok", "data": {
+                "enabled": params["enabled"],
+                "gain": params["gain"],
+                "device": self.input_device,
+            }}),
+            "set_input_device" => {
+                self.input_device = params["id"].clone();
+                ok()
+            }
+            "get_audio_stats" => json!({ "status": "ok", "data": {
+                "underruns": 0, "buffer_ms": 46.4, "sample_rate": 44100, "channels": 2,
+            }}),
+            "get_supported_formats" => json!({ "status": "ok", "data": {
+                "extensions": crate::ingest::DEFAULT_EXTENSIONS,
+            }}),
+            "waveform" => {
+                let Some(filepath) = params["filepath"].as_str() else {
+                    return (error("filepath required"), events);
+                };
+                if matches!(filepath.rsplit('.').next().map(str::to_ascii_lowercase).as_deref(), Some("kar" | "mid")) {
+                    return (error("MIDI songs have no audio to draw"), events);
+                }
+                let buckets = params["buckets"].as_u64().unwrap_or(0);
+                let peaks: Vec<f64> = (0..buckets).map(|i| (i * 37 % 100) as f64 / 100.0).collect();
+                json!({ "status": "ok", "data": { "peaks": peaks } })
+            }
+            "probe" => {
+                let Some(filepath) = params["filepath"].as_str() else {
+                    return (error("filepath required"), events);
+                };
+                let song = song_for(filepath);
+                let format = match filepath.rsplit('.').next().map(str::to_ascii_lowercase).as_deref() {
+                    Some("kar" | "mid") => "kar",
+                    Some("cdg") => "cdg",
+                    _ => "mpg",
+                };
+                json!({ "status": "ok", "data": {
+                    "title": song["title"],
+                    "artist": song["artist"],
+                    "duration_ms": MOCK_DURATION_MS,
+                    "format": format,
+                }})
+            }
+            "capture_frame" => error("Nothing is being displayed"),
+            "get_supported_languages" => json!({ "status": "ok", "data": { "languages": [
+                { "id": "en", "name": "Western European" },
+                { "id": "ja", "name": "Japanese" },
+            ]}}),
+            "set_lyrics_language" => ok(),
+            "get_backend_capabilities" => json!({ "status": "ok", "data": {
+                "codecs": ["cdg", "kar", "mid", "mp3", "ogg"],
+                "features": [
+                    "waveform", "fade_out", "preload", "capture_frame",
+                    "vocal_removal", "normalization", "crossfade", "themes", "audio_reinit", "test_tone",
+                    "mic_monitoring", "transpose", "tempo",
+                ],
+                "plugins": [],
+            }}),
+            "get_available_themes" => json!({ "status": "ok", "data": { "themes": [
+                { "name": "classic", "label": "Classic" },
+                { "name": "high_contrast", "label": "High contrast" },
+            ]}}),
+            "set_theme" | "set_crossfade" => ok(),
+            "ping" => json!({ "status": "ok", "data": { "pong": true } }),
+            "set_audio_device" | "set_auto_advance" | "reopen_audio" => ok(),
+            "reinit_audio" => {
+                let data = json!({
+                    "device": params["id"],
+                    "position_ms": self.position_ms,
+                    "resumed": matches!(self.state, "playing" | "paused"),
+                });
+                events.push(event("audio_ready", data.clone()));
+                json!({ "status": "ok", "data": data })
+            }
+            "set_shuffle" | "set_repeat_mode" | "set_av_sync_offset" => ok(),
+            // The canned songs carry no ReplayGain tags.
+            "set_normalization" => json!({
+                "status": "ok",
+                "data": {
+                    "enabled": params["enabled"],
+                    "target_lufs": params["target_lufs"],
+                    "has_gain_metadata": false,
+                    "gain_db": null,
+                },
+            }),
+            "cancel" => ok(),
+            // Mock scans finish within the command that starts them.
+            "cancel_scan" => error("No scan in progress"),
+            other => error(&format!("Unknown action: {other}")),
+        };
+        (response, events)
+    }
+
+    fn snapshot(&self) -> Value {
+        json!({
+            "playback_state": self.state,
+            "current_song": self.current_song,
+            "playlist": self.playlist,
+            "playlist_index": self.playlist_index,
+            "volume": self.volume,
+            "position_ms": self.position_ms,
+            "transpose": self.transpose,
+            "tempo": self.tempo,
+            "vocal_removal": self.vocal_removal,
+            "duration_ms": if self.current_song.is_some() { MOCK_DURATION_MS } else { 0 },
+            "error": null,
+        })
+    }
+
+    fn state_changed(&self) -> Value {
+        event("state_changed", self.snapshot())
+    }
+
+    fn playlist_updated(&self) -> Value {
+        event("playlist_updated", json!({ "playlist": self.playlist }))
+    }
+}
+
+fn ok() -> Value {
+    json!({ "status": "ok" })
+}
+
+fn error(message: &str) -> Value {
+    json!({ "status": "error", "message": message })
+}
+
+/// An event shaped like `PyKaraokeBackend._emit_event` output.
+fn event(event_type: &str, data: Value) -> Value {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default();
+    json!({ "type": event_type, "timestamp": timestamp, "data": data })
+}
+
+fn library() -> impl Iterator<Item = Value> {
+    MOCK_LIBRARY.iter().map(|(title, artist, filepath)| song(title, artist, filepath))
+}
+
+fn song(title: &str, artist: &str, filepath: &str) -> Value {
+    let filename = filepath.rsplit('/').next().unwrap_or(filepath);
+    json!({
+        "title": title,
+        "artist": artist,
+        "filename": filename,
+        "filepath": filepath,
+        "zip_name": null,
+    })
+}
+
+/// The library entry for `filepath`, or a bare entry for unknown files.
+fn song_for(filepath: &str) -> Value {
+    MOCK_LIBRARY
+        .iter()
+        .find(|(_, _, path)| *path == filepath)
+        .map(|(title, artist, path)| song(title, artist, path))
+        .unwrap_or_else(|| song("", "", filepath))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn recording_mock() -> (MockBackend, Arc<Mutex<Vec<Value>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        (MockBackend::new(move |e| sink.lock().unwrap().push(e)), events)
+    }
+
+    #[test]
+    fn get_state_matches_backend_shape() {
+        let (mut mock, _) = recording_mock();
+        let r = mock.handle("get_state", None);
+        assert_eq!(r["status"], "ok");
+        let state = crate::playback::PlaybackState::from_backend(&r["data"]).unwrap();
+        assert_eq!(state.state, "idle");
+    }
+
+    #[test]
+    fn enqueue_and_play_emit_events() {
+        let (mut mock, events) = recording_mock();
+        let filepath = MOCK_LIBRARY[0].2;
+        mock.handle("add_to_playlist", Some(&json!({ "filepath": filepath })));
+        assert_eq!(mock.handle("play", None)["status"], "ok");
+
+        let events = events.lock().unwrap();
+        let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["playlist_updated", "state_changed"]);
+        assert_eq!(events[1]["data"]["current_song"]["artist"], "Queen");
+    }
+
+    #[test]
+    fn moving_songs_keeps_the_current_one_current() {
+        let (mut mock, _) = recording_mock();
+        for (_, _, filepath) in &MOCK_LIBRARY[..3] {
+            mock.handle("add_to_playlist", Some(&json!({ "filepath": filepath })));
+        }
+        mock.handle("play", Some(&json!({ "playlist_index": 1 })));
+        mock.handle("move_in_playlist", Some(&json!({ "from": 1, "to": 2 })));
+        assert_eq!(mock.playlist_index, 2);
+        mock.handle("move_in_playlist", Some(&json!({ "from": 0, "to": 2 })));
+        assert_eq!(mock.playlist_index, 1);
+        mock.handle("move_in_playlist", Some(&json!({ "from": 1, "to": 0 })));
+        assert_eq!(mock.playlist_index, 0);
+    }
+
+    #[test]
+    fn search_filters_canned_library() {
+        let (mut mock, _) = recording_mock();
+        let r = mock.handle("search_songs", Some(&json!({ "query": "queen" })));
+        assert_eq!(r["data"]["results"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn unknown_actions_are_rejected_like_python() {
+        let (mut mock, _) = recording_mock();
+        let r = mock.handle("explode", None);
+        assert_eq!(r["status"], "error");
+        assert_eq!(r["message"], "Unknown action: explode");
+    }
+}
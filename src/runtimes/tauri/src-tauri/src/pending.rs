@@ -0,0 +1,306 @@
+//! Correlation of backend responses with the commands awaiting them.
+//!
+//! Every command written to the backend gets a `request_id` and a slot in
+//! [`PendingRequests`].  The stdout reader resolves slots as responses
+//! arrive: by `request_id` when the backend echoes one, otherwise in FIFO
+//! order, which matches the Python stdio loop handling one line at a time.
+//!
+//! A cancelled request keeps its slot (without waiters) so the backend's
+//! eventual reply is absorbed instead of being handed to the next caller.
+//!
+//! Identical read commands can share one slot: a caller registering with
+//! the same key as the newest unanswered request waits on that request's
+//! response instead of sending its own.  Only the newest request is
+//! joined, so the shared answer reflects every command sent before it.
+//!
+//! For debugging a stuck UI, the table can be listed with the age of each
+//! request, and cleared: every waiter is resolved with
+//! `BackendError::Abandoned` and the slots are dropped, so a request the
+//! backend will never answer stops holding up the ones behind it.
+
+use crate::error::BackendError;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+/// What a waiter receives: the raw `response` object or an error.
+pub type ResponseResult = Result<serde_json::Value, BackendError>;
+
+#[derive(Debug)]
+struct PendingRequest {
+    id: u64,
+    waiters: Vec<oneshot::Sender<ResponseResult>>,
+    /// Set for requests later identical ones may join.
+    key: Option<String>,
+    sent: Instant,
+}
+
+/// An unanswered request, as listed by `get_pending_requests`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PendingSummary {
+    pub request_id: u64,
+    /// Time since the request was registered.
+    pub age_ms: u64,
+    /// False for a slot only kept to absorb a late reply, e.g. after
+    /// `cancel_command`.
+    pub waiting: bool,
+}
+
+/// Requests written to the backend that have not been answered yet, oldest
+/// first.
+#[derive(Debug, Default)]
+pub struct PendingRequests {
+    last_id: u64,
+    entries: VecDeque<PendingRequest>,
+}
+
+impl PendingRequests {
+    fn push(&mut self, waiters: Vec<oneshot::Sender<ResponseResult>>, key: Option<String>) -> u64 {
+        self.last_id += 1;
+        self.entries.push_back(PendingRequest {
+            id: self.last_id,
+            waiters,
+            key,
+            sent: Instant::now(),
+        });
+        self.last_id
+    }
+
+    /// Register a request and return its id plus the receiver its response
+    /// will be delivered on.
+    pub fn register(&mut self) -> (u64, oneshot::Receiver<ResponseResult>) {
+        let (tx, rx) = oneshot::channel();
+        (self.push(vec![tx], None), rx)
+    }
+
+    /// Like `register`, but join the newest request instead if it has the
+    /// same `key` and is still unanswered.  The returned flag is true when
+    /// a request was joined, in which case nothing should be sent.
+    pub fn register_shared(&mut self, key: String) -> (u64, oneshot::Receiver<ResponseResult>, bool) {
+        let (tx, rx) = oneshot::channel();
+        match self.entries.back_mut() {
+            Some(newest) if newest.key.as_ref() == Some(&key) && !newest.waiters.is_empty() => {
+                newest.waiters.push(tx);
+                (newest.id, rx, true)
+            }
+            _ => (self.push(vec![tx], Some(key)), rx, false),
+        }
+    }
+
+    /// Register a request nobody waits on.  Its slot still consumes the
+    /// matching response so FIFO routing stays aligned.
+    pub fn register_detached(&mut self) -> u64 {
+        self.push(Vec::new(), None)
+    }
+
+    /// Deliver a backend response.  Returns false if no slot matched.
+    pub fn resolve(&mut self, request_id: Option<u64>, response: serde_json::Value) -> bool {
+        let position = match request_id {
+            Some(id) => self.entries.iter().position(|e| e.id == id),
+            None if self.entries.is_empty() => None,
+            None => Some(0),
+        };
+        let Some(entry) = position.and_then(|i| self.entries.remove(i)) else {
+            return false;
+        };
+        for waiter in entry.waiters {
+            waiter.send(Ok(response.clone())).ok();
+        }
+        true
+    }
+
+    /// Resolve the waiters for `id` with `BackendError::Cancelled`, keeping
+    /// the slot to absorb the backend's late reply.  Returns false if the
+    /// request is unknown or was already cancelled.
+    pub fn cancel(&mut self, id: u64) -> bool {
+        self.settle(id, Err(BackendError::Cancelled))
+    }
+
+    /// Resolve the waiters for `id` ahead of the backend's reply, e.g. from
+    /// a `scan_complete` event.  The slot stays to absorb the real reply.
+    /// Returns false if the request is unknown or already settled.
+    pub fn answer(&mut self, id: u64, response: serde_json::Value) -> bool {
+        self.settle(id, Ok(response))
+    }
+
+    fn settle(&mut self, id: u64, result: ResponseResult) -> bool {
+        let Some(entry) = self.entries.iter_mut().find(|e| e.id == id) else {
+            return false;
+        };
+        if entry.waiters.is_empty() {
+            return false;
+        }
+        for waiter in entry.waiters.drain(..) {
+            waiter.send(result.clone()).ok();
+        }
+        true
+    }
+
+    /// Drop the slot for a request that never reached the backend.
+    pub fn remove(&mut self, id: u64) {
+        self.entries.retain(|e| e.id != id);
+    }
+
+    /// Fail every outstanding request, e.g. when the backend goes away.
+    pub fn fail_all(&mut self, error: BackendError) {
+        for waiter in self.entries.drain(..).flat_map(|entry| entry.waiters) {
+            waiter.send(Err(error.clone())).ok();
+        }
+    }
+
+    /// Every unanswered request, oldest first.
+    pub fn summaries(&self) -> Vec<PendingSummary> {
+        self.entries
+            .iter()
+            .map(|entry| PendingSummary {
+                request_id: entry.id,
+                age_ms: entry.sent.elapsed().as_millis() as u64,
+                waiting: !entry.waiters.is_empty(),
+            })
+            .collect()
+    }
+
+    /// Resolve every waiter with `BackendError::Abandoned` and forget all
+    /// requests.  Returns how many were dropped.
+    pub fn abandon_all(&mut self) -> usize {
+        let dropped = self.entries.len();
+        self.fail_all(BackendError::Abandoned);
+        dropped
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ids_are_unique_and_increasing() {
+        let mut p = PendingRequests::default();
+        let (a, _ra) = p.register();
+        let (b, _rb) = p.register();
+        assert!(b > a);
+        assert_eq!(p.len(), 2);
+    }
+
+    #[test]
+    fn responses_without_id_resolve_in_fifo_order() {
+        let mut p = PendingRequests::default();
+        let (_, mut first) = p.register();
+        let (_, mut second) = p.register();
+        assert!(p.resolve(None, json!({"status": "ok", "n": 1})));
+        assert!(p.resolve(None, json!({"status": "ok", "n": 2})));
+        assert_eq!(first.try_recv().unwrap().unwrap()["n"], 1);
+        assert_eq!(second.try_recv().unwrap().unwrap()["n"], 2);
+        assert!(!p.resolve(None, json!({})));
+    }
+
+    #[test]
+    fn responses_with_id_resolve_that_request() {
+        let mut p = PendingRequests::default();
+        let (_, mut first) = p.register();
+        let (second_id, mut second) = p.register();
+        assert!(p.resolve(Some(second_id), json!({"n": 2})));
+        assert_eq!(second.try_recv().unwrap().unwrap()["n"], 2);
+        assert!(first.try_recv().is_err());
+        assert_eq!(p.len(), 1);
+    }
+
+    #[test]
+    fn cancel_resolves_waiter_and_absorbs_late_reply() {
+        let mut p = PendingRequests::default();
+        let (scan_id, mut scan) = p.register();
+        let (_, mut next) = p.register();
+
+        assert!(p.cancel(scan_id));
+        assert_eq!(scan.try_recv().unwrap(), Err(BackendError::Cancelled));
+        assert!(!p.cancel(scan_id), "second cancel is a no-op");
+
+        // The scan's late reply must not be handed to get_state.
+        p.resolve(None, json!({"scan": true}));
+        assert!(next.try_recv().is_err());
+        p.resolve(None, json!({"state": true}));
+        assert_eq!(next.try_recv().unwrap().unwrap()["state"], true);
+    }
+
+    #[test]
+    fn answered_requests_absorb_the_real_reply() {
+        let mut p = PendingRequests::default();
+        let (scan_id, mut scan) = p.register();
+        let (_, mut next) = p.register();
+
+        assert!(p.answer(scan_id, json!({"status": "ok", "data": {"songs_added": 3}})));
+        assert_eq!(scan.try_recv().unwrap().unwrap()["data"]["songs_added"], 3);
+        assert!(!p.answer(scan_id, json!({})));
+
+        p.resolve(Some(scan_id), json!({"scan": true}));
+        assert!(next.try_recv().is_err());
+        assert_eq!(p.len(), 1);
+    }
+
+    #[test]
+    fn identical_requests_share_the_newest_ones_response() {
+        let mut p = PendingRequests::default();
+        let (first_id, mut first, joined) = p.register_shared("get_state null".to_string());
+        assert!(!joined);
+        let (second_id, mut second, joined) = p.register_shared("get_state null".to_string());
+        assert!(joined);
+        assert_eq!(second_id, first_id);
+        assert_eq!(p.len(), 1);
+
+        // Anything sent in between means a fresh request.
+        p.register();
+        let (third_id, _third, joined) = p.register_shared("get_state null".to_string());
+        assert!(!joined && third_id != first_id);
+
+        p.resolve(Some(first_id), json!({"state": "idle"}));
+        assert_eq!(first.try_recv().unwrap().unwrap()["state"], "idle");
+        assert_eq!(second.try_recv().unwrap().unwrap()["state"], "idle");
+    }
+
+    #[test]
+    fn detached_requests_consume_their_response() {
+        let mut p = PendingRequests::default();
+        p.register_detached();
+        let (_, mut next) = p.register();
+        p.resolve(None, json!({"cancel": true}));
+        p.resolve(None, json!({"state": true}));
+        assert_eq!(next.try_recv().unwrap().unwrap()["state"], true);
+    }
+
+    #[test]
+    fn fail_all_notifies_every_waiter() {
+        let mut p = PendingRequests::default();
+        let (_, mut a) = p.register();
+        let (_, mut b) = p.register();
+        p.fail_all(BackendError::Disconnected);
+        assert_eq!(a.try_recv().unwrap(), Err(BackendError::Disconnected));
+        assert_eq!(b.try_recv().unwrap(), Err(BackendError::Disconnected));
+        assert!(p.is_empty());
+    }
+
+    #[test]
+    fn abandoning_lists_and_drops_every_request() {
+        let mut p = PendingRequests::default();
+        let (stuck, mut rx) = p.register();
+        let detached = p.register_detached();
+        let summaries = p.summaries();
+        let listed: Vec<_> = summaries.iter().map(|s| (s.request_id, s.waiting)).collect();
+        assert_eq!(listed, [(stuck, true), (detached, false)]);
+
+        assert_eq!(p.abandon_all(), 2);
+        assert_eq!(rx.try_recv().unwrap(), Err(BackendError::Abandoned));
+        assert!(p.summaries().is_empty());
+    }
+}
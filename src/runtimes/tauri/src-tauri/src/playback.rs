@@ -0,0 +1,349 @@
+//! Typed views of the backend's playback state.
+//!
+//! The Python backend answers `get_state` with an untyped `data` blob
+//! (see `PyKaraokeBackend.get_state`).  These types give the frontend a
+//! stable contract instead of hand-indexing JSON.
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A song as described by the backend's `_song_to_dict`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SongInfo {
+    pub title: String,
+    pub artist: String,
+    pub filename: String,
+    pub filepath: String,
+    pub zip_name: Option<String>,
+    /// Who the entry was queued for, on playlist entries added with one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub singer: Option<String>,
+}
+
+/// Tempo of a song played at its normal speed.
+pub const NORMAL_TEMPO: f64 = 1.0;
+/// Range `set_tempo` clamps to.
+pub const MIN_TEMPO: f64 = 0.5;
+pub const MAX_TEMPO: f64 = 1.5;
+/// Largest audio/video sync offset, in milliseconds, either way.
+pub const MAX_AV_SYNC_OFFSET_MS: i64 = 500;
+/// Range of `start_now_playing_feed` intervals, in milliseconds.
+pub const MIN_FEED_INTERVAL_MS: u64 = 100;
+pub const MAX_FEED_INTERVAL_MS: u64 = 60_000;
+/// Range of `preview_song` lengths, in milliseconds.
+pub const MIN_PREVIEW_MS: u64 = 1_000;
+pub const MAX_PREVIEW_MS: u64 = 60_000;
+/// Strongest `set_vocal_removal` setting.
+pub const MAX_VOCAL_REMOVAL: f64 = 1.0;
+/// Range of `set_normalization` loudness targets, in LUFS, and the
+/// ReplayGain reference level used until one is picked.
+pub const MIN_TARGET_LUFS: f64 = -30.0;
+pub const MAX_TARGET_LUFS: f64 = -5.0;
+pub const DEFAULT_TARGET_LUFS: f64 = -18.0;
+/// Range of `stop_with_fade` durations, in milliseconds.
+pub const MIN_FADE_MS: i64 = 100;
+pub const MAX_FADE_MS: i64 = 10_000;
+/// Ranges of `play_test_tone` frequencies, in Hz, and lengths, in
+/// milliseconds, and the speakers it can play on.
+pub const MIN_TONE_HZ: f64 = 20.0;
+pub const MAX_TONE_HZ: f64 = 20_000.0;
+pub const MIN_TONE_MS: i64 = 100;
+pub const MAX_TONE_MS: i64 = 10_000;
+pub const TONE_CHANNELS: &[&str] = &["left", "right", "both"];
+/// Longest `set_crossfade` duration, in milliseconds; 0 turns it off.
+pub const MAX_CROSSFADE_MS: u64 = 10_000;
+
+/// What `stop_with_fade` did before stopping.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FadeStop {
+    /// Whether the backend faded the audio out first.
+    pub faded: bool,
+    /// Why playback was stopped immediately instead, when it was.
+    pub note: Option<String>,
+}
+
+/// Vocal attenuation for the current song.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct VocalRemoval {
+    pub enabled: bool,
+    /// How much of the vocals to take out, 0..1.
+    pub strength: f64,
+}
+
+/// What `set_vocal_removal` applied.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AppliedVocalRemoval {
+    #[serde(flatten)]
+    pub settings: VocalRemoval,
+    /// False when the backend accepted the settings but had nothing to
+    /// filter, e.g. a CDG song whose audio is already a backing track.
+    pub active: bool,
+    /// Why the filter isn't active, when the backend said.
+    pub note: Option<String>,
+}
+
+/// Loudness normalization from songs' ReplayGain tags.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Normalization {
+    pub enabled: bool,
+    /// Loudness every tagged song is brought to.
+    pub target_lufs: f64,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization { enabled: false, target_lufs: DEFAULT_TARGET_LUFS }
+    }
+}
+
+/// What `set_normalization` applied.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AppliedNormalization {
+    #[serde(flatten)]
+    pub settings: Normalization,
+    /// Whether the current song carries a ReplayGain gain; songs without
+    /// one play unscaled.
+    pub has_gain_metadata: bool,
+    /// Gain applied to the current song, in dB, while enabled.
+    pub gain_db: Option<f64>,
+}
+
+/// What `set_crossfade` applied.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Crossfade {
+    pub duration_ms: u64,
+    /// False when the backend can't crossfade; the setting is kept for
+    /// the next backend that can.
+    pub supported: bool,
+}
+
+/// A song the backend has read ahead with `preload`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Preload {
+    pub playlist_index: usize,
+    /// Size of the song's files, as read into memory.
+    pub bytes: u64,
+    /// Set when the song had been preloaded before and nothing was sent.
+    pub already_preloaded: bool,
+}
+
+/// Snapshot of the player returned by `get_playback_state`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaybackState {
+    pub state: String,
+    pub volume: f64,
+    pub position_ms: u64,
+    pub duration_ms: u64,
+    pub current_song: Option<SongInfo>,
+    pub playlist_len: usize,
+    /// Position of the current song in the playlist, if it came from it.
+    pub playlist_index: Option<usize>,
+    /// Key shift in semitones applied to the current song.
+    pub transpose: i32,
+    /// Playback speed relative to normal for the current song.
+    pub tempo: f64,
+    pub vocal_removal: VocalRemoval,
+    /// Whether the backend reported `transpose` itself; if not, the value
+    /// last set through `set_transpose` is carried over.
+    #[serde(skip)]
+    pub transpose_reported: bool,
+    /// Likewise for `tempo` and `set_tempo`.
+    #[serde(skip)]
+    pub tempo_reported: bool,
+    /// Likewise for `vocal_removal` and `set_vocal_removal`.
+    #[serde(skip)]
+    pub vocal_removal_reported: bool,
+}
+
+impl Default for PlaybackState {
+    fn default() -> Self {
+        PlaybackState {
+            state: String::new(),
+            volume: 0.0,
+            position_ms: 0,
+            duration_ms: 0,
+            current_song: None,
+            playlist_len: 0,
+            playlist_index: None,
+            transpose: 0,
+            tempo: NORMAL_TEMPO,
+            vocal_removal: VocalRemoval::default(),
+            transpose_reported: false,
+            tempo_reported: false,
+            vocal_removal_reported: false,
+        }
+    }
+}
+
+/// The `get_state` payload exactly as the backend sends it.
+#[derive(Debug, Deserialize)]
+struct RawState {
+    playback_state: String,
+    #[serde(default)]
+    volume: f64,
+    #[serde(default, deserialize_with = "lenient_ms")]
+    position_ms: u64,
+    #[serde(default, deserialize_with = "lenient_ms")]
+    duration_ms: u64,
+    #[serde(default)]
+    current_song: Option<SongInfo>,
+    #[serde(default)]
+    playlist: Vec<serde_json::Value>,
+    /// -1 when no playlist entry is current.
+    #[serde(default)]
+    playlist_index: Option<i64>,
+    #[serde(default)]
+    transpose: Option<i32>,
+    #[serde(default)]
+    tempo: Option<f64>,
+    #[serde(default)]
+    vocal_removal: Option<VocalRemoval>,
+}
+
+impl PlaybackState {
+    /// Build from the `data` field of a `get_state` response.
+    pub fn from_backend(data: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        let raw = RawState::deserialize(data)?;
+        Ok(PlaybackState {
+            state: raw.playback_state,
+            volume: raw.volume,
+            position_ms: raw.position_ms,
+            duration_ms: raw.duration_ms,
+            current_song: raw.current_song,
+            playlist_len: raw.playlist.len(),
+            playlist_index: raw.playlist_index.and_then(|i| usize::try_from(i).ok()),
+            transpose: raw.transpose.unwrap_or(0),
+            tempo: raw.tempo.unwrap_or(NORMAL_TEMPO),
+            vocal_removal: raw.vocal_removal.unwrap_or_default(),
+            transpose_reported: raw.transpose.is_some(),
+            tempo_reported: raw.tempo.is_some(),
+            vocal_removal_reported: raw.vocal_removal.is_some(),
+        })
+    }
+
+    /// Keep the transpose, tempo and vocal removal from `previous` while
+    /// the same song is loaded, unless the backend reported its own.
+    /// Loading a different song resets them to 0, normal speed and off.
+    pub fn carry_adjustments(mut self, previous: Option<&PlaybackState>) -> Self {
+        let Some(previous) = previous else {
+            return self;
+        };
+        let same_song = self.current_song.as_ref().map(|s| &s.filepath)
+            == previous.current_song.as_ref().map(|s| &s.filepath);
+        if !same_song {
+            return self;
+        }
+        if !self.transpose_reported {
+            self.transpose = previous.transpose;
+        }
+        if !self.tempo_reported {
+            self.tempo = previous.tempo;
+        }
+        if !self.vocal_removal_reported {
+            self.vocal_removal = previous.vocal_removal;
+        }
+        self
+    }
+}
+
+impl PlaybackState {
+    /// Build from a `state_changed` event, whose data is a `get_state`
+    /// snapshot.  `None` for any other event.
+    pub fn from_event(event: &serde_json::Value) -> Option<Self> {
+        if event["type"] != "state_changed" {
+            return None;
+        }
+        PlaybackState::from_backend(&event["data"]).ok()
+    }
+}
+
+/// Accept millisecond values sent as floats or as pygame's `-1` "not
+/// playing" sentinel, clamping to a non-negative integer.
+fn lenient_ms<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let value = Option::<f64>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(if value.is_finite() && value > 0.0 { value as u64 } else { 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_backend_get_state_payload() {
+        let data = json!({
+            "playback_state": "playing",
+            "current_song": {
+                "title": "Bohemian Rhapsody",
+                "artist": "Queen",
+                "filename": "queen.cdg",
+                "filepath": "/songs/queen.cdg",
+                "zip_name": null
+            },
+            "playlist": [{}, {}, {}],
+            "playlist_index": 0,
+            "volume": 0.75,
+            "position_ms": 12345,
+            "duration_ms": 354000,
+            "error": null
+        });
+        let state = PlaybackState::from_backend(&data).unwrap();
+        assert_eq!(state.state, "playing");
+        assert_eq!(state.playlist_len, 3);
+        assert_eq!(state.position_ms, 12345);
+        assert_eq!(state.current_song.unwrap().artist, "Queen");
+    }
+
+    #[test]
+    fn tolerates_idle_state_with_missing_fields() {
+        let state = PlaybackState::from_backend(&json!({"playback_state": "idle"})).unwrap();
+        assert_eq!(state.state, "idle");
+        assert_eq!(state.playlist_len, 0);
+        assert!(state.current_song.is_none());
+    }
+
+    #[test]
+    fn clamps_negative_and_fractional_positions() {
+        let data = json!({"playback_state": "stopped", "position_ms": -1, "duration_ms": 1500.7});
+        let state = PlaybackState::from_backend(&data).unwrap();
+        assert_eq!(state.position_ms, 0);
+        assert_eq!(state.duration_ms, 1500);
+    }
+
+    #[test]
+    fn state_changed_events_carry_a_snapshot() {
+        let event = json!({
+            "type": "state_changed",
+            "timestamp": 0.0,
+            "data": { "playback_state": "playing", "duration_ms": 1000 },
+        });
+        assert_eq!(PlaybackState::from_event(&event).unwrap().duration_ms, 1000);
+        let other = json!({ "type": "volume_changed", "data": { "volume": 0.5 } });
+        assert!(PlaybackState::from_event(&other).is_none());
+    }
+
+    #[test]
+    fn adjustments_follow_the_loaded_song() {
+        let snapshot = |path: &str| {
+            let data = json!({"playback_state": "playing", "current_song": {"filepath": path}});
+            PlaybackState::from_backend(&data).unwrap()
+        };
+        let vocals = VocalRemoval { enabled: true, strength: 0.5 };
+        let previous = PlaybackState { transpose: -3, tempo: 0.8, vocal_removal: vocals, ..snapshot("/a.cdg") };
+        let same = snapshot("/a.cdg").carry_adjustments(Some(&previous));
+        assert_eq!((same.transpose, same.tempo, same.vocal_removal), (-3, 0.8, vocals));
+        let other = snapshot("/b.cdg").carry_adjustments(Some(&previous));
+        assert_eq!((other.transpose, other.tempo), (0, NORMAL_TEMPO));
+        assert!(!other.vocal_removal.enabled);
+
+        let data = json!({"playback_state": "playing", "current_song": {"filepath": "/a.cdg"}, "transpose": 2});
+        let reported = PlaybackState::from_backend(&data).unwrap();
+        let reported = reported.carry_adjustments(Some(&previous));
+        assert_eq!((reported.transpose, reported.tempo), (2, 0.8));
+    }
+
+    #[test]
+    fn rejects_payload_without_playback_state() {
+        assert!(PlaybackState::from_backend(&json!({"volume": 0.5})).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! Saving the playlist to disk and reading it back.
+//!
+//! `save_playlist` and `load_playlist` use a path the user picks.  The
+//! stdout reader also autosaves to [`AUTOSAVE_FILE`] in the app data
+//! directory on every `playlist_updated` event, and a freshly started
+//! backend is refilled from it, so a crash mid-session doesn't lose the
+//! queue.
+
+use crate::error::BackendError;
+use crate::playback::SongInfo;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const AUTOSAVE_FILE: &str = "playlist.json";
+
+/// Most songs `get_upcoming` returns.
+pub const MAX_UPCOMING: usize = 50;
+
+/// On-disk form of a saved playlist.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedPlaylist {
+    songs: Vec<SongInfo>,
+}
+
+/// Where the autosaved playlist lives for an app with `config`.
+pub fn autosave_path(config: &tauri::Config) -> Option<PathBuf> {
+    tauri::api::path::app_data_dir(config).map(|dir| dir.join(AUTOSAVE_FILE))
+}
+
+/// The songs in the `playlist` field of a `get_state` snapshot or a
+/// `playlist_updated` event's data.
+pub fn songs_in(data: &serde_json::Value) -> Result<Vec<SongInfo>, serde_json::Error> {
+    match data.get("playlist") {
+        Some(playlist) => Vec::<SongInfo>::deserialize(playlist),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Up to `count` (at most [`MAX_UPCOMING`]) songs queued after the
+/// current one in a `get_state` snapshot.  With no current song the whole
+/// playlist is still to come.
+pub fn upcoming(data: &serde_json::Value, count: usize) -> Result<Vec<SongInfo>, serde_json::Error> {
+    let start = data["playlist_index"]
+        .as_i64()
+        .and_then(|index| usize::try_from(index + 1).ok())
+        .unwrap_or(0);
+    let songs = songs_in(data)?;
+    Ok(songs.into_iter().skip(start).take(count.min(MAX_UPCOMING)).collect())
+}
+
+pub fn save(path: &Path, songs: &[SongInfo]) -> Result<(), BackendError> {
+    let failed = |reason: String| BackendError::PlaylistFile { path: path.display().to_string(), reason };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| failed(e.to_string()))?;
+    }
+    let saved = SavedPlaylist { songs: songs.to_vec() };
+    let text = serde_json::to_string_pretty(&saved).map_err(|e| failed(e.to_string()))?;
+    std::fs::write(path, text).map_err(|e| failed(e.to_string()))
+}
+
+pub fn load(path: &Path) -> Result<Vec<SongInfo>, BackendError> {
+    let failed = |reason: String| BackendError::PlaylistFile { path: path.display().to_string(), reason };
+    let text = std::fs::read_to_string(path).map_err(|e| failed(e.to_string()))?;
+    let saved: SavedPlaylist = serde_json::from_str(&text).map_err(|e| failed(e.to_string()))?;
+    Ok(saved.songs)
+}
+
+/// The autosaved playlist, or nothing if the file is missing or corrupt.
+pub fn load_autosave(path: &Path) -> Vec<SongInfo> {
+    load(path).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_playlist(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("pykaraoke-ng-playlist-{name}-{}", std::process::id()))
+            .join(AUTOSAVE_FILE)
+    }
+
+    #[test]
+    fn playlist_round_trips() {
+        let path = temp_playlist("round-trip");
+        let data = json!({ "playlist": [
+            { "title": "Dancing Queen", "artist": "ABBA", "filepath": "/songs/abba.cdg" },
+        ]});
+        let songs = songs_in(&data).unwrap();
+        save(&path, &songs).unwrap();
+        assert_eq!(load(&path).unwrap(), songs);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn upcoming_songs_follow_the_current_one() {
+        let playlist: Vec<_> = (0..4).map(|n| json!({ "title": format!("Song {n}") })).collect();
+        let titles = |data, count| -> Vec<String> {
+            upcoming(&data, count).unwrap().into_iter().map(|s| s.title).collect()
+        };
+        assert_eq!(
+            titles(json!({ "playlist": playlist, "playlist_index": 1 }), 5),
+            ["Song 2", "Song 3"]
+        );
+        assert_eq!(titles(json!({ "playlist": playlist, "playlist_index": -1 }), 1), ["Song 0"]);
+        assert!(titles(json!({ "playlist": playlist, "playlist_index": 3 }), 2).is_empty());
+    }
+
+    #[test]
+    fn missing_or_corrupt_autosave_starts_empty() {
+        let path = temp_playlist("corrupt");
+        assert!(load_autosave(&path).is_empty());
+        assert_eq!(load(&path).unwrap_err().kind(), "playlist_file");
+
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(load_autosave(&path).is_empty());
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,116 @@
+//! How often and how recently each song was played.
+//!
+//! The backend keeps no play history, so the stdout reader counts its
+//! `song_started` events into [`PLAYS_FILE`] in the app data directory.
+//! Songs are told apart by file path (and ZIP member), so a song keeps
+//! its count across sessions and playlist changes.
+
+use crate::playback::SongInfo;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const PLAYS_FILE: &str = "play_history.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayHistoryEntry {
+    pub song: SongInfo,
+    pub play_count: u64,
+    /// When the song last started, in seconds since the Unix epoch.
+    pub last_played: u64,
+}
+
+/// The `sort_by` values `get_play_history` takes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaySort {
+    /// Most played first, ties broken by recency.
+    #[default]
+    PlayCount,
+    /// Most recently played first.
+    LastPlayed,
+}
+
+/// Where the play history lives for an app with `config`.
+pub fn history_path(config: &tauri::Config) -> Option<PathBuf> {
+    tauri::api::path::app_data_dir(config).map(|dir| dir.join(PLAYS_FILE))
+}
+
+/// The saved history, or nothing if the file is missing or corrupt.
+pub fn load(path: &Path) -> Vec<PlayHistoryEntry> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(path: &Path, entries: &[PlayHistoryEntry]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let text = serde_json::to_string(entries).map_err(|e| e.to_string())?;
+    std::fs::write(path, text).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// Count a play of `song` starting at `at`.  The entry's song details are
+/// refreshed, since titles can change on rescans; who sang it isn't kept.
+pub fn record(entries: &mut Vec<PlayHistoryEntry>, song: SongInfo, at: u64) {
+    let song = SongInfo { singer: None, ..song };
+    let key = (song.filepath.clone(), song.zip_name.clone());
+    match entries.iter_mut().find(|entry| (&entry.song.filepath, &entry.song.zip_name) == (&key.0, &key.1)) {
+        Some(entry) => {
+            entry.play_count += 1;
+            entry.last_played = at;
+            entry.song = song;
+        }
+        None => entries.push(PlayHistoryEntry { song, play_count: 1, last_played: at }),
+    }
+}
+
+/// `entries` in `sort` order, cut to `limit` when given.
+pub fn sorted(mut entries: Vec<PlayHistoryEntry>, sort: PlaySort, limit: Option<usize>) -> Vec<PlayHistoryEntry> {
+    match sort {
+        PlaySort::PlayCount => entries.sort_by(|a, b| {
+            b.play_count.cmp(&a.play_count).then(b.last_played.cmp(&a.last_played))
+        }),
+        PlaySort::LastPlayed => entries.sort_by(|a, b| b.last_played.cmp(&a.last_played)),
+    }
+    if let Some(limit) = limit {
+        entries.truncate(limit);
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(path: &str) -> SongInfo {
+        SongInfo { filepath: path.to_string(), ..SongInfo::default() }
+    }
+
+    #[test]
+    fn plays_are_counted_per_file() {
+        let mut entries = Vec::new();
+        record(&mut entries, song("/a.cdg"), 100);
+        record(&mut entries, song("/b.cdg"), 200);
+        record(&mut entries, song("/a.cdg"), 300);
+        let counts: Vec<_> = entries
+            .iter()
+            .map(|e| (e.song.filepath.as_str(), e.play_count, e.last_played))
+            .collect();
+        assert_eq!(counts, [("/a.cdg", 2, 300), ("/b.cdg", 1, 200)]);
+    }
+
+    #[test]
+    fn history_sorts_by_count_or_recency() {
+        let mut entries = Vec::new();
+        record(&mut entries, song("/a.cdg"), 100);
+        record(&mut entries, song("/a.cdg"), 150);
+        record(&mut entries, song("/b.cdg"), 200);
+        let paths = |entries: Vec<PlayHistoryEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.song.filepath).collect()
+        };
+        assert_eq!(paths(sorted(entries.clone(), PlaySort::PlayCount, None)), ["/a.cdg", "/b.cdg"]);
+        assert_eq!(paths(sorted(entries, PlaySort::LastPlayed, Some(1))), ["/b.cdg"]);
+    }
+}
@@ -0,0 +1,118 @@
+//! Song metadata from `probe_song`, and the cache that makes re-probing a
+//! folder cheap.
+//!
+//! Results are keyed by path and modification time, so an edited or
+//! replaced file is probed again instead of answering from the cache.  The
+//! least recently used entry is dropped once the cache is full.  The same
+//! cache holds `get_waveform` peaks.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub const PROBE_CACHE_CAPACITY: usize = 256;
+
+/// What the backend's `probe` action reports about a song file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProbedSong {
+    pub title: String,
+    pub artist: String,
+    /// `None` when the length can't be read without loading the song.
+    pub duration_ms: Option<u64>,
+    /// `kar`, `cdg` or `mpg`.
+    pub format: String,
+}
+
+pub type ProbeCache = FileCache<ProbedSong>;
+
+/// Results about files, each valid while its file is unchanged.
+#[derive(Debug)]
+pub struct FileCache<T> {
+    capacity: usize,
+    /// Least recently used first.
+    entries: VecDeque<(PathBuf, SystemTime, T)>,
+}
+
+impl<T: Clone> Default for FileCache<T> {
+    fn default() -> Self {
+        FileCache::with_capacity(PROBE_CACHE_CAPACITY)
+    }
+}
+
+impl<T: Clone> FileCache<T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        FileCache {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// The cached result for `path`, if it was probed at `modified`.  A
+    /// result for an older version of the file is dropped.
+    pub fn get(&mut self, path: &Path, modified: SystemTime) -> Option<T> {
+        let index = self.entries.iter().position(|(p, _, _)| p == path)?;
+        let entry = self.entries.remove(index)?;
+        if entry.1 != modified {
+            return None;
+        }
+        let value = entry.2.clone();
+        self.entries.push_back(entry);
+        Some(value)
+    }
+
+    /// Every cached path and result, least recently used first.
+    pub fn entries(&self) -> impl Iterator<Item = (&Path, &T)> {
+        self.entries.iter().map(|(path, _, value)| (path.as_path(), value))
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn insert(&mut self, path: PathBuf, modified: SystemTime, value: T) {
+        self.entries.retain(|(p, _, _)| *p != path);
+        self.entries.push_back((path, modified, value));
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn probed(title: &str) -> ProbedSong {
+        ProbedSong { title: title.to_string(), ..ProbedSong::default() }
+    }
+
+    #[test]
+    fn changed_files_miss_the_cache() {
+        let mut cache = ProbeCache::default();
+        let path = Path::new("/songs/a.cdg");
+        let modified = SystemTime::UNIX_EPOCH;
+        cache.insert(path.to_path_buf(), modified, probed("A"));
+        assert_eq!(cache.get(path, modified), Some(probed("A")));
+
+        let edited = modified + Duration::from_secs(1);
+        assert_eq!(cache.get(path, edited), None);
+        assert_eq!(cache.get(path, modified), None, "stale entries are dropped");
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let mut cache = ProbeCache::with_capacity(2);
+        let modified = SystemTime::UNIX_EPOCH;
+        cache.insert(PathBuf::from("/a"), modified, probed("A"));
+        cache.insert(PathBuf::from("/b"), modified, probed("B"));
+        cache.get(Path::new("/a"), modified);
+        cache.insert(PathBuf::from("/c"), modified, probed("C"));
+
+        assert!(cache.get(Path::new("/b"), modified).is_none());
+        assert!(cache.get(Path::new("/a"), modified).is_some());
+        assert!(cache.get(Path::new("/c"), modified).is_some());
+    }
+}
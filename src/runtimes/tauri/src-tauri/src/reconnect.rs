@@ -0,0 +1,127 @@
+//! Restarting a backend that dies unexpectedly.
+//!
+//! Off until the frontend calls `set_auto_restart`.  When the stdout
+//! reader sees a backend exit that nobody asked for, the app waits
+//! [`backoff_delay`] and starts it again with the last launch options.
+//! Each consecutive failure doubles the wait, from [`INITIAL_DELAY`] up to
+//! [`MAX_DELAY`]; a successful handshake resets the count.  After
+//! `max_retries` failures in a row the app gives up.
+
+use std::time::Duration;
+
+pub const INITIAL_DELAY: Duration = Duration::from_millis(500);
+pub const MAX_DELAY: Duration = Duration::from_secs(30);
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// How long to wait before restart attempt `attempt` (counted from 0).
+pub fn backoff_delay(attempt: u32) -> Duration {
+    INITIAL_DELAY
+        .checked_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX))
+        .map_or(MAX_DELAY, |delay| delay.min(MAX_DELAY))
+}
+
+#[derive(Debug)]
+pub struct Reconnect {
+    enabled: bool,
+    max_retries: u32,
+    consecutive_failures: u32,
+    /// Set while a restart is waiting out its delay; cleared by a
+    /// deliberate stop so the restart is abandoned.
+    scheduled: bool,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Reconnect {
+            enabled: false,
+            max_retries: DEFAULT_MAX_RETRIES,
+            consecutive_failures: 0,
+            scheduled: false,
+        }
+    }
+}
+
+impl Reconnect {
+    pub fn configure(&mut self, enabled: bool, max_retries: u32) {
+        self.enabled = enabled;
+        self.max_retries = max_retries;
+        if !enabled {
+            self.scheduled = false;
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Record a death or failed restart and schedule the next attempt.
+    /// Returns the delay before it, or `None` once `max_retries`
+    /// consecutive attempts have been used up.
+    pub fn next_attempt(&mut self) -> Option<Duration> {
+        if self.consecutive_failures >= self.max_retries {
+            self.scheduled = false;
+            return None;
+        }
+        let delay = backoff_delay(self.consecutive_failures);
+        self.consecutive_failures += 1;
+        self.scheduled = true;
+        Some(delay)
+    }
+
+    /// Claim the scheduled restart when its delay is up; false if it was
+    /// cancelled meanwhile.
+    pub fn take_scheduled(&mut self) -> bool {
+        std::mem::take(&mut self.scheduled)
+    }
+
+    /// Abandon a scheduled restart, e.g. because the user stopped the
+    /// backend.
+    pub fn cancel(&mut self) {
+        self.scheduled = false;
+    }
+
+    /// The backend came up; the next death starts from the shortest delay.
+    pub fn reset(&mut self) {
+        self.consecutive_failures = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (0..8).map(|n| backoff_delay(n).as_millis() as u64).collect();
+        assert_eq!(delays, [500, 1_000, 2_000, 4_000, 8_000, 16_000, 30_000, 30_000]);
+        assert_eq!(backoff_delay(40), MAX_DELAY);
+    }
+
+    #[test]
+    fn gives_up_after_max_retries_until_reset() {
+        let mut reconnect = Reconnect::default();
+        reconnect.configure(true, 2);
+        assert_eq!(reconnect.next_attempt(), Some(Duration::from_millis(500)));
+        assert_eq!(reconnect.next_attempt(), Some(Duration::from_secs(1)));
+        assert_eq!(reconnect.next_attempt(), None);
+
+        reconnect.reset();
+        assert_eq!(reconnect.next_attempt(), Some(INITIAL_DELAY));
+    }
+
+    #[test]
+    fn cancelled_restarts_are_not_claimed() {
+        let mut reconnect = Reconnect::default();
+        reconnect.configure(true, DEFAULT_MAX_RETRIES);
+        reconnect.next_attempt();
+        reconnect.cancel();
+        assert!(!reconnect.take_scheduled());
+        reconnect.next_attempt();
+        assert!(reconnect.take_scheduled());
+        assert!(!reconnect.take_scheduled());
+    }
+}
@@ -0,0 +1,127 @@
+//! Picking up edits made to `config.json` outside the app.
+//!
+//! The app keeps the config it loaded at startup in [`LoadedConfig`], and
+//! every setting it saves itself is copied there too, so `reload_config`
+//! can tell which settings were edited in the file since.  Each one is
+//! either applied on the spot or reported as waiting for a restart: of the
+//! backend for what is read when it starts, of the app for what is read
+//! before the window exists.
+
+use crate::config::{AppConfig, ConfigProblem};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Settings `reload_config` applies to the running app and backend.  Any
+/// other setting needs a restart.
+pub const LIVE_SETTINGS: &[&str] = &[
+    "hotkeys",
+    "av_sync_offset_ms",
+    "normalization",
+    "crossfade_ms",
+    "lyrics_language",
+    "theme",
+    "scan_excludes",
+    "library_roots",
+    "always_on_top",
+    "resource_limits",
+];
+
+/// What a change waits for to take effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Restart {
+    Backend,
+    App,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RestartRequired {
+    pub field: String,
+    pub restart: Restart,
+}
+
+/// The settings `reload_config` found changed, by what became of them.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ConfigReload {
+    pub applied: Vec<String>,
+    pub restart_required: Vec<RestartRequired>,
+    /// Live settings the app or backend refused; they are tried again on
+    /// the next reload.
+    pub failed: Vec<ConfigProblem>,
+}
+
+/// What a change to `field`, now set as in `config`, needs to take effect;
+/// `None` when it can be applied live.  A setting cleared back to the
+/// backend's default only reaches the backend when it restarts.
+pub fn restart_needed(field: &str, config: &AppConfig) -> Option<Restart> {
+    match field {
+        "lyrics_language" if config.lyrics_language.is_none() => Some(Restart::Backend),
+        "theme" if config.theme.is_none() => Some(Restart::Backend),
+        "library_roots" if config.library_roots.is_none() => Some(Restart::Backend),
+        "reader_buffer_bytes" | "verify_backend_integrity" => Some(Restart::Backend),
+        field if LIVE_SETTINGS.contains(&field) => None,
+        _ => Some(Restart::App),
+    }
+}
+
+/// Top-level settings whose values differ between `old` and `new`.
+pub fn changed_fields(old: &AppConfig, new: &AppConfig) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(old)), Ok(new)) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return Vec::new();
+    };
+    old.into_iter().filter(|(field, value)| new.get(field) != Some(value)).map(|(field, _)| field).collect()
+}
+
+/// The config the app is running with.
+#[derive(Debug, Default)]
+pub struct LoadedConfig(Mutex<AppConfig>);
+
+impl LoadedConfig {
+    pub fn new(config: AppConfig) -> Self {
+        LoadedConfig(Mutex::new(config))
+    }
+
+    pub fn get(&self) -> AppConfig {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Take `fields` as they are in `config`, keeping the rest.
+    pub fn absorb(&self, config: &AppConfig, fields: &[String]) {
+        let mut loaded = self.0.lock().unwrap();
+        let (Ok(mut merged), Ok(config)) = (serde_json::to_value(&*loaded), serde_json::to_value(config)) else {
+            return;
+        };
+        for field in fields {
+            merged[field] = config[field].clone();
+        }
+        if let Ok(config) = AppConfig::deserialize(&merged) {
+            *loaded = config;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_are_split_into_live_and_restart() {
+        let old = AppConfig::default();
+        let new = AppConfig {
+            renderer_workaround: false,
+            reader_buffer_bytes: old.reader_buffer_bytes * 2,
+            crossfade_ms: 3000,
+            theme: Some("classic".to_string()),
+            ..old.clone()
+        };
+        let fields = changed_fields(&old, &new);
+        assert_eq!(fields, ["renderer_workaround", "reader_buffer_bytes", "crossfade_ms", "theme"]);
+        let restarts: Vec<_> = fields.iter().map(|field| restart_needed(field, &new)).collect();
+        assert_eq!(restarts, [Some(Restart::App), Some(Restart::Backend), None, None]);
+        assert_eq!(restart_needed("theme", &old), Some(Restart::Backend));
+
+        let loaded = LoadedConfig::new(old);
+        loaded.absorb(&new, &["theme".to_string()]);
+        assert_eq!(changed_fields(&loaded.get(), &new), ["renderer_workaround", "reader_buffer_bytes", "crossfade_ms"]);
+    }
+}
@@ -0,0 +1,283 @@
+//! How much memory and CPU the backend process uses.
+//!
+//! Venue machines are often small, and a runaway backend starves the
+//! lyrics window.  `get_process_resources` reads the backend's resident
+//! memory and CPU time from the OS by its pid: from `/proc` on Linux,
+//! from `ps` on other Unixes and from PowerShell's `Get-Process` on
+//! Windows.  CPU use is the share of one core used since
+//! the previous reading (since the process started, for the first), so
+//! two busy cores read as 200%.
+//!
+//! While limits are set with `set_resource_limits`, the backend is also
+//! read every [`CHECK_INTERVAL`], and `resource-warning` is emitted when a
+//! limit is first exceeded; it is emitted again only once usage has
+//! dropped back under that limit.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+/// One reading of the backend process.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessResources {
+    pub pid: u32,
+    pub rss_bytes: u64,
+    pub cpu_percent: f64,
+}
+
+/// Usage above which `resource-warning` is emitted; `None` is no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    pub max_rss_mb: Option<u64>,
+    pub max_cpu_percent: Option<f64>,
+}
+
+impl ResourceLimits {
+    pub fn any(&self) -> bool {
+        self.max_rss_mb.is_some() || self.max_cpu_percent.is_some()
+    }
+
+    /// The field out of range and why, if one is.
+    pub fn check(&self) -> Result<(), (&'static str, String)> {
+        if self.max_rss_mb == Some(0) {
+            return Err(("max_rss_mb", "must be at least 1".to_string()));
+        }
+        if self.max_cpu_percent.is_some_and(|max| !(max > 0.0 && max.is_finite())) {
+            return Err(("max_cpu_percent", "must be a positive number".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Payload of `resource-warning`: `resource` is `memory` (in MB) or `cpu`
+/// (in percent of one core).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourceWarning {
+    pub pid: u32,
+    pub resource: &'static str,
+    pub value: f64,
+    pub limit: f64,
+}
+
+/// What the OS reports for a process.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub rss_bytes: u64,
+    /// User and system CPU time used so far.
+    pub cpu_time: Duration,
+    /// How long the process has been running.
+    pub age: Duration,
+}
+
+/// Turns samples into usage, remembering the last one for the CPU rate
+/// and which limits are already exceeded.
+#[derive(Debug, Default)]
+pub struct ResourceMonitor {
+    last: Option<(u32, Duration, Instant)>,
+    over_memory: bool,
+    over_cpu: bool,
+}
+
+impl ResourceMonitor {
+    /// Usage of `pid` as of `sample` taken at `now`, and the limits it has
+    /// newly gone over.
+    pub fn record(
+        &mut self,
+        pid: u32,
+        sample: Sample,
+        now: Instant,
+        limits: &ResourceLimits,
+    ) -> (ProcessResources, Vec<ResourceWarning>) {
+        let (cpu_time, wall) = match self.last {
+            Some((last_pid, last_cpu, at)) if last_pid == pid && sample.cpu_time >= last_cpu => {
+                (sample.cpu_time - last_cpu, now.duration_since(at))
+            }
+            _ => {
+                self.over_memory = false;
+                self.over_cpu = false;
+                (sample.cpu_time, sample.age)
+            }
+        };
+        self.last = Some((pid, sample.cpu_time, now));
+        let cpu_percent = if wall.is_zero() { 0.0 } else { 100.0 * cpu_time.as_secs_f64() / wall.as_secs_f64() };
+        let usage = ProcessResources { pid, rss_bytes: sample.rss_bytes, cpu_percent };
+
+        let mut warnings = Vec::new();
+        let rss_mb = sample.rss_bytes as f64 / BYTES_PER_MB;
+        let checks = [
+            ("memory", rss_mb, limits.max_rss_mb.map(|max| max as f64), &mut self.over_memory),
+            ("cpu", cpu_percent, limits.max_cpu_percent, &mut self.over_cpu),
+        ];
+        for (resource, value, limit, over) in checks {
+            let exceeded = limit.filter(|&limit| value > limit);
+            if let (Some(limit), false) = (exceeded, *over) {
+                warnings.push(ResourceWarning { pid, resource, value, limit });
+            }
+            *over = exceeded.is_some();
+        }
+        (usage, warnings)
+    }
+}
+
+/// Read memory and CPU time of `pid` from `/proc`.
+#[cfg(target_os = "linux")]
+pub fn sample(pid: u32) -> Result<Sample, String> {
+    // USER_HZ, the unit of the times in `stat`, is 100 on every platform
+    // Linux runs on.
+    const TICKS_PER_SEC: f64 = 100.0;
+    let read = |file: &str| {
+        let path = format!("/proc/{pid}/{file}");
+        std::fs::read_to_string(&path).map_err(|e| format!("Can't read {path}: {e}"))
+    };
+    let status = read("status")?;
+    let rss_kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|rest| rest.split_whitespace().next()?.parse::<u64>().ok())
+        .ok_or_else(|| format!("No VmRSS in /proc/{pid}/status"))?;
+    // Fields after the command name, which may itself hold spaces.
+    let stat = read("stat")?;
+    let fields: Vec<_> = stat.rsplit_once(')').map(|(_, rest)| rest.split_whitespace().collect()).unwrap_or_default();
+    let field = |index: usize| -> Result<f64, String> {
+        fields.get(index).and_then(|value| value.parse().ok()).ok_or_else(|| format!("Unreadable /proc/{pid}/stat"))
+    };
+    let cpu_ticks = field(11)? + field(12)?;
+    let started_ticks = field(19)?;
+    let uptime: f64 = std::fs::read_to_string("/proc/uptime")
+        .ok()
+        .and_then(|text| text.split_whitespace().next()?.parse().ok())
+        .ok_or("Can't read /proc/uptime")?;
+    Ok(Sample {
+        rss_bytes: rss_kb * 1024,
+        cpu_time: Duration::from_secs_f64(cpu_ticks / TICKS_PER_SEC),
+        age: Duration::from_secs_f64((uptime - started_ticks / TICKS_PER_SEC).max(0.0)),
+    })
+}
+
+/// Read memory and CPU time of `pid` from `ps`.
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn sample(pid: u32) -> Result<Sample, String> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=", "-o", "time=", "-o", "etime=", "-p", &pid.to_string()])
+        .output()
+        .map_err(|e| format!("Can't run ps: {e}"))?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<_> = text.split_whitespace().collect();
+    let [rss_kb, time, etime] = fields[..] else {
+        return Err(format!("No process {pid}"));
+    };
+    let unreadable = || format!("Unreadable ps output: {}", text.trim());
+    Ok(Sample {
+        rss_bytes: rss_kb.parse::<u64>().map_err(|_| unreadable())? * 1024,
+        cpu_time: parse_clock(time).ok_or_else(unreadable)?,
+        age: parse_clock(etime).ok_or_else(unreadable)?,
+    })
+}
+
+/// Read memory and CPU time of `pid` from PowerShell's `Get-Process`.
+#[cfg(windows)]
+pub fn sample(pid: u32) -> Result<Sample, String> {
+    use std::os::windows::process::CommandExt;
+
+    // Ticks (100 ns) rather than seconds, which the user's locale may
+    // print with a decimal comma.
+    let script = format!(
+        "$p = Get-Process -Id {pid} -ErrorAction Stop; \
+         '{{0}} {{1}} {{2}}' -f $p.WorkingSet64, $p.TotalProcessorTime.Ticks, ((Get-Date) - $p.StartTime).Ticks"
+    );
+    // Without it a console window flashes up on every periodic check.
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| format!("Can't run powershell: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("No process {pid}"));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    parse_get_process(&text).ok_or_else(|| format!("Unreadable Get-Process output: {}", text.trim()))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn sample(_pid: u32) -> Result<Sample, String> {
+    Err("not supported on this platform".to_string())
+}
+
+/// Parse the `working-set cpu-ticks age-ticks` line `sample` has
+/// PowerShell print.
+#[cfg(any(windows, test))]
+fn parse_get_process(text: &str) -> Option<Sample> {
+    const TICKS_PER_SEC: u64 = 10_000_000;
+    let ticks = |value: u64| Duration::new(value / TICKS_PER_SEC, (value % TICKS_PER_SEC) as u32 * 100);
+    let fields: Vec<u64> = text.split_whitespace().map(str::parse).collect::<Result<_, _>>().ok()?;
+    let [rss_bytes, cpu_ticks, age_ticks] = fields[..] else {
+        return None;
+    };
+    Some(Sample { rss_bytes, cpu_time: ticks(cpu_ticks), age: ticks(age_ticks) })
+}
+
+/// Parse `ps`'s `[[dd-]hh:]mm:ss[.ff]` durations.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn parse_clock(text: &str) -> Option<Duration> {
+    let (days, clock) = match text.split_once('-') {
+        Some((days, clock)) => (days.parse::<f64>().ok()?, clock),
+        None => (0.0, text),
+    };
+    let mut seconds = 0.0;
+    for part in clock.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(Duration::from_secs_f64(days * 86_400.0 + seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_is_a_rate_and_warnings_fire_once_per_crossing() {
+        let limits = ResourceLimits { max_rss_mb: Some(100), max_cpu_percent: Some(50.0) };
+        let mut monitor = ResourceMonitor::default();
+        let start = Instant::now();
+        let sample = |rss_mb: u64, cpu_secs: u64| Sample {
+            rss_bytes: rss_mb * 1024 * 1024,
+            cpu_time: Duration::from_secs(cpu_secs),
+            age: Duration::from_secs(40),
+        };
+
+        let (usage, warnings) = monitor.record(7, sample(50, 10), start, &limits);
+        assert_eq!(usage.cpu_percent, 25.0, "averaged since the process started");
+        assert!(warnings.is_empty());
+        let (usage, warnings) = monitor.record(7, sample(150, 18), start + Duration::from_secs(10), &limits);
+        assert_eq!(usage.cpu_percent, 80.0);
+        let resources: Vec<_> = warnings.iter().map(|warning| warning.resource).collect();
+        assert_eq!(resources, ["memory", "cpu"]);
+        let (_, warnings) = monitor.record(7, sample(150, 19), start + Duration::from_secs(20), &limits);
+        assert_eq!(warnings, [], "memory is still over; cpu dropped back");
+        let (_, warnings) = monitor.record(7, sample(150, 29), start + Duration::from_secs(30), &limits);
+        assert_eq!(warnings.iter().map(|warning| warning.resource).collect::<Vec<_>>(), ["cpu"]);
+
+        assert_eq!(ResourceLimits { max_rss_mb: Some(0), ..limits }.check().unwrap_err().0, "max_rss_mb");
+    }
+
+    #[test]
+    fn get_process_output_is_parsed_from_ticks() {
+        let sample = parse_get_process("52428800 25000000 600000000\r\n").unwrap();
+        assert_eq!(sample.rss_bytes, 50 * 1024 * 1024);
+        assert_eq!((sample.cpu_time, sample.age), (Duration::from_millis(2500), Duration::from_secs(60)));
+        assert_eq!(parse_get_process("52428800 2,5 60"), None);
+        assert_eq!(parse_get_process(""), None);
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn own_process_can_be_sampled() {
+        assert!(sample(std::process::id()).unwrap().rss_bytes > 0);
+        assert!(sample(u32::MAX).is_err());
+    }
+}
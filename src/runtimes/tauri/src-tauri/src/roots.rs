@@ -0,0 +1,41 @@
+//! The folders the library is built from.
+//!
+//! Collections span several drives, so the app keeps a list of library
+//! roots in the config and hands it to every backend started, whose
+//! `scan_library` scans each folder on it.  Until a root is first added
+//! or removed through the app, the backend's own folder list stands.
+//! Roots are compared as paths, so `/songs/` and `/songs` are the same.
+
+use std::path::Path;
+
+/// Add `root` unless it is already on the list; returns whether it was
+/// added.
+pub fn add(roots: &mut Vec<String>, root: String) -> bool {
+    if roots.iter().any(|known| Path::new(known) == Path::new(&root)) {
+        return false;
+    }
+    roots.push(root);
+    true
+}
+
+/// Take `root` off the list; returns whether it was on it.
+pub fn remove(roots: &mut Vec<String>, root: &str) -> bool {
+    let before = roots.len();
+    roots.retain(|known| Path::new(known) != Path::new(root));
+    roots.len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roots_are_compared_as_paths() {
+        let mut roots = vec!["/songs".to_string()];
+        assert!(!add(&mut roots, "/songs/".to_string()));
+        assert!(add(&mut roots, "/mnt/karaoke".to_string()));
+        assert!(remove(&mut roots, "/songs/"));
+        assert!(!remove(&mut roots, "/songs"));
+        assert_eq!(roots, ["/mnt/karaoke"]);
+    }
+}
@@ -0,0 +1,71 @@
+//! Typed, paged results for the `search_songs` command.
+//!
+//! The backend answers `search_songs` with `{results, total}`, already
+//! cut to the requested page.  Backends from before paging send every
+//! match and no `total`, so the page is cut here instead.
+
+use crate::playback::SongInfo;
+use serde::{Deserialize, Serialize};
+
+/// Page size used when the caller doesn't give one.
+pub const DEFAULT_SEARCH_LIMIT: usize = 50;
+/// Largest page a caller may ask for, so the search box can't pull the
+/// whole library across the pipe.
+pub const MAX_SEARCH_LIMIT: usize = 500;
+
+/// One page of matches returned by `search_songs`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SearchResults {
+    /// Number of matches before paging.
+    pub total: usize,
+    pub items: Vec<SongInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawResults {
+    #[serde(default)]
+    results: Vec<SongInfo>,
+    total: Option<usize>,
+}
+
+impl SearchResults {
+    /// Build from the `data` field of a `search_songs` response for the
+    /// page starting at `offset`.
+    pub fn from_backend(data: &serde_json::Value, offset: usize, limit: usize) -> Result<Self, serde_json::Error> {
+        let raw = RawResults::deserialize(data)?;
+        Ok(match raw.total {
+            Some(total) => SearchResults { total, items: raw.results },
+            None => SearchResults {
+                total: raw.results.len(),
+                items: raw.results.into_iter().skip(offset).take(limit).collect(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn songs(n: usize) -> Vec<serde_json::Value> {
+        (0..n).map(|i| json!({ "title": format!("Song {i}"), "filepath": format!("/s/{i}.cdg") })).collect()
+    }
+
+    #[test]
+    fn paged_backend_results_are_taken_as_sent() {
+        let data = json!({ "results": songs(2), "total": 40 });
+        let page = SearchResults::from_backend(&data, 10, 2).unwrap();
+        assert_eq!(page.total, 40);
+        assert_eq!(page.items[0].title, "Song 0");
+    }
+
+    #[test]
+    fn unpaged_backend_results_are_cut_locally() {
+        let data = json!({ "results": songs(5) });
+        let page = SearchResults::from_backend(&data, 3, 10).unwrap();
+        assert_eq!(page.total, 5);
+        let titles: Vec<&str> = page.items.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Song 3", "Song 4"]);
+    }
+}
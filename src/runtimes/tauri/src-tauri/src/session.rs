@@ -0,0 +1,140 @@
+//! Saving the whole session to disk and replaying it into a backend.
+//!
+//! A snapshot holds the playlist, the song playing and how far into it,
+//! the volume and the shuffle and repeat modes.  `snapshot_state` writes
+//! one on request; the app also writes [`SNAPSHOT_FILE`] in the app data
+//! directory every [`AUTOSNAPSHOT_INTERVAL`] and on a clean exit, but
+//! only while a playlist song is loaded, so starting the app and quitting
+//! again doesn't overwrite where the last session left off.
+//!
+//! `restore_state` replays a snapshot as ordinary commands.  Songs whose
+//! files have moved since are skipped and reported, and the song to
+//! resume is found by its place among the songs that were restored.
+
+use crate::advance::RepeatMode;
+use crate::error::BackendError;
+use crate::playback::{PlaybackState, SongInfo};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const SNAPSHOT_FILE: &str = "session.json";
+
+/// How often the session is snapshotted while a backend runs.
+pub const AUTOSNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionSnapshot {
+    pub songs: Vec<SongInfo>,
+    /// Position of the current song in `songs`, if one was loaded.
+    pub playlist_index: Option<usize>,
+    pub position_ms: u64,
+    pub volume: f64,
+    /// Whether the current song was playing rather than paused or stopped.
+    pub playing: bool,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+}
+
+impl SessionSnapshot {
+    pub fn new(songs: Vec<SongInfo>, playback: &PlaybackState, shuffle: bool, repeat: RepeatMode) -> Self {
+        SessionSnapshot {
+            songs,
+            playlist_index: playback.playlist_index,
+            position_ms: playback.position_ms,
+            volume: playback.volume,
+            playing: playback.state == "playing",
+            shuffle,
+            repeat,
+        }
+    }
+}
+
+/// What `restore_state` put back.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct RestoreReport {
+    pub restored: usize,
+    /// File paths of the songs left out, in playlist order.
+    pub skipped: Vec<String>,
+    /// Where in the restored playlist playback resumed; `None` when no
+    /// song was loaded or its file is gone.
+    pub resumed_at: Option<usize>,
+}
+
+impl RestoreReport {
+    /// Count entry `index` of the snapshot as restored, noting where
+    /// playback resumes if it is the snapshot's current song.
+    pub fn restore(&mut self, snapshot: &SessionSnapshot, index: usize) {
+        if snapshot.playlist_index == Some(index) {
+            self.resumed_at = Some(self.restored);
+        }
+        self.restored += 1;
+    }
+
+    pub fn skip(&mut self, song: &SongInfo) {
+        self.skipped.push(song.filepath.clone());
+    }
+}
+
+/// Where the automatic snapshot lives for an app with `config`.
+pub fn snapshot_path(config: &tauri::Config) -> Option<PathBuf> {
+    tauri::api::path::app_data_dir(config).map(|dir| dir.join(SNAPSHOT_FILE))
+}
+
+pub fn save(path: &Path, snapshot: &SessionSnapshot) -> Result<(), BackendError> {
+    let failed = |reason: String| BackendError::PlaylistFile { path: path.display().to_string(), reason };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| failed(e.to_string()))?;
+    }
+    let text = serde_json::to_string_pretty(snapshot).map_err(|e| failed(e.to_string()))?;
+    std::fs::write(path, text).map_err(|e| failed(e.to_string()))
+}
+
+pub fn load(path: &Path) -> Result<SessionSnapshot, BackendError> {
+    let failed = |reason: String| BackendError::PlaylistFile { path: path.display().to_string(), reason };
+    let text = std::fs::read_to_string(path).map_err(|e| failed(e.to_string()))?;
+    serde_json::from_str(&text).map_err(|e| failed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(filepath: &str) -> SongInfo {
+        SongInfo { filepath: filepath.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn snapshot_round_trips() {
+        let path = std::env::temp_dir()
+            .join(format!("pykaraoke-ng-session-{}", std::process::id()))
+            .join(SNAPSHOT_FILE);
+        let playback = PlaybackState {
+            state: "playing".to_string(),
+            volume: 0.5,
+            position_ms: 42_000,
+            playlist_index: Some(1),
+            ..Default::default()
+        };
+        let snapshot = SessionSnapshot::new(vec![song("/a.cdg"), song("/b.cdg")], &playback, true, RepeatMode::All);
+        save(&path, &snapshot).unwrap();
+        assert_eq!(load(&path).unwrap(), snapshot);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+        assert_eq!(load(&path).unwrap_err().kind(), "playlist_file");
+    }
+
+    #[test]
+    fn resumed_song_is_found_among_the_restored_ones() {
+        let snapshot = SessionSnapshot {
+            songs: vec![song("/gone.cdg"), song("/a.cdg"), song("/b.cdg")],
+            playlist_index: Some(2),
+            ..Default::default()
+        };
+        let mut report = RestoreReport::default();
+        report.skip(&snapshot.songs[0]);
+        report.restore(&snapshot, 1);
+        report.restore(&snapshot, 2);
+        assert_eq!(report, RestoreReport { restored: 2, skipped: vec!["/gone.cdg".to_string()], resumed_at: Some(1) });
+    }
+}
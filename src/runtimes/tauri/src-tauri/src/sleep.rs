@@ -0,0 +1,62 @@
+//! Stopping playback later: "stop after this song" or "stop in 30
+//! minutes".
+//!
+//! A timer for the current song is a flag the `song_finished` handler
+//! checks instead of advancing; a duration is a task that fades the music
+//! out once it elapses.  Only one timer is set at a time, and stopping the
+//! backend cancels it.
+
+use serde::Serialize;
+
+/// Accepted sleep timer durations, in milliseconds.
+pub const MIN_SLEEP_MS: u64 = 1_000;
+pub const MAX_SLEEP_MS: u64 = 24 * 60 * 60 * 1000;
+/// How long playback fades out when a duration timer fires.
+pub const SLEEP_FADE_MS: u64 = 3_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SleepTimer {
+    /// Stop when the current song finishes.
+    AfterCurrent,
+    /// Stop once this many milliseconds have passed.
+    Duration(u64),
+}
+
+impl SleepTimer {
+    /// The timer `set_sleep_timer` params describe; the error names the
+    /// field at fault and why.
+    pub fn new(after_current: Option<bool>, duration_ms: Option<u64>) -> Result<Self, (&'static str, String)> {
+        match (after_current.unwrap_or(false), duration_ms) {
+            (true, Some(_)) => Err(("duration_ms", "can't be combined with after_current".to_string())),
+            (true, None) => Ok(SleepTimer::AfterCurrent),
+            (false, None) => Err(("duration_ms", "required unless after_current is true".to_string())),
+            (false, Some(ms)) if !(MIN_SLEEP_MS..=MAX_SLEEP_MS).contains(&ms) => {
+                Err(("duration_ms", format!("must be between {MIN_SLEEP_MS} and {MAX_SLEEP_MS}")))
+            }
+            (false, Some(ms)) => Ok(SleepTimer::Duration(ms)),
+        }
+    }
+
+    /// The `reason` `sleep-timer-fired` gives for this timer.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            SleepTimer::AfterCurrent => "after_current",
+            SleepTimer::Duration(_) => "duration",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timers_take_exactly_one_kind() {
+        assert_eq!(SleepTimer::new(Some(true), None), Ok(SleepTimer::AfterCurrent));
+        assert_eq!(SleepTimer::new(None, Some(60_000)), Ok(SleepTimer::Duration(60_000)));
+        assert!(SleepTimer::new(Some(true), Some(60_000)).is_err());
+        assert!(SleepTimer::new(Some(false), None).is_err());
+        assert_eq!(SleepTimer::new(None, Some(10)).unwrap_err().0, "duration_ms");
+    }
+}
@@ -0,0 +1,153 @@
+//! Detection of runaway backend stderr output.
+//!
+//! A backend stuck in an error loop (e.g. a codec failure on every frame)
+//! can write hundreds of lines a second.  The stderr reader feeds every
+//! line to a [`BurstDetector`], which reports a burst once when the rate
+//! crosses the threshold and stays quiet until the output calms down
+//! again.  How much the backend logs there is set with
+//! `set_backend_log_level`.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BURST_THRESHOLD: u64 = 100;
+
+/// Lines kept for the sample attached to a burst report.
+const SAMPLE_LINES: usize = 5;
+
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Python logging levels the backend accepts, most verbose first.
+pub const LOG_LEVELS: &[&str] = &["DEBUG", "INFO", "WARNING", "ERROR"];
+
+/// `level` as one of [`LOG_LEVELS`], in any case.
+pub fn log_level(level: &str) -> Result<&'static str, String> {
+    LOG_LEVELS
+        .iter()
+        .find(|known| known.eq_ignore_ascii_case(level.trim()))
+        .copied()
+        .ok_or_else(|| format!("must be one of {}", LOG_LEVELS.join(", ")))
+}
+
+/// Payload of the `backend-stderr-burst` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StderrBurst {
+    pub lines_per_sec: u64,
+    /// The most recent lines, oldest first.
+    pub sample: Vec<String>,
+}
+
+/// The lines-per-second threshold, adjustable while the reader runs.
+#[derive(Debug)]
+pub struct BurstThreshold(AtomicU64);
+
+impl Default for BurstThreshold {
+    fn default() -> Self {
+        BurstThreshold(AtomicU64::new(DEFAULT_BURST_THRESHOLD))
+    }
+}
+
+impl BurstThreshold {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, lines_per_sec: u64) {
+        self.0.store(lines_per_sec, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BurstDetector {
+    window_start: Option<Instant>,
+    count: u64,
+    alerted: bool,
+    recent: VecDeque<String>,
+}
+
+impl BurstDetector {
+    /// Count one line received at `now`.  Returns a report the first time
+    /// more than `threshold` lines arrive within a second; a threshold of
+    /// 0 disables detection.
+    pub fn line(&mut self, line: &str, now: Instant, threshold: u64) -> Option<StderrBurst> {
+        match self.window_start {
+            Some(start) if now.duration_since(start) < WINDOW => {}
+            start => {
+                // A quiet window (or silence) ends the burst, so a later
+                // one alerts again.
+                let silent = start.is_some_and(|s| now.duration_since(s) >= WINDOW * 2);
+                if silent || self.count <= threshold {
+                    self.alerted = false;
+                }
+                self.window_start = Some(now);
+                self.count = 0;
+            }
+        }
+        self.count += 1;
+        if self.recent.len() == SAMPLE_LINES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(line.to_string());
+
+        if threshold == 0 || self.alerted || self.count <= threshold {
+            return None;
+        }
+        self.alerted = true;
+        Some(StderrBurst {
+            lines_per_sec: self.count,
+            sample: self.recent.iter().cloned().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(detector: &mut BurstDetector, start: Instant, lines: u64) -> Vec<StderrBurst> {
+        (0..lines)
+            .filter_map(|n| {
+                let at = start + WINDOW.mul_f64(n as f64 / lines as f64);
+                detector.line(&format!("error {n}"), at, 10)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_a_burst_once() {
+        let mut detector = BurstDetector::default();
+        let start = Instant::now();
+        let bursts = feed(&mut detector, start, 50);
+        assert_eq!(bursts.len(), 1);
+        assert_eq!(bursts[0].lines_per_sec, 11);
+        assert_eq!(bursts[0].sample.last().unwrap(), "error 10");
+        // Still noisy in the next second: no second alert.
+        assert!(feed(&mut detector, start + WINDOW, 50).is_empty());
+    }
+
+    #[test]
+    fn quiet_output_rearms_the_alert() {
+        let mut detector = BurstDetector::default();
+        let start = Instant::now();
+        assert!(feed(&mut detector, start, 5).is_empty());
+        assert_eq!(feed(&mut detector, start + WINDOW, 50).len(), 1);
+        assert!(feed(&mut detector, start + WINDOW * 2, 3).is_empty());
+        assert_eq!(feed(&mut detector, start + WINDOW * 3, 50).len(), 1);
+    }
+
+    #[test]
+    fn zero_threshold_disables_detection() {
+        let mut detector = BurstDetector::default();
+        let now = Instant::now();
+        assert!((0..1000).all(|_| detector.line("x", now, 0).is_none()));
+    }
+
+    #[test]
+    fn log_levels_are_matched_in_any_case() {
+        assert_eq!(log_level("debug"), Ok("DEBUG"));
+        assert_eq!(log_level(" Warning "), Ok("WARNING"));
+        assert_eq!(log_level("TRACE"), Err("must be one of DEBUG, INFO, WARNING, ERROR".to_string()));
+    }
+}
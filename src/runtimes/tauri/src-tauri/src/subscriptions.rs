@@ -0,0 +1,78 @@
+//! Per-window interest in backend event types.
+//!
+//! Windows that never call `subscribe_events` keep receiving every
+//! `backend-event`, as before.  Once a window subscribes it only receives
+//! the event types it named.
+
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Default)]
+pub struct EventSubscriptions {
+    windows: HashMap<String, BTreeSet<String>>,
+}
+
+impl EventSubscriptions {
+    /// Add `event_types` to the window's subscription and return the
+    /// resulting set.
+    pub fn subscribe(&mut self, window: &str, event_types: Vec<String>) -> Vec<String> {
+        let set = self.windows.entry(window.to_string()).or_default();
+        set.extend(event_types);
+        set.iter().cloned().collect()
+    }
+
+    /// Remove `event_types` from the window's subscription, or with `None`
+    /// drop the subscription entirely so the window receives everything
+    /// again.  Returns the remaining set (empty when unsubscribed).
+    pub fn unsubscribe(&mut self, window: &str, event_types: Option<Vec<String>>) -> Vec<String> {
+        let Some(event_types) = event_types else {
+            self.windows.remove(window);
+            return Vec::new();
+        };
+        let Some(set) = self.windows.get_mut(window) else {
+            return Vec::new();
+        };
+        for event_type in &event_types {
+            set.remove(event_type);
+        }
+        set.iter().cloned().collect()
+    }
+
+    /// Whether `window` should receive an event of `event_type`.
+    pub fn wants(&self, window: &str, event_type: &str) -> bool {
+        self.windows
+            .get(window)
+            .is_none_or(|set| set.contains(event_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsubscribed_windows_receive_everything() {
+        let subs = EventSubscriptions::default();
+        assert!(subs.wants("main", "scan_progress"));
+    }
+
+    #[test]
+    fn subscribed_windows_receive_only_their_types() {
+        let mut subs = EventSubscriptions::default();
+        subs.subscribe("lyrics", vec!["state_changed".to_string()]);
+        assert!(subs.wants("lyrics", "state_changed"));
+        assert!(!subs.wants("lyrics", "scan_progress"));
+        assert!(subs.wants("main", "scan_progress"));
+    }
+
+    #[test]
+    fn unsubscribe_removes_types_or_restores_default() {
+        let mut subs = EventSubscriptions::default();
+        subs.subscribe("lyrics", vec!["state_changed".to_string(), "song_finished".to_string()]);
+        let left = subs.unsubscribe("lyrics", Some(vec!["state_changed".to_string()]));
+        assert_eq!(left, ["song_finished"]);
+        assert!(!subs.wants("lyrics", "state_changed"));
+
+        subs.unsubscribe("lyrics", None);
+        assert!(subs.wants("lyrics", "state_changed"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Revealing the app's log and config directories in the OS file manager,
+//! so users can attach their logs and config to a bug report.
+//!
+//! The shell scope only lets `tauri::api::shell::open` open URLs, so paths
+//! are handed to the `open` crate directly.  The resolved path is reported
+//! even when no file manager could be opened, so the UI can show it for
+//! copying instead.
+
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RevealedDir {
+    pub path: String,
+    pub opened: bool,
+    /// Why the directory couldn't be opened, when it couldn't.
+    pub error: Option<String>,
+}
+
+/// Create `dir` if needed and open it with `opener`.  `what` names the
+/// directory for the error when the system has none.
+pub fn reveal(
+    what: &str,
+    dir: Option<PathBuf>,
+    opener: impl FnOnce(&Path) -> io::Result<()>,
+) -> Result<RevealedDir, String> {
+    let dir = dir.ok_or_else(|| format!("No app {what} directory on this system"))?;
+    let result = std::fs::create_dir_all(&dir).and_then(|()| opener(&dir));
+    Ok(RevealedDir {
+        path: dir.display().to_string(),
+        opened: result.is_ok(),
+        error: result.err().map(|e| e.to_string()),
+    })
+}
+
+/// Open `path` in the OS file manager.
+pub fn open_in_file_manager(path: &Path) -> io::Result<()> {
+    open::that(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_the_path_even_when_opening_fails() {
+        let dir = std::env::temp_dir().join("pykaraoke-reveal-test");
+        let revealed = reveal("log", Some(dir.clone()), |_| Err(io::Error::other("no file manager"))).unwrap();
+        assert_eq!(revealed.path, dir.display().to_string());
+        assert!(!revealed.opened);
+        assert_eq!(revealed.error.as_deref(), Some("no file manager"));
+        assert!(dir.is_dir(), "directory is created so there is something to open");
+
+        let mut opened = None;
+        let revealed = reveal("log", Some(dir.clone()), |path| {
+            opened = Some(path.to_path_buf());
+            Ok(())
+        })
+        .unwrap();
+        assert!(revealed.opened && revealed.error.is_none());
+        assert_eq!(opened, Some(dir));
+    }
+
+    #[test]
+    fn missing_directory_is_an_error() {
+        assert_eq!(
+            reveal("config", None, |_| Ok(())),
+            Err("No app config directory on this system".to_string())
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Colour schemes for the lyrics the backend draws.
+//!
+//! KAR and MIDI lyrics are rendered by the backend, so their colours
+//! can't follow a stylesheet: the backend lists its themes through
+//! `get_available_themes`, and a choice is checked against that list
+//! before it is sent.  CDG graphics carry their own colours.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    /// Identifier `set_theme` takes, e.g. `high_contrast`.
+    pub name: String,
+    /// Name to show in the picker.
+    pub label: String,
+}
+
+/// Check that `name` is one of the `available` themes.
+pub fn check_theme(name: &str, available: &[Theme]) -> Result<(), String> {
+    if available.iter().any(|theme| theme.name == name) {
+        return Ok(());
+    }
+    let names: Vec<&str> = available.iter().map(|theme| theme.name.as_str()).collect();
+    Err(format!("must be one of {}", names.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_available_themes_are_accepted() {
+        let available = [
+            Theme { name: "classic".to_string(), label: "Classic".to_string() },
+            Theme { name: "stage".to_string(), label: "Stage".to_string() },
+        ];
+        assert!(check_theme("stage", &available).is_ok());
+        assert_eq!(check_theme("neon", &available), Err("must be one of classic, stage".to_string()));
+    }
+}
@@ -0,0 +1,140 @@
+//! The system tray icon, for controlling playback while the window is
+//! hidden behind the lyrics or minimized.
+//!
+//! Its menu runs the same actions as the global hotkeys, plus Quit, and
+//! its tooltip names the song playing.  Built with the `system-tray`
+//! feature and shown unless `tray_icon` is turned off in the config (see
+//! `set_tray_icon`).
+
+use crate::hotkeys::HotkeyAction;
+use crate::playback::PlaybackState;
+use std::sync::Mutex;
+use tauri::{CustomMenuItem, SystemTrayMenu, SystemTrayMenuItem};
+
+pub const TRAY_ID: &str = "main";
+
+/// Shown in the tooltip, and alone when nothing is playing.
+pub const APP_NAME: &str = "PyKaraoke NG";
+
+/// What a tray menu item does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayItem {
+    Playback(HotkeyAction),
+    Quit,
+}
+
+/// Menu item ids and titles, top to bottom.
+pub const MENU_ITEMS: &[(&str, &str)] = &[
+    ("play_pause", "Play/Pause"),
+    ("next", "Next"),
+    ("previous", "Previous"),
+    ("quit", "Quit"),
+];
+
+impl TrayItem {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "play_pause" => Some(TrayItem::Playback(HotkeyAction::PlayPause)),
+            "next" => Some(TrayItem::Playback(HotkeyAction::Next)),
+            "previous" => Some(TrayItem::Playback(HotkeyAction::Previous)),
+            "quit" => Some(TrayItem::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// The tray menu, with Quit set apart from the playback items.
+pub fn menu() -> SystemTrayMenu {
+    MENU_ITEMS.iter().fold(SystemTrayMenu::new(), |menu, &(id, title)| {
+        let menu = match TrayItem::from_id(id) {
+            Some(TrayItem::Quit) => menu.add_native_item(SystemTrayMenuItem::Separator),
+            _ => menu,
+        };
+        menu.add_item(CustomMenuItem::new(id, title))
+    })
+}
+
+/// The tooltip for `playback`: the app name, then the song while one is
+/// playing or paused.
+pub fn tooltip(playback: Option<&PlaybackState>) -> String {
+    let Some((playback, song)) = playback
+        .filter(|p| p.state == "playing" || p.state == "paused")
+        .and_then(|p| Some((p, p.current_song.as_ref()?)))
+    else {
+        return APP_NAME.to_string();
+    };
+    let title = if song.title.is_empty() { &song.filename } else { &song.title };
+    let mut text = match song.artist.as_str() {
+        "" => format!("{APP_NAME}: {title}"),
+        artist => format!("{APP_NAME}: {title} – {artist}"),
+    };
+    if playback.state == "paused" {
+        text.push_str(" (paused)");
+    }
+    text
+}
+
+/// The tooltip last set, so a `state_changed` event that leaves it as it
+/// was doesn't touch the tray.
+#[derive(Default)]
+pub struct Tooltip(Mutex<Option<String>>);
+
+impl Tooltip {
+    /// Remember `text`, returning it if it differs from the last one.
+    pub fn replace(&self, text: String) -> Option<String> {
+        let mut shown = self.0.lock().unwrap();
+        if shown.as_ref() == Some(&text) {
+            return None;
+        }
+        *shown = Some(text.clone());
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playback::SongInfo;
+
+    fn playing(state: &str, title: &str, artist: &str) -> PlaybackState {
+        PlaybackState {
+            state: state.to_string(),
+            current_song: Some(SongInfo {
+                title: title.to_string(),
+                artist: artist.to_string(),
+                filename: "dancing-queen.cdg".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn every_menu_item_has_an_action() {
+        for (id, _) in MENU_ITEMS {
+            assert!(TrayItem::from_id(id).is_some(), "{id}");
+        }
+        assert_eq!(TrayItem::from_id("next"), Some(TrayItem::Playback(HotkeyAction::Next)));
+        assert_eq!(TrayItem::from_id("volume_up"), None);
+    }
+
+    #[test]
+    fn tooltip_names_the_song_playing() {
+        assert_eq!(tooltip(None), APP_NAME);
+        assert_eq!(tooltip(Some(&playing("playing", "Dancing Queen", "ABBA"))), "PyKaraoke NG: Dancing Queen – ABBA");
+        assert_eq!(
+            tooltip(Some(&playing("paused", "", ""))),
+            "PyKaraoke NG: dancing-queen.cdg (paused)",
+            "falls back to the file name"
+        );
+        assert_eq!(tooltip(Some(&playing("stopped", "Dancing Queen", "ABBA"))), APP_NAME);
+    }
+
+    #[test]
+    fn an_unchanged_tooltip_is_not_set_again() {
+        let shown = Tooltip::default();
+        assert_eq!(shown.replace(APP_NAME.to_string()).as_deref(), Some(APP_NAME));
+        assert_eq!(shown.replace(APP_NAME.to_string()), None);
+        assert!(shown.replace("PyKaraoke NG: Dancing Queen".to_string()).is_some());
+    }
+}
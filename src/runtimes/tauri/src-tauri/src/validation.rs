@@ -0,0 +1,381 @@
+//! Per-action validation of command params.
+//!
+//! `send_command` forwards arbitrary JSON to the Python backend, so a bad
+//! value (e.g. `{"volume":"loud"}`) would otherwise only fail deep inside
+//! the player.  Each entry in [`ACTION_SCHEMAS`] describes the params an
+//! action accepts; actions without an entry are passed through unchecked.
+//! Supporting a new action is a matter of adding a table entry.
+
+use crate::error::BackendError;
+
+/// The accepted type (and range) of a single param.
+#[derive(Debug, Clone, Copy)]
+pub enum FieldType {
+    /// Any JSON number within the inclusive bounds.
+    Number { min: f64, max: f64 },
+    /// A JSON integer within the inclusive bounds.
+    Integer { min: i64, max: i64 },
+    /// One of the listed strings.
+    OneOf(&'static [&'static str]),
+}
+
+#[derive(Debug)]
+pub struct FieldSchema {
+    pub name: &'static str,
+    pub required: bool,
+    pub ty: FieldType,
+}
+
+#[derive(Debug)]
+pub struct ActionSchema {
+    pub action: &'static str,
+    pub fields: &'static [FieldSchema],
+}
+
+pub const ACTION_SCHEMAS: &[ActionSchema] = &[
+    ActionSchema {
+        action: "set_volume",
+        fields: &[FieldSchema {
+            name: "volume",
+            required: true,
+            ty: FieldType::Number { min: 0.0, max: 1.0 },
+        }],
+    },
+    ActionSchema {
+        action: "seek",
+        fields: &[FieldSchema {
+            name: "position_ms",
+            required: true,
+            ty: FieldType::Number { min: 0.0, max: f64::MAX },
+        }],
+    },
+    ActionSchema {
+        action: "play",
+        fields: &[FieldSchema {
+            name: "playlist_index",
+            required: false,
+            ty: FieldType::Integer { min: 0, max: i64::MAX },
+        }],
+    },
+    ActionSchema {
+        action: "insert_in_playlist",
+        fields: &[FieldSchema {
+            name: "index",
+            required: true,
+            ty: FieldType::Integer { min: 0, max: i64::MAX },
+        }],
+    },
+    ActionSchema {
+        action: "preload",
+        fields: &[FieldSchema {
+            name: "playlist_index",
+            required: true,
+            ty: FieldType::Integer { min: 0, max: i64::MAX },
+        }],
+    },
+    ActionSchema {
+        action: "waveform",
+        fields: &[FieldSchema {
+            name: "buckets",
+            required: true,
+            ty: FieldType::Integer {
+                min: crate::waveform::MIN_BUCKETS as i64,
+                max: crate::waveform::MAX_BUCKETS as i64,
+            },
+        }],
+    },
+    ActionSchema {
+        action: "set_transpose",
+        fields: &[FieldSchema {
+            name: "semitones",
+            required: true,
+            ty: FieldType::Integer { min: -12, max: 12 },
+        }],
+    },
+    ActionSchema {
+        action: "set_tempo",
+        fields: &[FieldSchema {
+            name: "ratio",
+            required: true,
+            ty: FieldType::Number { min: crate::playback::MIN_TEMPO, max: crate::playback::MAX_TEMPO },
+        }],
+    },
+    ActionSchema {
+        action: "set_vocal_removal",
+        fields: &[FieldSchema {
+            name: "strength",
+            required: true,
+            ty: FieldType::Number { min: 0.0, max: crate::playback::MAX_VOCAL_REMOVAL },
+        }],
+    },
+    ActionSchema {
+        action: "set_av_sync_offset",
+        fields: &[FieldSchema {
+            name: "offset_ms",
+            required: true,
+            ty: FieldType::Integer {
+                min: -crate::playback::MAX_AV_SYNC_OFFSET_MS,
+                max: crate::playback::MAX_AV_SYNC_OFFSET_MS,
+            },
+        }],
+    },
+    ActionSchema {
+        action: "set_normalization",
+        fields: &[FieldSchema {
+            name: "target_lufs",
+            required: true,
+            ty: FieldType::Number { min: crate::playback::MIN_TARGET_LUFS, max: crate::playback::MAX_TARGET_LUFS },
+        }],
+    },
+    ActionSchema {
+        action: "fade_out",
+        fields: &[FieldSchema {
+            name: "duration_ms",
+            required: true,
+            ty: FieldType::Integer { min: crate::playback::MIN_FADE_MS, max: crate::playback::MAX_FADE_MS },
+        }],
+    },
+    ActionSchema {
+        action: "play_test_tone",
+        fields: &[
+            FieldSchema {
+                name: "frequency_hz",
+                required: true,
+                ty: FieldType::Number { min: crate::playback::MIN_TONE_HZ, max: crate::playback::MAX_TONE_HZ },
+            },
+            FieldSchema {
+                name: "duration_ms",
+                required: true,
+                ty: FieldType::Integer { min: crate::playback::MIN_TONE_MS, max: crate::playback::MAX_TONE_MS },
+            },
+            FieldSchema {
+                name: "channel",
+                required: true,
+                ty: FieldType::OneOf(crate::playback::TONE_CHANNELS),
+            },
+        ],
+    },
+    ActionSchema {
+        action: "set_mic_monitoring",
+        fields: &[FieldSchema {
+            name: "gain",
+            required: true,
+            ty: FieldType::Number { min: 0.0, max: crate::audio::MAX_MIC_GAIN },
+        }],
+    },
+    ActionSchema {
+        action: "set_crossfade",
+        fields: &[FieldSchema {
+            name: "duration_ms",
+            required: true,
+            ty: FieldType::Integer { min: 0, max: crate::playback::MAX_CROSSFADE_MS as i64 },
+        }],
+    },
+    ActionSchema {
+        action: "set_log_level",
+        fields: &[FieldSchema {
+            name: "level",
+            required: true,
+            ty: FieldType::OneOf(crate::stderr::LOG_LEVELS),
+        }],
+    },
+    ActionSchema {
+        action: "set_repeat_mode",
+        fields: &[FieldSchema {
+            name: "mode",
+            required: true,
+            ty: FieldType::OneOf(crate::advance::RepeatMode::NAMES),
+        }],
+    },
+    ActionSchema {
+        action: "get_library",
+        fields: &[
+            FieldSchema {
+                name: "sort_by",
+                required: false,
+                ty: FieldType::OneOf(crate::library::LibrarySort::NAMES),
+            },
+            FieldSchema {
+                name: "limit",
+                required: false,
+                ty: FieldType::Integer { min: 1, max: crate::library::MAX_PAGE_SIZE as i64 },
+            },
+            FieldSchema {
+                name: "offset",
+                required: false,
+                ty: FieldType::Integer { min: 0, max: i64::MAX },
+            },
+        ],
+    },
+    ActionSchema {
+        action: "search_songs",
+        fields: &[
+            FieldSchema {
+                name: "limit",
+                required: false,
+                ty: FieldType::Integer { min: 1, max: crate::search::MAX_SEARCH_LIMIT as i64 },
+            },
+            FieldSchema {
+                name: "offset",
+                required: false,
+                ty: FieldType::Integer { min: 0, max: i64::MAX },
+            },
+        ],
+    },
+];
+
+/// Look up the schema registered for `action`, if any.
+pub fn schema_for(action: &str) -> Option<&'static ActionSchema> {
+    ACTION_SCHEMAS.iter().find(|s| s.action == action)
+}
+
+/// Check `params` against the schema for `action`.
+///
+/// Unknown actions and fields not mentioned in the schema are accepted, so
+/// the backend stays free to grow new options ahead of the table.
+pub fn validate_params(action: &str, params: Option<&serde_json::Value>) -> Result<(), BackendError> {
+    let Some(schema) = schema_for(action) else {
+        return Ok(());
+    };
+    let invalid = |field: &str, reason: String| BackendError::InvalidParams {
+        action: action.to_string(),
+        field: field.to_string(),
+        reason,
+    };
+
+    let empty = serde_json::Map::new();
+    let object = match params {
+        None | Some(serde_json::Value::Null) => &empty,
+        Some(serde_json::Value::Object(map)) => map,
+        Some(_) => return Err(invalid("params", "must be an object".to_string())),
+    };
+
+    for field in schema.fields {
+        let value = match object.get(field.name) {
+            None | Some(serde_json::Value::Null) if field.required => {
+                return Err(invalid(field.name, "is required".to_string()));
+            }
+            None | Some(serde_json::Value::Null) => continue,
+            Some(value) => value,
+        };
+        check_field(value, field.ty).map_err(|reason| invalid(field.name, reason))?;
+    }
+    Ok(())
+}
+
+fn check_field(value: &serde_json::Value, ty: FieldType) -> Result<(), String> {
+    match ty {
+        FieldType::Number { min, max } => {
+            let n = value.as_f64().ok_or_else(|| "must be a number".to_string())?;
+            if n < min || n > max {
+                return Err(range_message(min, max, max == f64::MAX));
+            }
+        }
+        FieldType::Integer { min, max } => {
+            let n = value.as_i64().ok_or_else(|| "must be an integer".to_string())?;
+            if n < min || n > max {
+                return Err(range_message(min, max, max == i64::MAX));
+            }
+        }
+        FieldType::OneOf(allowed) => {
+            let s = value.as_str().ok_or_else(|| "must be a string".to_string())?;
+            if !allowed.contains(&s) {
+                return Err(format!("must be one of {}", allowed.join(", ")));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn range_message(min: impl std::fmt::Display, max: impl std::fmt::Display, open_ended: bool) -> String {
+    if open_ended {
+        format!("must be at least {min}")
+    } else {
+        format!("must be between {min} and {max}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field_of(err: BackendError) -> String {
+        match err {
+            BackendError::InvalidParams { field, .. } => field,
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[test]
+    fn set_volume_accepts_values_in_range() {
+        assert!(validate_params("set_volume", Some(&json!({"volume": 0.0}))).is_ok());
+        assert!(validate_params("set_volume", Some(&json!({"volume": 1}))).is_ok());
+    }
+
+    #[test]
+    fn set_volume_rejects_strings_and_out_of_range() {
+        let err = validate_params("set_volume", Some(&json!({"volume": "loud"}))).unwrap_err();
+        assert_eq!(field_of(err), "volume");
+        let err = validate_params("set_volume", Some(&json!({"volume": 1.5}))).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'volume' for set_volume: must be between 0 and 1");
+    }
+
+    #[test]
+    fn set_volume_requires_volume() {
+        let err = validate_params("set_volume", Some(&json!({}))).unwrap_err();
+        assert_eq!(field_of(err), "volume");
+        assert!(validate_params("set_volume", None).is_err());
+    }
+
+    #[test]
+    fn seek_rejects_negative_position() {
+        let err = validate_params("seek", Some(&json!({"position_ms": -1}))).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'position_ms' for seek: must be at least 0");
+        assert!(validate_params("seek", Some(&json!({"position_ms": 1500}))).is_ok());
+    }
+
+    #[test]
+    fn play_index_is_optional_but_must_be_an_integer() {
+        assert!(validate_params("play", None).is_ok());
+        assert!(validate_params("play", Some(&json!({}))).is_ok());
+        assert!(validate_params("play", Some(&json!({"playlist_index": 3}))).is_ok());
+        let err = validate_params("play", Some(&json!({"playlist_index": 1.5}))).unwrap_err();
+        assert_eq!(field_of(err), "playlist_index");
+    }
+
+    #[test]
+    fn non_object_params_are_rejected_for_schema_actions() {
+        let err = validate_params("seek", Some(&json!([1, 2]))).unwrap_err();
+        assert_eq!(field_of(err), "params");
+    }
+
+    #[test]
+    fn actions_without_schema_pass_through() {
+        assert!(validate_params("load_song", Some(&json!({"filepath": 42}))).is_ok());
+    }
+
+    #[test]
+    fn transpose_is_limited_to_an_octave() {
+        assert!(validate_params("set_transpose", Some(&json!({"semitones": -12}))).is_ok());
+        let err = validate_params("set_transpose", Some(&json!({"semitones": 13}))).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'semitones' for set_transpose: must be between -12 and 12");
+    }
+
+    #[test]
+    fn library_sort_must_be_known() {
+        assert!(validate_params("get_library", Some(&json!({"sort_by": "date_added"}))).is_ok());
+        let err = validate_params("get_library", Some(&json!({"sort_by": "bpm"}))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid 'sort_by' for get_library: must be one of title, artist, date_added"
+        );
+    }
+
+    #[test]
+    fn search_limit_is_capped() {
+        assert!(validate_params("search_songs", Some(&json!({"query": "a", "limit": 500}))).is_ok());
+        let err = validate_params("search_songs", Some(&json!({"limit": 501}))).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid 'limit' for search_songs: must be between 1 and 500");
+    }
+}
@@ -0,0 +1,132 @@
+//! Trailing-edge debouncing for `set_volume`, and muting.
+//!
+//! Dragging the volume slider fires a call per pixel.  Each call takes a
+//! ticket and sleeps for [`VOLUME_DEBOUNCE`]; only the call still holding
+//! the latest ticket afterwards sends its value, and only if it differs
+//! from what the backend was last told.
+//!
+//! Muting sets the volume to 0 and keeps the level it replaced in
+//! [`Mute`], so unmuting from any window restores it.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long a volume change waits for a newer one before being sent.
+pub const VOLUME_DEBOUNCE: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Default)]
+pub struct VolumeDebounce {
+    latest: u64,
+    pending: Option<f64>,
+    last_sent: Option<f64>,
+}
+
+impl VolumeDebounce {
+    /// Record `volume` as the newest request and return its ticket.
+    pub fn propose(&mut self, volume: f64) -> u64 {
+        self.latest += 1;
+        self.pending = Some(volume);
+        self.latest
+    }
+
+    /// Whether no request newer than `ticket` has been made.
+    pub fn is_latest(&self, ticket: u64) -> bool {
+        ticket == self.latest
+    }
+
+    /// The value to send for `ticket`, or `None` if a newer request
+    /// superseded it or the backend already has this value.  Returning a
+    /// value marks it as sent.
+    pub fn take_if_latest(&mut self, ticket: u64) -> Option<f64> {
+        if ticket != self.latest {
+            return None;
+        }
+        let volume = self.pending.take()?;
+        if self.last_sent == Some(volume) {
+            return None;
+        }
+        self.last_sent = Some(volume);
+        Some(volume)
+    }
+
+    /// Record `volume` as sent outside the debounce, so a later request
+    /// for the level before it isn't skipped as already sent.
+    pub fn mark_sent(&mut self, volume: f64) {
+        self.pending = None;
+        self.last_sent = Some(volume);
+    }
+
+    /// Forget the last sent value, e.g. because a new backend started with
+    /// its own default.
+    pub fn reset(&mut self) {
+        self.pending = None;
+        self.last_sent = None;
+    }
+}
+
+/// The volume muting replaced, while muted.
+#[derive(Debug, Default)]
+pub struct Mute {
+    saved: Option<f64>,
+}
+
+impl Mute {
+    pub fn is_muted(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// Remember `volume` as the level to restore on unmuting.
+    pub fn mute(&mut self, volume: f64) {
+        self.saved = Some(volume);
+    }
+
+    /// The level to restore, if muted.
+    pub fn saved(&self) -> Option<f64> {
+        self.saved
+    }
+
+    /// Forget the saved level: unmuted, or the volume was set directly.
+    pub fn clear(&mut self) {
+        self.saved = None;
+    }
+}
+
+/// What `set_mute` and `toggle_mute` leave the player at.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MuteState {
+    pub muted: bool,
+    /// Volume the backend now plays at: 0 while muted.
+    pub volume: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_latest_request_is_sent() {
+        let mut debounce = VolumeDebounce::default();
+        let first = debounce.propose(0.2);
+        let second = debounce.propose(0.3);
+        assert_eq!(debounce.take_if_latest(first), None);
+        assert!(!debounce.is_latest(first) && debounce.is_latest(second));
+        assert_eq!(debounce.take_if_latest(second), Some(0.3));
+    }
+
+    #[test]
+    fn identical_values_are_not_resent() {
+        let mut debounce = VolumeDebounce::default();
+        let ticket = debounce.propose(0.5);
+        assert_eq!(debounce.take_if_latest(ticket), Some(0.5));
+        let ticket = debounce.propose(0.5);
+        assert_eq!(debounce.take_if_latest(ticket), None);
+
+        debounce.reset();
+        let ticket = debounce.propose(0.5);
+        assert_eq!(debounce.take_if_latest(ticket), Some(0.5));
+
+        debounce.mark_sent(0.0);
+        let ticket = debounce.propose(0.5);
+        assert_eq!(debounce.take_if_latest(ticket), Some(0.5), "muting sent 0 in between");
+    }
+}
@@ -0,0 +1,110 @@
+//! Noticing a backend that is still running but no longer answering.
+//!
+//! Crash detection only sees a process that exits; one deadlocked inside
+//! the Python stdio loop stays alive forever.  Once enabled with
+//! `set_watchdog`, every started backend is pinged each interval, and a
+//! ping unanswered by the next one counts as missed.  After
+//! `missed_pings` misses in a row the backend is treated as hung and
+//! restarted.
+
+use serde::Serialize;
+use std::time::Duration;
+
+pub const DEFAULT_INTERVAL_MS: u64 = 5_000;
+pub const DEFAULT_MISSED_PINGS: u32 = 3;
+/// Accepted ping intervals, in milliseconds.
+pub const MIN_INTERVAL_MS: u64 = 500;
+pub const MAX_INTERVAL_MS: u64 = 60_000;
+pub const MAX_MISSED_PINGS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct WatchdogSettings {
+    pub enabled: bool,
+    pub interval_ms: u64,
+    /// Consecutive missed pings after which the backend counts as hung.
+    pub missed_pings: u32,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        WatchdogSettings {
+            enabled: false,
+            interval_ms: DEFAULT_INTERVAL_MS,
+            missed_pings: DEFAULT_MISSED_PINGS,
+        }
+    }
+}
+
+impl WatchdogSettings {
+    /// Settings with the defaults for what isn't given; the error names
+    /// the field out of range and why.
+    pub fn new(
+        enabled: bool,
+        interval_ms: Option<u64>,
+        missed_pings: Option<u32>,
+    ) -> Result<Self, (&'static str, String)> {
+        let interval_ms = interval_ms.unwrap_or(DEFAULT_INTERVAL_MS);
+        if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&interval_ms) {
+            let reason = format!("must be between {MIN_INTERVAL_MS} and {MAX_INTERVAL_MS}");
+            return Err(("interval_ms", reason));
+        }
+        let missed_pings = missed_pings.unwrap_or(DEFAULT_MISSED_PINGS);
+        if !(1..=MAX_MISSED_PINGS).contains(&missed_pings) {
+            return Err(("missed_pings", format!("must be between 1 and {MAX_MISSED_PINGS}")));
+        }
+        Ok(WatchdogSettings { enabled, interval_ms, missed_pings })
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+}
+
+/// Counts consecutive missed pings; any answer starts the count over.
+#[derive(Debug)]
+pub struct MissCounter {
+    threshold: u32,
+    missed: u32,
+}
+
+impl MissCounter {
+    pub fn new(threshold: u32) -> Self {
+        MissCounter { threshold, missed: 0 }
+    }
+
+    /// Record one ping; true once `threshold` pings in a row went
+    /// unanswered.
+    pub fn record(&mut self, answered: bool) -> bool {
+        self.missed = if answered { 0 } else { self.missed + 1 };
+        self.missed >= self.threshold
+    }
+
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_consecutive_misses_count() {
+        let mut misses = MissCounter::new(3);
+        assert!(!misses.record(false));
+        assert!(!misses.record(false));
+        assert!(!misses.record(true), "an answer starts the count over");
+        assert!(!misses.record(false));
+        assert!(!misses.record(false));
+        assert!(misses.record(false));
+        assert_eq!(misses.missed(), 3);
+    }
+
+    #[test]
+    fn settings_are_range_checked() {
+        let settings = WatchdogSettings::new(true, None, Some(2)).unwrap();
+        assert_eq!(settings.interval(), Duration::from_secs(5));
+        assert_eq!(WatchdogSettings::new(true, Some(100), None).unwrap_err().0, "interval_ms");
+        assert_eq!(WatchdogSettings::new(true, None, Some(0)).unwrap_err().0, "missed_pings");
+    }
+}
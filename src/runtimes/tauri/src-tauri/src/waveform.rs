@@ -0,0 +1,51 @@
+//! Peak levels for drawing a song's waveform under the seek bar.
+//!
+//! The backend splits the song's audio into `buckets` equal slices and
+//! reports the loudest sample of each, scaled to 0..1.  Waveforms only
+//! change with the file, so they are cached by path and modification time
+//! alongside the bucket count they were drawn at.
+
+use serde::Serialize;
+
+/// Accepted `buckets` values.
+pub const MIN_BUCKETS: usize = 1;
+pub const MAX_BUCKETS: usize = 4096;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Waveform {
+    /// One peak per bucket, 0..1; empty when there is no waveform.
+    pub peaks: Vec<f32>,
+    /// Why there is no waveform, when there isn't.
+    pub note: Option<String>,
+}
+
+impl Waveform {
+    /// The `peaks` of a backend `waveform` response, clamped to 0..1.
+    pub fn from_backend(data: &serde_json::Value) -> Result<Self, String> {
+        let peaks = data["peaks"].as_array().ok_or("waveform response without peaks")?;
+        let peaks = peaks
+            .iter()
+            .map(|peak| peak.as_f64().map(|p| p.clamp(0.0, 1.0) as f32).ok_or("peaks must be numbers"))
+            .collect::<Result<_, _>>()?;
+        Ok(Waveform { peaks, note: None })
+    }
+
+    /// The empty waveform for a song the backend can't draw.
+    pub fn unavailable(reason: &str) -> Self {
+        Waveform { peaks: Vec::new(), note: Some(format!("No waveform: {reason}")) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn peaks_are_clamped_to_unit_range() {
+        let waveform = Waveform::from_backend(&json!({ "peaks": [0.25, 1.5, -0.1] })).unwrap();
+        assert_eq!(waveform.peaks, [0.25, 1.0, 0.0]);
+        assert!(Waveform::from_backend(&json!({ "peaks": ["loud"] })).is_err());
+        assert!(Waveform::from_backend(&json!({})).is_err());
+    }
+}
@@ -34,6 +34,9 @@ class PyKaraokeApp {
         this.lastBackendCheckAt = 0;
         this.backendStartRetries = 0;
         this.maxBackendRetries = 3;
+        // Append ?mock to the URL to drive the UI from the in-process mock
+        // backend instead of Python.
+        this.useMockBackend = new URLSearchParams(globalThis.location?.search || '').has('mock');
     }
 
     async init() {
@@ -70,7 +73,7 @@ class PyKaraokeApp {
     async ensureBackendStarted() {
         try {
             this.updateStatus('Starting backend...');
-            await invoke('start_backend');
+            await invoke('start_backend', { mock: this.useMockBackend });
 
             // Validate round-trip so we know command pipe is alive.
             const r = await this.sendCommand('get_state');
@@ -85,6 +88,14 @@ class PyKaraokeApp {
             this.updateStatus('Backend connected');
         } catch (e) {
             console.error('Backend startup failed:', e);
+            if (e?.kind === 'protocol_mismatch') {
+                // Retrying can't help: the installed backend is the wrong version.
+                this.backendStartRetries = this.maxBackendRetries;
+                this.updateStatus(this.errorMessage(e));
+                this.backendRunning = false;
+                this.updateBackendStatus(false);
+                return;
+            }
             this.backendStartRetries++;
             if (this.backendStartRetries >= this.maxBackendRetries) {
                 this.updateStatus(
@@ -138,6 +149,22 @@ class PyKaraokeApp {
         function $(id) { return document.getElementById(id); }
         var self = this;
 
+        // Files dropped onto the window are queued by the Rust side.
+        listen('files_added', function(e) {
+            let summary = e.payload || {};
+            let msg = 'Added ' + (summary.accepted || 0) + ' file(s)';
+            if (summary.rejected) {
+                msg += ', skipped ' + summary.rejected + ': ' + summary.rejections[0].reason;
+            }
+            self.updateStatus(msg);
+        });
+
+        // Failures the backend reports as structured `error` events.
+        listen('backend-error', function(e) {
+            let error = e.payload || {};
+            self.updateStatus('Error: ' + (error.message || error.code));
+        });
+
         $('play-btn').addEventListener('click', async () => {
             try {
                 let r = await this.sendCommand('play');
@@ -176,7 +203,7 @@ class PyKaraokeApp {
         $('volume-slider').addEventListener('input', function(e) {
             let v = Number.parseInt(e.target.value);
             $('volume-value').textContent = v + '%';
-            self.sendCommand('set_volume', { volume: v / 100 }).catch(function(err) {
+            invoke('set_volume', { volume: v / 100 }).catch(function(err) {
                 console.warn('Volume update failed:', err);
             });
         });
@@ -196,8 +223,7 @@ class PyKaraokeApp {
                 let pct = Number.parseInt(e.target.value) / 10;
                 let pos_ms = Math.round((pct / 100) * s.duration_ms);
                 try {
-                    let r = await self.sendCommand('seek', { position_ms: pos_ms });
-                    if (r && r.status !== 'ok') self.updateStatus(r.message || 'Seek failed');
+                    await invoke('seek', { positionMs: pos_ms });
                 } catch (ex) {
                     self.updateStatus('Seek error: ' + self.errorMessage(ex));
                 }
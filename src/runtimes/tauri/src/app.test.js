@@ -233,7 +233,7 @@ describe("Backend event handling", () => {
       "song_finished",
       "playback_error",
       "playlist_updated",
-      "library_scan_complete",
+      "scan_complete",
     ];
     // App should handle each without throwing
     for (const t of knownTypes) {
@@ -772,12 +772,12 @@ describe("Regression: volume slider handles errors gracefully", () => {
     "utf-8"
   );
 
-  it("volume-slider handler uses .catch() on sendCommand", () => {
+  it("volume-slider handler uses .catch() on the set_volume invoke", () => {
     const volIdx = appJsSource.indexOf("volume-slider");
     const handler = appJsSource.slice(volIdx, volIdx + 400);
     assert.ok(
       handler.includes(".catch("),
-      "volume-slider handler must use .catch() to handle sendCommand errors; " +
+      "volume-slider handler must use .catch() to handle set_volume errors; " +
         "previously errors from the backend were silently swallowed"
     );
   });
@@ -786,8 +786,8 @@ describe("Regression: volume slider handles errors gracefully", () => {
     const volIdx = appJsSource.indexOf("volume-slider");
     const handler = appJsSource.slice(volIdx, volIdx + 400);
     assert.ok(
-      handler.indexOf("volume-value") < handler.indexOf("sendCommand") ||
-        handler.indexOf("textContent") < handler.indexOf("sendCommand"),
+      handler.indexOf("volume-value") < handler.indexOf("invoke('set_volume'") ||
+        handler.indexOf("textContent") < handler.indexOf("invoke('set_volume'"),
       "volume-slider should update the displayed value before sending the " +
         "command so the UI feels responsive even if the backend is slow"
     );
@@ -1065,10 +1065,10 @@ describe("Regression: self is captured in setupEventListeners", () => {
     );
   });
 
-  it("volume slider references self.sendCommand with 'set_volume' string", () => {
+  it("volume slider invokes the debounced set_volume command", () => {
     assert.ok(
-      appJsSource.includes("self.sendCommand('set_volume'"),
-      "Volume slider must use self.sendCommand('set_volume', ...) — " +
+      appJsSource.includes("invoke('set_volume'"),
+      "Volume slider must use invoke('set_volume', ...) — " +
         "the old code had undeclared 'action' variable and globalThis.sendCommand"
     );
   });
@@ -193,6 +193,158 @@ class TestBackendStdioProtocol:
         assert parsed["type"] == "response"
         assert parsed["response"]["status"] == "ok"
 
+    def test_stdio_cycle_echoes_request_id(self):
+        """A request_id on the command should be echoed on its response."""
+        import io
+        import json
+        from unittest import mock
+        import threading
+
+        from pykaraoke.core import backend as backend_module
+
+        backend_instance = backend_module.PyKaraokeBackend()
+
+        command = {"action": "get_state", "params": {}, "request_id": 7}
+        stdin_mock = io.StringIO(json.dumps(command) + "\n")
+        stdout_mock = io.StringIO()
+
+        exc_info = []
+
+        def run():
+            try:
+                with (
+                    mock.patch.object(sys, "stdin", stdin_mock),
+                    mock.patch.object(sys, "stderr", io.StringIO()),
+                ):
+                    backend_module.create_stdio_server(backend_instance, json_out=stdout_mock)
+            except Exception as e:
+                exc_info.append(e)
+
+        t = threading.Thread(target=run, daemon=True)
+        t.start()
+        t.join(timeout=5)
+
+        if exc_info:
+            raise exc_info[0]
+
+        parsed = json.loads(stdout_mock.getvalue().splitlines()[-1])
+        assert parsed["type"] == "response"
+        assert parsed["request_id"] == 7
+
+    def test_stdio_tags_only_events_from_the_command_thread(self):
+        """An event another thread emits mid-command isn't the command's."""
+        import io
+        import json
+        from unittest import mock
+        import threading
+
+        from pykaraoke.core import backend as backend_module
+
+        backend_instance = backend_module.PyKaraokeBackend()
+
+        def handle_command(command):
+            backend_instance._emit_event("from_command")
+            other = threading.Thread(target=backend_instance._emit_event, args=("from_elsewhere",))
+            other.start()
+            other.join()
+            return {"status": "ok"}
+
+        command = {"action": "get_state", "params": {}, "request_id": 9}
+        stdin_mock = io.StringIO(json.dumps(command) + "\n")
+        stdout_mock = io.StringIO()
+
+        def run():
+            with (
+                mock.patch.object(sys, "stdin", stdin_mock),
+                mock.patch.object(sys, "stderr", io.StringIO()),
+                mock.patch.object(backend_instance, "handle_command", handle_command),
+            ):
+                backend_module.create_stdio_server(backend_instance, json_out=stdout_mock)
+
+        t = threading.Thread(target=run, daemon=True)
+        t.start()
+        t.join(timeout=5)
+
+        events = {}
+        for line in stdout_mock.getvalue().splitlines():
+            parsed = json.loads(line)
+            if parsed["type"] == "event":
+                events[parsed["event"]["type"]] = parsed.get("request_id")
+        assert events == {"from_command": 9, "from_elsewhere": None}
+
+    def test_stdio_answers_cancel_scan_with_its_own_request_id(self):
+        """Immediate actions are answered by the reader thread."""
+        import io
+        import json
+        from unittest import mock
+        import threading
+
+        from pykaraoke.core import backend as backend_module
+
+        backend_instance = backend_module.PyKaraokeBackend()
+
+        command = {"action": "cancel_scan", "params": {}, "request_id": 3}
+        stdin_mock = io.StringIO(json.dumps(command) + "\n")
+        stdout_mock = io.StringIO()
+
+        def run():
+            with (
+                mock.patch.object(sys, "stdin", stdin_mock),
+                mock.patch.object(sys, "stderr", io.StringIO()),
+            ):
+                backend_module.create_stdio_server(backend_instance, json_out=stdout_mock)
+
+        t = threading.Thread(target=run, daemon=True)
+        t.start()
+        t.join(timeout=5)
+
+        parsed = json.loads(stdout_mock.getvalue().splitlines()[-1])
+        assert parsed["request_id"] == 3
+        assert parsed["response"] == {"status": "error", "message": "No scan in progress"}
+
+    def test_stdio_cycle_length_prefixed(self):
+        """Length-prefixed framing should round-trip a command."""
+        import io
+        import json
+        import struct
+        from unittest import mock
+        import threading
+
+        from pykaraoke.core import backend as backend_module
+
+        backend_instance = backend_module.PyKaraokeBackend()
+
+        payload = json.dumps({"action": "get_state", "params": {}}).encode()
+        stdin_mock = io.BytesIO(struct.pack(">I", len(payload)) + payload)
+        stdout_mock = io.BytesIO()
+
+        exc_info = []
+
+        def run():
+            try:
+                with (
+                    mock.patch.object(sys, "stdin", stdin_mock),
+                    mock.patch.object(sys, "stderr", io.StringIO()),
+                ):
+                    backend_module.create_stdio_server(
+                        backend_instance, json_out=stdout_mock, framing="length-prefixed"
+                    )
+            except Exception as e:
+                exc_info.append(e)
+
+        t = threading.Thread(target=run, daemon=True)
+        t.start()
+        t.join(timeout=5)
+
+        if exc_info:
+            raise exc_info[0]
+
+        output = stdout_mock.getvalue()
+        (length,) = struct.unpack(">I", output[:4])
+        parsed = json.loads(output[4 : 4 + length])
+        assert parsed["type"] == "response"
+        assert parsed["response"]["status"] == "ok"
+
     def test_stdio_cycle_invalid_json(self):
         """Invalid JSON input should produce an error response."""
         import io
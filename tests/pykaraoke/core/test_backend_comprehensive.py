@@ -8,6 +8,7 @@ without requiring pygame display or audio.
 import json
 import sys
 import time
+from types import SimpleNamespace
 from unittest.mock import MagicMock, patch, PropertyMock
 
 import pytest
@@ -116,6 +117,208 @@ class TestBackendCommands:
         assert response["status"] == "error"
         assert "Unknown action" in response["message"]
 
+    def test_version_command(self):
+        from pykaraoke import __version__
+
+        backend = self._get_backend()
+        response = backend.handle_command({"action": "version"})
+        assert response["status"] == "ok"
+        assert response["data"]["version"] == __version__
+
+    def test_handshake_reports_protocol_version(self):
+        from pykaraoke.core.backend import PROTOCOL_VERSION
+
+        backend = self._get_backend()
+        response = backend.handle_command({"action": "handshake", "params": {"protocol": 1}})
+        assert response["status"] == "ok"
+        assert response["data"]["protocol"] == PROTOCOL_VERSION
+
+    def test_reopen_audio_restarts_an_open_mixer(self):
+        from pykaraoke.core import backend as backend_module
+
+        backend = self._get_backend()
+        with patch.object(backend_module, "manager") as manager:
+            manager.audio_props = (44100, -16, 2, 4096)
+            response = backend.handle_command({"action": "reopen_audio"})
+        assert response["status"] == "ok"
+        manager.close_audio.assert_called_once()
+        manager.open_audio.assert_called_once_with(44100, -16, 2)
+
+    def test_capture_frame_saves_the_render_surface(self):
+        from pykaraoke.core import backend as backend_module
+
+        backend = self._get_backend()
+        with patch.object(backend_module, "manager") as manager, patch("pygame.image.save") as save:
+            manager.surface = None
+            response = backend.handle_command({"action": "capture_frame", "params": {"path": "/tmp/f.png"}})
+            assert response == {"status": "error", "message": "Nothing is being displayed"}
+            manager.surface = MagicMock()
+            response = backend.handle_command({"action": "capture_frame", "params": {"path": "/tmp/f.png"}})
+        assert response == {"status": "ok", "data": {"path": "/tmp/f.png"}}
+        save.assert_called_once_with(manager.surface, "/tmp/f.png")
+
+    def test_fade_out_fades_the_music_while_playing(self):
+        from pykaraoke.core.backend import BackendState
+
+        backend = self._get_backend()
+        with patch("pygame.mixer.music.fadeout") as fadeout:
+            response = backend.handle_command({"action": "fade_out", "params": {"duration_ms": 2000}})
+            assert response == {"status": "error", "message": "Not playing"}
+            backend.current_player = MagicMock()
+            backend.state = BackendState.PLAYING
+            response = backend.handle_command({"action": "fade_out", "params": {"duration_ms": 2000}})
+        assert response == {"status": "ok"}
+        fadeout.assert_called_once_with(2000)
+
+    def test_set_av_sync_offset_sets_the_sync_delay(self):
+        backend = self._get_backend()
+        with patch.object(backend.song_db.settings, "sync_delay_ms", 0):
+            response = backend.handle_command({"action": "set_av_sync_offset", "params": {"offset_ms": -120}})
+            assert response == {"status": "ok"}
+            assert backend.song_db.settings.sync_delay_ms == -120
+            response = backend.handle_command({"action": "set_av_sync_offset", "params": {"offset_ms": 900}})
+            assert response["status"] == "error"
+            assert backend.song_db.settings.sync_delay_ms == -120
+
+    def test_set_library_roots_replaces_the_folder_list(self):
+        backend = self._get_backend()
+        with patch.object(backend.song_db.settings, "folder_list", ["/old"]), \
+                patch.object(backend.song_db, "save_settings") as save:
+            params = {"roots": ["/songs", "/mnt/karaoke", "/songs"]}
+            response = backend.handle_command({"action": "set_library_roots", "params": params})
+            assert response == {"status": "ok", "data": {"roots": ["/songs", "/mnt/karaoke"]}}
+            save.assert_called_once()
+            response = backend.handle_command({"action": "set_library_roots", "params": {"roots": "/songs"}})
+            assert response["status"] == "error"
+            assert backend.song_db.get_folder_list() == ["/songs", "/mnt/karaoke"]
+
+    def test_set_log_level_changes_the_root_level(self):
+        import logging
+
+        backend = self._get_backend()
+        root = logging.getLogger()
+        previous = root.level
+        try:
+            response = backend.handle_command({"action": "set_log_level", "params": {"level": "DEBUG"}})
+            assert response == {"status": "ok", "data": {"level": "DEBUG"}}
+            assert root.level == logging.DEBUG
+            response = backend.handle_command({"action": "set_log_level", "params": {"level": "TRACE"}})
+            assert response["status"] == "error"
+            assert root.level == logging.DEBUG
+        finally:
+            root.setLevel(previous)
+
+    def test_probe_reads_a_cdg_length_from_its_audio(self, tmp_path):
+        from pykaraoke.core import backend as backend_module
+
+        backend = self._get_backend()
+        cdg = tmp_path / "Queen - Bohemian Rhapsody.cdg"
+        cdg.write_bytes(b"")
+        (tmp_path / "Queen - Bohemian Rhapsody.mp3").write_bytes(b"")
+        with patch.object(backend_module, "_audio_length_ms", return_value=354000) as length:
+            response = backend.handle_command({"action": "probe", "params": {"filepath": str(cdg)}})
+        assert response["status"] == "ok"
+        assert response["data"]["format"] == "cdg"
+        assert response["data"]["duration_ms"] == 354000
+        length.assert_called_once_with(str(tmp_path / "Queen - Bohemian Rhapsody.mp3"))
+
+    def test_waveform_reads_peaks_from_a_cdg_audio_file(self, tmp_path):
+        import array
+        import wave
+
+        backend = self._get_backend()
+        cdg = tmp_path / "Queen - Bohemian Rhapsody.cdg"
+        cdg.write_bytes(b"")
+        with wave.open(str(tmp_path / "Queen - Bohemian Rhapsody.wav"), "wb") as wav:
+            wav.setnchannels(1)
+            wav.setsampwidth(2)
+            wav.setframerate(8000)
+            wav.writeframes(array.array("h", [0, 16384, 0, -32768]).tobytes())
+        response = backend.handle_command({"action": "waveform", "params": {"filepath": str(cdg), "buckets": 2}})
+        assert response == {"status": "ok", "data": {"peaks": [0.5, 1.0]}}
+
+        kar = tmp_path / "song.kar"
+        kar.write_bytes(b"")
+        response = backend.handle_command({"action": "waveform", "params": {"filepath": str(kar), "buckets": 2}})
+        assert response["status"] == "error"
+
+    def test_waveform_peaks_agree_with_and_without_numpy(self):
+        import array
+        import sys
+        from unittest.mock import patch
+
+        from pykaraoke.core.backend import _peaks
+
+        samples = array.array("h", [100, -200, 50])
+        expected = [0.1, 0.1, 0.2, 0.2, 0.05, 0.05]
+        assert _peaks(samples, 1000.0, 6) == expected
+        with patch.dict(sys.modules, {"numpy": None}):
+            assert _peaks(samples, 1000.0, 6) == expected
+            assert _peaks(array.array("h"), 1000.0, 2) == [0.0, 0.0]
+
+    def test_set_lyrics_language_picks_encoding_and_font(self):
+        backend = self._get_backend()
+        response = backend.handle_command({"action": "get_supported_languages"})
+        ids = [language["id"] for language in response["data"]["languages"]]
+        assert "ja" in ids and "en" in ids
+
+        settings = backend.song_db.settings
+        with patch.object(settings, "kar_encoding", "cp1252"), patch.object(settings, "kar_font", None):
+            response = backend.handle_command({"action": "set_lyrics_language", "params": {"lang": "ja"}})
+            assert response == {"status": "ok"}
+            assert settings.kar_encoding == "shift_jis"
+            assert settings.kar_font.size == 10
+            response = backend.handle_command({"action": "set_lyrics_language", "params": {"lang": "xx"}})
+            assert response["status"] == "error"
+            assert settings.kar_encoding == "shift_jis"
+
+    def test_set_theme_recolours_lyrics(self):
+        backend = self._get_backend()
+        response = backend.handle_command({"action": "get_available_themes"})
+        names = [theme["name"] for theme in response["data"]["themes"]]
+        assert "classic" in names and "high_contrast" in names
+
+        settings = backend.song_db.settings
+        with patch.object(settings, "kar_ready_colour", None), patch.object(settings, "kar_background_colour", None):
+            response = backend.handle_command({"action": "set_theme", "params": {"name": "high_contrast"}})
+            assert response == {"status": "ok"}
+            assert settings.kar_ready_colour == (255, 255, 0)
+            response = backend.handle_command({"action": "set_theme", "params": {"name": "neon"}})
+            assert response == {"status": "error", "message": "Unknown theme: neon"}
+            assert settings.kar_background_colour == (0, 0, 0)
+
+    def test_ping_answers_pong(self):
+        backend = self._get_backend()
+        response = backend.handle_command({"action": "ping"})
+        assert response == {"status": "ok", "data": {"pong": True}}
+
+    def test_list_and_set_audio_devices(self):
+        backend = self._get_backend()
+        names = ["Built-in Audio", "HDMI"]
+        with patch("pykaraoke.core.backend._audio_device_names", return_value=names), \
+                patch("pykaraoke.core.backend.manager") as manager:
+            manager.audio_device = None
+            response = backend.handle_command({"action": "list_audio_devices"})
+            assert response["status"] == "ok"
+            devices = response["data"]["devices"]
+            assert [d["id"] for d in devices] == names
+            assert [d["is_default"] for d in devices] == [True, False]
+
+            response = backend.handle_command(
+                {"action": "set_audio_device", "params": {"id": "HDMI"}}
+            )
+            assert response["status"] == "ok"
+            manager.set_audio_device.assert_called_once_with("HDMI")
+
+    def test_get_supported_formats(self):
+        backend = self._get_backend()
+        backend.song_db.settings.ignored_extensions = [".ogg"]
+        response = backend.handle_command({"action": "get_supported_formats"})
+        assert response["status"] == "ok"
+        extensions = response["data"]["extensions"]
+        assert "cdg" in extensions and "kar" in extensions and "zip" in extensions
+        assert "ogg" not in extensions
+
     def test_get_state_command(self):
         backend = self._get_backend()
         response = backend.handle_command({"action": "get_state"})
@@ -214,6 +417,69 @@ class TestBackendCommands:
         )
         assert response["status"] == "error"
 
+    def test_playlist_entries_keep_their_singer(self):
+        backend = self._get_backend()
+        with patch.object(backend.song_db, "make_song_struct", side_effect=lambda path: SimpleNamespace(title="", artist="", filepath=path)):
+            backend.handle_command({"action": "add_to_playlist", "params": {"filepath": "/a.cdg", "singer": "Ann"}})
+            backend.handle_command({"action": "add_to_playlist", "params": {"filepath": "/b.cdg"}})
+            response = backend.handle_command(
+                {"action": "add_to_playlist", "params": {"filepath": "/c.cdg", "singer": 7}}
+            )
+        assert response["status"] == "error"
+        backend.handle_command({"action": "move_in_playlist", "params": {"from": 0, "to": 1}})
+        playlist = backend.get_state()["playlist"]
+        assert "singer" not in playlist[0]
+        assert playlist[1]["singer"] == "Ann"
+
+    def test_move_in_playlist_keeps_current_song(self):
+        backend = self._get_backend()
+        backend.playlist = ["a", "b", "c"]
+        backend.playlist_index = 1
+        with patch.object(backend, "_song_to_dict", side_effect=lambda s: s):
+            response = backend.handle_command({"action": "move_in_playlist", "params": {"from": 0, "to": 2}})
+        assert response["data"]["playlist"] == ["b", "c", "a"]
+        assert backend.playlist_index == 0
+
+        response = backend.handle_command({"action": "move_in_playlist", "params": {"from": 0, "to": 3}})
+        assert response["status"] == "error"
+
+    def test_shuffle_keeps_current_song_and_restores_order(self):
+        backend = self._get_backend()
+        songs = [f"song{i}" for i in range(8)]
+        backend.playlist = list(songs)
+        backend.playlist_index = 2
+        with patch.object(backend, "_song_to_dict", side_effect=lambda s: s), \
+                patch("random.shuffle", side_effect=lambda items: items.reverse()):
+            backend.handle_command({"action": "set_shuffle", "params": {"enabled": True}})
+            assert backend.playlist[:3] == songs[:3]
+            assert backend.playlist[3:] == songs[:2:-1]
+            backend.playlist_index = 4
+            backend.handle_command({"action": "set_shuffle", "params": {"enabled": False}})
+        assert backend.playlist == songs
+        assert backend.playlist_index == 6
+
+    def test_repeat_one_replays_the_finished_song(self):
+        backend = self._get_backend()
+        assert backend.handle_command({"action": "set_repeat_mode", "params": {"mode": "loop"}})["status"] == "error"
+        backend.handle_command({"action": "set_repeat_mode", "params": {"mode": "one"}})
+        backend.playlist = ["a", "b"]
+        backend.playlist_index = 0
+        backend.current_song = "a"
+        with patch.object(backend, "_start_playback") as start:
+            backend._on_song_finished()
+        start.assert_called_once()
+        assert (backend.playlist_index, backend.current_song) == (0, "a")
+
+    def test_repeat_all_wraps_to_the_first_song(self):
+        backend = self._get_backend()
+        backend.handle_command({"action": "set_repeat_mode", "params": {"mode": "all"}})
+        backend.playlist = ["a", "b"]
+        backend.playlist_index = 1
+        backend.current_song = "b"
+        with patch.object(backend, "_start_playback"):
+            backend._on_song_finished()
+        assert (backend.playlist_index, backend.current_song) == (0, "a")
+
     def test_add_folder_no_folder(self):
         backend = self._get_backend()
         response = backend.handle_command(
@@ -221,6 +487,28 @@ class TestBackendCommands:
         )
         assert response["status"] == "error"
 
+    def test_add_folder_streams_scan_progress(self, tmp_path):
+        backend = self._get_backend()
+        (tmp_path / "song.cdg").write_bytes(b"")
+        (tmp_path / "sub").mkdir()
+        events = []
+        backend.set_event_callback(events.append)
+        with patch.object(backend.song_db, "do_search") as do_search, \
+                patch.object(backend.song_db, "folder_add") as folder_add, \
+                patch.object(backend.song_db, "save_settings"), \
+                patch.object(backend.song_db, "save_database"):
+            do_search.side_effect = lambda roots, _y, dlg: dlg.set_progress("Scanning", 0.5)
+            response = backend.handle_command({
+                "action": "add_folder",
+                "params": {"folder": str(tmp_path), "recursive": False},
+            })
+        assert response["status"] == "ok"
+        folder_add.assert_not_called()
+        assert do_search.call_args[0][0] == [str(tmp_path / "song.cdg")]
+        types = [e["type"] for e in events]
+        assert types == ["scan_progress", "scan_complete"]
+        assert events[0]["data"] == {"label": "Scanning", "progress": 0.5}
+
     def test_get_settings_command(self):
         backend = self._get_backend()
         response = backend.handle_command({"action": "get_settings"})
@@ -242,16 +530,131 @@ class TestBackendCommands:
         # May succeed or fail depending on database state
         assert response["status"] in ("ok", "error")
 
+    def test_search_songs_pages_results(self):
+        backend = self._get_backend()
+        songs = [MagicMock() for _ in range(5)]
+        with patch.object(backend.song_db, "search_database", return_value=songs), \
+                patch.object(backend, "_song_to_dict", side_effect=lambda s: songs.index(s)):
+            response = backend.handle_command(
+                {"action": "search_songs", "params": {"query": "q", "offset": 1, "limit": 2}}
+            )
+        assert response["status"] == "ok"
+        assert response["data"] == {"results": [1, 2], "total": 5}
+
     def test_get_library_command(self):
         backend = self._get_backend()
         response = backend.handle_command({"action": "get_library"})
         assert response["status"] in ("ok", "error")
 
+    def test_get_library_filters_sorts_and_pages(self):
+        backend = self._get_backend()
+        songs = []
+        for title, artist in [("Zombie", "The Cranberries"), ("Angels", "Robbie Williams"),
+                              ("Mr. Brightside", "The Killers")]:
+            song = MagicMock(title=title, artist=artist, display_filename=title + ".cdg")
+            songs.append(song)
+        backend.song_db.full_song_list = songs
+        with patch.object(backend, "_song_to_dict", side_effect=lambda s: s.title):
+            response = backend.handle_command({"action": "get_library", "params": {
+                "filter": "the", "sort_by": "title", "ascending": False, "limit": 1,
+            }})
+            assert response["data"] == {"songs": ["Zombie"], "total": 2}
+
+            response = backend.handle_command(
+                {"action": "get_library", "params": {"sort_by": "date_added", "offset": 1}}
+            )
+            assert response["data"]["songs"] == ["Angels", "Mr. Brightside"]
+
+        response = backend.handle_command({"action": "get_library", "params": {"sort_by": "bpm"}})
+        assert response["status"] == "error"
+
     def test_scan_library_command(self):
         backend = self._get_backend()
         response = backend.handle_command({"action": "scan_library"})
         assert response["status"] in ("ok", "error")
 
+    def test_scan_library_passes_excludes_to_the_scan(self):
+        backend = self._get_backend()
+        with patch.object(backend.song_db, "scan_excludes", []), \
+                patch.object(backend.song_db, "build_search_database"), \
+                patch.object(backend.song_db, "save_database"):
+            response = backend.handle_command({"action": "scan_library", "params": {"excludes": "/proc"}})
+            assert response == {"status": "error", "message": "excludes must be a list of strings"}
+            response = backend.handle_command({"action": "scan_library", "params": {"excludes": ["/proc"]}})
+            assert response["status"] == "ok"
+            assert backend.song_db.scan_excludes == ["/proc"]
+
+    def test_scan_library_emits_summary(self):
+        backend = self._get_backend()
+        events = []
+        backend.set_event_callback(events.append)
+
+        def scan(_yielder, _dialog):
+            backend.song_db.full_song_list.extend(["a", "b"])
+            backend.song_db.scan_errors = ["Couldn't scan /gone"]
+
+        with patch.object(backend.song_db, "full_song_list", []), \
+                patch.object(backend.song_db, "scan_errors", [], create=True), \
+                patch.object(backend.song_db, "build_search_database", side_effect=scan), \
+                patch.object(backend.song_db, "select_sort"), \
+                patch.object(backend.song_db, "save_database"):
+            response = backend.handle_command({"action": "scan_library"})
+        assert response["status"] == "ok"
+        summary = events[-1]["data"]
+        assert events[-1]["type"] == "scan_complete"
+        assert summary["song_count"] == 2
+        assert summary["songs_added"] == 2
+        assert summary["errors"] == ["Couldn't scan /gone"]
+        assert summary["duration_ms"] >= 0
+        assert response["data"] == summary
+
+    def test_cancel_scan_keeps_the_library_and_reports_a_partial_summary(self):
+        backend = self._get_backend()
+        events = []
+        backend.set_event_callback(events.append)
+        assert backend.handle_command({"action": "cancel_scan"})["status"] == "error"
+
+        def scan(_yielder, dialog):
+            backend.song_db.full_song_list = ["partial"]
+            assert backend.handle_command({"action": "cancel_scan"}) == {"status": "ok"}
+            assert dialog.clicked
+
+        with patch.object(backend.song_db, "full_song_list", ["a", "b"]), \
+                patch.object(backend.song_db, "build_search_database", side_effect=scan), \
+                patch.object(backend.song_db, "select_sort"), \
+                patch.object(backend.song_db, "save_database") as save:
+            response = backend.handle_command({"action": "scan_library"})
+            assert backend.song_db.full_song_list == ["a", "b"]
+        save.assert_not_called()
+        assert response["data"]["cancelled"] is True
+        assert events[-1]["type"] == "scan_cancelled"
+        assert events[-1]["data"]["song_count"] == 2
+
+    def test_cancel_stops_the_running_scan_or_skips_a_queued_command(self):
+        backend = self._get_backend()
+        cancel = {"action": "cancel", "params": {"request_id": 9}}
+        assert backend.handle_command(cancel) == {"status": "ok", "data": {"cancelled": True}}
+        assert not backend.begin_request(9)
+        assert backend.begin_request(9), "skipped only once"
+        assert backend.running_request_id == 9
+
+        assert backend.handle_command(cancel)["data"] == {"cancelled": False}, "get_state can't be interrupted"
+        assert not backend._cancelled_requests, "nor is it left to skip later"
+
+        def scan(_yielder, dialog):
+            assert backend.handle_command(cancel)["data"] == {"cancelled": True}
+            assert dialog.clicked
+
+        with patch.object(backend.song_db, "full_song_list", ["a"]), \
+                patch.object(backend.song_db, "build_search_database", side_effect=scan), \
+                patch.object(backend.song_db, "select_sort"), \
+                patch.object(backend.song_db, "save_database"):
+            response = backend.handle_command({"action": "scan_library"})
+        assert response["data"]["cancelled"] is True
+        from pykaraoke.core.backend import IMMEDIATE_ACTIONS
+
+        assert "cancel" in IMMEDIATE_ACTIONS
+
 
 class TestBackendEvents:
     """Tests for backend event emission."""
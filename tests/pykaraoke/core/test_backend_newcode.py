@@ -9,6 +9,7 @@ Covers the error paths and dispatch-table changes introduced by recent refactori
 - _on_player_error callback (lines 327-330)
 """
 
+import array
 import os
 import sys
 from unittest.mock import MagicMock, patch, PropertyMock
@@ -196,6 +197,373 @@ class TestHandlePlayFailure:
         assert "player init failed" in result["message"]
 
 
+# ---------- song_started event ----------
+
+class TestSongStarted:
+    """A song that starts playing is announced for the play history."""
+
+    def test_play_emits_song_started(self):
+        backend = _make_backend()
+        cb = MagicMock()
+        backend.set_event_callback(cb)
+        song = MagicMock(title="Dancing Queen", artist="ABBA", filepath="/songs/abba.cdg", singer=None)
+        song.make_player.return_value = MagicMock(is_valid=True)
+        backend.current_song = song
+        with patch("pykaraoke.core.backend.manager"):
+            result = backend.handle_command({"action": "play", "params": {}})
+        assert result["status"] == "ok"
+        started = [c.args[0] for c in cb.call_args_list if c.args[0].get("type") == "song_started"]
+        assert len(started) == 1
+        assert started[0]["data"]["song"]["filepath"] == "/songs/abba.cdg"
+
+
+# ---------- _handle_preload ----------
+
+class TestPreload:
+    """preload reads a playlist entry ahead for its player to use."""
+
+    def test_preloaded_files_reach_the_player(self):
+        backend = _make_backend()
+        song_data = MagicMock()
+        song_data.get_data.return_value = b"cdg"
+        song = MagicMock()
+        song.get_song_datas.return_value = [song_data]
+        seen = []
+        song.make_player.side_effect = lambda *a, **k: seen.append(song.get_song_datas()) or MagicMock()
+        backend.playlist = [song]
+
+        assert backend.handle_command({"action": "preload", "params": {"playlist_index": 1}})["status"] == "error"
+        result = backend.handle_command({"action": "preload", "params": {"playlist_index": 0}})
+        assert result == {"status": "ok", "data": {"playlist_index": 0, "bytes": 3}}
+        song.get_song_datas.reset_mock()
+        backend._make_player(song)
+        assert seen == [[song_data]]
+        song.get_song_datas.assert_not_called()
+        assert backend._preloaded is None
+
+
+# ---------- _handle_insert_in_playlist ----------
+
+class TestInsertInPlaylist:
+    """insert_in_playlist queues a song at a given position."""
+
+    def test_song_is_queued_after_the_current_one(self):
+        backend = _make_backend()
+        backend.song_db = MagicMock()
+        backend.song_db.make_song_struct.side_effect = lambda path: MagicMock(filepath=path)
+        for path in ("/a.cdg", "/b.cdg"):
+            backend.handle_command({"action": "add_to_playlist", "params": {"filepath": path}})
+        backend.playlist_index = 0
+
+        result = backend.handle_command(
+            {"action": "insert_in_playlist", "params": {"filepath": "/next.cdg", "index": 1}}
+        )
+        assert result == {"status": "ok"}
+        assert [song.filepath for song in backend.playlist] == ["/a.cdg", "/next.cdg", "/b.cdg"]
+        assert backend.playlist_index == 0
+
+        backend.handle_command({"action": "insert_in_playlist", "params": {"filepath": "/first.cdg", "index": 0}})
+        assert backend.playlist_index == 1, "the current song keeps playing"
+        bad = backend.handle_command({"action": "insert_in_playlist", "params": {"filepath": "/x.cdg", "index": 9}})
+        assert bad == {"status": "error", "message": "index must be between 0 and 4"}
+
+
+# ---------- _handle_get_audio_stats ----------
+
+class TestAudioStats:
+    """get_audio_stats reports the format the mixer was opened with."""
+
+    def test_stats_follow_the_open_mixer(self):
+        backend = _make_backend()
+        with patch("pykaraoke.core.backend.manager") as mock_manager:
+            mock_manager.audio_props = None
+            mock_manager.get_audio_buffer_ms.return_value = 0
+            closed = backend.handle_command({"action": "get_audio_stats"})["data"]
+            assert closed == {"underruns": None, "buffer_ms": 0, "sample_rate": None, "channels": None}
+
+            mock_manager.audio_props = (44100, -16, 2, 4096)
+            mock_manager.get_audio_buffer_ms.return_value = 46.4
+            opened = backend.handle_command({"action": "get_audio_stats"})["data"]
+            assert (opened["sample_rate"], opened["channels"], opened["buffer_ms"]) == (44100, 2, 46.4)
+
+
+# ---------- _handle_load_song failure reasons ----------
+
+class TestLoadFailed:
+    """load_song says why a song can't be loaded."""
+
+    def test_failures_carry_a_reason(self, tmp_path):
+        backend = _make_backend()
+        events = []
+        backend.event_callback = events.append
+        (tmp_path / "notes.txt").write_text("not a song")
+        (tmp_path / "lonely.cdg").write_bytes(b"\0" * 96)
+
+        for name, reason in (("notes.txt", "unsupported_format"), ("lonely.cdg", "missing_companion")):
+            path = str(tmp_path / name)
+            result = backend.handle_command({"action": "load_song", "params": {"filepath": path}})
+            assert (result["status"], result["data"]) == ("error", {"reason": reason})
+            assert events[-1]["type"] == "load_failed"
+            assert events[-1]["data"]["filepath"] == path
+            assert events[-1]["data"]["reason"] == reason
+        assert backend.current_song is None
+
+        backend.song_db = MagicMock()
+        backend.song_db.make_song_struct.side_effect = PermissionError("Permission denied")
+        result = backend.handle_command({"action": "load_song", "params": {"filepath": "/locked.cdg"}})
+        assert result["data"] == {"reason": "permission_denied"}
+
+
+# ---------- _handle_set_normalization ----------
+
+class TestNormalization:
+    """set_normalization scales tagged songs to a loudness target."""
+
+    def test_tagged_songs_are_scaled_to_the_target(self):
+        backend = _make_backend()
+        backend.volume = 0.5
+        with patch("pykaraoke.core.backend.manager") as mock_manager:
+            mock_manager.initialized = False
+            params = {"enabled": True, "target_lufs": -24}
+            untagged = backend.handle_command({"action": "set_normalization", "params": params})["data"]
+            assert (untagged["has_gain_metadata"], untagged["gain_db"]) == (False, None)
+
+            backend._song_gain_db = -2.0
+            tagged = backend.handle_command({"action": "set_normalization", "params": params})["data"]
+            assert tagged == {"enabled": True, "target_lufs": -24.0, "has_gain_metadata": True, "gain_db": -8.0}
+            assert backend._output_volume() == pytest.approx(0.5 * 10 ** (-8 / 20))
+
+        params = {"enabled": True, "target_lufs": -3}
+        bad = backend.handle_command({"action": "set_normalization", "params": params})
+        assert bad == {"status": "error", "message": "target_lufs must be a number between -30 and -5"}
+
+    def test_advancing_picks_up_the_next_songs_tags(self):
+        backend = _make_backend()
+        backend.normalization = {"enabled": True, "target_lufs": -18.0}
+        songs = [MagicMock(filepath=path, zip_stored_name=None) for path in ("/loud.mp3", "/quiet.mp3")]
+        backend.playlist, backend.playlist_index = songs, 0
+        backend.current_song = songs[0]
+        tags = {"/loud.mp3": -6.0, "/quiet.mp3": 3.0}
+        with patch("pykaraoke.core.backend.manager"), \
+                patch("pykaraoke.core.backend._replaygain_db", side_effect=tags.get), \
+                patch.object(backend, "_make_player", return_value=MagicMock(is_valid=True)):
+            backend.handle_command({"action": "play"})
+            assert backend._normalization_report()["gain_db"] == -6.0
+            assert backend.handle_command({"action": "next"})["status"] == "ok"
+            assert backend._normalization_report()["gain_db"] == 3.0
+
+
+# ---------- _handle_reinit_audio ----------
+
+class TestReinitAudio:
+    """reinit_audio reopens the output and picks the song up again."""
+
+    def test_paused_song_resumes_where_it_was(self):
+        backend = _make_backend()
+        events = []
+        backend.event_callback = events.append
+        old_player, new_player = MagicMock(), MagicMock()
+        backend.current_player = old_player
+        backend.current_song = MagicMock()
+        backend.state = BackendState.PAUSED
+        backend.position_ms = 42_000
+        with patch("pykaraoke.core.backend.manager") as mock_manager, \
+                patch.object(backend, "_make_player", return_value=new_player):
+            mock_manager.audio_props = (44100, -16, 2, 4096)
+            mock_manager.audio_device = "USB Audio"
+            result = backend.handle_command({"action": "reinit_audio", "params": {"id": "USB Audio"}})
+
+        assert result["data"] == {"device": "USB Audio", "position_ms": 42_000, "resumed": True}
+        mock_manager.set_audio_device.assert_called_once_with("USB Audio")
+        mock_manager.open_audio.assert_called_once_with(44100, -16, 2)
+        old_player.close.assert_called_once()
+        new_player.seek.assert_called_once_with(42_000)
+        new_player.pause.assert_called_once()
+        assert [event["type"] for event in events] == ["audio_ready"]
+        assert backend.state == BackendState.PAUSED
+
+
+# ---------- _handle_get_backend_capabilities ----------
+
+class TestBackendCapabilities:
+    """get_backend_capabilities lists only what this backend can do."""
+
+    def test_features_follow_the_handled_actions(self):
+        backend = _make_backend()
+        backend.song_db = MagicMock()
+        backend.song_db.settings.mpg_external = 'no-such-video-player -fs "%(file)s"'
+        data = backend.handle_command({"action": "get_backend_capabilities"})["data"]
+        assert "mp3" in data["codecs"] and "mpg" not in data["codecs"]
+        assert "waveform" in data["features"]
+        assert "vocal_removal" not in data["features"], "no set_vocal_removal handler"
+        assert "transpose" not in data["features"], "no set_transpose handler"
+        assert "tempo" not in data["features"], "no set_tempo handler"
+        assert set(data["plugins"]) <= {"mutagen", "_pycdgAux", "pygame._sdl2"}
+
+
+# ---------- _handle_set_crossfade ----------
+
+class TestCrossfade:
+    """set_crossfade fades song ends out and the song advanced to in."""
+
+    def test_advanced_to_song_fades_in_and_every_song_fades_out(self):
+        backend = _make_backend()
+        backend.volume = 0.8
+        backend.auto_advance = False
+        backend.playlist = [MagicMock()]
+        player = MagicMock()
+        player.get_length.return_value = 60
+        with patch("pykaraoke.core.backend.manager") as mock_manager, \
+                patch.object(backend, "_make_player", return_value=player):
+            mock_manager.initialized = False
+            result = backend.handle_command({"action": "set_crossfade", "params": {"duration_ms": 4000}})
+            assert result["data"] == {"duration_ms": 4000}
+
+            # The host advances after song_finished.
+            backend._on_song_finished()
+            backend.handle_command({"action": "play", "params": {"playlist_index": 0}})
+            assert backend._fading_in
+            backend.position_ms = 1000
+            assert backend._output_volume() == pytest.approx(0.2)
+            backend.position_ms = 30_000
+            assert backend._output_volume() == pytest.approx(0.8)
+            backend.position_ms = 58_000
+            assert backend._output_volume() == pytest.approx(0.4)
+
+            backend._handle_stop()
+            backend.handle_command({"action": "play", "params": {"playlist_index": 0}})
+            assert not backend._fading_in, "a song started by hand starts at full volume"
+
+            # The last song ending leaves nothing for the host to advance to.
+            backend._on_song_finished()
+            backend.handle_command({"action": "play", "params": {"playlist_index": 0}})
+            assert not backend._fading_in, "replaying the playlist by hand starts at full volume"
+
+            import pygame
+            with patch.object(pygame.mixer.music, "fadeout", create=True):
+                assert backend.handle_command({"action": "fade_out", "params": {"duration_ms": 2000}})["status"] == "ok"
+            mock_manager.set_volume.reset_mock()
+            backend.poll()
+            mock_manager.set_volume.assert_not_called()
+
+        bad = backend.handle_command({"action": "set_crossfade", "params": {"duration_ms": 20000}})
+        assert bad == {"status": "error", "message": "duration_ms must be an integer between 0 and 10000"}
+
+
+# ---------- _handle_play_test_tone ----------
+
+class TestPlayTestTone:
+    """play_test_tone plays a tone on the chosen speakers."""
+
+    def test_tone_is_only_on_the_chosen_channel(self):
+        import pygame
+
+        backend = _make_backend()
+        with patch.object(pygame.mixer, "get_init", return_value=(1000, -16, 2)), \
+                patch.object(pygame.mixer, "Sound") as sound:
+            params = {"frequency_hz": 250, "duration_ms": 100, "channel": "left"}
+            result = backend.handle_command({"action": "play_test_tone", "params": params})
+
+        assert result == {"status": "ok", "data": {"duration_ms": 100}}
+        samples = array.array("h", sound.call_args.kwargs["buffer"])
+        assert len(samples) == 2 * 100
+        assert max(samples[0::2]) > 0 and set(samples[1::2]) == {0}
+        sound.return_value.play.assert_called_once()
+        assert backend.playlist == []
+
+        params = {"frequency_hz": 250, "duration_ms": 100, "channel": "center"}
+        bad = backend.handle_command({"action": "play_test_tone", "params": params})
+        assert bad == {"status": "error", "message": "channel must be one of left, right, both"}
+
+
+# ---------- input devices and mic monitoring ----------
+
+class TestMicMonitoring:
+    """The microphone is chosen and played through the output at a gain."""
+
+    def test_input_devices_are_listed_and_chosen(self):
+        backend = _make_backend()
+        names = ["Built-in Mic", "USB Mic"]
+        with patch("pykaraoke.core.backend._audio_device_names", return_value=names) as listed:
+            response = backend.handle_command({"action": "list_input_devices"})
+            assert listed.call_args.kwargs == {"capture": True}
+            assert [d["is_default"] for d in response["data"]["devices"]] == [True, False]
+
+            backend.handle_command({"action": "set_input_device", "params": {"id": "USB Mic"}})
+            response = backend.handle_command({"action": "list_input_devices"})
+            assert [d["is_default"] for d in response["data"]["devices"]] == [False, True]
+        assert backend.handle_command({"action": "set_input_device", "params": {}})["status"] == "error"
+
+    def test_monitoring_forwards_the_mic_at_the_gain(self):
+        import pygame
+
+        backend = _make_backend()
+        with patch.object(pygame.mixer, "get_init", return_value=(44100, -16, 2)), \
+                patch.object(pygame.mixer, "set_reserved", create=True), \
+                patch.object(pygame.mixer, "Channel", create=True) as channel, \
+                patch.object(pygame.mixer, "Sound") as sound, \
+                patch("pykaraoke.core.backend._audio_device_names", return_value=["Built-in Mic"]), \
+                patch("pykaraoke.core.backend._open_capture_device") as capture:
+            params = {"enabled": True, "gain": 2}
+            result = backend.handle_command({"action": "set_mic_monitoring", "params": params})
+            assert result == {"status": "ok", "data": {"enabled": True, "gain": 2.0, "device": "Built-in Mic"}}
+            name, rate, channels, forward = capture.call_args.args
+            assert (name, rate, channels) == ("Built-in Mic", 44100, 2)
+
+            channel.return_value.get_busy.return_value = False
+            forward(None, array.array("h", [100, -20000]).tobytes())
+            samples = array.array("h", sound.call_args.kwargs["buffer"])
+            assert list(samples) == [200, -0x8000]
+            channel.return_value.play.assert_called_once_with(sound.return_value)
+
+            # Chunks arriving faster than the channel plays them wait their turn.
+            channel.return_value.get_busy.return_value = True
+            channel.return_value.get_queue.return_value = None
+            forward(None, array.array("h", [1]).tobytes())
+            channel.return_value.get_queue.return_value = sound.return_value
+            forward(None, array.array("h", [2]).tobytes())
+            forward(None, array.array("h", [3]).tobytes())
+            assert channel.return_value.queue.call_count == 1
+            channel.return_value.get_queue.return_value = None
+            forward(None, array.array("h", [4]).tobytes())
+            assert channel.return_value.queue.call_count == 2
+
+            params = {"enabled": False, "gain": 1}
+            backend.handle_command({"action": "set_mic_monitoring", "params": params})
+            capture.return_value.close.assert_called_once()
+            assert backend._mic is None
+
+        params = {"enabled": True, "gain": 3}
+        bad = backend.handle_command({"action": "set_mic_monitoring", "params": params})
+        assert bad == {"status": "error", "message": "gain must be a number between 0 and 2"}
+
+    def test_a_failed_switch_keeps_monitoring_the_old_mic(self):
+        backend = _make_backend()
+        backend.input_device, backend._mic = "Built-in Mic", MagicMock()
+
+        def start():
+            if backend.input_device == "Broken Mic":
+                raise RuntimeError("no such device")
+            backend._mic = MagicMock()
+
+        with patch.object(backend, "_start_mic_monitoring", side_effect=start):
+            result = backend.handle_command({"action": "set_input_device", "params": {"id": "Broken Mic"}})
+        assert result == {"status": "error", "message": "Can't monitor Broken Mic: no such device"}
+        assert backend.input_device == "Built-in Mic"
+        assert backend._mic is not None
+
+    def test_monitoring_is_moved_onto_the_reopened_mixer(self):
+        backend = _make_backend()
+        old_mic = backend._mic = MagicMock()
+        with patch("pykaraoke.core.backend.manager") as mock_manager, \
+                patch.object(backend, "_start_mic_monitoring") as start:
+            mock_manager.audio_props = (44100, -16, 2, 4096)
+            mock_manager.close_audio.side_effect = lambda: start.assert_not_called()
+            assert backend.handle_command({"action": "reinit_audio", "params": {}})["status"] == "ok"
+        old_mic.close.assert_called_once()
+        start.assert_called_once()
+
+
 # ---------- _on_player_error callback (lines 325-330) ----------
 
 class TestOnPlayerError:
@@ -214,6 +582,17 @@ class TestOnPlayerError:
             for call in cb.call_args_list
         )
 
+    def test_on_player_error_emits_a_structured_error(self):
+        backend = _make_backend()
+        cb = MagicMock()
+        backend.set_event_callback(cb)
+        backend._on_player_error("bad file")
+        errors = [call.args[0] for call in cb.call_args_list if call.args[0]["type"] == "error"]
+        assert len(errors) == 1
+        assert errors[0]["code"] == "player_error"
+        assert errors[0]["message"] == "bad file"
+        assert "data" not in errors[0]
+
     def test_on_player_error_without_callback(self):
         backend = _make_backend()
         backend.event_callback = None
@@ -234,6 +613,18 @@ class TestOnSongFinished:
         backend._on_song_finished()
         assert backend.state == BackendState.IDLE
 
+    def test_on_song_finished_without_auto_advance_stays_put(self):
+        backend = _make_backend()
+        backend.playlist = [MagicMock(), MagicMock()]
+        backend.playlist_index = 0
+        response = backend.handle_command(
+            {"action": "set_auto_advance", "params": {"enabled": False}}
+        )
+        assert response["status"] == "ok"
+        backend._on_song_finished()
+        assert backend.playlist_index == 0
+        assert backend.state == BackendState.IDLE
+
     def test_on_song_finished_emits_event(self):
         backend = _make_backend()
         cb = MagicMock()
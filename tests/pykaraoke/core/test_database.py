@@ -485,6 +485,20 @@ class TestSongDBDuplicateDetection:
             assert len(db.full_song_list) == 1, \
                 "Same file scanned twice must not create duplicates"
 
+    def test_file_scan_skips_excluded_folders(self):
+        """Folders matching scan_excludes by path or name aren't entered."""
+        db = SongDB()
+        with tempfile.TemporaryDirectory() as tmpdir:
+            for folder in ("keep", "node_modules", "skip"):
+                os.mkdir(os.path.join(tmpdir, folder))
+                with open(os.path.join(tmpdir, folder, "song.kar"), "w") as f:
+                    f.write("dummy karaoke content")
+            db.scan_excludes = ["node_modules", os.path.join(tmpdir, "skip")]
+            db.do_search([tmpdir], AppYielder(), BusyCancelDialog())
+
+            found = [os.path.basename(os.path.dirname(song.filepath)) for song in db.full_song_list]
+            assert found == ["keep"]
+
     def test_file_scan_normalises_path_separators(self):
         """Path normalisation must prevent case/separator dupes."""
         db = SongDB()